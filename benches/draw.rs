@@ -0,0 +1,144 @@
+//! Benchmarks for the pixel pipelines feeding the draw paths. The display is driven through an
+//! interface which discards everything, so that only the cost of the iterator chains and the
+//! region's own bookkeeping is measured.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use ssd1322::interface::chunked::ChunkedInterface;
+use ssd1322::interface::DisplayInterface;
+use ssd1322::{ComLayout, ComScanDirection, Config, Display, PixelCoord};
+
+/// An interface that accepts and discards everything immediately.
+struct NullInterface;
+
+impl DisplayInterface for NullInterface {
+    type Error = core::convert::Infallible;
+
+    fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+        black_box(cmd);
+        Ok(())
+    }
+    fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        black_box(buf);
+        Ok(())
+    }
+    #[cfg(feature = "nb")]
+    fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        black_box(word);
+        Ok(())
+    }
+}
+
+fn display() -> Display<NullInterface> {
+    display_on(NullInterface)
+}
+
+fn display_on<DI>(iface: DI) -> Display<DI>
+where
+    DI: DisplayInterface,
+    DI::Error: core::fmt::Debug,
+{
+    let mut disp = Display::new(iface, PixelCoord(256, 64), PixelCoord(0, 0));
+    disp.init(Config::new(
+        ComScanDirection::RowZeroLast,
+        ComLayout::DualProgressive,
+    ))
+    .unwrap();
+    disp
+}
+
+fn draw_packed(c: &mut Criterion) {
+    let mut disp = display();
+    c.bench_function("region draw_packed 256x64", |b| {
+        b.iter(|| {
+            disp.region(PixelCoord(0, 0), PixelCoord(256, 64))
+                .unwrap()
+                .draw_packed((0..256 * 64 / 2).map(|i| i as u8))
+                .unwrap()
+        })
+    });
+}
+
+fn draw_unpacked(c: &mut Criterion) {
+    // This exercises the 8-to-4 bit packing adapter in front of `draw_packed`.
+    let mut disp = display();
+    c.bench_function("region draw 256x64", |b| {
+        b.iter(|| {
+            disp.region(PixelCoord(0, 0), PixelCoord(256, 64))
+                .unwrap()
+                .draw((0..256 * 64).map(|i| i as u8 & 0x0F))
+                .unwrap()
+        })
+    });
+}
+
+fn overscanned(c: &mut Criterion) {
+    let mut disp = display();
+    c.bench_function("overscanned draw_packed interior 256x64", |b| {
+        b.iter(|| {
+            disp.overscanned_region(PixelCoord(0, 0), PixelCoord(256, 64))
+                .unwrap()
+                .draw_packed((0..256 * 64 / 2).map(|i| i as u8))
+                .unwrap()
+        })
+    });
+    c.bench_function("overscanned draw_packed cropped 320x192", |b| {
+        b.iter(|| {
+            disp.overscanned_region(PixelCoord(-32, -32), PixelCoord(288, 160))
+                .unwrap()
+                .draw_packed((0..320 * 192 / 2).map(|i| i as u8))
+                .unwrap()
+        })
+    });
+}
+
+fn chunking(c: &mut Criterion) {
+    // The same frame sent with each way of handing image data to the interface: word by word (or
+    // in small chunks without the `nb` feature) from an iterator, one transfer per row, chunks
+    // read from a stream, and rows split further by a `ChunkedInterface`.
+    let frame: Vec<u8> = (0..256 * 64 / 2).map(|i| i as u8).collect();
+    let mut group = c.benchmark_group("chunking 256x64");
+    let mut disp = display();
+    group.bench_function("draw_packed", |b| {
+        b.iter(|| {
+            disp.region(PixelCoord(0, 0), PixelCoord(256, 64))
+                .unwrap()
+                .draw_packed(frame.iter().cloned())
+                .unwrap()
+        })
+    });
+    group.bench_function("draw_rows", |b| {
+        b.iter(|| {
+            disp.region(PixelCoord(0, 0), PixelCoord(256, 64))
+                .unwrap()
+                .draw_rows(frame.chunks(128))
+                .unwrap()
+        })
+    });
+    group.bench_function("draw_from_reader", |b| {
+        b.iter(|| {
+            disp.region(PixelCoord(0, 0), PixelCoord(256, 64))
+                .unwrap()
+                .draw_from_reader(&frame[..])
+                .unwrap()
+        })
+    });
+    for &max_chunk in [1, 8, 32].iter() {
+        let mut disp = display_on(ChunkedInterface::new(NullInterface, max_chunk));
+        group.bench_with_input(
+            BenchmarkId::new("draw_rows chunked", max_chunk),
+            &max_chunk,
+            |b, _| {
+                b.iter(|| {
+                    disp.region(PixelCoord(0, 0), PixelCoord(256, 64))
+                        .unwrap()
+                        .draw_rows(frame.chunks(128))
+                        .unwrap()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, draw_packed, draw_unpacked, overscanned, chunking);
+criterion_main!(benches);
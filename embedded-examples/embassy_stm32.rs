@@ -0,0 +1,93 @@
+//! Example code for driving an SSD1322 display from an Embassy task with `AsyncDisplay`, so that
+//! other tasks keep running while image data is sent. This runs on an STM32F303RE, using a Newhaven
+//! Displays NHD-3.12-25664UCY2 connected to SPI1 with DMA, PA8 for D/C, and PA9 for /RESET.
+//!
+//! Requires the `embassy` feature of this crate.
+
+#![no_main]
+#![no_std]
+
+use embassy_executor::Spawner;
+use embassy_stm32::gpio::{Level, Output, Speed};
+use embassy_stm32::mode::Async;
+use embassy_stm32::spi::{self, Spi};
+use embassy_stm32::time::Hertz;
+use embassy_time::Timer;
+use panic_halt as _;
+use ssd1322 as oled;
+use ssd1322::asynch::{AsyncDisplay, AsyncDisplayInterface};
+
+/// The 4-wire SPI interface, using Embassy's DMA-driven SPI writes.
+struct Interface {
+    spi: Spi<'static, Async>,
+    dc: Output<'static>,
+}
+
+impl AsyncDisplayInterface for Interface {
+    type Error = spi::Error;
+
+    async fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+        self.dc.set_low();
+        let result = self.spi.write(&[cmd]).await;
+        self.dc.set_high();
+        result
+    }
+
+    async fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.spi.write(buf).await
+    }
+}
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+
+    let mut spi_config = spi::Config::default();
+    spi_config.frequency = Hertz(8_000_000);
+    let spi = Spi::new_txonly(p.SPI1, p.PA5, p.PA7, p.DMA1_CH3, spi_config);
+    let dc = Output::new(p.PA8, Level::High, Speed::VeryHigh);
+
+    // The ssd1322 library does not control the /RESET pin; we will assert reset separately.
+    let mut rst = Output::new(p.PA9, Level::Low, Speed::Low);
+    Timer::after_millis(1).await;
+    rst.set_high();
+    Timer::after_millis(1).await;
+
+    let mut disp = AsyncDisplay::new(
+        Interface { spi: spi, dc: dc },
+        oled::PixelCoord(256, 64),
+        oled::PixelCoord(112, 0),
+    );
+    disp.init(
+        oled::Config::new(
+            oled::ComScanDirection::RowZeroLast,
+            oled::ComLayout::DualProgressive,
+        )
+        .clock_fosc_divset(9, 1)
+        .display_enhancements(true, true)
+        .contrast_current(159)
+        .phase_lengths(5, 14)
+        .precharge_voltage(31)
+        .second_precharge_period(8)
+        .com_deselect_voltage(7),
+    )
+    .await
+    .unwrap();
+
+    // Draw a gradient into a frame buffer and flush it while the display is dark, then fade it
+    // in.
+    let mut frame = [0u8; 256 * 64 / 2];
+    for (i, pixels) in frame.iter_mut().enumerate() {
+        let x = (i % 128) * 2;
+        *pixels = ((x / 16) << 4 | (x + 1) / 16) as u8;
+    }
+    disp.contrast(0).await.unwrap();
+    disp.flush(oled::PixelCoord(0, 0), oled::PixelCoord(256, 64), &frame)
+        .await
+        .unwrap();
+    disp.fade_in(15, 500).await.unwrap();
+
+    loop {
+        Timer::after_secs(1).await;
+    }
+}
@@ -76,7 +76,9 @@ fn main() -> ! {
         .pa9
         .into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper);
 
-    // Create the SpiInterface and Display.
+    // Create the SpiInterface and Display. The HAL's SPI device is full duplex, so image data is
+    // sent with non-blocking writes. A HAL offering only blocking SPI writes would use a
+    // BlockingSpiInterface instead.
     let mut disp = oled::Display::new(
         oled::SpiInterface::new(disp_spi, disp_dc),
         oled::PixelCoord(256, 64),
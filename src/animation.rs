@@ -0,0 +1,452 @@
+//! Tweening of positions, brightness levels, scroll offsets and other values over time, for smooth
+//! UI motion.
+//!
+//! Animations are driven by a millisecond timestamp from the application, such as a free-running
+//! timer or RTOS tick count, which may wrap around. A `Tween` gives the value of one quantity at
+//! any time. An `Animator` runs several of them for items on the display, and each frame reports
+//! the rectangles whose contents changed, ready to pass to `Scheduler::damage` or to redraw
+//! directly:
+//!
+//! ```
+//! # use ssd1322::animation::{Animator, Easing, Tween};
+//! # use ssd1322::display::{PixelCoord, Rect};
+//! # use ssd1322::scheduler::Scheduler;
+//! let mut animator = Animator::<4>::new();
+//! let mut scheduler = Scheduler::<4>::new();
+//! // Slide a 32x16 icon from the left edge to x = 64 over a quarter of a second.
+//! let slide = Tween::new(PixelCoord(0, 8), PixelCoord(64, 8), 1000, 250, Easing::EaseInOut);
+//! animator.animate_position(1, slide, PixelCoord(32, 16)).unwrap();
+//! animator.frame(1100, |id, rect| {
+//!     let _ = scheduler.damage(id, rect, 0);
+//! });
+//! // Redraw the icon at `animator.position(1)` when the damage is serviced.
+//! assert_eq!(scheduler.pending(), 1);
+//! ```
+
+use crate::display::{PixelCoord, Rect};
+
+/// A fraction of 1 in units of 1/65536, the precision of easing and interpolation.
+const ONE: u32 = 1 << 16;
+
+/// How a tween's value accelerates between its start and end.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    /// Constant speed throughout.
+    Linear,
+    /// Starting slowly and speeding up.
+    EaseIn,
+    /// Starting quickly and slowing down to a stop.
+    EaseOut,
+    /// Speeding up through the first half and slowing down through the second.
+    EaseInOut,
+}
+
+impl Easing {
+    /// Apply the easing curve to `t`, the fraction of the duration elapsed, giving the fraction of
+    /// the way from the start value to the end value. Both are fractions of `ONE`.
+    fn apply(self, t: u32) -> u32 {
+        let t = t.min(ONE) as u64;
+        let one = ONE as u64;
+        let eased = match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t / one,
+            Easing::EaseOut => one - (one - t) * (one - t) / one,
+            Easing::EaseInOut if t < one / 2 => 2 * t * t / one,
+            Easing::EaseInOut => one - 2 * (one - t) * (one - t) / one,
+        };
+        eased as u32
+    }
+}
+
+/// A value which can be interpolated between two others.
+pub trait Lerp: Copy + PartialEq {
+    /// The value `fraction` of the way from `from` to `to`, where `fraction` is in units of
+    /// 1/65536, rounded to the nearest value.
+    fn lerp(from: Self, to: Self, fraction: u32) -> Self;
+}
+
+macro_rules! lerp_int {
+    ($($t:ty),*) => {$(
+        impl Lerp for $t {
+            fn lerp(from: Self, to: Self, fraction: u32) -> Self {
+                let delta = to as i64 - from as i64;
+                (from as i64 + ((delta * fraction as i64 + (ONE / 2) as i64) >> 16)) as $t
+            }
+        }
+    )*};
+}
+
+lerp_int!(u8, u16, i16, i32);
+
+impl Lerp for PixelCoord {
+    fn lerp(from: Self, to: Self, fraction: u32) -> Self {
+        PixelCoord(
+            i16::lerp(from.0, to.0, fraction),
+            i16::lerp(from.1, to.1, fraction),
+        )
+    }
+}
+
+/// A value moving from `from` to `to` over `duration_ms` milliseconds from `start_ms`, following
+/// an easing curve. Before the start the value is `from`, and after the end it is `to`.
+///
+/// Times are compared with wrapping arithmetic, so a tween works across the timestamp wrapping
+/// around as long as it is less than about 24 days long. A start up to that far in the future
+/// delays the tween.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tween<T> {
+    from: T,
+    to: T,
+    start_ms: u32,
+    duration_ms: u32,
+    easing: Easing,
+}
+
+impl<T> Tween<T>
+where
+    T: Lerp,
+{
+    /// Construct a tween.
+    pub fn new(from: T, to: T, start_ms: u32, duration_ms: u32, easing: Easing) -> Self {
+        Tween {
+            from: from,
+            to: to,
+            start_ms: start_ms,
+            duration_ms: duration_ms,
+            easing: easing,
+        }
+    }
+
+    /// The value at time `now_ms`.
+    pub fn value(&self, now_ms: u32) -> T {
+        match self.elapsed(now_ms) {
+            None => self.from,
+            Some(elapsed) if elapsed >= self.duration_ms => self.to,
+            Some(elapsed) => {
+                let t = (elapsed as u64 * ONE as u64 / self.duration_ms as u64) as u32;
+                T::lerp(self.from, self.to, self.easing.apply(t))
+            }
+        }
+    }
+
+    /// Whether the value has reached its end at time `now_ms`.
+    pub fn is_finished(&self, now_ms: u32) -> bool {
+        self.elapsed(now_ms)
+            .is_some_and(|elapsed| elapsed >= self.duration_ms)
+    }
+
+    /// The value at the end of the tween.
+    pub fn target(&self) -> T {
+        self.to
+    }
+
+    /// Head for `to` instead, starting from the value at `now_ms` and taking `duration_ms` from
+    /// then, so that a moving item can change course without jumping.
+    pub fn retarget(&mut self, to: T, now_ms: u32, duration_ms: u32) {
+        self.from = self.value(now_ms);
+        self.to = to;
+        self.start_ms = now_ms;
+        self.duration_ms = duration_ms;
+    }
+
+    /// The milliseconds since the start, or `None` if the tween has not started yet.
+    fn elapsed(&self, now_ms: u32) -> Option<u32> {
+        let elapsed = now_ms.wrapping_sub(self.start_ms);
+        (elapsed <= i32::MAX as u32).then_some(elapsed)
+    }
+}
+
+/// What an animation changes, and the value shown as of the last frame.
+#[derive(Clone, Copy, Debug)]
+enum Motion {
+    /// An item `size` pixels wide and tall, with its upper left corner moving.
+    Position {
+        tween: Tween<PixelCoord>,
+        size: PixelCoord,
+        shown: PixelCoord,
+    },
+    /// A value such as a brightness level or scroll offset, changing what is shown in `area`.
+    Value {
+        tween: Tween<i32>,
+        area: Rect,
+        shown: i32,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Track {
+    id: u16,
+    motion: Motion,
+}
+
+impl Track {
+    fn is_finished(&self, now_ms: u32) -> bool {
+        match self.motion {
+            Motion::Position { tween, shown, .. } => {
+                tween.is_finished(now_ms) && shown == tween.target()
+            }
+            Motion::Value { tween, shown, .. } => {
+                tween.is_finished(now_ms) && shown == tween.target()
+            }
+        }
+    }
+}
+
+/// A set of up to `N` animations of items on the display, each identified by an `id` chosen by
+/// the application as for `Scheduler::damage`.
+///
+/// Once an animation has finished and its final value has been reported by `frame`, it stays
+/// available through `position` or `value` until its slot is needed for a new animation.
+pub struct Animator<const N: usize> {
+    tracks: [Option<Track>; N],
+    /// The time of the last frame.
+    now_ms: u32,
+}
+
+impl<const N: usize> Animator<N> {
+    /// Construct an animator with no animations.
+    pub const fn new() -> Self {
+        Animator {
+            tracks: [None; N],
+            now_ms: 0,
+        }
+    }
+
+    /// Move the item `id`, `size` pixels wide and tall, with its upper left corner following
+    /// `tween`. The damage reported each frame covers where the item was drawn in the previous
+    /// frame as well as where it is now, so that its old position gets erased.
+    ///
+    /// Replaces any animation of `id` already running. Returns the tween back if all `N` slots
+    /// are taken by other animations still running.
+    pub fn animate_position(
+        &mut self,
+        id: u16,
+        tween: Tween<PixelCoord>,
+        size: PixelCoord,
+    ) -> Result<(), Tween<PixelCoord>> {
+        let shown = match self.find(id).map(|track| track.motion) {
+            Some(Motion::Position { shown, .. }) => shown,
+            _ => tween.value(self.now_ms),
+        };
+        let motion = Motion::Position {
+            tween: tween,
+            size: size,
+            shown: shown,
+        };
+        self.insert(id, motion).map_err(|_| tween)
+    }
+
+    /// Change a value of the item `id` following `tween`, such as its brightness level or a scroll
+    /// offset, reporting `area` as damaged each frame the value changes.
+    ///
+    /// Replaces any animation of `id` already running. Returns the tween back if all `N` slots
+    /// are taken by other animations still running.
+    pub fn animate_value(
+        &mut self,
+        id: u16,
+        tween: Tween<i32>,
+        area: Rect,
+    ) -> Result<(), Tween<i32>> {
+        let shown = match self.find(id).map(|track| track.motion) {
+            Some(Motion::Value { shown, .. }) => shown,
+            _ => tween.value(self.now_ms),
+        };
+        let motion = Motion::Value {
+            tween: tween,
+            area: area,
+            shown: shown,
+        };
+        self.insert(id, motion).map_err(|_| tween)
+    }
+
+    /// Advance every animation to time `now_ms`, calling `damage` with the id of each item whose
+    /// position or value changed since the last frame and the rectangle needing redrawing.
+    ///
+    /// Returns the number of animations still running, so that the application can stop calling
+    /// this when it reaches 0.
+    pub fn frame<F>(&mut self, now_ms: u32, mut damage: F) -> usize
+    where
+        F: FnMut(u16, Rect),
+    {
+        self.now_ms = now_ms;
+        let mut running = 0;
+        for track in self.tracks.iter_mut().flatten() {
+            if track.is_finished(now_ms) {
+                continue;
+            }
+            match &mut track.motion {
+                Motion::Position { tween, size, shown } => {
+                    let now = tween.value(now_ms);
+                    if now != *shown {
+                        let old = Rect::new(*shown, *shown + *size);
+                        damage(track.id, old.union(&Rect::new(now, now + *size)));
+                        *shown = now;
+                    }
+                    running += !tween.is_finished(now_ms) as usize;
+                }
+                Motion::Value { tween, area, shown } => {
+                    let now = tween.value(now_ms);
+                    if now != *shown {
+                        damage(track.id, *area);
+                        *shown = now;
+                    }
+                    running += !tween.is_finished(now_ms) as usize;
+                }
+            }
+        }
+        running
+    }
+
+    /// The position of the item `id` as of the last frame, if it is moved by an animation.
+    pub fn position(&self, id: u16) -> Option<PixelCoord> {
+        match self.find(id)?.motion {
+            Motion::Position { shown, .. } => Some(shown),
+            _ => None,
+        }
+    }
+
+    /// The value of the item `id` as of the last frame, if it is changed by an animation.
+    pub fn value(&self, id: u16) -> Option<i32> {
+        match self.find(id)?.motion {
+            Motion::Value { shown, .. } => Some(shown),
+            _ => None,
+        }
+    }
+
+    /// Stop animating the item `id`, leaving it where it was last shown.
+    pub fn remove(&mut self, id: u16) {
+        for slot in self.tracks.iter_mut() {
+            if slot.is_some_and(|track| track.id == id) {
+                *slot = None;
+            }
+        }
+    }
+
+    fn find(&self, id: u16) -> Option<&Track> {
+        self.tracks.iter().flatten().find(|track| track.id == id)
+    }
+
+    /// Put an animation in the slot of `id`, or else an empty slot, or else the slot of one which
+    /// has finished.
+    fn insert(&mut self, id: u16, motion: Motion) -> Result<(), ()> {
+        let now_ms = self.now_ms;
+        let slot = match self
+            .tracks
+            .iter()
+            .position(|slot| slot.is_some_and(|t| t.id == id))
+        {
+            Some(index) => Some(index),
+            None => self.tracks.iter().position(|slot| match slot {
+                None => true,
+                Some(track) => track.is_finished(now_ms),
+            }),
+        };
+        match slot {
+            Some(index) => {
+                self.tracks[index] = Some(Track {
+                    id: id,
+                    motion: motion,
+                });
+                Ok(())
+            }
+            None => Err(()),
+        }
+    }
+}
+
+impl<const N: usize> Default for Animator<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use std::vec::Vec;
+
+    #[test]
+    fn easing() {
+        let at = |easing: Easing, t| easing.apply(t) * 100 / ONE;
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ] {
+            assert_eq!(easing.apply(0), 0);
+            assert_eq!(easing.apply(ONE), ONE);
+        }
+        assert_eq!(at(Easing::Linear, ONE / 4), 25);
+        assert_eq!(at(Easing::EaseIn, ONE / 2), 25);
+        assert_eq!(at(Easing::EaseOut, ONE / 2), 75);
+        assert_eq!(at(Easing::EaseInOut, ONE / 4), 12);
+        assert_eq!(at(Easing::EaseInOut, ONE / 2), 50);
+        assert_eq!(at(Easing::EaseInOut, ONE * 3 / 4), 87);
+    }
+
+    #[test]
+    fn tween_values() {
+        let tween = Tween::new(10u8, 0, 100, 100, Easing::Linear);
+        assert_eq!(tween.value(50), 10);
+        assert_eq!(tween.value(100), 10);
+        assert_eq!(tween.value(125), 8);
+        assert_eq!(tween.value(150), 5);
+        assert!(!tween.is_finished(199));
+        assert_eq!(tween.value(200), 0);
+        assert!(tween.is_finished(5000));
+        // Across the timestamp wrapping around.
+        let tween = Tween::new(Px(0, -8), Px(16, 8), u32::MAX - 9, 20, Easing::Linear);
+        assert_eq!(tween.value(u32::MAX - 20), Px(0, -8));
+        assert_eq!(tween.value(0), Px(8, 0));
+        assert_eq!(tween.value(10), Px(16, 8));
+    }
+
+    #[test]
+    fn retarget() {
+        let mut tween = Tween::new(0i32, 100, 0, 100, Easing::Linear);
+        tween.retarget(0, 40, 20);
+        assert_eq!(tween.value(40), 40);
+        assert_eq!(tween.value(50), 20);
+        assert_eq!(tween.target(), 0);
+    }
+
+    #[test]
+    fn animator_damage() {
+        let mut animator = Animator::<2>::new();
+        let slide = Tween::new(Px(0, 0), Px(8, 0), 0, 4, Easing::Linear);
+        animator.animate_position(1, slide, Px(4, 2)).unwrap();
+        let fade = Tween::new(0, 15, 2, 2, Easing::Linear);
+        let area = Rect::new(Px(0, 8), Px(16, 16));
+        animator.animate_value(2, fade, area).unwrap();
+        // Both slots are taken by running animations.
+        assert!(animator.animate_value(3, fade, area).is_err());
+
+        let mut frame = |now| {
+            let mut damage = Vec::new();
+            let running = animator.frame(now, |id, rect| damage.push((id, rect)));
+            (running, damage)
+        };
+        assert_eq!(frame(0), (2, vec![]));
+        assert_eq!(frame(2), (2, vec![(1, Rect::new(Px(0, 0), Px(8, 2)))]));
+        assert_eq!(
+            frame(3),
+            (2, vec![(1, Rect::new(Px(4, 0), Px(10, 2))), (2, area)])
+        );
+        assert_eq!(
+            frame(9),
+            (0, vec![(1, Rect::new(Px(6, 0), Px(12, 2))), (2, area)])
+        );
+        assert_eq!(frame(10), (0, vec![]));
+        assert_eq!(animator.position(1), Some(Px(8, 0)));
+        assert_eq!(animator.value(2), Some(15));
+        assert_eq!(animator.value(1), None);
+
+        // Finished animations make way for new ones.
+        animator.animate_value(3, fade, area).unwrap();
+        assert_eq!(animator.position(1), None);
+        animator.remove(2);
+        assert_eq!(animator.value(2), None);
+    }
+}
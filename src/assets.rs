@@ -0,0 +1,308 @@
+//! Conversion of images into packed 4-bit assets at build time, for use from `build.rs` or asset
+//! tools running on the host.
+//!
+//! `Asset::load` reads a PNG, BMP, PGM or PBM file and converts it to the panel's 16 gray levels,
+//! optionally with dithering. The result can be written out as Rust source declaring a `Sprite`
+//! with the image data inline, or as a raw binary of packed pixels together with the source
+//! declaring a `Sprite` which includes it:
+//!
+//! ```no_run
+//! // build.rs
+//! use ssd1322::assets::{Asset, Conversion};
+//!
+//! let out = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+//! let logo = Asset::load("assets/logo.png", Conversion::Dither).unwrap();
+//! std::fs::write(out.join("logo.bin"), logo.data()).unwrap();
+//! std::fs::write(out.join("logo.rs"), logo.rust_include("LOGO", "logo.bin")).unwrap();
+//! ```
+//!
+//! and in the firmware, `include!(concat!(env!("OUT_DIR"), "/logo.rs"));` declares `LOGO`.
+//!
+//! Color images are converted to their luma, and transparent pixels are blended onto black.
+//!
+//! Available with the `assets` feature.
+
+use std::fmt::{self, Write};
+use std::io;
+use std::path::Path;
+use std::string::String;
+use std::vec::Vec;
+
+use embedded_graphics_core::pixelcolor::{Rgb888, RgbColor};
+use embedded_graphics_core::prelude::OriginDimensions;
+
+use crate::image::dither::Dither;
+use crate::image::netpbm::{Netpbm, NetpbmError};
+use crate::image::{luma, Gray8To4};
+use crate::sprite::Sprite;
+
+/// Errors which can occur while loading an asset.
+#[derive(Debug)]
+pub enum AssetError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The file is not a PNG, BMP, PGM or PBM image.
+    UnknownFormat,
+    /// The image could not be decoded. The message describes why.
+    Decode(String),
+    /// The image is wider or taller than 65535 pixels.
+    TooLarge,
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Io(e) => write!(f, "{}", e),
+            AssetError::UnknownFormat => f.write_str("not a PNG, BMP, PGM or PBM image"),
+            AssetError::Decode(msg) => write!(f, "bad image: {}", msg),
+            AssetError::TooLarge => f.write_str("image is too large"),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+impl From<io::Error> for AssetError {
+    fn from(e: io::Error) -> Self {
+        AssetError::Io(e)
+    }
+}
+
+/// How 8-bit gray values are reduced to the panel's 4-bit gray levels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Conversion {
+    /// Round each pixel to the nearest level. Best for line art, icons and text.
+    Round,
+    /// Floyd-Steinberg error diffusion, spreading the rounding error onto neighbouring pixels.
+    /// Best for photographs and gradients, which would otherwise show banding.
+    Dither,
+}
+
+/// An image converted to packed 4-bit pixels, in the same format as `Sprite`: each row starts on a
+/// new byte, with the leftmost of each pair of pixels in the upper nibble.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Asset {
+    width: u16,
+    height: u16,
+    data: Vec<u8>,
+}
+
+impl Asset {
+    /// Load the image file at `path` and convert it with `conversion`. The format is detected
+    /// from the contents of the file.
+    pub fn load<P: AsRef<Path>>(path: P, conversion: Conversion) -> Result<Self, AssetError> {
+        Self::decode(&std::fs::read(path)?, conversion)
+    }
+
+    /// Decode an image held in memory, as with `load`.
+    pub fn decode(bytes: &[u8], conversion: Conversion) -> Result<Self, AssetError> {
+        let (width, height, gray) = if bytes.starts_with(b"\x89PNG") {
+            decode_png(bytes)?
+        } else if bytes.starts_with(b"BM") {
+            decode_bmp(bytes)?
+        } else if bytes.starts_with(b"P4") || bytes.starts_with(b"P5") {
+            decode_netpbm(bytes)?
+        } else {
+            return Err(AssetError::UnknownFormat);
+        };
+        if width > u16::MAX as usize || height > u16::MAX as usize {
+            return Err(AssetError::TooLarge);
+        }
+        Ok(Self::from_gray8(
+            width as u16,
+            height as u16,
+            &gray,
+            conversion,
+        ))
+    }
+
+    /// Convert an image of 8-bit gray values, in left-to-right, top-to-bottom order, with
+    /// `conversion`.
+    ///
+    /// Panics if `gray` is not `width * height` values long.
+    pub fn from_gray8(width: u16, height: u16, gray: &[u8], conversion: Conversion) -> Self {
+        assert_eq!(gray.len(), width as usize * height as usize);
+        let values = gray.iter().cloned();
+        let levels: Vec<u8> = match conversion {
+            Conversion::Round => Gray8To4(values).collect(),
+            Conversion::Dither => {
+                let mut errors = vec![0; width as usize];
+                Dither::new(values, &mut errors).collect()
+            }
+        };
+        let mut data = Vec::with_capacity((width as usize).div_ceil(2) * height as usize);
+        for row in levels.chunks(width.max(1) as usize) {
+            data.extend(
+                row.chunks(2)
+                    .map(|pair| pair[0] << 4 | pair.get(1).unwrap_or(&0)),
+            );
+        }
+        Asset {
+            width: width,
+            height: height,
+            data: data,
+        }
+    }
+
+    /// The width of the image in pixels.
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// The height of the image in pixels.
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// The packed pixel data, as written to a raw binary.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The image as a sprite, to draw it on the host, for example into a simulator.
+    pub fn sprite(&self) -> Sprite<'_> {
+        Sprite::new(self.width, self.height, &self.data)
+    }
+
+    /// Rust source declaring a `pub const` sprite called `name` with the image data inline.
+    pub fn rust_source(&self, name: &str) -> String {
+        let mut src = String::new();
+        let _ = writeln!(
+            src,
+            "pub const {}: ::ssd1322::sprite::Sprite<'static> = ::ssd1322::gray4_image!({}, {}, &[",
+            name, self.width, self.height
+        );
+        for row in self.data.chunks(16) {
+            src.push_str("   ");
+            for byte in row {
+                let _ = write!(src, " 0x{:02X},", byte);
+            }
+            src.push('\n');
+        }
+        src.push_str("]);\n");
+        src
+    }
+
+    /// Rust source declaring a `pub const` sprite called `name` with the image data included from
+    /// the raw binary at `bin_path`, resolved as by `include_bytes!`.
+    pub fn rust_include(&self, name: &str, bin_path: &str) -> String {
+        format!(
+            "pub const {}: ::ssd1322::sprite::Sprite<'static> = \
+             ::ssd1322::gray4_image!({}, {}, include_bytes!({:?}));\n",
+            name, self.width, self.height, bin_path
+        )
+    }
+}
+
+/// Decode a PNG into its size and 8-bit gray values.
+fn decode_png(bytes: &[u8]) -> Result<(usize, usize, Vec<u8>), AssetError> {
+    let decode_error = |e: png::DecodingError| AssetError::Decode(e.to_string());
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(decode_error)?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buf).map_err(decode_error)?;
+    let buf = &buf[..frame.buffer_size()];
+    // Blend `value` onto black by `alpha`.
+    let blend = |value: u8, alpha: u8| (value as u16 * alpha as u16 / 255) as u8;
+    let gray = match frame.color_type {
+        png::ColorType::Grayscale => buf.to_vec(),
+        png::ColorType::GrayscaleAlpha => buf.chunks(2).map(|p| blend(p[0], p[1])).collect(),
+        png::ColorType::Rgb => buf.chunks(3).map(|p| luma(p[0], p[1], p[2])).collect(),
+        png::ColorType::Rgba => buf
+            .chunks(4)
+            .map(|p| blend(luma(p[0], p[1], p[2]), p[3]))
+            .collect(),
+        png::ColorType::Indexed => {
+            return Err(AssetError::Decode("indexed color was not expanded".into()))
+        }
+    };
+    Ok((frame.width as usize, frame.height as usize, gray))
+}
+
+/// Decode a BMP into its size and 8-bit gray values.
+fn decode_bmp(bytes: &[u8]) -> Result<(usize, usize, Vec<u8>), AssetError> {
+    let bmp = tinybmp::Bmp::<Rgb888>::from_slice(bytes)
+        .map_err(|e| AssetError::Decode(format!("{:?}", e)))?;
+    let size = bmp.size();
+    let gray = bmp
+        .pixels()
+        .map(|pixel| luma(pixel.1.r(), pixel.1.g(), pixel.1.b()))
+        .collect();
+    Ok((size.width as usize, size.height as usize, gray))
+}
+
+/// Decode a PGM or PBM into its size and 8-bit gray values.
+fn decode_netpbm(bytes: &[u8]) -> Result<(usize, usize, Vec<u8>), AssetError> {
+    let image = Netpbm::parse(bytes).map_err(|e| match e {
+        NetpbmError::BadMagic => AssetError::UnknownFormat,
+        e => AssetError::Decode(format!("{:?}", e)),
+    })?;
+    // The decoder yields 4-bit levels, which map exactly onto 8-bit values.
+    let gray = image.pixels().map(|level| level * 17).collect();
+    Ok((image.width() as usize, image.height() as usize, gray))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode an RGBA image as a PNG.
+    fn png(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(rgba).unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
+
+    #[test]
+    fn decode_formats() {
+        // White, half-transparent white, red, black; the odd width leaves a padding nibble.
+        #[rustfmt::skip]
+        let rgba = [
+            255, 255, 255, 255, 255, 255, 255, 128, 255, 0, 0, 255,
+            0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0, 255,
+        ];
+        let asset = Asset::decode(&png(3, 2, &rgba), Conversion::Round).unwrap();
+        assert_eq!((asset.width(), asset.height()), (3, 2));
+        assert_eq!(asset.data(), &[0xF8, 0x50, 0x00, 0x00]);
+        assert_eq!(asset.sprite().pixel(2, 0), Some(5));
+
+        let pgm = b"P5 2 1 255\n\x00\xFF";
+        let asset = Asset::decode(pgm, Conversion::Round).unwrap();
+        assert_eq!(asset.data(), &[0x0F]);
+
+        assert!(matches!(
+            Asset::decode(b"GIF89a", Conversion::Round),
+            Err(AssetError::UnknownFormat)
+        ));
+    }
+
+    #[test]
+    fn dither() {
+        let gray = [144; 64];
+        let round = Asset::from_gray8(8, 8, &gray, Conversion::Round);
+        assert!(round.data().iter().all(|&b| b == 0x88));
+        let dithered = Asset::from_gray8(8, 8, &gray, Conversion::Dither);
+        assert!(dithered.data().iter().any(|&b| b != 0x88));
+    }
+
+    #[test]
+    fn rust_source() {
+        let asset = Asset::from_gray8(2, 1, &[0, 255], Conversion::Round);
+        assert_eq!(
+            asset.rust_source("DOT"),
+            "pub const DOT: ::ssd1322::sprite::Sprite<'static> = \
+             ::ssd1322::gray4_image!(2, 1, &[\n    0x0F,\n]);\n"
+        );
+        assert_eq!(
+            asset.rust_include("DOT", "dot.bin"),
+            "pub const DOT: ::ssd1322::sprite::Sprite<'static> = \
+             ::ssd1322::gray4_image!(2, 1, include_bytes!(\"dot.bin\"));\n"
+        );
+    }
+}
@@ -0,0 +1,480 @@
+//! An `async` counterpart to `Display`, for executors such as Embassy where the display should not
+//! block other tasks while commands and image data are on the bus.
+//!
+//! `AsyncDisplay` drives an `AsyncDisplayInterface`, which is small enough to implement over any
+//! async HAL, and waits with `embassy_time::Timer` where the blocking driver would use a blocking
+//! delay, so the application must provide an Embassy time driver.
+//!
+//! Available with the `embassy` feature.
+//!
+//! Commands are encoded by the same code as the blocking driver: each command sequence is first
+//! recorded into a small buffer by a `CommandRecorder`, then replayed to the interface.
+
+// The futures returned by these traits are deliberately not required to be `Send`, since embedded
+// executors run tasks on a single core.
+#![allow(async_fn_in_trait)]
+
+use crate::chip::{Chip, Ssd1322};
+use crate::command::{Command, CommandError};
+use crate::config::{Config, PersistentConfig};
+use crate::display::region::Pack8to4;
+use crate::display::{check_geometry, region_in_bounds, send_init_sequence, PixelCoord};
+use crate::interface::DisplayInterface;
+use embassy_time::Timer;
+
+/// An interface for the SSD1322 with `async` operations for sending pre-encoded commands and data
+/// to the chip.
+pub trait AsyncDisplayInterface {
+    type Error;
+
+    async fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error>;
+    async fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// The number of command and data words a `CommandRecorder` can hold, which is enough for the
+/// longest sequence the driver sends, the init sequence.
+const RECORDER_WORDS: usize = 64;
+
+/// A `DisplayInterface` which records the commands and data sent to it, so that the blocking
+/// command encoding can be reused and the result sent asynchronously.
+struct CommandRecorder {
+    words: [u8; RECORDER_WORDS],
+    /// Whether each word is a command rather than data.
+    is_command: [bool; RECORDER_WORDS],
+    len: usize,
+}
+
+/// The error given by a `CommandRecorder` when a sequence does not fit in it.
+#[derive(Debug)]
+struct RecorderFull;
+
+impl CommandRecorder {
+    fn new() -> Self {
+        CommandRecorder {
+            words: [0; RECORDER_WORDS],
+            is_command: [false; RECORDER_WORDS],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, word: u8, is_command: bool) -> Result<(), RecorderFull> {
+        if self.len == RECORDER_WORDS {
+            return Err(RecorderFull);
+        }
+        self.words[self.len] = word;
+        self.is_command[self.len] = is_command;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Send the recorded sequence to `iface`, with each run of data words in a single transfer.
+    async fn replay<DI>(&self, iface: &mut DI) -> Result<(), DI::Error>
+    where
+        DI: AsyncDisplayInterface,
+    {
+        let mut i = 0;
+        while i < self.len {
+            if self.is_command[i] {
+                iface.send_command(self.words[i]).await?;
+                i += 1;
+            } else {
+                let start = i;
+                while i < self.len && !self.is_command[i] {
+                    i += 1;
+                }
+                iface.send_data(&self.words[start..i]).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DisplayInterface for CommandRecorder {
+    type Error = RecorderFull;
+
+    fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+        self.push(cmd, true)
+    }
+
+    fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        buf.iter().try_for_each(|word| self.push(*word, false))
+    }
+
+    #[cfg(feature = "nb")]
+    fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.push(word, false).map_err(nb::Error::Other)
+    }
+}
+
+/// Record the command sequence produced by `f` and send it to `iface`. Running out of room in the
+/// recorder is a bug in the driver, so it panics rather than returning an error.
+async fn send_recorded<DI, F>(iface: &mut DI, f: F) -> Result<(), CommandError<DI::Error>>
+where
+    DI: AsyncDisplayInterface,
+    F: FnOnce(&mut CommandRecorder) -> Result<(), CommandError<RecorderFull>>,
+{
+    let mut recorder = CommandRecorder::new();
+    match f(&mut recorder) {
+        Ok(()) => {}
+        Err(CommandError::InterfaceError(RecorderFull)) => panic!("Command sequence too long"),
+        Err(CommandError::OutOfRange) => return Err(CommandError::OutOfRange),
+        Err(CommandError::BadTableLength) => return Err(CommandError::BadTableLength),
+        Err(CommandError::BadRowLength) => return Err(CommandError::BadRowLength),
+        Err(CommandError::BadDataLength { expected, actual }) => {
+            return Err(CommandError::BadDataLength {
+                expected: expected,
+                actual: actual,
+            })
+        }
+        Err(CommandError::Uninitialized) => return Err(CommandError::Uninitialized),
+        Err(CommandError::VerifyFailed) => return Err(CommandError::VerifyFailed),
+        Err(CommandError::Busy) => return Err(CommandError::Busy),
+        Err(CommandError::BlockingInterface) => return Err(CommandError::BlockingInterface),
+        Err(CommandError::Locked) => return Err(CommandError::Locked),
+        Err(CommandError::BadMuxRatio { ratio }) => {
+            return Err(CommandError::BadMuxRatio { ratio: ratio })
+        }
+    }
+    recorder
+        .replay(iface)
+        .await
+        .map_err(CommandError::InterfaceError)
+}
+
+/// An `async` driver for an SSD1322 display. See `Display` for the meaning of the methods.
+pub struct AsyncDisplay<DI>
+where
+    DI: AsyncDisplayInterface,
+{
+    iface: DI,
+    display_size: PixelCoord,
+    display_offset: PixelCoord,
+    persistent_config: Option<PersistentConfig>,
+    master_contrast: u8,
+}
+
+impl<DI> AsyncDisplay<DI>
+where
+    DI: AsyncDisplayInterface,
+{
+    /// Construct a new display driver, with the same arguments as `Display::new`.
+    pub fn new(iface: DI, display_size: PixelCoord, display_offset: PixelCoord) -> Self {
+        check_geometry::<Ssd1322>(display_size, display_offset);
+        AsyncDisplay {
+            iface: iface,
+            display_size: display_size,
+            display_offset: display_offset,
+            persistent_config: None,
+            master_contrast: 15,
+        }
+    }
+
+    /// Borrow the interface the display is connected to.
+    pub fn interface(&self) -> &DI {
+        &self.iface
+    }
+
+    /// Initialize the display with a config message.
+    pub async fn init(&mut self, config: Config) -> Result<(), CommandError<DI::Error>> {
+        let (size, offset) = (self.display_size, self.display_offset);
+        let mut persistent_config = None;
+        send_recorded(&mut self.iface, |r| {
+            persistent_config = Some(send_init_sequence(r, config, size, offset)?);
+            Ok(())
+        })
+        .await?;
+        self.persistent_config = persistent_config;
+        Ok(())
+    }
+
+    /// Control sleep mode.
+    pub async fn sleep(&mut self, enabled: bool) -> Result<(), CommandError<DI::Error>> {
+        send_recorded(&mut self.iface, |r| Command::SetSleepMode(enabled).send(r)).await
+    }
+
+    /// Control the master contrast.
+    pub async fn contrast(&mut self, contrast: u8) -> Result<(), CommandError<DI::Error>> {
+        send_recorded(&mut self.iface, |r| {
+            Command::SetMasterContrast(contrast).send(r)
+        })
+        .await?;
+        self.master_contrast = contrast;
+        Ok(())
+    }
+
+    /// Fade the display out to black over roughly `duration_ms` milliseconds.
+    pub async fn fade_out(&mut self, duration_ms: u16) -> Result<(), CommandError<DI::Error>> {
+        self.fade_to(0, duration_ms).await
+    }
+
+    /// Fade the display in to master contrast `contrast` over roughly `duration_ms` milliseconds.
+    pub async fn fade_in(
+        &mut self,
+        contrast: u8,
+        duration_ms: u16,
+    ) -> Result<(), CommandError<DI::Error>> {
+        if contrast > 15 {
+            return Err(CommandError::OutOfRange);
+        }
+        self.fade_to(contrast, duration_ms).await
+    }
+
+    /// Step the master contrast one level at a time towards `target`, waiting between steps.
+    async fn fade_to(
+        &mut self,
+        target: u8,
+        duration_ms: u16,
+    ) -> Result<(), CommandError<DI::Error>> {
+        let steps = (self.master_contrast as i16 - target as i16).unsigned_abs();
+        if steps == 0 {
+            return Ok(());
+        }
+        let step_delay = duration_ms / steps;
+        while self.master_contrast != target {
+            Timer::after_millis(step_delay as u64).await;
+            let next = if self.master_contrast < target {
+                self.master_contrast + 1
+            } else {
+                self.master_contrast - 1
+            };
+            self.contrast(next).await?;
+        }
+        Ok(())
+    }
+
+    /// Set the vertical pan.
+    pub async fn vertical_pan(&mut self, offset: u8) -> Result<(), CommandError<DI::Error>> {
+        send_recorded(&mut self.iface, |r| Command::SetStartLine(offset).send(r)).await
+    }
+
+    /// Construct a rectangular region onto which to draw image data, with the same restrictions
+    /// as `Display::region`.
+    pub fn region<'di>(
+        &'di mut self,
+        upper_left: PixelCoord,
+        lower_right: PixelCoord,
+    ) -> Result<AsyncRegion<'di, DI>, CommandError<DI::Error>> {
+        if !region_in_bounds::<Ssd1322>(self.display_size, upper_left, lower_right) {
+            return Err(CommandError::OutOfRange);
+        }
+        let left = upper_left.0 + self.display_offset.0;
+        let right = lower_right.0 + self.display_offset.0;
+        let cols = Ssd1322::PIXELS_PER_COLUMN as i16;
+        Ok(AsyncRegion {
+            iface: &mut self.iface,
+            cols: ((left / cols) as u8, (right / cols - 1) as u8),
+            rows: (upper_left.1 as u8, (lower_right.1 - 1) as u8),
+            total_bytes: (right - left) as usize * (lower_right.1 - upper_left.1) as usize / 2,
+        })
+    }
+
+    /// Send `data`, packed pixels as for `AsyncRegion::draw_packed`, to the region with corners
+    /// `upper_left` and `lower_right`, in one transfer. This is the counterpart of
+    /// `Display::flush`: rather than being fed by polling, the task awaiting it yields while the
+    /// interface sends, for example by DMA.
+    ///
+    /// The region has the same restrictions as for `region`, and `data` must fill it exactly, or
+    /// `CommandError::BadDataLength` is returned without sending anything.
+    pub async fn flush(
+        &mut self,
+        upper_left: PixelCoord,
+        lower_right: PixelCoord,
+        data: &[u8],
+    ) -> Result<(), CommandError<DI::Error>> {
+        let mut region = self.region(upper_left, lower_right)?;
+        if data.len() != region.total_bytes {
+            return Err(CommandError::BadDataLength {
+                expected: region.total_bytes,
+                actual: data.len(),
+            });
+        }
+        region.begin().await.map_err(CommandError::InterfaceError)?;
+        region
+            .iface
+            .send_data(data)
+            .await
+            .map_err(CommandError::InterfaceError)
+    }
+}
+
+/// A handle to a rectangular region of an `AsyncDisplay` which can be drawn into.
+pub struct AsyncRegion<'di, DI>
+where
+    DI: 'di + AsyncDisplayInterface,
+{
+    iface: &'di mut DI,
+    /// The inclusive ranges of column addresses and rows.
+    cols: (u8, u8),
+    rows: (u8, u8),
+    total_bytes: usize,
+}
+
+impl<'di, DI> AsyncRegion<'di, DI>
+where
+    DI: 'di + AsyncDisplayInterface,
+{
+    /// Set up the address window of the region and start writing image data.
+    async fn begin(&mut self) -> Result<(), DI::Error> {
+        let (cols, rows) = (self.cols, self.rows);
+        // Unwrap the CommandError as an interface error, as all bounds checking should be done by
+        // the time we are here.
+        send_recorded(self.iface, |r| Ssd1322::begin_write(r, cols, rows))
+            .await
+            .map_err(CommandError::unwrap_interface)
+    }
+
+    /// Draw packed-pixel image data into the region, such that each byte is two 4-bit gray scale
+    /// values of horizontally-adjacent pixels. Pixels are drawn left-to-right and top-to-bottom,
+    /// and sent to the interface in chunks.
+    pub async fn draw_packed<I>(&mut self, iter: I) -> Result<(), DI::Error>
+    where
+        I: Iterator<Item = u8>,
+    {
+        self.begin().await?;
+
+        let mut chunk = [0u8; 32];
+        let mut len = 0;
+        for pixels in iter.take(self.total_bytes) {
+            chunk[len] = pixels;
+            len += 1;
+            if len == chunk.len() {
+                self.iface.send_data(&chunk).await?;
+                len = 0;
+            }
+        }
+        if len > 0 {
+            self.iface.send_data(&chunk[..len]).await?;
+        }
+        Ok(())
+    }
+
+    /// Draw unpacked pixel image data into the region, where each byte independently represents a
+    /// single pixel intensity value in the range [0, 15]. Pixels are drawn left-to-right and
+    /// top-to-bottom.
+    pub async fn draw<I>(&mut self, iter: I) -> Result<(), DI::Error>
+    where
+        I: Iterator<Item = u8>,
+    {
+        self.draw_packed(Pack8to4(iter)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{ComLayout, ComScanDirection};
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use embassy_time::{Duration, Instant, MockDriver};
+
+    impl AsyncDisplayInterface for MockInterface {
+        type Error = core::convert::Infallible;
+
+        async fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+            DisplayInterface::send_command(self, cmd)
+        }
+        async fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+            DisplayInterface::send_data(self, buf)
+        }
+    }
+
+    /// Run a future which never has to wait to completion.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("Future was not ready"),
+        }
+    }
+
+    /// Run a future to completion, advancing the mock time driver by a millisecond whenever it has
+    /// to wait. Returns its output and the number of milliseconds it took.
+    fn run_timed<F: Future>(future: F) -> (F::Output, u64) {
+        let start = Instant::now();
+        let mut future = pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return (output, start.elapsed().as_millis());
+            }
+            MockDriver::get().advance(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn init_matches_blocking() {
+        let sync_di = MockInterface::new();
+        let mut disp = crate::display::Display::new(sync_di.split(), Px(128, 64), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive)
+            .contrast_current(0x9F)
+            .phase_lengths(5, 14);
+        disp.init(cfg.clone()).unwrap();
+
+        let async_di = MockInterface::new();
+        let mut disp = AsyncDisplay::new(async_di.split(), Px(128, 64), Px(0, 0));
+        block_on(disp.init(cfg)).unwrap();
+        assert_eq!(async_di.sent(), sync_di.sent());
+    }
+
+    #[test]
+    fn draw_region() {
+        let mut di = MockInterface::new();
+        let mut disp = AsyncDisplay::new(di.split(), Px(128, 64), Px(0, 0));
+        block_on(async {
+            let mut region = disp.region(Px(12, 10), Px(16, 12)).unwrap();
+            region.draw_packed(0..10).await
+        })
+        .unwrap();
+        di.check_multi(&[
+            Sent::Cmd(0x15),
+            Sent::Data(vec![3, 3]),
+            Sent::Cmd(0x75),
+            Sent::Data(vec![10, 11]),
+            Sent::Cmd(0x5C),
+            Sent::Data(vec![0, 1, 2, 3]),
+        ]);
+        di.clear();
+        assert_eq!(block_on(disp.contrast(16)), Err(CommandError::OutOfRange));
+        assert!(disp.region(Px(13, 10), Px(16, 12)).is_err());
+        assert!(di.sent().is_empty());
+    }
+
+    #[test]
+    fn flush() {
+        let mut di = MockInterface::new();
+        let mut disp = AsyncDisplay::new(di.split(), Px(128, 64), Px(0, 0));
+        let data = [0x12, 0x34, 0x56, 0x78];
+        block_on(disp.flush(Px(12, 10), Px(16, 12), &data)).unwrap();
+        di.check_multi(&[
+            Sent::Cmd(0x15),
+            Sent::Data(vec![3, 3]),
+            Sent::Cmd(0x75),
+            Sent::Data(vec![10, 11]),
+            Sent::Cmd(0x5C),
+            Sent::Data(data.to_vec()),
+        ]);
+        di.clear();
+        assert_eq!(
+            block_on(disp.flush(Px(12, 10), Px(16, 12), &data[1..])),
+            Err(CommandError::BadDataLength {
+                expected: 4,
+                actual: 3
+            })
+        );
+        assert!(di.sent().is_empty());
+    }
+
+    #[test]
+    fn fade() {
+        let di = MockInterface::new();
+        let mut disp = AsyncDisplay::new(di.split(), Px(128, 64), Px(0, 0));
+        let (result, elapsed) = run_timed(disp.fade_out(300));
+        result.unwrap();
+        assert_eq!(elapsed, 300);
+        assert_eq!(di.sent().len(), 30);
+    }
+}
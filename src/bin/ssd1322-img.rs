@@ -0,0 +1,197 @@
+//! Convert images to packed 4-bit gray assets for the SSD1322 driver. Run with `--help` for usage.
+//!
+//! Built with the `cli` feature: `cargo install ssd1322 --features cli`.
+
+// Explicit `field: field` initializers are deliberate house style, as in the library.
+#![allow(clippy::redundant_field_names)]
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use ssd1322::assets::{Asset, Conversion};
+use ssd1322::image::heatshrink;
+
+const USAGE: &str = "\
+Usage: ssd1322-img [OPTIONS] INPUT OUTPUT
+
+Convert a PNG, BMP, PGM or PBM image to packed 4-bit gray pixels, two to a byte
+with the left pixel in the upper nibble and each row starting on a new byte.
+
+Options:
+  --dither            Dither to 16 gray levels instead of rounding
+  --format FORMAT     Output format [default: bin]
+                        bin         raw packed pixels
+                        rust        Rust source declaring a Sprite
+                        heatshrink  heatshrink-compressed packed pixels
+  --name NAME         Name of the Rust constant [default: from INPUT]
+  --window BITS       heatshrink window size in bits [default: 8]
+  --lookahead BITS    heatshrink lookahead size in bits [default: 4]
+  -h, --help          Print this help
+
+The image size is printed as WIDTHxHEIGHT, for use with raw and compressed
+output, which do not record it.";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Bin,
+    Rust,
+    Heatshrink,
+}
+
+#[derive(Debug, PartialEq)]
+struct Args {
+    conversion: Conversion,
+    format: Format,
+    name: Option<String>,
+    window_bits: u8,
+    lookahead_bits: u8,
+    input: PathBuf,
+    output: PathBuf,
+}
+
+/// Parse the command line arguments after the program name. Returns `Ok(None)` if help was
+/// requested.
+fn parse_args<I>(args: I) -> Result<Option<Args>, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter();
+    let mut conversion = Conversion::Round;
+    let mut format = Format::Bin;
+    let mut name = None;
+    let mut window_bits = 8;
+    let mut lookahead_bits = 4;
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--dither" => conversion = Conversion::Dither,
+            "--format" => {
+                format = match value()?.as_str() {
+                    "bin" => Format::Bin,
+                    "rust" => Format::Rust,
+                    "heatshrink" => Format::Heatshrink,
+                    other => return Err(format!("unknown format {}", other)),
+                }
+            }
+            "--name" => name = Some(value()?),
+            "--window" => window_bits = parse_bits(&value()?)?,
+            "--lookahead" => lookahead_bits = parse_bits(&value()?)?,
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.len() != 2 {
+        return Err("expected an INPUT and an OUTPUT path".into());
+    }
+    let output = paths.pop().unwrap();
+    let input = paths.pop().unwrap();
+    Ok(Some(Args {
+        conversion: conversion,
+        format: format,
+        name: name,
+        window_bits: window_bits,
+        lookahead_bits: lookahead_bits,
+        input: input,
+        output: output,
+    }))
+}
+
+fn parse_bits(value: &str) -> Result<u8, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} is not a number of bits", value))
+}
+
+/// A constant name derived from the file name of `path`, e.g. `LOGO_SMALL` for `logo-small.png`.
+fn const_name(path: &Path) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        name.insert(0, '_');
+    }
+    name
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let asset = Asset::load(&args.input, args.conversion)
+        .map_err(|e| format!("{}: {}", args.input.display(), e))?;
+    let output = match args.format {
+        Format::Bin => asset.data().to_vec(),
+        Format::Rust => {
+            let name = match args.name {
+                Some(ref name) => name.clone(),
+                None => const_name(&args.input),
+            };
+            asset.rust_source(&name).into_bytes()
+        }
+        Format::Heatshrink => {
+            heatshrink::compress(asset.data(), args.window_bits, args.lookahead_bits)
+                .map_err(|e| format!("bad heatshrink parameters: {:?}", e))?
+        }
+    };
+    std::fs::write(&args.output, output)
+        .map_err(|e| format!("{}: {}", args.output.display(), e))?;
+    println!("{}x{}", asset.width(), asset.height());
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let result = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => run(args),
+        Ok(None) => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Err(e) => Err(format!("{}\n\n{}", e, USAGE)),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("ssd1322-img: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Result<Option<Args>, String> {
+        parse_args(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn parse() {
+        let parsed = args("--dither --format heatshrink --window 10 in.png out.hs")
+            .unwrap()
+            .unwrap();
+        assert_eq!(parsed.conversion, Conversion::Dither);
+        assert_eq!(parsed.format, Format::Heatshrink);
+        assert_eq!((parsed.window_bits, parsed.lookahead_bits), (10, 4));
+        assert_eq!(parsed.input, PathBuf::from("in.png"));
+        assert_eq!(parsed.output, PathBuf::from("out.hs"));
+
+        assert_eq!(args("--help in.png"), Ok(None));
+        assert!(args("in.png").is_err());
+        assert!(args("--format gif in.png out").is_err());
+        assert!(args("--name").is_err());
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(const_name(Path::new("icons/logo-small.png")), "LOGO_SMALL");
+        assert_eq!(const_name(Path::new("8x8.bmp")), "_8X8");
+    }
+}
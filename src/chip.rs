@@ -0,0 +1,165 @@
+//! Descriptions of the controller chips supported by the region and interface machinery.
+//!
+//! Solomon Systech makes several 4 bit/pixel grayscale OLED controllers which differ mainly in the
+//! size of their display RAM, how many pixels one RAM column address covers, and the opcodes used
+//! to set up a write window. The `Chip` trait captures just those differences, so that `Display`
+//! and `Region` can drive any of them. The rest of the command set varies too much between chips
+//! to share, so initialization and the other features of `Display` are specific to the SSD1322.
+
+use crate::command::CommandError;
+use crate::interface::DisplayInterface;
+
+/// The RAM geometry and window addressing commands of a controller chip.
+pub trait Chip {
+    /// The width of the display RAM in pixels.
+    const NUM_PIXEL_COLS: u16;
+
+    /// The height of the display RAM in pixels.
+    const NUM_PIXEL_ROWS: u8;
+
+    /// The number of horizontally-adjacent pixels addressed by one RAM column address. Region
+    /// boundaries must be aligned to this many pixels.
+    const PIXELS_PER_COLUMN: u16;
+
+    /// The opcode of the command taking the first and last RAM column address of the write window.
+    const SET_COLUMN_ADDRESS: u8;
+
+    /// The opcode of the command taking the first and last RAM row of the write window.
+    const SET_ROW_ADDRESS: u8;
+
+    /// The opcode of the command which must precede image data, or `None` if image data may be
+    /// written as soon as the window is set.
+    const WRITE_IMAGE_DATA: Option<u8>;
+
+    /// The number of RAM column addresses.
+    fn num_buf_cols() -> u16 {
+        Self::NUM_PIXEL_COLS / Self::PIXELS_PER_COLUMN
+    }
+
+    /// Set the write window to the inclusive ranges of RAM column addresses `cols` and rows `rows`,
+    /// and prepare the chip to receive image data.
+    #[allow(clippy::nonminimal_bool)]
+    fn begin_write<DI>(
+        iface: &mut DI,
+        cols: (u8, u8),
+        rows: (u8, u8),
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: DisplayInterface,
+    {
+        if false
+            || cols.0 as u16 >= Self::num_buf_cols()
+            || cols.1 as u16 >= Self::num_buf_cols()
+            || rows.0 >= Self::NUM_PIXEL_ROWS
+            || rows.1 >= Self::NUM_PIXEL_ROWS
+        {
+            return Err(CommandError::OutOfRange);
+        }
+        let send = |iface: &mut DI, cmd: u8, data: &[u8]| {
+            iface.send_command(cmd)?;
+            if data.is_empty() {
+                Ok(())
+            } else {
+                iface.send_data(data)
+            }
+        };
+        (|| {
+            send(iface, Self::SET_COLUMN_ADDRESS, &[cols.0, cols.1])?;
+            send(iface, Self::SET_ROW_ADDRESS, &[rows.0, rows.1])?;
+            match Self::WRITE_IMAGE_DATA {
+                Some(cmd) => send(iface, cmd, &[]),
+                None => Ok(()),
+            }
+        })()
+        .map_err(CommandError::InterfaceError)
+    }
+}
+
+/// The SSD1322, with 480x128 pixels of display RAM addressed in columns of 4 pixels.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ssd1322;
+
+impl Chip for Ssd1322 {
+    const NUM_PIXEL_COLS: u16 = 480;
+    const NUM_PIXEL_ROWS: u8 = 128;
+    const PIXELS_PER_COLUMN: u16 = 4;
+    const SET_COLUMN_ADDRESS: u8 = 0x15;
+    const SET_ROW_ADDRESS: u8 = 0x75;
+    const WRITE_IMAGE_DATA: Option<u8> = Some(0x5C);
+}
+
+/// The SSD1327, with 128x128 pixels of display RAM addressed in columns of 2 pixels.
+///
+/// The chip must be initialized by the application, and the nibble remapping must be set so that
+/// the upper nibble of each byte is the left hand pixel.
+#[cfg(feature = "ssd1327")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ssd1327;
+
+#[cfg(feature = "ssd1327")]
+impl Chip for Ssd1327 {
+    const NUM_PIXEL_COLS: u16 = 128;
+    const NUM_PIXEL_ROWS: u8 = 128;
+    const PIXELS_PER_COLUMN: u16 = 2;
+    const SET_COLUMN_ADDRESS: u8 = 0x15;
+    const SET_ROW_ADDRESS: u8 = 0x75;
+    const WRITE_IMAGE_DATA: Option<u8> = None;
+}
+
+/// The SSD1362, with 256x64 pixels of display RAM addressed in columns of 2 pixels.
+///
+/// The chip must be initialized by the application, and the nibble remapping must be set so that
+/// the upper nibble of each byte is the left hand pixel.
+#[cfg(feature = "ssd1362")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ssd1362;
+
+#[cfg(feature = "ssd1362")]
+impl Chip for Ssd1362 {
+    const NUM_PIXEL_COLS: u16 = 256;
+    const NUM_PIXEL_ROWS: u8 = 64;
+    const PIXELS_PER_COLUMN: u16 = 2;
+    const SET_COLUMN_ADDRESS: u8 = 0x15;
+    const SET_ROW_ADDRESS: u8 = 0x75;
+    const WRITE_IMAGE_DATA: Option<u8> = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::mock::{MockInterface, Sent};
+
+    #[test]
+    fn ssd1322_window() {
+        let mut di = MockInterface::new();
+        Ssd1322::begin_write(&mut di, (2, 5), (0, 127)).unwrap();
+        di.check_multi(&[
+            Sent::Cmd(0x15),
+            Sent::Data(vec![2, 5]),
+            Sent::Cmd(0x75),
+            Sent::Data(vec![0, 127]),
+            Sent::Cmd(0x5C),
+        ]);
+        assert_eq!(
+            Ssd1322::begin_write(&mut di, (0, 120), (0, 1)),
+            Err(CommandError::OutOfRange)
+        );
+    }
+
+    #[cfg(feature = "ssd1362")]
+    #[test]
+    fn ssd1362_window() {
+        let mut di = MockInterface::new();
+        Ssd1362::begin_write(&mut di, (0, 127), (0, 63)).unwrap();
+        di.check_multi(&[
+            Sent::Cmd(0x15),
+            Sent::Data(vec![0, 127]),
+            Sent::Cmd(0x75),
+            Sent::Data(vec![0, 63]),
+        ]);
+        assert_eq!(
+            Ssd1362::begin_write(&mut di, (0, 1), (0, 64)),
+            Err(CommandError::OutOfRange)
+        );
+    }
+}
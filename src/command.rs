@@ -11,14 +11,16 @@ use crate::interface::DisplayInterface;
 pub mod consts {
     //! Constants describing max supported display size and the display RAM layout.
 
+    use crate::chip::{Chip, Ssd1322};
+
     /// The maximum supported display width in pixels.
-    pub const NUM_PIXEL_COLS: u16 = 480;
+    pub const NUM_PIXEL_COLS: u16 = Ssd1322::NUM_PIXEL_COLS;
 
     /// The maximum supported display height in pixels.
-    pub const NUM_PIXEL_ROWS: u8 = 128;
+    pub const NUM_PIXEL_ROWS: u8 = Ssd1322::NUM_PIXEL_ROWS;
 
     /// The number of display RAM column addresses.
-    pub const NUM_BUF_COLS: u8 = (NUM_PIXEL_COLS / 4) as u8;
+    pub const NUM_BUF_COLS: u8 = (NUM_PIXEL_COLS / Ssd1322::PIXELS_PER_COLUMN) as u8;
 
     /// The highest valid pixel column index.
     pub const PIXEL_COL_MAX: u16 = NUM_PIXEL_COLS - 1;
@@ -33,7 +35,7 @@ pub mod consts {
 /// The address increment orientation when writing image data. This configures how the SSD1322 will
 /// auto-increment the row and column addresses when image data is written using the
 /// `WriteImageData` command.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum IncrementAxis {
     /// The column address will increment as image data is written, writing pairs of bytes
     /// (horizontal groups of 4 pixels) from left to right in the range set by `SetColumnAddress`
@@ -47,7 +49,7 @@ pub enum IncrementAxis {
 
 /// Setting of column address remapping. This controls the direction of mapping display RAM column
 /// addresses onto groups of pixel column driver lines.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ColumnRemap {
     /// Column addresses 0->119 map to pixel columns 0,1,2,3->476,477,478,479.
     Forward,
@@ -60,7 +62,7 @@ pub enum ColumnRemap {
 /// Setting of data nibble remapping. This controls how the SSD1322 will interpret the nibble-wise
 /// endianness of each 2-byte word, changing the order in which each group of 4 pixels is mapped
 /// onto the 4 nibbles stored at the corresponding display RAM column address.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum NibbleRemap {
     /// The 2-byte sequence at each column address 0xABCD maps (in L->R order) to pixels 3,2,1,0.
     Reverse,
@@ -71,7 +73,7 @@ pub enum NibbleRemap {
 /// Setting of the COM line scanning of rows. This controls the order in which COM lines are
 /// scanned, leaving the order in which display RAM row addresses are scanned unchanged. Toggling
 /// this setting will thus flip the displayed image vertically.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ComScanDirection {
     /// COM lines scan row addresses top to bottom, so that row address 0 is the first row of the
     /// display.
@@ -85,7 +87,7 @@ pub enum ComScanDirection {
 /// display module itself wires the OLED matrix to the driver chip, and changing it to anything
 /// other than the correct setting for your module will yield a corrupted image. See the display
 /// module datasheet for the correct value to use.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ComLayout {
     /// COM lines are connected to display rows in a progressive arrangement, so that COM lines
     /// 0->127 map to display rows 0->127.
@@ -103,7 +105,7 @@ pub enum ComLayout {
 
 /// Setting of the display mode. The display mode controls whether the display is blanked, and
 /// whether the pixel intensities are rendered normal or inverted.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DisplayMode {
     /// The display is blanked with all pixels turned OFF (to grayscale level 0).
     BlankDark,
@@ -119,7 +121,7 @@ pub enum DisplayMode {
 /// Enumerates most of the valid commands that can be sent to the SSD1322 along with their
 /// parameter values. Commands which accept an array of similar "arguments" as a slice are encoded
 /// by `BufCommand` instead to avoid lifetime parameters on this enum.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Command {
     /// Enable the gray scale gamma table (see `BufCommand::SetGrayScaleTable`).
     EnableGrayScaleTable,
@@ -131,6 +133,11 @@ pub enum Command {
     /// pointer is reset to the start row address such that `WriteImageData` will begin writing
     /// there. Range is 0-127.
     SetRowAddress(u8, u8),
+    /// Read image data from the display RAM, starting at the row and column address pointers set
+    /// by `SetRowAddress` and `SetColumnAddress`. The data can only be read over a parallel bus,
+    /// and the first word read after this command is a dummy. See
+    /// `interface::ReadableInterface`.
+    ReadImageData,
     /// Set the direction of display address increment, column address remapping, data nibble
     /// remapping, COM scan direction, and COM line layout. See documentation for each enum for
     /// details.
@@ -226,9 +233,29 @@ pub enum CommandError<IE> {
     OutOfRange,
     /// The gray scale table provided was not the correct length.
     BadTableLength,
+    /// A row of image data was not the same width as the region it was drawn into.
+    BadRowLength,
+    /// The image data was not the right length to fill the region it was drawn into. Both lengths
+    /// are in bytes.
+    BadDataLength { expected: usize, actual: usize },
+    /// The operation requires the display to have been initialized with `Display::init` first.
+    Uninitialized,
+    /// Image data written to the display did not match its source when verified.
+    VerifyFailed,
+    /// The display is busy with a background flush started by `Display::flush`.
+    Busy,
+    /// The interface waits for every word of image data to be sent, so `Display::flush` cannot
+    /// send it in the background. Nothing was sent.
+    BlockingInterface,
+    /// The chip's command interface is locked, so it would ignore the command or image data.
+    /// Unlock it with `Display::command_lock` first. Nothing was sent.
+    Locked,
+    /// The MUX ratio given to `Config::mux_ratio` is outside the chip's range of 16 to 128 COM
+    /// lines, so the display cannot be initialized with it. Nothing was sent.
+    BadMuxRatio { ratio: u8 },
 }
 
-impl<IE > CommandError<IE> {
+impl<IE> CommandError<IE> {
     /// Unwrap a `CommandError` that is assumed to be of the `InterfaceError` variant, or panic if
     /// it is any other variant. This is particularly used inside the region abstractions where we
     /// assume that non-interface-related errors are prevented by the correctness checks performed
@@ -274,6 +301,7 @@ impl Command {
                 (0..=PIXEL_ROW_MAX, 0..=PIXEL_ROW_MAX) => ok_command!(arg_buf, 0x75, [start, end]),
                 _ => Err(CommandError::OutOfRange),
             },
+            Command::ReadImageData => ok_command!(arg_buf, 0x5D, []),
             Command::SetRemapping(
                 increment_axis,
                 column_remap,
@@ -392,13 +420,11 @@ impl Command {
         }?;
         iface
             .send_command(cmd)
-            .map_err(|e| CommandError::InterfaceError(e))?;
-        if data.len() == 0 {
+            .map_err(CommandError::InterfaceError)?;
+        if data.is_empty() {
             Ok(())
         } else {
-            iface
-                .send_data(data)
-                .map_err(|e| CommandError::InterfaceError(e))
+            iface.send_data(data).map_err(CommandError::InterfaceError)
         }
     }
 }
@@ -433,13 +459,11 @@ impl<'a> BufCommand<'a> {
         }?;
         iface
             .send_command(cmd)
-            .map_err(|e| CommandError::InterfaceError(e))?;
-        if data.len() == 0 {
+            .map_err(CommandError::InterfaceError)?;
+        if data.is_empty() {
             Ok(())
         } else {
-            iface
-                .send_data(data)
-                .map_err(|e| CommandError::InterfaceError(e))
+            iface.send_data(data).map_err(CommandError::InterfaceError)
         }
     }
 }
@@ -447,12 +471,12 @@ impl<'a> BufCommand<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::interface::test_spy::TestSpyInterface;
+    use crate::interface::mock::MockInterface;
     use std::vec::Vec;
 
     #[test]
     fn set_column_address() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         Command::SetColumnAddress(23, 42).send(&mut di).unwrap();
         di.check(0x15, &[23, 42]);
         assert_eq!(
@@ -467,7 +491,7 @@ mod tests {
 
     #[test]
     fn set_row_address() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         Command::SetRowAddress(23, 42).send(&mut di).unwrap();
         di.check(0x75, &[23, 42]);
         assert_eq!(
@@ -482,7 +506,7 @@ mod tests {
 
     #[test]
     fn set_remapping() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         Command::SetRemapping(
             IncrementAxis::Horizontal,
             ColumnRemap::Forward,
@@ -521,7 +545,7 @@ mod tests {
 
     #[test]
     fn write_image_data() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         let image_buf = (0..24).collect::<Vec<u8>>();
         BufCommand::WriteImageData(&image_buf[..])
             .send(&mut di)
@@ -529,9 +553,16 @@ mod tests {
         di.check(0x5C, &(0..24u8).collect::<Vec<_>>()[..]);
     }
 
+    #[test]
+    fn read_image_data() {
+        let mut di = MockInterface::new();
+        Command::ReadImageData.send(&mut di).unwrap();
+        di.check(0x5D, &[]);
+    }
+
     #[test]
     fn set_start_line() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         Command::SetStartLine(23).send(&mut di).unwrap();
         di.check(0xA1, &[23]);
         assert_eq!(
@@ -542,7 +573,7 @@ mod tests {
 
     #[test]
     fn set_display_offset() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         Command::SetDisplayOffset(23).send(&mut di).unwrap();
         di.check(0xA2, &[23]);
         assert_eq!(
@@ -553,7 +584,7 @@ mod tests {
 
     #[test]
     fn set_display_mode() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         Command::SetDisplayMode(DisplayMode::BlankDark)
             .send(&mut di)
             .unwrap();
@@ -577,7 +608,7 @@ mod tests {
 
     #[test]
     fn enable_partial_display() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         Command::EnablePartialDisplay(23, 42).send(&mut di).unwrap();
         di.check(0xA8, &[23, 42]);
         assert_eq!(
@@ -596,7 +627,7 @@ mod tests {
 
     #[test]
     fn sleep_mode() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         Command::SetSleepMode(true).send(&mut di).unwrap();
         di.check(0xAE, &[]);
         di.clear();
@@ -606,7 +637,7 @@ mod tests {
 
     #[test]
     fn set_phase_lengths() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         Command::SetPhaseLengths(5, 3).send(&mut di).unwrap();
         di.check(0xB1, &[0x32]);
         di.clear();
@@ -638,7 +669,7 @@ mod tests {
 
     #[test]
     fn set_clock_fosc_divset() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         Command::SetClockFoscDivset(0, 0).send(&mut di).unwrap();
         di.check(0xB3, &[0x00]);
         di.clear();
@@ -656,7 +687,7 @@ mod tests {
 
     #[test]
     fn set_display_enhancements() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         Command::SetDisplayEnhancements(false, false)
             .send(&mut di)
             .unwrap();
@@ -675,7 +706,7 @@ mod tests {
 
     #[test]
     fn set_second_precharge_period() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         Command::SetSecondPrechargePeriod(0).send(&mut di).unwrap();
         di.check(0xB6, &[0]);
         di.clear();
@@ -690,7 +721,7 @@ mod tests {
 
     #[test]
     fn set_gray_scale_table() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         BufCommand::SetGrayScaleTable(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14])
             .send(&mut di)
             .unwrap();
@@ -738,7 +769,7 @@ mod tests {
 
     #[test]
     fn set_pre_charge_voltage() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         Command::SetPreChargeVoltage(17).send(&mut di).unwrap();
         di.check(0xBB, &[17]);
         assert_eq!(
@@ -749,7 +780,7 @@ mod tests {
 
     #[test]
     fn set_com_deselect_voltage() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         Command::SetComDeselectVoltage(3).send(&mut di).unwrap();
         di.check(0xBE, &[3]);
         assert_eq!(
@@ -760,7 +791,7 @@ mod tests {
 
     #[test]
     fn set_master_contrasat() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         Command::SetMasterContrast(3).send(&mut di).unwrap();
         di.check(0xC7, &[3]);
         assert_eq!(
@@ -771,7 +802,7 @@ mod tests {
 
     #[test]
     fn set_mux_ratio() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         Command::SetMuxRatio(128).send(&mut di).unwrap();
         di.check(0xCA, &[127]);
         di.clear();
@@ -789,7 +820,7 @@ mod tests {
 
     #[test]
     fn set_command_lock() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         Command::SetCommandLock(true).send(&mut di).unwrap();
         di.check(0xFD, &[0b00010110]);
         di.clear();
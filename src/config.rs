@@ -4,9 +4,18 @@
 use crate::command::*;
 use crate::interface;
 
+/// The address increment axis, column remap and nibble remap sent by `Display::init` unless
+/// `Config` overrides them.
+pub(crate) const DEFAULT_REMAPPING: (IncrementAxis, ColumnRemap, NibbleRemap) = (
+    IncrementAxis::Horizontal,
+    ColumnRemap::Forward,
+    NibbleRemap::Forward,
+);
+
 /// The portion of the configuration which will persist inside the `Display` because it shares
 /// registers with functions that can be changed after initialization. This allows the rest of the
 /// `Config` struct to be thrown away to save RAM after `Display::init` finishes.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) struct PersistentConfig {
     com_scan_direction: ComScanDirection,
     com_layout: ComLayout,
@@ -34,12 +43,36 @@ impl PersistentConfig {
         )
         .send(iface)
     }
+
+    /// The COM scan direction, which determines the order the panel scans display RAM rows.
+    #[cfg(feature = "buffered")]
+    pub(crate) fn com_scan_direction(&self) -> ComScanDirection {
+        self.com_scan_direction
+    }
+}
+
+/// Errors that can occur when building a `Config` from a raw init sequence with
+/// `Config::from_raw_sequence`.
+#[derive(Debug, PartialEq)]
+pub enum RawSequenceError {
+    /// The sequence contains a command with this opcode which `Config` cannot represent.
+    UnknownOpcode(u8),
+    /// The command with this opcode has the wrong number of argument bytes.
+    BadArguments(u8),
+    /// The command with this opcode selects a setting which `Config` cannot represent, such as
+    /// an external VDD supply with function selection (0xAB).
+    UnsupportedSetting(u8),
+    /// The sequence does not contain a remapping command (0xA0), from which the COM scan
+    /// direction and COM layout are taken.
+    MissingRemapping,
 }
 
 /// A configuration for the display. Builder methods offer a declarative way to either sent a
 /// configuration command at init time, or to leave it at the chip's POR default.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Config {
     pub(crate) persistent_config: PersistentConfig,
+    pub(crate) remapping: (IncrementAxis, ColumnRemap, NibbleRemap),
     contrast_current_cmd: Option<Command>,
     phase_lengths_cmd: Option<Command>,
     clock_fosc_divset_cmd: Option<Command>,
@@ -47,6 +80,9 @@ pub struct Config {
     second_precharge_period_cmd: Option<Command>,
     precharge_voltage_cmd: Option<Command>,
     com_deselect_voltage_cmd: Option<Command>,
+    gray_scale_table: Option<[u8; 15]>,
+    pub(crate) mux_ratio: Option<u8>,
+    pub(crate) command_lock: Option<bool>,
 }
 
 impl Config {
@@ -60,6 +96,7 @@ impl Config {
                 com_scan_direction: com_scan_direction,
                 com_layout: com_layout,
             },
+            remapping: DEFAULT_REMAPPING,
             contrast_current_cmd: None,
             phase_lengths_cmd: None,
             clock_fosc_divset_cmd: None,
@@ -67,6 +104,9 @@ impl Config {
             second_precharge_period_cmd: None,
             precharge_voltage_cmd: None,
             com_deselect_voltage_cmd: None,
+            gray_scale_table: None,
+            mux_ratio: None,
+            command_lock: None,
         }
     }
 
@@ -136,25 +176,379 @@ impl Config {
         }
     }
 
+    /// Extend this `Config` to load a custom gray scale table at init time and enable it with
+    /// `Command::EnableGrayScaleTable`. The table must be valid for `BufCommand::SetGrayScaleTable`,
+    /// or `Display::init` returns `CommandError::OutOfRange` part way through the init sequence.
+    pub fn gray_scale_table(self, table: [u8; 15]) -> Self {
+        Self {
+            gray_scale_table: Some(table),
+            ..self
+        }
+    }
+
+    /// Extend this `Config` to override the address increment axis, which by default is
+    /// `IncrementAxis::Horizontal`. Regions lay out their image data for horizontal increment, so
+    /// with `IncrementAxis::Vertical` the image data of each region must be sent column by column
+    /// instead. See `Command::SetRemapping`.
+    pub fn increment_axis(self, increment_axis: IncrementAxis) -> Self {
+        Self {
+            remapping: (increment_axis, self.remapping.1, self.remapping.2),
+            ..self
+        }
+    }
+
+    /// Extend this `Config` to override the column remap, which by default is
+    /// `ColumnRemap::Forward`, for modules whose column drivers are wired right to left so that the
+    /// image is otherwise mirrored. See `Command::SetRemapping`.
+    pub fn column_remap(self, column_remap: ColumnRemap) -> Self {
+        Self {
+            remapping: (self.remapping.0, column_remap, self.remapping.2),
+            ..self
+        }
+    }
+
+    /// Extend this `Config` to override the nibble remap, which by default is
+    /// `NibbleRemap::Forward`, for modules on which the pixels of each column otherwise appear in
+    /// the wrong order. See `Command::SetRemapping`.
+    pub fn nibble_remap(self, nibble_remap: NibbleRemap) -> Self {
+        Self {
+            remapping: (self.remapping.0, self.remapping.1, nibble_remap),
+            ..self
+        }
+    }
+
+    /// Extend this `Config` to override the MUX ratio, which by default is the number of rows of
+    /// the display. Some modules need a different number of active COM lines, for example to
+    /// leave defective rows at the bottom of the panel dark. See `Command::SetMuxRatio`; a ratio
+    /// outside its range of 16 to 128 causes `Display::init` to return
+    /// `CommandError::BadMuxRatio` without sending anything.
+    pub fn mux_ratio(self, ratio: u8) -> Self {
+        Self {
+            mux_ratio: Some(ratio),
+            ..self
+        }
+    }
+
+    /// Extend this `Config` to manage the command lock during init. The chip is unlocked before
+    /// anything else is sent, for modules which ship locked, and if `relock` is true it is locked
+    /// again once configuration is complete, so that noise on the bus cannot change registers.
+    ///
+    /// While locked the chip ignores all commands and image data, so a relocked display must be
+    /// unlocked with `Display::command_lock` before it is drawn on or reconfigured, and can be
+    /// locked again afterwards; until then the driver refuses them with `CommandError::Locked`.
+    /// See `Command::SetCommandLock`.
+    pub fn command_lock(self, relock: bool) -> Self {
+        Self {
+            command_lock: Some(relock),
+            ..self
+        }
+    }
+
+    /// Build a configuration from a raw init sequence of `(opcode, arguments)` pairs, such as the
+    /// example code published by display module vendors.
+    ///
+    /// Commands which correspond to a builder method of `Config` are decoded into it. Commands
+    /// which `Display` sends itself during init or while drawing, such as sleep mode, display mode,
+    /// start line, display offset, master contrast and the RAM address window, are accepted and
+    /// ignored. Of the remapping command, the address increment axis is not used, since regions
+    /// need horizontal increment. The display offset must instead be passed to `Display::new`.
+    ///
+    /// A custom gray scale table (0xB8) is decoded as with `gray_scale_table`, and selecting the
+    /// default table (0xB9) drops any custom table decoded before it, since the chip starts with
+    /// the default table after reset. Function selection (0xAB) is accepted only with the internal
+    /// VDD regulator, which is the reset value. The GPIO setting (0xB5) and display enhancement B
+    /// (0xD1) have no `Config` setting, and are accepted and ignored.
+    pub fn from_raw_sequence(sequence: &[(u8, &[u8])]) -> Result<Config, RawSequenceError> {
+        // The remapping may come anywhere in the sequence, so start with placeholder settings.
+        let mut config = Config::new(ComScanDirection::RowZeroFirst, ComLayout::Progressive);
+        let mut remapping = None;
+        for &(opcode, args) in sequence {
+            let expected_args = match opcode {
+                0xB8 => 15,
+                0xA0 | 0xA8 | 0xB4 | 0xD1 | 0x15 | 0x75 => 2,
+                0xA1 | 0xA2 | 0xAB | 0xB1 | 0xB3 | 0xB5 | 0xB6 | 0xBB | 0xBE | 0xC1 | 0xC7
+                | 0xCA | 0xFD => 1,
+                0x00 | 0xA4..=0xA7 | 0xA9 | 0xAE | 0xAF | 0xB9 | 0x5C => 0,
+                _ => return Err(RawSequenceError::UnknownOpcode(opcode)),
+            };
+            if args.len() != expected_args {
+                return Err(RawSequenceError::BadArguments(opcode));
+            }
+            config = match opcode {
+                0xA0 => {
+                    remapping = Some((args[0], args[1]));
+                    config
+                }
+                0xAB if args[0] & 0x01 == 0 => {
+                    return Err(RawSequenceError::UnsupportedSetting(opcode))
+                }
+                0xB1 => config.phase_lengths((args[0] & 0x0F) * 2 + 1, args[0] >> 4),
+                0xB3 => config.clock_fosc_divset(args[0] >> 4, args[0] & 0x0F),
+                0xB4 => config.display_enhancements(args[0] == 0xA0, args[1] == 0xFD),
+                0xB6 => config.second_precharge_period(args[0]),
+                0xBB => config.precharge_voltage(args[0]),
+                0xBE => config.com_deselect_voltage(args[0]),
+                0xC1 => config.contrast_current(args[0]),
+                0xCA => config.mux_ratio(args[0].wrapping_add(1)),
+                0xB8 => {
+                    let mut table = [0; 15];
+                    table.copy_from_slice(args);
+                    config.gray_scale_table(table)
+                }
+                0xB9 => Config {
+                    gray_scale_table: None,
+                    ..config
+                },
+                // An unlock means the module may ship locked, and a lock means it should be
+                // relocked after init.
+                0xFD => {
+                    let relock = args[0] == 0x16 || config.command_lock == Some(true);
+                    config.command_lock(relock)
+                }
+                _ => config,
+            };
+        }
+
+        let (a, b) = remapping.ok_or(RawSequenceError::MissingRemapping)?;
+        config = config
+            .column_remap(match a & 0x02 {
+                0 => ColumnRemap::Forward,
+                _ => ColumnRemap::Reverse,
+            })
+            .nibble_remap(match a & 0x04 {
+                0 => NibbleRemap::Reverse,
+                _ => NibbleRemap::Forward,
+            });
+        config.persistent_config = PersistentConfig {
+            com_scan_direction: match a & 0x10 {
+                0 => ComScanDirection::RowZeroFirst,
+                _ => ComScanDirection::RowZeroLast,
+            },
+            com_layout: match (b & 0x10, a & 0x20) {
+                (0, 0) => ComLayout::Progressive,
+                (0, _) => ComLayout::Interlaced,
+                _ => ComLayout::DualProgressive,
+            },
+        };
+        Ok(config)
+    }
+
+    /// This configuration with the COM scan direction and COM layout replaced.
+    #[cfg(feature = "bringup")]
+    pub(crate) fn with_com(
+        self,
+        com_scan_direction: ComScanDirection,
+        com_layout: ComLayout,
+    ) -> Self {
+        Self {
+            persistent_config: PersistentConfig {
+                com_scan_direction: com_scan_direction,
+                com_layout: com_layout,
+            },
+            ..self
+        }
+    }
+
+    /// The COM scan direction this configuration sets.
+    pub fn com_scan_direction(&self) -> ComScanDirection {
+        self.persistent_config.com_scan_direction
+    }
+
+    /// The COM layout this configuration sets.
+    pub fn com_layout(&self) -> ComLayout {
+        self.persistent_config.com_layout
+    }
+
+    /// The optional configuration commands which have been set, in the order `Display::init`
+    /// sends them. Settings left at the chip's POR default are omitted. The COM scan direction,
+    /// COM layout, MUX ratio and command lock are not included, because they are sent together
+    /// with settings chosen by the driver, and neither is the gray scale table, which `Display::init`
+    /// sends after these.
+    pub fn commands(&self) -> impl Iterator<Item = Command> {
+        IntoIterator::into_iter([
+            self.phase_lengths_cmd,
+            self.contrast_current_cmd,
+            self.clock_fosc_divset_cmd,
+            self.display_enhancements_cmd,
+            self.second_precharge_period_cmd,
+            self.precharge_voltage_cmd,
+            self.com_deselect_voltage_cmd,
+        ])
+        .flatten()
+    }
+
     /// Transmit commands to the display at `iface` necessary to put that display into the
     /// configuration encoded in `self`.
     pub(crate) fn send<DI>(&self, iface: &mut DI) -> Result<(), CommandError<DI::Error>>
     where
         DI: interface::DisplayInterface,
     {
-        self.phase_lengths_cmd.map_or(Ok(()), |c| c.send(iface))?;
-        self.contrast_current_cmd
-            .map_or(Ok(()), |c| c.send(iface))?;
-        self.clock_fosc_divset_cmd
-            .map_or(Ok(()), |c| c.send(iface))?;
-        self.display_enhancements_cmd
-            .map_or(Ok(()), |c| c.send(iface))?;
-        self.second_precharge_period_cmd
-            .map_or(Ok(()), |c| c.send(iface))?;
-        self.precharge_voltage_cmd
-            .map_or(Ok(()), |c| c.send(iface))?;
-        self.com_deselect_voltage_cmd
-            .map_or(Ok(()), |c| c.send(iface))?;
+        for cmd in self.commands() {
+            cmd.send(iface)?;
+        }
+        if let Some(table) = self.gray_scale_table {
+            BufCommand::SetGrayScaleTable(&table).send(iface)?;
+            Command::EnableGrayScaleTable.send(iface)?;
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::{Display, PixelCoord as Px};
+    use crate::interface::mock::{MockInterface, Sent};
+
+    #[test]
+    fn from_raw_sequence() {
+        // The init sequence of the Newhaven NHD-3.12-25664 example code.
+        #[rustfmt::skip]
+        let vendor: &[(u8, &[u8])] = &[
+            (0xFD, &[0x12]),
+            (0xAE, &[]),
+            (0xB3, &[0x91]),
+            (0xCA, &[0x3F]),
+            (0xA2, &[0x00]),
+            (0xA1, &[0x00]),
+            (0xA0, &[0x14, 0x11]),
+            (0xB5, &[0x00]),
+            (0xAB, &[0x01]),
+            (0xB4, &[0xA0, 0xFD]),
+            (0xC1, &[0x9F]),
+            (0xC7, &[0x0F]),
+            (0xB9, &[]),
+            (0xB1, &[0xE2]),
+            (0xD1, &[0x82, 0x20]),
+            (0xBB, &[0x1F]),
+            (0xB6, &[0x08]),
+            (0xBE, &[0x07]),
+            (0xA6, &[]),
+            (0xA9, &[]),
+            (0xAF, &[]),
+        ];
+        let config = Config::from_raw_sequence(vendor).unwrap();
+        let expected = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive)
+            .command_lock(false)
+            .clock_fosc_divset(9, 1)
+            .mux_ratio(64)
+            .display_enhancements(true, true)
+            .contrast_current(159)
+            .phase_lengths(5, 14)
+            .precharge_voltage(31)
+            .second_precharge_period(8)
+            .com_deselect_voltage(7);
+
+        let init = |config: Config| {
+            let di = MockInterface::new();
+            let mut disp = Display::new(di.split(), Px(256, 64), Px(112, 0));
+            disp.init(config).unwrap();
+            di.sent()
+        };
+        assert_eq!(config, expected);
+        assert_eq!(init(config), init(expected.clone()));
+        #[rustfmt::skip]
+        assert_eq!(
+            expected.commands().collect::<Vec<_>>(),
+            [
+                Command::SetPhaseLengths(5, 14),
+                Command::SetContrastCurrent(159),
+                Command::SetClockFoscDivset(9, 1),
+                Command::SetDisplayEnhancements(true, true),
+                Command::SetSecondPrechargePeriod(8),
+                Command::SetPreChargeVoltage(31),
+                Command::SetComDeselectVoltage(7),
+            ]
+        );
+
+        assert_eq!(
+            Config::from_raw_sequence(&[(0xB0, &[0x01])]).err(),
+            Some(RawSequenceError::UnknownOpcode(0xB0))
+        );
+        assert_eq!(
+            Config::from_raw_sequence(&[(0xAB, &[0x00])]).err(),
+            Some(RawSequenceError::UnsupportedSetting(0xAB))
+        );
+        assert_eq!(
+            Config::from_raw_sequence(&[(0xC1, &[])]).err(),
+            Some(RawSequenceError::BadArguments(0xC1))
+        );
+        assert_eq!(
+            Config::from_raw_sequence(&[(0xAF, &[])]).err(),
+            Some(RawSequenceError::MissingRemapping)
+        );
+    }
+
+    #[test]
+    fn gray_scale_table() {
+        let table = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+        #[rustfmt::skip]
+        let config = Config::from_raw_sequence(&[
+            (0xA0, &[0x14, 0x11]),
+            (0xB8, &table),
+            (0x00, &[]),
+        ])
+        .unwrap();
+        let expected = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive)
+            .gray_scale_table(table);
+        assert_eq!(config, expected);
+
+        let mut di = MockInterface::new();
+        config.send(&mut di).unwrap();
+        #[rustfmt::skip]
+        assert_eq!(di.sent(), [
+            Sent::Cmd(0xB8),
+            Sent::Data(table.to_vec()),
+            Sent::Cmd(0x00),
+        ]);
+
+        // The default table replaces a custom one.
+        assert_eq!(
+            Config::from_raw_sequence(&[(0xA0, &[0x14, 0x11]), (0xB8, &table), (0xB9, &[])]),
+            Ok(Config::new(
+                ComScanDirection::RowZeroLast,
+                ComLayout::DualProgressive
+            ))
+        );
+        assert_eq!(
+            Config::from_raw_sequence(&[(0xB8, &table[1..])]).err(),
+            Some(RawSequenceError::BadArguments(0xB8))
+        );
+    }
+
+    #[test]
+    fn remap_options() {
+        let config = Config::new(ComScanDirection::RowZeroFirst, ComLayout::Progressive)
+            .column_remap(ColumnRemap::Reverse)
+            .nibble_remap(NibbleRemap::Reverse);
+        let di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(256, 64), Px(0, 0));
+        disp.init(config.clone()).unwrap();
+        assert!(di
+            .sent()
+            .windows(2)
+            .any(|pair| pair == [Sent::Cmd(0xA0), Sent::Data(vec![0b00000010, 0b00000001])]));
+        assert_eq!(
+            disp.remapping(),
+            (
+                IncrementAxis::Horizontal,
+                ColumnRemap::Reverse,
+                NibbleRemap::Reverse
+            )
+        );
+
+        // The increment axis of a raw sequence is left to the driver.
+        assert_eq!(
+            Config::from_raw_sequence(&[(0xA0, &[0x03, 0x01])]),
+            Ok(config.clone())
+        );
+        assert_eq!(
+            config.increment_axis(IncrementAxis::Vertical).remapping,
+            (
+                IncrementAxis::Vertical,
+                ColumnRemap::Reverse,
+                NibbleRemap::Reverse
+            )
+        );
+    }
+}
@@ -0,0 +1,273 @@
+//! A text console which turns the display into a scrolling terminal for debug output. The
+//! `Console` keeps a grid of characters, implements `core::fmt::Write` so it can be used with
+//! `write!`, and redraws only the text lines that changed.
+//!
+//! When the font height evenly divides the 128 rows of display RAM, scrolling is done in hardware
+//! by moving the display start line (`Command::SetStartLine`) over display RAM treated as a ring
+//! of text lines, so that only the newly exposed line has to be drawn. Otherwise, every line is
+//! redrawn when the console scrolls.
+
+use core::fmt;
+
+use crate::command::consts::*;
+use crate::command::CommandError;
+use crate::display::{Display, PixelCoord};
+use crate::interface;
+use crate::text::{Font, TextPixels};
+
+/// A text console of `COLS` columns by `ROWS` lines, drawing characters in the monospaced font `F`
+/// onto a `Display` it owns.
+pub struct Console<DI, F, const COLS: usize, const ROWS: usize>
+where
+    DI: interface::DisplayInterface,
+{
+    display: Display<DI>,
+    font: F,
+    fg: u8,
+    bg: u8,
+    /// The character grid, stored as a ring of lines where line `top` is shown at the top of the
+    /// screen.
+    grid: [[u8; COLS]; ROWS],
+    top: usize,
+    dirty: [bool; ROWS],
+    /// The cursor line, relative to the top of the screen, and column.
+    line: usize,
+    col: usize,
+    /// Whether scrolling is done with the start line, and the current start line if so.
+    hw_scroll: bool,
+    start_line: u8,
+}
+
+impl<DI, F, const COLS: usize, const ROWS: usize> Console<DI, F, COLS, ROWS>
+where
+    DI: interface::DisplayInterface,
+    F: Font,
+{
+    /// Create a console drawing on an initialized `display` in `font`, which should be monospaced.
+    /// The console starts out blank but nothing is drawn until the first write or `clear`.
+    ///
+    /// Panics if `COLS` columns or `ROWS` lines of text do not fit on the display.
+    #[allow(clippy::nonminimal_bool)]
+    pub fn new(display: Display<DI>, font: F) -> Self {
+        let size = display.size();
+        let cell_width = font.advance(' ') as usize;
+        let line_height = font.height() as usize;
+        if false
+            || COLS == 0
+            || ROWS == 0
+            || line_height == 0
+            || COLS * cell_width > size.0 as usize
+            || ROWS * line_height > size.1 as usize
+        {
+            panic!("Console does not fit on the display.");
+        }
+        Console {
+            display: display,
+            fg: 15,
+            bg: 0,
+            grid: [[b' '; COLS]; ROWS],
+            top: 0,
+            dirty: [true; ROWS],
+            line: 0,
+            col: 0,
+            hw_scroll: (NUM_PIXEL_ROWS as usize).is_multiple_of(line_height),
+            start_line: 0,
+            font: font,
+        }
+    }
+
+    /// Set the gray levels used for text and background. Only lines drawn afterwards are affected.
+    pub fn set_colors(&mut self, fg: u8, bg: u8) {
+        self.fg = fg & 0x0F;
+        self.bg = bg & 0x0F;
+    }
+
+    /// Consume the console, returning the display.
+    pub fn into_inner(self) -> Display<DI> {
+        self.display
+    }
+
+    /// Blank the console, home the cursor, and redraw it.
+    pub fn clear(&mut self) -> Result<(), CommandError<DI::Error>> {
+        self.grid = [[b' '; COLS]; ROWS];
+        self.dirty = [true; ROWS];
+        self.top = 0;
+        self.line = 0;
+        self.col = 0;
+        self.start_line = 0;
+        self.display.vertical_pan(0)?;
+        self.flush()
+    }
+
+    /// Draw all lines which have changed since they were last drawn.
+    pub fn flush(&mut self) -> Result<(), CommandError<DI::Error>> {
+        for line in 0..ROWS {
+            if self.dirty[line] {
+                self.draw_line(line)?;
+                self.dirty[line] = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Put a character at the cursor, handling control characters.
+    fn put(&mut self, c: char) -> Result<(), CommandError<DI::Error>> {
+        match c {
+            '\n' => return self.newline(),
+            '\r' => self.col = 0,
+            _ => {
+                if self.col >= COLS {
+                    self.newline()?;
+                }
+                let byte = if c.is_ascii() && !c.is_ascii_control() {
+                    c as u8
+                } else {
+                    b'?'
+                };
+                self.grid[(self.top + self.line) % ROWS][self.col] = byte;
+                self.dirty[self.line] = true;
+                self.col += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Move the cursor to the start of the next line, scrolling if it is on the last line.
+    fn newline(&mut self) -> Result<(), CommandError<DI::Error>> {
+        self.col = 0;
+        if self.line + 1 < ROWS {
+            self.line += 1;
+            return Ok(());
+        }
+        // Draw anything pending before the lines shift, then drop the top line and recycle it as
+        // the new, blank, bottom line.
+        self.flush()?;
+        self.grid[self.top] = [b' '; COLS];
+        self.top = (self.top + 1) % ROWS;
+        if self.hw_scroll {
+            // The lines already drawn move up along with the start line. Draw the new line below
+            // the visible area before panning, so it never shows stale RAM contents.
+            let height = self.font.height() as u16;
+            self.start_line = ((self.start_line as u16 + height) % NUM_PIXEL_ROWS as u16) as u8;
+            self.draw_line(ROWS - 1)?;
+            self.display.vertical_pan(self.start_line)?;
+        } else {
+            self.dirty = [true; ROWS];
+        }
+        Ok(())
+    }
+
+    /// Draw the text line shown at `line` lines from the top of the screen.
+    fn draw_line(&mut self, line: usize) -> Result<(), CommandError<DI::Error>> {
+        let height = self.font.height() as u16;
+        let width = self.display.size().0;
+        let top = (self.start_line as u16 + line as u16 * height) % NUM_PIXEL_ROWS as u16;
+        let chars = &self.grid[(self.top + line) % ROWS];
+        // The grid only ever holds printable ASCII, so this cannot fail.
+        let text = core::str::from_utf8(chars).unwrap_or("");
+        let pixels = TextPixels::new(&self.font, text, width as u16, self.fg, self.bg);
+        self.display
+            .region(
+                PixelCoord(0, top as i16),
+                PixelCoord(width, (top + height) as i16),
+            )?
+            .draw(pixels)
+            .map_err(CommandError::InterfaceError)
+    }
+}
+
+impl<DI, F, const COLS: usize, const ROWS: usize> fmt::Write for Console<DI, F, COLS, ROWS>
+where
+    DI: interface::DisplayInterface,
+    F: Font,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            self.put(c).map_err(|_| fmt::Error)?;
+        }
+        self.flush().map_err(|_| fmt::Error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+    use crate::test_support::initialized_display;
+    use crate::text::tests::TEST_FONT;
+    use crate::text::MonoFont;
+    use core::fmt::Write;
+    use std::vec::Vec;
+
+    fn console<const COLS: usize, const ROWS: usize>(
+        font: MonoFont<'static>,
+    ) -> (
+        MockInterface,
+        Console<MockInterface, MonoFont<'static>, COLS, ROWS>,
+    ) {
+        let (di, disp) = initialized_display(Px(8, 16));
+        let mut con = Console::new(disp, font);
+        con.clear().unwrap();
+        (di, con)
+    }
+
+    /// Render the visible image as a string of characters, '#' for lit pixels.
+    fn screen(di: &MockInterface) -> Vec<u8> {
+        di.chip_model(Px(8, 16), Px(0, 0))
+            .render()
+            .iter()
+            .map(|&p| if p > 0 { b'#' } else { b'.' })
+            .collect()
+    }
+
+    #[test]
+    fn write_and_wrap() {
+        let (di, mut con) = console::<2, 3>(TEST_FONT);
+        write!(con, "ABA").unwrap();
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di)[..8 * 6], &b"\
+            ####.#..\
+            #..#.#..\
+            ####.#..\
+            ####....\
+            #..#....\
+            ####....\
+        "[..]);
+    }
+
+    #[test]
+    fn hardware_scroll() {
+        // A font 16 rows tall divides display RAM evenly, so scrolling uses the start line.
+        const TALL: MonoFont<'static> = MonoFont {
+            width: 4,
+            height: 16,
+            first: 'A',
+            glyphs: &[0xF0; 16],
+        };
+        let (di, mut con) = console::<2, 1>(TALL);
+        write!(con, "A").unwrap();
+        let before = di.sent().len();
+        write!(con, "\nAA").unwrap();
+        let sent = &di.sent()[before..];
+        // Blank line drawn below, panned into view, and then the new text drawn.
+        assert_eq!(sent.iter().filter(|s| **s == Sent::Cmd(0xA1)).count(), 1);
+        assert!(sent.contains(&Sent::Data(vec![16])));
+        assert!(screen(&di).chunks(8).all(|row| row == b"########"));
+    }
+
+    #[test]
+    fn software_scroll() {
+        let (di, mut con) = console::<2, 2>(TEST_FONT);
+        write!(con, "A\nB\nAB").unwrap();
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di)[..8 * 6], &b"\
+            .#......\
+            .#......\
+            .#......\
+            ####.#..\
+            #..#.#..\
+            ####.#..\
+        "[..]);
+    }
+}
@@ -0,0 +1,57 @@
+//! CRC-32 checksums of image data, for checking that what reached the display is what was meant to
+//! be drawn. This is the common IEEE 802.3 CRC-32, computed bitwise so that no table is needed.
+
+/// An incremental CRC-32 calculation.
+#[derive(Clone, Copy, Debug)]
+pub struct Crc32 {
+    state: u32,
+}
+
+impl Crc32 {
+    /// Start a new calculation.
+    pub fn new() -> Self {
+        Crc32 { state: !0 }
+    }
+
+    /// Add a byte to the calculation.
+    pub fn update(&mut self, byte: u8) {
+        self.state ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (self.state & 1).wrapping_neg();
+            self.state = (self.state >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+
+    /// The checksum of the bytes added so far.
+    pub fn finish(&self) -> u32 {
+        !self.state
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The CRC-32 of a sequence of bytes, e.g. the packed image data to be passed to
+/// `Region::draw_packed_checked`.
+pub fn crc32<I>(iter: I) -> u32
+where
+    I: IntoIterator<Item = u8>,
+{
+    let mut crc = Crc32::new();
+    iter.into_iter().for_each(|byte| crc.update(byte));
+    crc.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_value() {
+        assert_eq!(crc32(b"123456789".iter().cloned()), 0xCBF4_3926);
+        assert_eq!(crc32(core::iter::empty()), 0);
+    }
+}
@@ -0,0 +1,337 @@
+//! A shadow buffer for a band of rows, such as a status bar.
+//!
+//! A `BufferedDisplay` shadows the whole display, which on a 256x64 panel takes 8 KiB of RAM. Often
+//! only a small part of the screen needs pixel-level drawing, for example a status bar where text
+//! is drawn over icons, while the rest is drawn straight to the display through regions. A
+//! `BandBuffer` shadows just the rows of such a band, packed as for `Region::draw_packed`, so it
+//! needs `width * rows / 2` bytes. Pixels in the band can be read back and combined before
+//! `flush` sends the rows which changed.
+//!
+//! The band does not own the display, so drawing outside it goes on as before. Anything drawn into
+//! the band's rows other than through the band is overwritten by its next flush of those rows.
+
+use crate::chip::Chip;
+use crate::command::CommandError;
+use crate::display::{Display, PixelCoord, Rect};
+use crate::interface;
+use crate::sprite::Sprite;
+#[cfg(feature = "text")]
+use crate::text::{blend, Font};
+
+/// A shadow buffer for the full-width band of rows of a display from `first_row`. See the module
+/// documentation.
+pub struct BandBuffer<B>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    buffer: B,
+    /// The area of the display covered by the band.
+    band: Rect,
+    /// The first and last rows of the band changed since the last flush, if any, counted from the
+    /// top of the band.
+    dirty: Option<(usize, usize)>,
+}
+
+impl<'b> BandBuffer<&'b mut [u8]> {
+    /// Buffer the band of `rows` rows of `display` starting at `first_row` in `buffer`, which must
+    /// be exactly `width * rows / 2` bytes long for the display's width, or
+    /// `CommandError::BadDataLength` is returned. The band must lie within the display's viewable
+    /// area, or `CommandError::OutOfRange` is returned. The buffer contents are taken as the
+    /// current image of the band.
+    pub fn with_buffer<DI, C>(
+        display: &Display<DI, C>,
+        first_row: i16,
+        rows: i16,
+        buffer: &'b mut [u8],
+    ) -> Result<Self, CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+        C: Chip,
+    {
+        Self::from_parts(display, first_row, rows, buffer)
+    }
+}
+
+impl<const N: usize> BandBuffer<[u8; N]> {
+    /// Buffer the band of `rows` rows of `display` starting at `first_row` in an embedded buffer
+    /// of `N` bytes, with the same requirements as for `with_buffer`. For example, a 16 row band
+    /// of a 256 pixel wide display needs `BandBuffer<[u8; 2048]>`. The buffer starts out all
+    /// black, but is not sent until the first `flush`.
+    pub fn new<DI, C>(
+        display: &Display<DI, C>,
+        first_row: i16,
+        rows: i16,
+    ) -> Result<Self, CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+        C: Chip,
+    {
+        Self::from_parts(display, first_row, rows, [0; N])
+    }
+}
+
+impl<B> BandBuffer<B>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    fn from_parts<DI, C>(
+        display: &Display<DI, C>,
+        first_row: i16,
+        rows: i16,
+        buffer: B,
+    ) -> Result<Self, CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+        C: Chip,
+    {
+        let size = display.size();
+        if first_row < 0 || rows <= 0 || first_row + rows > size.1 {
+            return Err(CommandError::OutOfRange);
+        }
+        let expected = size.0 as usize * rows as usize / 2;
+        if buffer.as_ref().len() != expected {
+            return Err(CommandError::BadDataLength {
+                expected: expected,
+                actual: buffer.as_ref().len(),
+            });
+        }
+        Ok(BandBuffer {
+            buffer: buffer,
+            band: Rect::new(
+                PixelCoord(0, first_row),
+                PixelCoord(size.0, first_row + rows),
+            ),
+            dirty: None,
+        })
+    }
+
+    /// The area of the display covered by the band.
+    pub fn band(&self) -> Rect {
+        self.band
+    }
+
+    /// Consume the band, returning the buffer.
+    pub fn into_inner(self) -> B {
+        self.buffer
+    }
+
+    /// The buffer contents.
+    pub fn buffer(&self) -> &[u8] {
+        self.buffer.as_ref()
+    }
+
+    /// Mutably borrow the buffer contents, to draw into it directly. The whole band is sent on the
+    /// next `flush`.
+    pub fn buffer_mut(&mut self) -> &mut [u8] {
+        self.dirty = Some((0, self.band.height() as usize - 1));
+        self.buffer.as_mut()
+    }
+
+    /// The gray level of the pixel at `point`, in display coordinates, or `None` if it is outside
+    /// the band.
+    pub fn pixel(&self, point: PixelCoord) -> Option<u8> {
+        let (index, shift) = self.locate(point)?;
+        Some(self.buffer.as_ref()[index] >> shift & 0x0F)
+    }
+
+    /// Set the pixel at `point`, in display coordinates, to gray level `level`. Pixels outside the
+    /// band are ignored.
+    pub fn set_pixel(&mut self, point: PixelCoord, level: u8) {
+        if let Some((index, shift)) = self.locate(point) {
+            let byte = &mut self.buffer.as_mut()[index];
+            *byte = *byte & !(0x0F << shift) | (level & 0x0F) << shift;
+            self.mark_dirty((point.1 - self.band.ul.1) as usize);
+        }
+    }
+
+    /// Set every pixel of the band to gray level `level`.
+    pub fn fill(&mut self, level: u8) {
+        let level = level & 0x0F;
+        for byte in self.buffer_mut() {
+            *byte = level << 4 | level;
+        }
+    }
+
+    /// Copy `sprite` into the band with its upper left corner at `origin`, in display
+    /// coordinates, clipped to the band.
+    pub fn draw_sprite(&mut self, sprite: &Sprite<'_>, origin: PixelCoord) {
+        for y in 0..sprite.height() as i16 {
+            for x in 0..sprite.width() as i16 {
+                if let Some(level) = sprite.pixel(x, y) {
+                    self.set_pixel(origin + PixelCoord(x, y), level);
+                }
+            }
+        }
+    }
+
+    /// Draw one line of `text` with `font` in gray level `fg`, with the upper left corner of the
+    /// first character at `origin`, in display coordinates, clipped to the band. Unlike text drawn
+    /// through a region, there is no background: each pixel of a glyph is blended over what the
+    /// band already holds according to its coverage, so text can be drawn over icons. Returns the
+    /// column just right of the text.
+    #[cfg(feature = "text")]
+    pub fn draw_text<F>(&mut self, font: &F, text: &str, origin: PixelCoord, fg: u8) -> i16
+    where
+        F: Font,
+    {
+        let fg = fg & 0x0F;
+        let mut left = origin.0;
+        for c in text.chars() {
+            for y in 0..font.height() {
+                for x in 0..font.advance(c) {
+                    let coverage = font.pixel(c, x, y);
+                    let point = PixelCoord(left + x as i16, origin.1 + y as i16);
+                    if let (1..=15, Some(bg)) = (coverage, self.pixel(point)) {
+                        self.set_pixel(point, blend(coverage, fg, bg));
+                    }
+                }
+            }
+            left += font.advance(c) as i16;
+        }
+        left
+    }
+
+    /// Send the rows of the band changed since the last flush to `display`, which must be the
+    /// display the band was created for.
+    pub fn flush<DI, C>(
+        &mut self,
+        display: &mut Display<DI, C>,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+        C: Chip,
+    {
+        let (first, last) = match self.dirty {
+            Some(rows) => rows,
+            None => return Ok(()),
+        };
+        let row_bytes = self.band.width() as usize / 2;
+        let data = &self.buffer.as_ref()[first * row_bytes..(last + 1) * row_bytes];
+        display
+            .region(
+                PixelCoord(0, self.band.ul.1 + first as i16),
+                PixelCoord(self.band.lr.0, self.band.ul.1 + last as i16 + 1),
+            )?
+            .draw_packed(data.iter().cloned())
+            .map_err(CommandError::InterfaceError)?;
+        self.dirty = None;
+        Ok(())
+    }
+
+    /// The byte index and bit shift of the pixel at `point` in the buffer.
+    fn locate(&self, point: PixelCoord) -> Option<(usize, u8)> {
+        if !self.band.contains(point) {
+            return None;
+        }
+        let row = (point.1 - self.band.ul.1) as usize;
+        let index = (row * self.band.width() as usize + point.0 as usize) / 2;
+        let shift = if point.0 % 2 == 0 { 4 } else { 0 };
+        Some((index, shift))
+    }
+
+    fn mark_dirty(&mut self, row: usize) {
+        self.dirty = Some(match self.dirty {
+            Some((first, last)) => (first.min(row), last.max(row)),
+            None => (row, row),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+    use std::vec::Vec;
+
+    /// The row ranges of the regions drawn, and the image data sent.
+    fn drawn(di: &MockInterface) -> (Vec<Vec<u8>>, Vec<u8>) {
+        let sent = di.sent();
+        let rows = sent
+            .windows(2)
+            .filter_map(|pair| match pair {
+                [Sent::Cmd(0x75), Sent::Data(rows)] => Some(rows.clone()),
+                _ => None,
+            })
+            .collect();
+        let start = sent.iter().position(|s| *s == Sent::Cmd(0x5C));
+        let image = sent[start.map_or(sent.len(), |start| start + 1)..]
+            .iter()
+            .flat_map(|s| match s {
+                Sent::Data(data) => data.clone(),
+                Sent::Cmd(_) => Vec::new(),
+            })
+            .collect();
+        (rows, image)
+    }
+
+    #[test]
+    fn bounds() {
+        let di = MockInterface::new();
+        let disp = Display::new(di.split(), Px(16, 16), Px(0, 0));
+        assert!(BandBuffer::<[u8; 32]>::new(&disp, 12, 4).is_ok());
+        assert_eq!(
+            BandBuffer::<[u8; 32]>::new(&disp, 13, 4).err(),
+            Some(CommandError::OutOfRange)
+        );
+        assert_eq!(
+            BandBuffer::<[u8; 24]>::new(&disp, 0, 4).err(),
+            Some(CommandError::BadDataLength {
+                expected: 32,
+                actual: 24
+            })
+        );
+
+        let mut band = BandBuffer::<[u8; 32]>::new(&disp, 12, 4).unwrap();
+        assert_eq!(band.band(), Rect::new(Px(0, 12), Px(16, 16)));
+        band.set_pixel(Px(3, 11), 15);
+        band.set_pixel(Px(3, 13), 15);
+        assert_eq!(band.pixel(Px(3, 11)), None);
+        assert_eq!(band.pixel(Px(3, 13)), Some(15));
+        assert_eq!(band.buffer()[9], 0x0F);
+    }
+
+    #[test]
+    fn flush_changed_rows() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(8, 16), Px(0, 0));
+        let mut band = BandBuffer::<[u8; 16]>::new(&disp, 4, 4).unwrap();
+        band.flush(&mut disp).unwrap();
+        assert_eq!(di.sent(), []);
+
+        band.set_pixel(Px(1, 6), 3);
+        band.set_pixel(Px(6, 5), 4);
+        band.flush(&mut disp).unwrap();
+        #[rustfmt::skip]
+        assert_eq!(drawn(&di), (vec![vec![5, 6]], vec![
+            0x00, 0x00, 0x00, 0x40,
+            0x03, 0x00, 0x00, 0x00,
+        ]));
+        di.clear();
+        band.flush(&mut disp).unwrap();
+        assert_eq!(di.sent(), []);
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn text_over_sprite() {
+        use crate::text::tests::TEST_FONT;
+
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(8, 16), Px(0, 0));
+        let mut band = BandBuffer::<[u8; 12]>::new(&disp, 13, 3).unwrap();
+        let icon = Sprite::new(2, 3, &[0x55, 0x55, 0x55]);
+        band.draw_sprite(&icon, Px(1, 13));
+        // The box outline of 'A' covers the left and right columns, so the icon shows through the
+        // middle of it.
+        assert_eq!(band.draw_text(&TEST_FONT, "A", Px(0, 13), 15), 4);
+        di.clear();
+        band.flush(&mut disp).unwrap();
+        #[rustfmt::skip]
+        assert_eq!(drawn(&di), (vec![vec![13, 15]], vec![
+            0xFF, 0xFF, 0x00, 0x00,
+            0xF5, 0x5F, 0x00, 0x00,
+            0xFF, 0xFF, 0x00, 0x00,
+        ]));
+    }
+}
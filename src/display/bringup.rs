@@ -0,0 +1,187 @@
+//! Diagnostics for bringing up display modules which come without a datasheet.
+//!
+//! The COM scan direction, COM layout and row offset of a module depend on how its glass is wired
+//! to the chip, and a wrong guess at any of them gives a scrambled, mirrored or shifted image which
+//! does not say much about which setting is off. `Display::probe_remapping` tries each combination
+//! in turn, drawing a pattern which only looks right when all of them are correct, and asks the
+//! application after each whether it does, for example by waiting for a button press.
+
+use crate::command::{consts, ComLayout, ComScanDirection, CommandError};
+use crate::config::Config;
+use crate::display::{Display, PixelCoord};
+use crate::interface;
+
+/// The COM layouts tried by `Display::probe_remapping`, in order.
+const LAYOUTS: [ComLayout; 3] = [
+    ComLayout::Progressive,
+    ComLayout::Interlaced,
+    ComLayout::DualProgressive,
+];
+
+/// The COM scan directions tried by `Display::probe_remapping`, in order.
+const DIRECTIONS: [ComScanDirection; 2] = [
+    ComScanDirection::RowZeroFirst,
+    ComScanDirection::RowZeroLast,
+];
+
+/// One combination of settings shown by `Display::probe_remapping`. Once the right one is found,
+/// pass its COM scan direction and COM layout to `Config::new`, and its row offset as
+/// `display_offset.1` to `Display::new`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RemapCandidate {
+    /// The COM scan direction.
+    pub com_scan_direction: ComScanDirection,
+    /// The COM layout.
+    pub com_layout: ComLayout,
+    /// The COM line of pixel row 0 of the display.
+    pub row_offset: u8,
+}
+
+impl<DI> Display<DI>
+where
+    DI: interface::DisplayInterface,
+{
+    /// Initialize the display with each combination of COM scan direction, COM layout and the row
+    /// offsets in `row_offsets` in turn, drawing a test pattern and then calling `looks_right`
+    /// with the combination shown. Offsets which would put the last row of the display past the
+    /// last COM line are skipped. Other settings are taken from `base`.
+    ///
+    /// When the settings are right, the pattern is a dim border around the whole display, with a
+    /// bright square in the top left corner and a bright, unbroken diagonal line running down and
+    /// to the right from it. A mirrored pattern means a wrong scan direction, a broken or doubled
+    /// line a wrong layout, and a missing or wrapped edge of the border a wrong offset.
+    ///
+    /// Stops at the first combination for which `looks_right` returns true and returns it, leaving
+    /// the display initialized with it. Otherwise returns `None` once all have been shown, and
+    /// the display must be initialized again before use.
+    pub fn probe_remapping<F>(
+        &mut self,
+        base: &Config,
+        row_offsets: &[u8],
+        mut looks_right: F,
+    ) -> Result<Option<RemapCandidate>, CommandError<DI::Error>>
+    where
+        F: FnMut(RemapCandidate) -> bool,
+    {
+        let offset = self.display_offset;
+        for &row_offset in row_offsets {
+            if row_offset as i16 + self.display_size.1 > consts::NUM_PIXEL_ROWS as i16 {
+                continue;
+            }
+            for &layout in LAYOUTS.iter() {
+                for &direction in DIRECTIONS.iter() {
+                    let candidate = RemapCandidate {
+                        com_scan_direction: direction,
+                        com_layout: layout,
+                        row_offset: row_offset,
+                    };
+                    self.display_offset = PixelCoord(offset.0, row_offset as i16);
+                    let shown = self
+                        .init(base.clone().with_com(direction, layout))
+                        .and_then(|()| self.draw_probe_pattern());
+                    if let Err(e) = shown {
+                        self.display_offset = offset;
+                        return Err(e);
+                    }
+                    if looks_right(candidate) {
+                        return Ok(Some(candidate));
+                    }
+                }
+            }
+        }
+        self.display_offset = offset;
+        Ok(None)
+    }
+
+    /// Draw the pattern described by `probe_remapping` over the whole display.
+    fn draw_probe_pattern(&mut self) -> Result<(), CommandError<DI::Error>> {
+        let PixelCoord(width, height) = self.display_size;
+        let corner = (width.min(height) / 4).max(2);
+        let level = move |x: i16, y: i16| -> u8 {
+            if (x < corner && y < corner) || x == y {
+                15
+            } else if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+                6
+            } else {
+                0
+            }
+        };
+        let packed = (0..height).flat_map(move |y| {
+            (0..width)
+                .step_by(2)
+                .map(move |x| level(x, y) << 4 | level(x + 1, y))
+        });
+        self.region(PixelCoord(0, 0), self.display_size)?
+            .draw_packed(packed)
+            .map_err(CommandError::InterfaceError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+    use std::vec::Vec;
+
+    #[test]
+    fn finds_candidate() {
+        let di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(8, 16), Px(0, 0));
+        let base = Config::new(ComScanDirection::RowZeroFirst, ComLayout::Progressive);
+        let mut shown = Vec::new();
+        let found = disp
+            .probe_remapping(&base, &[120, 0, 4], |candidate| {
+                shown.push(candidate);
+                shown.len() == 8
+            })
+            .unwrap();
+        // The offset of 120 does not leave room for 16 rows.
+        assert_eq!(shown[0].row_offset, 0);
+        assert_eq!(shown[1].com_scan_direction, ComScanDirection::RowZeroLast);
+        assert_eq!(shown[2].com_layout, ComLayout::Interlaced);
+        assert_eq!(
+            found,
+            Some(RemapCandidate {
+                com_scan_direction: ComScanDirection::RowZeroLast,
+                com_layout: ComLayout::Progressive,
+                row_offset: 4,
+            })
+        );
+        assert_eq!(disp.offset(), Px(0, 4));
+
+        // The pattern drawn for the last candidate.
+        let sent = di.sent();
+        let start = sent.iter().rposition(|s| *s == Sent::Cmd(0x5C)).unwrap();
+        let image: Vec<u8> = sent[start + 1..]
+            .iter()
+            .flat_map(|s| match s {
+                Sent::Data(data) => data.clone(),
+                Sent::Cmd(_) => Vec::new(),
+            })
+            .collect();
+        assert_eq!(image.len(), 64);
+        #[rustfmt::skip]
+        assert_eq!(image[..12], [
+            0xFF, 0x66, 0x66, 0x66,
+            0xFF, 0x00, 0x00, 0x06,
+            0x60, 0xF0, 0x00, 0x06,
+        ]);
+    }
+
+    #[test]
+    fn none_found() {
+        let di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(8, 16), Px(0, 2));
+        let base = Config::new(ComScanDirection::RowZeroFirst, ComLayout::Progressive);
+        let mut count = 0;
+        let found = disp
+            .probe_remapping(&base, &[0], |_| {
+                count += 1;
+                false
+            })
+            .unwrap();
+        assert_eq!((found, count), (None, 6));
+        assert_eq!(disp.offset(), Px(0, 2));
+    }
+}
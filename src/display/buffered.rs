@@ -0,0 +1,432 @@
+//! Buffered drawing through a shadow copy of the display image.
+//!
+//! The display RAM can only be written in groups of 4 pixels and is not read back, so drawing
+//! individual pixels directly would clobber their neighbours. A `BufferedDisplay` keeps a shadow
+//! framebuffer in the host, where pixels can be set individually, and `flush` sends the rows which
+//! changed since the last flush to the display.
+//!
+//! The framebuffer holds the viewable area of the display packed as for `Region::draw_packed`,
+//! two pixels to a byte, so it needs `width * height / 2` bytes. `BufferedDisplay` does not
+//! allocate it; `with_buffer` takes a slice provided by the caller, which may for example live in
+//! a `static` or a particular RAM bank. Alternatively, `new` embeds the buffer in the
+//! `BufferedDisplay` as an array of `N` bytes, which for small panels gives a self-contained
+//! buffered driver.
+//!
+//! The panel scans display RAM continuously, with no way to synchronize writes to the scan, so a
+//! full-screen change written while the scan passes over it shows the old image above the scan
+//! line and the new one below it for a frame. `set_flush_order` offers ways of ordering the rows
+//! sent by `flush` which make this tearing less likely or less visible.
+
+use crate::command::CommandError;
+use crate::display::{Display, PixelCoord};
+use crate::interface;
+
+/// The order in which `BufferedDisplay::flush` sends changed rows. See the module documentation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FlushOrder {
+    /// Send all changed rows from top to bottom. This is the default.
+    TopDown,
+    /// Send all changed rows, starting with the half of RAM which the panel is estimated not to be
+    /// scanning at the time given to `flush_at`, so that the scan is more likely to be in the half
+    /// already written by the time the other half is sent. The estimate is based on
+    /// `Display::estimated_frame_period` and the time of a frame start recorded with `sync_scan`,
+    /// so it drifts with the panel's oscillator and assumes a progressive COM layout.
+    AvoidScan,
+    /// Send the changed rows of one half of RAM per flush, alternating between the halves and
+    /// leaving the other half's changes for the next flush. Each write then takes at most half a
+    /// frame, so with one flush per frame, paced for example by `Display::wait_for_frame`, a
+    /// full-screen change is shown in two steps instead of tearing at a random row.
+    SplitFrames,
+}
+
+/// A display with a shadow framebuffer. See the module documentation.
+pub struct BufferedDisplay<DI, B>
+where
+    DI: interface::DisplayInterface,
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    display: Display<DI>,
+    buffer: B,
+    /// The first and last rows changed since the last flush, if any.
+    dirty: Option<(usize, usize)>,
+    order: FlushOrder,
+    /// The time a frame scan was last known to start, for `FlushOrder::AvoidScan`.
+    scan_origin_us: u32,
+    /// Whether the bottom half is sent next, for `FlushOrder::SplitFrames`.
+    bottom_next: bool,
+    /// Whether the rows being flushed include every row of the display.
+    sending_all: bool,
+}
+
+/// The length in bytes of a framebuffer for `display`.
+fn buffer_len<DI>(display: &Display<DI>) -> usize
+where
+    DI: interface::DisplayInterface,
+{
+    let size = display.size();
+    size.0 as usize * size.1 as usize / 2
+}
+
+impl<'b, DI> BufferedDisplay<DI, &'b mut [u8]>
+where
+    DI: interface::DisplayInterface,
+{
+    /// Buffer drawing to `display` in `buffer`, which must be exactly `width * height / 2` bytes
+    /// long for the display's viewable size, or `CommandError::BadDataLength` is returned along
+    /// with the display. The buffer contents are taken as the current display image; use `fill` to
+    /// start from a known image.
+    #[allow(clippy::result_large_err)]
+    pub fn with_buffer(
+        display: Display<DI>,
+        buffer: &'b mut [u8],
+    ) -> Result<Self, (Display<DI>, CommandError<DI::Error>)> {
+        Self::from_parts(display, buffer)
+    }
+}
+
+impl<DI, const N: usize> BufferedDisplay<DI, [u8; N]>
+where
+    DI: interface::DisplayInterface,
+{
+    /// Buffer drawing to `display` in an embedded buffer of `N` bytes, which must be exactly
+    /// `width * height / 2` for the display's viewable size, or `CommandError::BadDataLength` is
+    /// returned along with the display. For example, a 128x64 display needs
+    /// `BufferedDisplay<_, [u8; 4096]>`. The buffer starts out all black, but is not sent until
+    /// the first `flush`.
+    #[allow(clippy::result_large_err)]
+    pub fn new(display: Display<DI>) -> Result<Self, (Display<DI>, CommandError<DI::Error>)> {
+        Self::from_parts(display, [0; N])
+    }
+}
+
+impl<DI, B> BufferedDisplay<DI, B>
+where
+    DI: interface::DisplayInterface,
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    // The display is returned by value on error, as there is no allocator to box it with.
+    #[allow(clippy::result_large_err)]
+    fn from_parts(
+        display: Display<DI>,
+        buffer: B,
+    ) -> Result<Self, (Display<DI>, CommandError<DI::Error>)> {
+        let expected = buffer_len(&display);
+        if buffer.as_ref().len() != expected {
+            let actual = buffer.as_ref().len();
+            return Err((
+                display,
+                CommandError::BadDataLength {
+                    expected: expected,
+                    actual: actual,
+                },
+            ));
+        }
+        Ok(BufferedDisplay {
+            display: display,
+            buffer: buffer,
+            dirty: None,
+            order: FlushOrder::TopDown,
+            scan_origin_us: 0,
+            bottom_next: false,
+            sending_all: false,
+        })
+    }
+
+    /// Borrow the underlying display, for example to change its contrast.
+    pub fn display(&mut self) -> &mut Display<DI> {
+        &mut self.display
+    }
+
+    /// Consume the buffered display, returning the display and the buffer.
+    pub fn into_inner(self) -> (Display<DI>, B) {
+        (self.display, self.buffer)
+    }
+
+    /// The framebuffer contents.
+    pub fn buffer(&self) -> &[u8] {
+        self.buffer.as_ref()
+    }
+
+    /// Mutably borrow the framebuffer contents, to draw into it directly. The whole display is
+    /// sent on the next `flush`.
+    pub fn buffer_mut(&mut self) -> &mut [u8] {
+        self.dirty = Some((0, self.display.size().1 as usize - 1));
+        self.buffer.as_mut()
+    }
+
+    /// The gray level of the pixel at `point`, or `None` if it is outside the display.
+    pub fn pixel(&self, point: PixelCoord) -> Option<u8> {
+        let (index, shift) = self.locate(point)?;
+        Some(self.buffer.as_ref()[index] >> shift & 0x0F)
+    }
+
+    /// Set the pixel at `point` to gray level `level`. Pixels outside the display are ignored.
+    pub fn set_pixel(&mut self, point: PixelCoord, level: u8) {
+        if let Some((index, shift)) = self.locate(point) {
+            let byte = &mut self.buffer.as_mut()[index];
+            *byte = *byte & !(0x0F << shift) | (level & 0x0F) << shift;
+            self.mark_dirty(point.1 as usize);
+        }
+    }
+
+    /// Set every pixel to gray level `level`.
+    pub fn fill(&mut self, level: u8) {
+        let level = level & 0x0F;
+        for byte in self.buffer_mut() {
+            *byte = level << 4 | level;
+        }
+    }
+
+    /// Set the order in which `flush` sends changed rows.
+    pub fn set_flush_order(&mut self, order: FlushOrder) {
+        self.order = order;
+    }
+
+    /// Record that the panel started scanning a frame at `now_us`, a timestamp in microseconds
+    /// which may wrap around, for `FlushOrder::AvoidScan` to estimate the scan position from.
+    /// The panel starts scanning when it is switched on, so the time just after `Display::init`
+    /// is a reasonable choice.
+    pub fn sync_scan(&mut self, now_us: u32) {
+        self.scan_origin_us = now_us;
+    }
+
+    /// Send the rows changed since the last flush to the display, in the order set with
+    /// `set_flush_order`. Once every row has been sent, by one flush or over two with
+    /// `FlushOrder::SplitFrames`, the display's `ram_is_dirty` is cleared. For
+    /// `FlushOrder::AvoidScan` the scan is taken to be at the start of a frame; use `flush_at` to
+    /// give the time instead.
+    pub fn flush(&mut self) -> Result<(), CommandError<DI::Error>> {
+        self.flush_at(self.scan_origin_us)
+    }
+
+    /// Send the rows changed since the last flush to the display, as for `flush`, where `now_us`
+    /// is the current time on the clock given to `sync_scan`.
+    pub fn flush_at(&mut self, now_us: u32) -> Result<(), CommandError<DI::Error>> {
+        let (first, last) = match self.dirty {
+            Some(rows) => rows,
+            None => return Ok(()),
+        };
+        let height = self.display.size().1 as usize;
+        self.sending_all |= (first, last) == (0, height - 1);
+        let half = height / 2;
+        let top = (first < half).then(|| (first, last.min(half - 1)));
+        let bottom = (last >= half).then(|| (first.max(half), last));
+        match self.order {
+            FlushOrder::TopDown => self.send_rows(first, last)?,
+            FlushOrder::AvoidScan => {
+                let halves = if self.scanning_top(now_us) {
+                    [bottom, top]
+                } else {
+                    [top, bottom]
+                };
+                for (first, last) in halves.iter().flatten() {
+                    self.send_rows(*first, *last)?;
+                }
+            }
+            FlushOrder::SplitFrames => {
+                let (send, keep) = match (top, bottom) {
+                    (Some(top), Some(bottom)) if self.bottom_next => (bottom, Some(top)),
+                    (Some(top), bottom) => (top, bottom),
+                    (None, Some(bottom)) => (bottom, None),
+                    (None, None) => return Ok(()),
+                };
+                self.send_rows(send.0, send.1)?;
+                self.bottom_next = send.0 < half;
+                self.dirty = keep;
+            }
+        }
+        if self.order != FlushOrder::SplitFrames {
+            self.dirty = None;
+        }
+        if self.dirty.is_none() && self.sending_all {
+            self.sending_all = false;
+            self.display.mark_ram_clean();
+        }
+        Ok(())
+    }
+
+    /// Send rows `first` to `last` inclusive from the buffer.
+    fn send_rows(&mut self, first: usize, last: usize) -> Result<(), CommandError<DI::Error>> {
+        let width = self.display.size().0;
+        let row_bytes = width as usize / 2;
+        let data = &self.buffer.as_ref()[first * row_bytes..(last + 1) * row_bytes];
+        self.display
+            .region(
+                PixelCoord(0, first as i16),
+                PixelCoord(width, last as i16 + 1),
+            )?
+            .draw_packed(data.iter().cloned())
+            .map_err(CommandError::InterfaceError)
+    }
+
+    /// Whether the panel is estimated to be scanning the top half of RAM at `now_us`.
+    fn scanning_top(&self, now_us: u32) -> bool {
+        let period = self.display.estimated_frame_period().max(1);
+        let phase = now_us.wrapping_sub(self.scan_origin_us) % period;
+        let first_half = phase < period / 2;
+        first_half == self.display.scans_row_zero_first()
+    }
+
+    /// The byte index and bit shift of the pixel at `point` in the buffer.
+    fn locate(&self, point: PixelCoord) -> Option<(usize, u8)> {
+        let size = self.display.size();
+        if point.0 < 0 || point.1 < 0 || point.0 >= size.0 || point.1 >= size.1 {
+            return None;
+        }
+        let index = (point.1 as usize * size.0 as usize + point.0 as usize) / 2;
+        let shift = if point.0 % 2 == 0 { 4 } else { 0 };
+        Some((index, shift))
+    }
+
+    fn mark_dirty(&mut self, row: usize) {
+        self.dirty = Some(match self.dirty {
+            Some((first, last)) => (first.min(row), last.max(row)),
+            None => (row, row),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+
+    #[test]
+    fn with_buffer() {
+        let mut di = MockInterface::new();
+        let disp = Display::new(di.split(), Px(8, 4), Px(0, 0));
+        let mut small = [0u8; 15];
+        let disp = match BufferedDisplay::with_buffer(disp, &mut small) {
+            Err((disp, e)) => {
+                assert_eq!(
+                    e,
+                    CommandError::BadDataLength {
+                        expected: 16,
+                        actual: 15
+                    }
+                );
+                disp
+            }
+            Ok(_) => panic!("buffer of the wrong length accepted"),
+        };
+
+        // The display is given back, so it can be used with a buffer of the right length.
+        let mut buf = [0u8; 16];
+        let mut buffered = BufferedDisplay::with_buffer(disp, &mut buf)
+            .map_err(|(_, e)| e)
+            .unwrap();
+        buffered.flush().unwrap();
+        assert_eq!(di.sent(), []);
+
+        buffered.set_pixel(Px(1, 1), 0xF);
+        buffered.set_pixel(Px(6, 2), 0x7);
+        buffered.set_pixel(Px(8, 0), 0x1);
+        assert_eq!(buffered.pixel(Px(1, 1)), Some(0xF));
+        assert_eq!(buffered.pixel(Px(0, 1)), Some(0));
+        assert_eq!(buffered.pixel(Px(-1, 0)), None);
+        buffered.flush().unwrap();
+        #[rustfmt::skip]
+        assert_eq!(di.sent(), [
+            Sent::Cmd(0x15), Sent::Data(vec![0, 1]),
+            Sent::Cmd(0x75), Sent::Data(vec![1, 2]),
+            Sent::Cmd(0x5C),
+            Sent::Data(vec![0x0F, 0, 0, 0, 0, 0, 0, 0x70]),
+        ]);
+        di.clear();
+        buffered.flush().unwrap();
+        assert_eq!(di.sent(), []);
+
+        buffered.fill(3);
+        buffered.flush().unwrap();
+        assert_eq!(di.sent()[5], Sent::Data(vec![0x33; 16]));
+        let (_, buf) = buffered.into_inner();
+        assert_eq!(buf[0], 0x33);
+    }
+
+    /// The ranges of rows written to the display.
+    fn rows_written(di: &MockInterface) -> Vec<Vec<u8>> {
+        di.sent()
+            .windows(2)
+            .filter_map(|pair| match pair {
+                [Sent::Cmd(0x75), Sent::Data(rows)] => Some(rows.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn avoid_scan() {
+        let mut di = MockInterface::new();
+        let disp = Display::new(di.split(), Px(8, 16), Px(0, 0));
+        // 196 DCLKs per row, 16 rows, at half of 2.74 MHz.
+        assert_eq!(disp.estimated_frame_period(), 2289);
+        let mut buffered = BufferedDisplay::<_, [u8; 64]>::new(disp)
+            .map_err(|(_, e)| e)
+            .unwrap();
+        buffered.set_flush_order(FlushOrder::AvoidScan);
+        buffered.sync_scan(1000);
+        // The scan is in the top half, so the bottom half goes first.
+        buffered.fill(1);
+        buffered.flush_at(1000 + 2289 * 10 + 100).unwrap();
+        assert_eq!(rows_written(&di), [vec![8, 15], vec![0, 7]]);
+        di.clear();
+        buffered.fill(2);
+        buffered.flush_at(1000 + 1500).unwrap();
+        // The window of rows 0 to 7 was the last written, so it is not set up again.
+        assert_eq!(rows_written(&di), [vec![8, 15]]);
+        assert_eq!(
+            di.sent()[..2],
+            [Sent::Cmd(0x5C), Sent::Data(vec![0x22; 32])]
+        );
+        // Only one half changed.
+        di.clear();
+        buffered.set_pixel(Px(0, 3), 5);
+        buffered.flush().unwrap();
+        assert_eq!(rows_written(&di), [vec![3, 3]]);
+    }
+
+    #[test]
+    fn split_frames() {
+        let di = MockInterface::new();
+        let disp = Display::new(di.split(), Px(8, 4), Px(0, 0));
+        let mut buffered = BufferedDisplay::<_, [u8; 16]>::new(disp)
+            .map_err(|(_, e)| e)
+            .unwrap();
+        buffered.set_flush_order(FlushOrder::SplitFrames);
+        buffered.fill(1);
+        buffered.flush().unwrap();
+        assert_eq!(rows_written(&di), [vec![0, 1]]);
+        assert!(buffered.display().ram_is_dirty());
+        // The next frame's changes in the top half wait until the bottom half has been sent.
+        buffered.set_pixel(Px(0, 0), 2);
+        buffered.flush().unwrap();
+        buffered.flush().unwrap();
+        buffered.flush().unwrap();
+        assert_eq!(rows_written(&di), [vec![0, 1], vec![2, 3], vec![0, 1]]);
+        // Both halves of the fill have been sent.
+        assert!(!buffered.display().ram_is_dirty());
+        assert_eq!(buffered.pixel(Px(0, 0)), Some(2));
+    }
+
+    #[test]
+    fn embedded_buffer() {
+        let di = MockInterface::new();
+        let disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let disp = match BufferedDisplay::<_, [u8; 4095]>::new(disp) {
+            Err((disp, _)) => disp,
+            Ok(_) => panic!("buffer of the wrong length accepted"),
+        };
+        let mut buffered = BufferedDisplay::<_, [u8; 4096]>::new(disp)
+            .map_err(|(_, e)| e)
+            .unwrap();
+        buffered.set_pixel(Px(127, 63), 9);
+        assert_eq!(buffered.buffer()[4095], 0x09);
+        buffered.flush().unwrap();
+        #[rustfmt::skip]
+        assert_eq!(&di.sent()[..4], [
+            Sent::Cmd(0x15), Sent::Data(vec![0, 31]),
+            Sent::Cmd(0x75), Sent::Data(vec![63, 63]),
+        ]);
+    }
+}
@@ -0,0 +1,178 @@
+//! One-call construction and initialization of a `Display`.
+//!
+//! Bringing up a display otherwise takes constructing it, pulsing the chip's reset line, and
+//! sending a `Config` with `Display::init`. A `DisplayBuilder` collects the geometry and
+//! configuration, either given explicitly or taken from a preset for a known display module, and
+//! does all of that in `build` or `build_with_reset`.
+
+use core::convert::Infallible;
+
+use embedded_hal as hal;
+
+use crate::command::{ComLayout, ComScanDirection, CommandError};
+use crate::config::Config;
+use crate::display::{Display, PixelCoord};
+use crate::interface;
+
+/// The time in milliseconds `build_with_reset` holds the chip in reset.
+const RESET_PULSE_MS: u16 = 10;
+
+/// The time in milliseconds `build_with_reset` waits after releasing reset before sending the init
+/// sequence.
+const RESET_RECOVERY_MS: u16 = 1;
+
+/// A builder for an initialized `Display`. See the module documentation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisplayBuilder {
+    size: PixelCoord,
+    offset: PixelCoord,
+    config: Config,
+}
+
+impl DisplayBuilder {
+    /// Start building a display with viewable dimensions `size` and configuration `config`, with
+    /// no offset. See `Display::with_chip` for a description of the geometry.
+    pub fn new(size: PixelCoord, config: Config) -> Self {
+        DisplayBuilder {
+            size: size,
+            offset: PixelCoord(0, 0),
+            config: config,
+        }
+    }
+
+    /// Start building a display from the preset for the display module with part number `name`,
+    /// or return `None` if there is no such preset. The presets use the geometry and configuration
+    /// from the module manufacturer's datasheet. Available presets:
+    ///
+    /// - `"NHD-3.12-25664UCY2"`: Newhaven 3.12" 256x64.
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "NHD-3.12-25664UCY2" => Some(
+                Self::new(
+                    PixelCoord(256, 64),
+                    Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive)
+                        .clock_fosc_divset(9, 1)
+                        .display_enhancements(true, true)
+                        .contrast_current(159)
+                        .phase_lengths(5, 14)
+                        .precharge_voltage(31)
+                        .second_precharge_period(8)
+                        .com_deselect_voltage(7),
+                )
+                .offset(PixelCoord(112, 0)),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Set the offset of the display's pixel numbering. See `Display::with_chip`.
+    pub fn offset(self, offset: PixelCoord) -> Self {
+        Self {
+            offset: offset,
+            ..self
+        }
+    }
+
+    /// Replace the configuration.
+    pub fn config(self, config: Config) -> Self {
+        Self {
+            config: config,
+            ..self
+        }
+    }
+
+    /// Adjust the configuration with `tweak`, for example to change a preset's contrast current.
+    pub fn tweak_config<F>(self, tweak: F) -> Self
+    where
+        F: FnOnce(Config) -> Config,
+    {
+        Self {
+            config: tweak(self.config),
+            ..self
+        }
+    }
+
+    /// Construct a `Display` connected to `iface` and initialize it. The chip must already be out
+    /// of reset.
+    pub fn build<DI>(self, iface: DI) -> Result<Display<DI>, CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        let mut display = Display::new(iface, self.size, self.offset);
+        display.init(self.config)?;
+        Ok(display)
+    }
+
+    /// Reset the chip by pulsing its active low /RESET line `reset`, then construct a `Display`
+    /// connected to `iface` and initialize it.
+    pub fn build_with_reset<DI, RST, D>(
+        self,
+        iface: DI,
+        reset: &mut RST,
+        delay: &mut D,
+    ) -> Result<Display<DI>, CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+        RST: hal::digital::v2::OutputPin<Error = Infallible>,
+        D: hal::blocking::delay::DelayMs<u16>,
+    {
+        let Ok(()) = reset.set_low();
+        delay.delay_ms(RESET_PULSE_MS);
+        let Ok(()) = reset.set_high();
+        delay.delay_ms(RESET_RECOVERY_MS);
+        self.build(iface)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::mock::{MockInterface, Sent};
+    use crate::test_support::CountingDelay;
+
+    struct Pin(Vec<(bool, usize)>, MockInterface);
+
+    impl hal::digital::v2::OutputPin for Pin {
+        type Error = Infallible;
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.0.push((true, self.1.sent().len()));
+            Ok(())
+        }
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.0.push((false, self.1.sent().len()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn build_preset() {
+        assert_eq!(DisplayBuilder::preset("NHD-0.0-0"), None);
+
+        let di = MockInterface::new();
+        let mut reset = Pin(Vec::new(), di.split());
+        let mut delay = CountingDelay(0);
+        let disp = DisplayBuilder::preset("NHD-3.12-25664UCY2")
+            .unwrap()
+            .tweak_config(|cfg| cfg.contrast_current(80))
+            .build_with_reset(di.split(), &mut reset, &mut delay)
+            .unwrap();
+        assert_eq!(disp.size(), PixelCoord(256, 64));
+        assert_eq!(disp.offset(), PixelCoord(112, 0));
+        assert_eq!(reset.0, [(false, 0), (true, 0)]);
+        assert_eq!(delay.0, 11);
+
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive)
+            .clock_fosc_divset(9, 1)
+            .display_enhancements(true, true)
+            .contrast_current(80)
+            .phase_lengths(5, 14)
+            .precharge_voltage(31)
+            .second_precharge_period(8)
+            .com_deselect_voltage(7);
+        let expected = MockInterface::new();
+        let mut manual = Display::new(expected.split(), PixelCoord(256, 64), PixelCoord(112, 0));
+        manual.init(cfg).unwrap();
+        assert_eq!(di.sent(), expected.sent());
+        assert!(di.sent().contains(&Sent::Cmd(0xC1)));
+    }
+}
@@ -0,0 +1,279 @@
+//! Background flushing of a packed image buffer to the display, driven from an interrupt or DMA
+//! completion callback rather than a blocking loop.
+//!
+//! `Display::flush` sets up the region and sends the first chunk, and then the application calls
+//! `Display::on_transfer_complete` each time the interface is ready for more data, for example from
+//! the SPI transmit-empty interrupt. With the `nb` feature each call sends as many bytes as the
+//! interface accepts without blocking, and interfaces which can only send by blocking are refused;
+//! otherwise each call sends one column group (four pixels, the unit the chip addresses RAM in), so
+//! that no call blocks for longer than it takes to send two bytes.
+
+use crate::chip::Chip;
+use crate::command::CommandError;
+use crate::display::region::Region;
+use crate::display::{region_in_bounds, Display, PixelCoord};
+use crate::interface;
+
+/// The bytes sent by each call to `Display::on_transfer_complete` without the `nb` feature: one
+/// column group of four pixels.
+#[cfg(not(feature = "nb"))]
+const CHUNK_BYTES: usize = 2;
+
+/// The progress of a flush in flight.
+pub(crate) struct FlushState {
+    data: &'static [u8],
+    sent: usize,
+}
+
+impl<DI, C> Display<DI, C>
+where
+    DI: interface::DisplayInterface,
+    C: Chip,
+{
+    /// Begin flushing `data` into the region from `upper_left` to `lower_right` in the background.
+    ///
+    /// `data` holds packed pixels as for `Region::draw_packed`, and must fill the region exactly,
+    /// or `CommandError::BadDataLength` is returned without sending anything.
+    /// The region has the same restrictions as for `Display::region`. The buffer must not change
+    /// until `is_flushing` returns false, which the `'static` borrow enforces; double buffering
+    /// is the usual way to draw the next frame while this one is sent.
+    ///
+    /// Returns `CommandError::Busy` if a flush is already in progress, and `CommandError::Locked`
+    /// if the command interface is locked. With the `nb` feature, returns
+    /// `CommandError::BlockingInterface` if the interface does not set
+    /// `DisplayInterface::NON_BLOCKING`, since its `send_data_async` would send the whole image
+    /// before returning.
+    pub fn flush(
+        &mut self,
+        upper_left: PixelCoord,
+        lower_right: PixelCoord,
+        data: &'static [u8],
+    ) -> Result<(), CommandError<DI::Error>> {
+        self.check_ready()?;
+        #[cfg(feature = "nb")]
+        if !DI::NON_BLOCKING {
+            return Err(CommandError::BlockingInterface);
+        }
+        if !region_in_bounds::<C>(self.display_size, upper_left, lower_right) {
+            return Err(CommandError::OutOfRange);
+        }
+        let ul = PixelCoord(upper_left.0 + self.display_offset.0, upper_left.1);
+        let lr = PixelCoord(lower_right.0 + self.display_offset.0, lower_right.1);
+        let mut region: Region<'_, DI, C> =
+            Region::new(&mut self.iface, &mut self.write_window, ul, lr);
+        if data.len() != region.total_bytes() {
+            return Err(CommandError::BadDataLength {
+                expected: region.total_bytes(),
+                actual: data.len(),
+            });
+        }
+        region.begin().map_err(CommandError::InterfaceError)?;
+        self.pixels.clear();
+        self.flush = Some(FlushState {
+            data: data,
+            sent: 0,
+        });
+        self.on_transfer_complete()
+            .map_err(CommandError::InterfaceError)
+    }
+
+    /// Whether a flush started by `Display::flush` is still in progress. Other drawing and every
+    /// command are refused with `CommandError::Busy` until it finishes.
+    pub fn is_flushing(&self) -> bool {
+        self.flush.is_some()
+    }
+
+    /// Feed the next chunk of an in-progress flush to the interface. Call this when the previous
+    /// transfer has completed; it does nothing if no flush is in progress.
+    ///
+    /// If the interface returns an error, the flush is abandoned and the error is returned.
+    pub fn on_transfer_complete(&mut self) -> Result<(), DI::Error> {
+        let state = match self.flush.as_mut() {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+        let mut result = Self::feed(&mut self.iface, state);
+        if result.is_err() || state.sent == state.data.len() {
+            self.flush = None;
+            result = result.and(self.iface.end_transaction());
+        }
+        result
+    }
+
+    #[cfg(feature = "nb")]
+    fn feed(iface: &mut DI, state: &mut FlushState) -> Result<(), DI::Error> {
+        while let Some(&byte) = state.data.get(state.sent) {
+            match iface.send_data_async(byte) {
+                Ok(()) => state.sent += 1,
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "nb"))]
+    fn feed(iface: &mut DI, state: &mut FlushState) -> Result<(), DI::Error> {
+        let end = (state.sent + CHUNK_BYTES).min(state.data.len());
+        iface.send_data(&state.data[state.sent..end])?;
+        state.sent = end;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{ComLayout, ComScanDirection, Command};
+    use crate::config::Config;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::MockInterface;
+    #[cfg(not(feature = "nb"))]
+    use crate::interface::mock::Sent;
+    use crate::interface::DisplayInterface;
+    use crate::test_support::initialized_display;
+
+    static FRAME: [u8; 8] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF];
+
+    #[test]
+    fn flush_in_background() {
+        let (di, mut disp) = initialized_display(Px(8, 16));
+
+        assert_eq!(
+            disp.flush(Px(0, 0), Px(8, 3), &FRAME),
+            Err(CommandError::BadDataLength {
+                expected: 12,
+                actual: 8
+            })
+        );
+        assert_eq!(
+            disp.flush(Px(0, 0), Px(12, 2), &FRAME),
+            Err(CommandError::OutOfRange)
+        );
+        disp.flush(Px(0, 2), Px(8, 4), &FRAME).unwrap();
+        while disp.is_flushing() {
+            assert!(matches!(
+                disp.region(Px(0, 0), Px(4, 1)),
+                Err(CommandError::Busy)
+            ));
+            disp.on_transfer_complete().unwrap();
+        }
+        disp.on_transfer_complete().unwrap();
+
+        let ram = di.chip_model(Px(8, 16), Px(0, 0)).render();
+        assert_eq!(&ram[16..24], &[0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(&ram[24..32], &[8, 9, 10, 11, 12, 13, 14, 15]);
+    }
+
+    #[cfg(not(feature = "nb"))]
+    #[test]
+    fn one_column_group_per_transfer() {
+        let (mut di, mut disp) = initialized_display(Px(8, 16));
+        di.clear();
+
+        disp.flush(Px(0, 2), Px(8, 4), &FRAME).unwrap();
+        let mut calls = 1;
+        while disp.is_flushing() {
+            disp.on_transfer_complete().unwrap();
+            calls += 1;
+        }
+        assert_eq!(calls, 4);
+        let sent = di.sent();
+        let start = sent.iter().position(|s| *s == Sent::Cmd(0x5C)).unwrap() + 1;
+        #[rustfmt::skip]
+        assert_eq!(&sent[start..], &[
+            Sent::Data(vec![0x01, 0x23]),
+            Sent::Data(vec![0x45, 0x67]),
+            Sent::Data(vec![0x89, 0xAB]),
+            Sent::Data(vec![0xCD, 0xEF]),
+        ]);
+    }
+
+    /// A mock interface which accepts only every other word sent with `send_data_async`, so that
+    /// a flush takes several calls to `on_transfer_complete` with or without the `nb` feature.
+    struct Throttled(MockInterface, bool);
+
+    impl DisplayInterface for Throttled {
+        type Error = core::convert::Infallible;
+
+        #[cfg(feature = "nb")]
+        const NON_BLOCKING: bool = true;
+
+        fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+            self.0.send_command(cmd)
+        }
+        fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+            self.0.send_data(buf)
+        }
+        #[cfg(feature = "nb")]
+        fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+            self.1 = !self.1;
+            match self.1 {
+                true => self.0.send_data_async(word),
+                false => Err(nb::Error::WouldBlock),
+            }
+        }
+    }
+
+    /// A mock interface relying on the default `send_data_async`, which waits for each word.
+    #[cfg(feature = "nb")]
+    struct Blocking(MockInterface);
+
+    #[cfg(feature = "nb")]
+    impl DisplayInterface for Blocking {
+        type Error = core::convert::Infallible;
+
+        fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+            self.0.send_command(cmd)
+        }
+        fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+            self.0.send_data(buf)
+        }
+    }
+
+    #[cfg(feature = "nb")]
+    #[test]
+    fn blocking_interface_refused() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(Blocking(di.split()), Px(8, 16), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::Progressive);
+        disp.init(cfg).unwrap();
+        di.clear();
+
+        assert_eq!(
+            disp.flush(Px(0, 2), Px(8, 4), &FRAME),
+            Err(CommandError::BlockingInterface)
+        );
+        assert!(!disp.is_flushing());
+        di.check_multi(&[]);
+    }
+
+    #[test]
+    fn commands_refused_while_flushing() {
+        let di = MockInterface::new();
+        let mut disp = Display::new(Throttled(di.split(), false), Px(8, 16), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::Progressive);
+        disp.init(cfg.clone()).unwrap();
+
+        disp.flush(Px(0, 2), Px(8, 4), &FRAME).unwrap();
+        assert!(disp.is_flushing());
+        assert_eq!(disp.contrast(3), Err(CommandError::Busy));
+        assert_eq!(disp.vertical_pan(3), Err(CommandError::Busy));
+        assert_eq!(
+            disp.send_command(Command::SetStartLine(3)),
+            Err(CommandError::Busy)
+        );
+        assert_eq!(disp.gray_scale_table(&[0; 15]), Err(CommandError::Busy));
+        assert_eq!(disp.init(cfg), Err(CommandError::Busy));
+        while disp.is_flushing() {
+            disp.on_transfer_complete().unwrap();
+        }
+
+        // The image data was not interrupted by any command.
+        let ram = di.chip_model(Px(8, 16), Px(0, 0)).render();
+        assert_eq!(&ram[16..24], &[0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(&ram[24..32], &[8, 9, 10, 11, 12, 13, 14, 15]);
+        assert_eq!(disp.current_start_line(), 0);
+        disp.contrast(3).unwrap();
+    }
+}
@@ -0,0 +1,164 @@
+//! Dimming of a display which has been idle for a while, to extend the life of always-on OLED
+//! panels.
+//!
+//! The application reports user activity and calls `IdleDimmer::tick` regularly with timestamps
+//! in milliseconds from a wrapping clock, as for `Display::reinit_tick`. Once no activity has been
+//! reported for the timeout, the master contrast is stepped down to the dim level, and the next
+//! activity restores it.
+
+use crate::command::CommandError;
+use crate::display::Display;
+use crate::interface;
+
+/// Settings for an `IdleDimmer`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IdleDimmerConfig {
+    timeout_ms: u32,
+    dim_contrast: u8,
+    step_ms: u32,
+}
+
+impl IdleDimmerConfig {
+    /// Dim the display to master contrast `dim_contrast` once it has been idle for `timeout_ms`.
+    /// By default the contrast is stepped down one level every 100 ms.
+    pub fn new(timeout_ms: u32, dim_contrast: u8) -> Self {
+        IdleDimmerConfig {
+            timeout_ms: timeout_ms,
+            dim_contrast: dim_contrast,
+            step_ms: 100,
+        }
+    }
+
+    /// Set the time between contrast steps while dimming, or 0 to dim in one step.
+    pub fn step_ms(self, step_ms: u32) -> Self {
+        Self {
+            step_ms: step_ms,
+            ..self
+        }
+    }
+}
+
+/// Tracks activity and dims the display while it is idle. See the module documentation.
+pub struct IdleDimmer {
+    config: IdleDimmerConfig,
+    last_activity: Option<u32>,
+    last_step: u32,
+    /// The master contrast to restore on activity, when dimmed.
+    restore: Option<u8>,
+}
+
+impl IdleDimmer {
+    /// Create a dimmer with `config`. The idle timeout starts at the first `tick` or `activity`.
+    pub fn new(config: IdleDimmerConfig) -> Self {
+        IdleDimmer {
+            config: config,
+            last_activity: None,
+            last_step: 0,
+            restore: None,
+        }
+    }
+
+    /// Whether the display is dimmed, or being dimmed.
+    pub fn is_dimmed(&self) -> bool {
+        self.restore.is_some()
+    }
+
+    /// Report user activity at time `now`, restoring the master contrast if the display was
+    /// dimmed.
+    pub fn activity<DI>(
+        &mut self,
+        display: &mut Display<DI>,
+        now: u32,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        self.last_activity = Some(now);
+        if let Some(contrast) = self.restore.take() {
+            display.contrast(contrast)?;
+        }
+        Ok(())
+    }
+
+    /// Advance the dimmer to time `now`, stepping the master contrast down if the display has been
+    /// idle for the timeout. Returns whether the contrast was changed.
+    pub fn tick<DI>(
+        &mut self,
+        display: &mut Display<DI>,
+        now: u32,
+    ) -> Result<bool, CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        let last_activity = match self.last_activity {
+            Some(last_activity) => last_activity,
+            None => {
+                self.last_activity = Some(now);
+                return Ok(false);
+            }
+        };
+        let current = display.master_contrast;
+        let dim = self.config.dim_contrast;
+        if now.wrapping_sub(last_activity) < self.config.timeout_ms || current <= dim {
+            return Ok(false);
+        }
+        if self.restore.is_none() {
+            self.restore = Some(current);
+        } else if now.wrapping_sub(self.last_step) < self.config.step_ms {
+            return Ok(false);
+        }
+        self.last_step = now;
+        let next = if self.config.step_ms == 0 {
+            dim
+        } else {
+            current - 1
+        };
+        display.contrast(next)?;
+        Ok(true)
+    }
+}
+
+impl<DI> Display<DI>
+where
+    DI: interface::DisplayInterface,
+{
+    /// Create an `IdleDimmer` for this display with `config`.
+    pub fn idle_dimmer(&self, config: IdleDimmerConfig) -> IdleDimmer {
+        IdleDimmer::new(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+
+    #[test]
+    fn dim_and_restore() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        disp.contrast(12).unwrap();
+        let mut dimmer = disp.idle_dimmer(IdleDimmerConfig::new(1000, 10).step_ms(50));
+        di.clear();
+
+        assert_eq!(dimmer.tick(&mut disp, u32::MAX - 100), Ok(false));
+        assert_eq!(dimmer.tick(&mut disp, 898), Ok(false));
+        // Idle across the clock wrapping around, then one step per 50 ms down to 10.
+        assert_eq!(dimmer.tick(&mut disp, 899), Ok(true));
+        assert_eq!(dimmer.tick(&mut disp, 948), Ok(false));
+        assert_eq!(dimmer.tick(&mut disp, 949), Ok(true));
+        assert_eq!(dimmer.tick(&mut disp, 2000), Ok(false));
+        assert!(dimmer.is_dimmed());
+
+        dimmer.activity(&mut disp, 2001).unwrap();
+        assert!(!dimmer.is_dimmed());
+        assert_eq!(dimmer.tick(&mut disp, 3000), Ok(false));
+        #[rustfmt::skip]
+        assert_eq!(di.sent(), [
+            Sent::Cmd(0xC7), Sent::Data(vec![11]),
+            Sent::Cmd(0xC7), Sent::Data(vec![10]),
+            Sent::Cmd(0xC7), Sent::Data(vec![12]),
+        ]);
+    }
+}
@@ -0,0 +1,116 @@
+//! Scheduled inversion cycles, to even out the wear of OLED pixels.
+//!
+//! Static content such as labels and frames ages the pixels which draw it faster than the rest of
+//! the panel, leaving a visible burn-in. Where shifting the content around is not acceptable, an
+//! alternative is to periodically show the display inverted for a while, so that the usually dark
+//! pixels carry some of the load. Typically this is enabled only at times nobody is looking, such
+//! as during the night.
+//!
+//! `InversionCycle::tick` is called regularly with timestamps in milliseconds from a wrapping
+//! clock, as for `Display::reinit_tick`, and switches between `DisplayMode::Normal` and
+//! `DisplayMode::Inverse` as scheduled.
+
+use crate::command::{Command, CommandError, DisplayMode};
+use crate::display::Display;
+use crate::interface;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    Idle,
+    Normal(u32),
+    Inverse(u32),
+}
+
+/// A `tick`-driven schedule alternating the display between normal and inverse modes. See the
+/// module documentation.
+pub struct InversionCycle {
+    normal_ms: u32,
+    inverse_ms: u32,
+    enabled: bool,
+    phase: Phase,
+}
+
+impl InversionCycle {
+    /// Create a schedule which shows the display normally for `normal_ms`, then inverted for
+    /// `inverse_ms`, repeating. The schedule starts disabled.
+    pub fn new(normal_ms: u32, inverse_ms: u32) -> Self {
+        InversionCycle {
+            normal_ms: normal_ms,
+            inverse_ms: inverse_ms,
+            enabled: false,
+            phase: Phase::Idle,
+        }
+    }
+
+    /// Enable or disable the schedule. Disabling it returns the display to normal mode at the next
+    /// `tick`; enabling it starts a cycle with the normal period.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Whether the schedule currently has the display inverted.
+    pub fn is_inverted(&self) -> bool {
+        matches!(self.phase, Phase::Inverse(_))
+    }
+
+    /// Advance the schedule to time `now`, switching the display mode if a period has ended.
+    /// Returns whether the display mode was changed.
+    pub fn tick<DI>(
+        &mut self,
+        display: &mut Display<DI>,
+        now: u32,
+    ) -> Result<bool, CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        let (next, mode) = match self.phase {
+            Phase::Idle if self.enabled => (Phase::Normal(now), None),
+            Phase::Normal(_) | Phase::Idle if !self.enabled => (Phase::Idle, None),
+            Phase::Inverse(_) if !self.enabled => (Phase::Idle, Some(DisplayMode::Normal)),
+            Phase::Normal(since) if now.wrapping_sub(since) >= self.normal_ms => {
+                (Phase::Inverse(now), Some(DisplayMode::Inverse))
+            }
+            Phase::Inverse(since) if now.wrapping_sub(since) >= self.inverse_ms => {
+                (Phase::Normal(now), Some(DisplayMode::Normal))
+            }
+            phase => (phase, None),
+        };
+        if let Some(mode) = mode {
+            display.send_command(Command::SetDisplayMode(mode))?;
+        }
+        self.phase = next;
+        Ok(mode.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+
+    #[test]
+    fn cycle() {
+        let di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let mut cycle = InversionCycle::new(1000, 200);
+
+        assert_eq!(cycle.tick(&mut disp, 0), Ok(false));
+        cycle.set_enabled(true);
+        assert_eq!(cycle.tick(&mut disp, u32::MAX - 499), Ok(false));
+        assert_eq!(cycle.tick(&mut disp, 499), Ok(false));
+        assert_eq!(cycle.tick(&mut disp, 500), Ok(true));
+        assert!(cycle.is_inverted());
+        assert_eq!(cycle.tick(&mut disp, 699), Ok(false));
+        assert_eq!(cycle.tick(&mut disp, 700), Ok(true));
+        assert!(!cycle.is_inverted());
+        assert_eq!(cycle.tick(&mut disp, 1700), Ok(true));
+        cycle.set_enabled(false);
+        assert_eq!(cycle.tick(&mut disp, 1701), Ok(true));
+        assert_eq!(cycle.tick(&mut disp, 5000), Ok(false));
+        #[rustfmt::skip]
+        assert_eq!(di.sent(), [
+            Sent::Cmd(0xA7), Sent::Cmd(0xA6), Sent::Cmd(0xA7), Sent::Cmd(0xA6),
+        ]);
+    }
+}
@@ -14,38 +14,237 @@ pub mod testing {
     }
 }
 
+#[cfg(feature = "buffered")]
+pub mod band;
+#[cfg(feature = "bringup")]
+pub mod bringup;
+#[cfg(feature = "buffered")]
+pub mod buffered;
+pub mod builder;
+pub mod flush;
+#[cfg(feature = "effects")]
+pub mod idle;
+#[cfg(feature = "effects")]
+pub mod inversion;
+#[cfg(feature = "overscan")]
 pub mod overscanned_region;
+#[cfg(feature = "pages")]
+pub mod page;
+pub mod pixel;
 pub mod region;
+#[cfg(feature = "critical-section")]
+pub mod shared;
+#[cfg(feature = "split")]
+pub mod split;
+#[cfg(feature = "tiled")]
+pub mod tiled;
+#[cfg(feature = "effects")]
+pub mod transition;
 
-use crate::command::consts::*;
+use core::iter;
+use core::marker::PhantomData;
+use core::ops;
+
+use embedded_hal as hal;
+
+use crate::chip::{Chip, Ssd1322};
 use crate::command::*;
-use crate::config::{Config, PersistentConfig};
+use crate::config::{Config, PersistentConfig, DEFAULT_REMAPPING};
+#[cfg(feature = "overscan")]
 use crate::display::overscanned_region::OverscannedRegion;
-use crate::display::region::Region;
+use crate::display::region::{AddressWindow, Region};
 use crate::interface;
+#[cfg(feature = "adapters")]
+use crate::interface::crc::{CrcInterface, FrameCrc};
+#[cfg(feature = "adapters")]
+use crate::interface::stats::{Stats, StatsInterface};
+#[cfg(feature = "power")]
+use crate::power::{PanelPower, PowerError};
 
 /// A pixel coordinate pair of `column` and `row`. `column` must be in the range [0,
 /// `consts::PIXEL_COL_MAX`], and `row` must be in the range [0, `consts::PIXEL_ROW_MAX`].
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct PixelCoord(pub i16, pub i16);
 
-/// A driver for an SSD1322 display.
-pub struct Display<DI>
+impl ops::Add for PixelCoord {
+    type Output = PixelCoord;
+
+    fn add(self, rhs: PixelCoord) -> PixelCoord {
+        PixelCoord(self.0 + rhs.0, self.1 + rhs.1)
+    }
+}
+
+impl ops::Sub for PixelCoord {
+    type Output = PixelCoord;
+
+    fn sub(self, rhs: PixelCoord) -> PixelCoord {
+        PixelCoord(self.0 - rhs.0, self.1 - rhs.1)
+    }
+}
+
+impl ops::Mul<i16> for PixelCoord {
+    type Output = PixelCoord;
+
+    fn mul(self, rhs: i16) -> PixelCoord {
+        PixelCoord(self.0 * rhs, self.1 * rhs)
+    }
+}
+
+/// A rectangle of pixels from the upper left corner `ul`, inclusive, to the lower right corner
+/// `lr`, exclusive, the same convention used for the corners of a region.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub ul: PixelCoord,
+    pub lr: PixelCoord,
+}
+
+impl Rect {
+    /// Construct a rectangle from its upper left and lower right corners.
+    pub fn new(ul: PixelCoord, lr: PixelCoord) -> Self {
+        Rect { ul: ul, lr: lr }
+    }
+
+    /// The width of the rectangle in pixels.
+    pub fn width(&self) -> i16 {
+        self.lr.0 - self.ul.0
+    }
+
+    /// The height of the rectangle in pixels.
+    pub fn height(&self) -> i16 {
+        self.lr.1 - self.ul.1
+    }
+
+    /// Whether the pixel at `point` lies within the rectangle.
+    pub fn contains(&self, point: PixelCoord) -> bool {
+        point.0 >= self.ul.0 && point.0 < self.lr.0 && point.1 >= self.ul.1 && point.1 < self.lr.1
+    }
+
+    /// The same rectangle moved by `offset`.
+    pub fn translate(&self, offset: PixelCoord) -> Rect {
+        Rect::new(self.ul + offset, self.lr + offset)
+    }
+
+    /// The overlap between this rectangle and `other`, or `None` if they do not overlap.
+    pub fn intersect(&self, other: &Rect) -> Option<Rect> {
+        let ul = PixelCoord(self.ul.0.max(other.ul.0), self.ul.1.max(other.ul.1));
+        let lr = PixelCoord(self.lr.0.min(other.lr.0), self.lr.1.min(other.lr.1));
+        if ul.0 < lr.0 && ul.1 < lr.1 {
+            Some(Rect::new(ul, lr))
+        } else {
+            None
+        }
+    }
+
+    /// The smallest rectangle covering both this rectangle and `other`.
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect::new(
+            PixelCoord(self.ul.0.min(other.ul.0), self.ul.1.min(other.ul.1)),
+            PixelCoord(self.lr.0.max(other.lr.0), self.lr.1.max(other.lr.1)),
+        )
+    }
+
+    /// The part of this rectangle within the viewable area of `display`, or `None` if it lies
+    /// entirely outside it.
+    pub fn clip_to_display<DI, C>(&self, display: &Display<DI, C>) -> Option<Rect>
+    where
+        DI: interface::DisplayInterface,
+        C: Chip,
+    {
+        self.intersect(&Rect::new(PixelCoord(0, 0), display.size()))
+    }
+}
+
+/// A driver for an SSD1322 display, or for a display driven by a sibling chip `C`.
+pub struct Display<DI, C = Ssd1322>
 where
     DI: interface::DisplayInterface,
+    C: Chip,
 {
     iface: DI,
     display_size: PixelCoord,
     display_offset: PixelCoord,
     persistent_config: Option<PersistentConfig>,
+    /// The address increment axis, column remap and nibble remap sent by `init` or
+    /// `set_remapping`.
+    remapping: (IncrementAxis, ColumnRemap, NibbleRemap),
+    master_contrast: u8,
+    start_line: u8,
+    /// The reset and first pre-charge phase lengths, and the MUX ratio, sent by `init`, which
+    /// together with the clock settings determine the frame rate.
+    row_timing: (u8, u8, u8),
+    /// The oscillator frequency and clock divider settings, as sent by `init` or
+    /// `set_refresh_rate`.
+    clock: (u8, u8),
+    /// The address window of the last region drawn, if it was filled exactly and no command has
+    /// been sent since, so that the chip is ready to write the same window again from its start.
+    write_window: Option<AddressWindow>,
+    registers: RegisterCache,
+    /// Whether the display RAM may hold anything, as after power-up or `init`, until the whole
+    /// viewable area is next written.
+    ram_dirty: bool,
+    /// The column groups most recently written by `set_pixel`.
+    pixels: pixel::PixelCache,
+    flush: Option<flush::FlushState>,
+    /// Whether the chip's command interface is locked, by `command_lock` or by an init sequence
+    /// which relocks it.
+    locked: bool,
+    #[cfg(feature = "retain-config")]
+    config: Option<Config>,
+    #[cfg(feature = "retain-config")]
+    reinit_schedule: ReinitSchedule,
+    chip: PhantomData<C>,
 }
 
-impl<DI> Display<DI>
+/// The values last sent for the registers which applications tend to set over and over, such as
+/// once per frame, or `None` where the value held by the chip is not known.
+#[derive(Clone, Copy, Default)]
+struct RegisterCache {
+    master_contrast: Option<u8>,
+    contrast_current: Option<u8>,
+    start_line: Option<u8>,
+    display_mode: Option<DisplayMode>,
+}
+
+impl RegisterCache {
+    /// Whether sending `cmd` is known to leave the chip unchanged.
+    fn holds(&self, cmd: Command) -> bool {
+        match cmd {
+            Command::SetMasterContrast(contrast) => self.master_contrast == Some(contrast),
+            Command::SetContrastCurrent(current) => self.contrast_current == Some(current),
+            Command::SetStartLine(line) => self.start_line == Some(line),
+            Command::SetDisplayMode(mode) => self.display_mode == Some(mode),
+            _ => false,
+        }
+    }
+
+    /// Note that `cmd` has been sent.
+    fn record(&mut self, cmd: Command) {
+        match cmd {
+            Command::SetMasterContrast(contrast) => self.master_contrast = Some(contrast),
+            Command::SetContrastCurrent(current) => self.contrast_current = Some(current),
+            Command::SetStartLine(line) => self.start_line = Some(line),
+            Command::SetDisplayMode(mode) => self.display_mode = Some(mode),
+            _ => {}
+        }
+    }
+}
+
+/// State for periodically re-sending the init sequence. Timestamps are in milliseconds from an
+/// application-supplied wrapping clock.
+#[cfg(feature = "retain-config")]
+#[derive(Clone, Copy, Default)]
+struct ReinitSchedule {
+    period: Option<u32>,
+    last: Option<u32>,
+}
+
+impl<DI, C> Display<DI, C>
 where
     DI: interface::DisplayInterface,
+    C: Chip,
 {
-    /// Construct a new display driver for a display with viewable dimensions `display_size`, which
-    /// is connected to the interface `iface`.
+    /// Construct a new display driver for a display driven by `chip`, with viewable dimensions
+    /// `display_size`, which is connected to the interface `iface`.
     ///
     /// Some display modules with resolution lower than the maximum supported by the chip will
     /// connect column driver or COM lines starting in the middle rather than from 0 for mechanical
@@ -56,74 +255,102 @@ where
     /// numbering has relative to the driver and COM line numbering: `display_offset.0` indicates
     /// the driver line column which corresponds to pixel column 0 of the display, and
     /// `display_offset.1` indicates which COM line corresponds to pixel row 0 of the display.
-    pub fn new(iface: DI, display_size: PixelCoord, display_offset: PixelCoord) -> Self {
-        if false
-            || display_size.0 > NUM_PIXEL_COLS as i16
-            || display_size.1 > NUM_PIXEL_ROWS as i16
-            || display_offset.0 + display_size.0 > NUM_PIXEL_COLS as i16
-            || display_offset.1 + display_size.1 > NUM_PIXEL_ROWS as i16
-            || display_size.0.rem_euclid(4) != 0
-            || display_offset.0.rem_euclid(4) != 0
-        {
-            panic!("Display size or column offset not supported by SSD1322.");
-        }
+    ///
+    /// Panels with fewer than the 16 rows the chip can scan are supported: unless `Config`
+    /// overrides the MUX ratio, they are scanned as 16 rows, with the extra COM lines driving
+    /// nothing.
+    pub fn with_chip(
+        _chip: C,
+        iface: DI,
+        display_size: PixelCoord,
+        display_offset: PixelCoord,
+    ) -> Self {
+        check_geometry::<C>(display_size, display_offset);
         Display {
             iface: iface,
             display_size: display_size,
             display_offset: display_offset,
             persistent_config: None,
+            remapping: DEFAULT_REMAPPING,
+            master_contrast: 15,
+            start_line: 0,
+            row_timing: (
+                POR_PHASE_LENGTHS.0,
+                POR_PHASE_LENGTHS.1,
+                default_mux_ratio(display_size),
+            ),
+            clock: POR_CLOCK_FOSC_DIVSET,
+            write_window: None,
+            registers: RegisterCache::default(),
+            ram_dirty: true,
+            pixels: pixel::PixelCache::default(),
+            flush: None,
+            locked: false,
+            #[cfg(feature = "retain-config")]
+            config: None,
+            #[cfg(feature = "retain-config")]
+            reinit_schedule: ReinitSchedule::default(),
+            chip: PhantomData,
         }
     }
 
-    /// Initialize the display with a config message.
-    pub fn init(&mut self, config: Config) -> Result<(), CommandError<DI::Error>> {
-        self.sleep(true)?;
-        Command::SetDisplayMode(DisplayMode::BlankDark).send(&mut self.iface)?;
-        config.send(&mut self.iface)?;
-        self.persistent_config = Some(config.persistent_config);
-        Command::SetMuxRatio(self.display_size.1 as u8).send(&mut self.iface)?;
-        Command::SetDisplayOffset(self.display_offset.1 as u8).send(&mut self.iface)?;
-        Command::SetStartLine(0).send(&mut self.iface)?;
-        self.persistent_config.as_ref().unwrap().send(
-            &mut self.iface,
-            IncrementAxis::Horizontal,
-            ColumnRemap::Forward,
-            NibbleRemap::Forward,
-        )?;
-        self.sleep(false)?;
-        Command::SetDisplayMode(DisplayMode::Normal).send(&mut self.iface)
+    /// The viewable size of the display, as given at construction.
+    pub fn size(&self) -> PixelCoord {
+        self.display_size
     }
 
-    /// Control sleep mode.
-    pub fn sleep(&mut self, enabled: bool) -> Result<(), CommandError<DI::Error>> {
-        Command::SetSleepMode(enabled).send(&mut self.iface)
+    /// The offset of the display's pixel numbering from the chip's column driver and COM line
+    /// numbering, as given at construction.
+    pub fn offset(&self) -> PixelCoord {
+        self.display_offset
     }
 
-    /// Control the master contrast.
-    pub fn contrast(&mut self, contrast: u8) -> Result<(), CommandError<DI::Error>> {
-        Command::SetMasterContrast(contrast).send(&mut self.iface)
+    /// The display start line currently set on the chip, as last set by `vertical_pan` or
+    /// `present_page`. This is 0 after initialization.
+    pub fn current_start_line(&self) -> u8 {
+        self.start_line
     }
 
-    /// Set the display brightness look-up table.
-    pub fn gray_scale_table(&mut self, table: &[u8]) -> Result<(), CommandError<DI::Error>> {
-        BufCommand::SetGrayScaleTable(table).send(&mut self.iface)
+    /// Whether the contents of the display RAM are unknown, so that the whole display must be
+    /// redrawn rather than only what changed. This is the case after power-up and after every
+    /// `init` or re-initialization, as a reset or whatever disturbed the chip may have scrambled
+    /// the RAM. It remains so until `clear`, a `BufferedDisplay` flush of the whole display, or
+    /// `mark_ram_clean`.
+    pub fn ram_is_dirty(&self) -> bool {
+        self.ram_dirty
     }
 
-    /// Set the vertical pan.
-    ///
-    /// This uses the `Command::SetStartLine` feature to shift the display RAM row addresses
-    /// relative to the active set of COM lines, allowing any display-height-sized window of the
-    /// entire 128 rows of display RAM to be made visible.
-    pub fn vertical_pan(&mut self, offset: u8) -> Result<(), CommandError<DI::Error>> {
-        Command::SetStartLine(offset).send(&mut self.iface)
+    /// Record that the application has redrawn the whole viewable area, so that `ram_is_dirty`
+    /// returns false until the next `init`.
+    pub fn mark_ram_clean(&mut self) {
+        self.ram_dirty = false;
+    }
+
+    /// Fill the whole viewable area of the display with gray level `level`. Afterwards the RAM
+    /// contents are known again; see `ram_is_dirty`.
+    pub fn clear(&mut self, level: u8) -> Result<(), CommandError<DI::Error>> {
+        let level = level & 0x0F;
+        let size = self.display_size;
+        let bytes = size.0 as usize * size.1 as usize / 2;
+        self.region(PixelCoord(0, 0), size)?
+            .draw_packed(iter::repeat_n(level << 4 | level, bytes))
+            .map_err(CommandError::InterfaceError)?;
+        self.ram_dirty = false;
+        Ok(())
+    }
+
+    /// Borrow the interface the display is connected to.
+    pub fn interface(&self) -> &DI {
+        &self.iface
     }
 
     /// Construct a rectangular region onto which to draw image data.
     ///
-    /// The region start and end horizontal coordinates must be divisible by 4, because pixels can
-    /// only be addressed by column address (groups of 4), not individually. The region rectangle
-    /// must also be within the viewable area of the display buffer, where the viewable area
-    /// includes all 128 rows to support vertical panning.
+    /// The region start and end horizontal coordinates must be divisible by the chip's column
+    /// width (4 for the SSD1322), because pixels can only be addressed by column address, not
+    /// individually. The region rectangle must also be within the viewable area of the display
+    /// buffer, where the viewable area includes all rows of RAM (128 for the SSD1322) to support
+    /// vertical panning.
     ///
     /// Regions are intended to be short-lived, and mutably borrow the display so clashing writes
     /// are prevented.
@@ -131,28 +358,20 @@ where
         &'di mut self,
         upper_left: PixelCoord,
         lower_right: PixelCoord,
-    ) -> Result<Region<'di, DI>, CommandError<DI::Error>> {
-        // The row fields are bounds-checked against the chip's maximum supported row rather than
-        // the display size, because the display supports vertical scrolling by adding an offset to
-        // the memory address that corresponds to row 0 (`SetStartLine` command). This feature
-        // makes it possible to "pan" displays with fewer rows up and down over the entire 128
-        // buffer rows. So, allow users to draw in that area even if it's currently hidden.
-        //
-        // The chip does not have any such panning support for buffer column addresses outside of
-        // the display's viewable area, so even though the chip allows data to be written there, it
-        // is probably an error because it can never be read back and can never be visible on the
-        // display. So, check column values against the display size and do not allow drawing
-        // outside them.
-        if false
-            || upper_left.0 > self.display_size.0
-            || lower_right.0 > self.display_size.0
-            || upper_left.1 > NUM_PIXEL_ROWS as i16
-            || lower_right.1 > NUM_PIXEL_ROWS as i16
-            || upper_left.0 >= lower_right.0
-            || upper_left.1 >= lower_right.1
-            || upper_left.0.rem_euclid(4) != 0
-            || lower_right.0.rem_euclid(4) != 0
-        {
+    ) -> Result<Region<'di, DI, C>, CommandError<DI::Error>> {
+        self.pixels.clear();
+        self.region_keeping_pixels(upper_left, lower_right)
+    }
+
+    /// Construct a region as for `region`, without forgetting the column groups written by
+    /// `set_pixel`.
+    fn region_keeping_pixels<'di>(
+        &'di mut self,
+        upper_left: PixelCoord,
+        lower_right: PixelCoord,
+    ) -> Result<Region<'di, DI, C>, CommandError<DI::Error>> {
+        self.check_ready()?;
+        if !region_in_bounds::<C>(self.display_size, upper_left, lower_right) {
             return Err(CommandError::OutOfRange);
         }
 
@@ -160,7 +379,577 @@ where
         // is handled by the display driver itself using the `SetDisplayOffset` command.
         let ul = PixelCoord(upper_left.0 + self.display_offset.0, upper_left.1);
         let lr = PixelCoord(lower_right.0 + self.display_offset.0, lower_right.1);
-        Ok(Region::new(&mut self.iface, ul, lr))
+        Ok(Region::new(&mut self.iface, &mut self.write_window, ul, lr))
+    }
+
+    /// Check that commands and image data can be sent: no background flush is in progress, and
+    /// the chip's command interface is not locked.
+    fn check_ready(&self) -> Result<(), CommandError<DI::Error>> {
+        if self.flush.is_some() {
+            return Err(CommandError::Busy);
+        }
+        if self.locked {
+            return Err(CommandError::Locked);
+        }
+        Ok(())
+    }
+
+    /// Construct a rectangular region covering `rect`, with the same restrictions as `region`.
+    pub fn region_rect<'di>(
+        &'di mut self,
+        rect: Rect,
+    ) -> Result<Region<'di, DI, C>, CommandError<DI::Error>> {
+        self.region(rect.ul, rect.lr)
+    }
+}
+
+impl<DI> Display<DI>
+where
+    DI: interface::DisplayInterface,
+{
+    /// Construct a new display driver for an SSD1322 display. See `with_chip` for a description of
+    /// the parameters.
+    pub fn new(iface: DI, display_size: PixelCoord, display_offset: PixelCoord) -> Self {
+        Self::with_chip(Ssd1322, iface, display_size, display_offset)
+    }
+
+    /// Initialize the display with a config message.
+    ///
+    /// With the `retain-config` feature enabled, the whole `Config` is kept inside the `Display` so
+    /// that the init sequence can later be replayed with `reinit`. It is only kept once the init
+    /// sequence has been sent successfully, so a failed `init` leaves the previous one in place.
+    pub fn init(&mut self, config: Config) -> Result<(), CommandError<DI::Error>> {
+        if self.flush.is_some() {
+            return Err(CommandError::Busy);
+        }
+        #[cfg(feature = "retain-config")]
+        {
+            let retained = config.clone();
+            self.send_init(config)?;
+            self.config = Some(retained);
+            Ok(())
+        }
+        #[cfg(not(feature = "retain-config"))]
+        self.send_init(config)
+    }
+
+    /// The configuration most recently sent by `init`, or `None` if the display has not been
+    /// initialized, for introspecting what the chip was actually configured with.
+    #[cfg(feature = "retain-config")]
+    pub fn config(&self) -> Option<&Config> {
+        self.config.as_ref()
+    }
+
+    /// Send the full init sequence for `config` to the display.
+    fn send_init(&mut self, config: Config) -> Result<(), CommandError<DI::Error>> {
+        if self.flush.is_some() {
+            return Err(CommandError::Busy);
+        }
+        let (phase_1, phase_2) = config
+            .commands()
+            .find_map(|cmd| match cmd {
+                Command::SetPhaseLengths(phase_1, phase_2) => Some((phase_1, phase_2)),
+                _ => None,
+            })
+            .unwrap_or(POR_PHASE_LENGTHS);
+        let clock = config
+            .commands()
+            .find_map(|cmd| match cmd {
+                Command::SetClockFoscDivset(fosc, divset) => Some((fosc, divset)),
+                _ => None,
+            })
+            .unwrap_or(POR_CLOCK_FOSC_DIVSET);
+        let contrast_current = config.commands().find_map(|cmd| match cmd {
+            Command::SetContrastCurrent(current) => Some(current),
+            _ => None,
+        });
+        let mux_ratio = init_mux_ratio(&config, self.display_size)?;
+        let remapping = config.remapping;
+        self.write_window = None;
+        self.registers = RegisterCache::default();
+        self.ram_dirty = true;
+        self.pixels.clear();
+        // A config which manages the lock unlocks the chip itself.
+        if self.locked && config.command_lock.is_none() {
+            self.command_lock(false)?;
+        }
+        let relock = config.command_lock == Some(true);
+        let persistent_config = send_init_sequence(
+            &mut self.iface,
+            config,
+            self.display_size,
+            self.display_offset,
+        )?;
+        self.persistent_config = Some(persistent_config);
+        self.locked = relock;
+        self.remapping = remapping;
+        self.start_line = 0;
+        // The master contrast is not part of the init sequence, so whatever the chip holds is
+        // left alone.
+        self.registers = RegisterCache {
+            master_contrast: None,
+            contrast_current: contrast_current,
+            start_line: Some(0),
+            display_mode: Some(DisplayMode::Normal),
+        };
+        self.row_timing = (phase_1, phase_2, mux_ratio);
+        self.clock = clock;
+        Ok(())
+    }
+
+    /// Recover the display after its chip has lost its state, by re-sending the complete init
+    /// sequence using the `Config` retained from `Display::init`, then restoring the settings
+    /// changed since through `Display` methods: the master contrast, contrast current (also set by
+    /// `set_brightness`), vertical pan, display mode, remapping and clock settings.
+    ///
+    /// OLED modules can lose their register state to ESD or supply dips, which leaves a scrambled
+    /// image until the chip is configured again. The SSD1322 registers cannot be read back, so
+    /// nothing is checked first: the only way to be sure the configuration is intact is to re-send
+    /// it unconditionally, which is what this does. Display RAM contents are not touched by the
+    /// init sequence, but they may have been corrupted by whatever disturbed the registers, so a
+    /// redraw is advisable. Settings sent only with `send_command` or `send_buf_command`, such as
+    /// a gray scale table, are not restored.
+    ///
+    /// Returns `CommandError::Uninitialized` if `init` has not been called yet.
+    #[cfg(feature = "retain-config")]
+    pub fn reinit(&mut self) -> Result<(), CommandError<DI::Error>> {
+        let config = self.config.clone().ok_or(CommandError::Uninitialized)?;
+        let (contrast, start_line) = (self.master_contrast, self.start_line);
+        let (remapping, clock, registers) = (self.remapping, self.clock, self.registers);
+        self.send_init(config)?;
+        // A config which relocks the chip leaves it locked, so unlock it to restore the rest.
+        let relock = self.locked;
+        if relock {
+            self.command_lock(false)?;
+        }
+        // The init sequence sets the start line, remapping, clock, contrast current and display
+        // mode as configured, so only those changed since need sending again. It does not set the
+        // master contrast, so that is always sent, whatever the chip holds now.
+        if remapping != self.remapping {
+            self.set_remapping(remapping.0, remapping.1, remapping.2)?;
+        }
+        if clock != self.clock {
+            self.send_command(Command::SetClockFoscDivset(clock.0, clock.1))?;
+        }
+        if let Some(current) = registers.contrast_current {
+            self.contrast_current(current)?;
+        }
+        if let Some(mode) = registers.display_mode {
+            self.display_mode(mode)?;
+        }
+        self.contrast(contrast)?;
+        self.vertical_pan(start_line)?;
+        if relock {
+            self.command_lock(true)?;
+        }
+        Ok(())
+    }
+
+    /// Set the period in milliseconds at which `reinit_tick` will call `reinit`, or `None` to
+    /// disable periodic re-initialization.
+    #[cfg(feature = "retain-config")]
+    pub fn set_reinit_period(&mut self, period: Option<u32>) {
+        self.reinit_schedule = ReinitSchedule {
+            period: period,
+            last: None,
+        };
+    }
+
+    /// Drive the periodic re-initialization schedule. `now` is a timestamp in milliseconds from a
+    /// monotonic clock supplied by the application, which is allowed to wrap around. The first call
+    /// after setting the period starts the schedule; thereafter the display is re-initialized with
+    /// `reinit` whenever at least one period has elapsed. Returns whether re-initialization took place.
+    #[cfg(feature = "retain-config")]
+    pub fn reinit_tick(&mut self, now: u32) -> Result<bool, CommandError<DI::Error>> {
+        let period = match self.reinit_schedule.period {
+            Some(period) => period,
+            None => return Ok(false),
+        };
+        match self.reinit_schedule.last {
+            Some(last) if now.wrapping_sub(last) >= period => {
+                self.reinit_schedule.last = Some(now);
+                self.reinit().map(|_| true)
+            }
+            Some(_) => Ok(false),
+            None => {
+                self.reinit_schedule.last = Some(now);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Send an arbitrary command to the chip, for features which have no dedicated method such as
+    /// partial display mode. The command arguments are validated and encoded as usual.
+    ///
+    /// The command is always sent, even if it sets a register to the value it already holds.
+    /// Changes to the master contrast and start line are tracked as if made through `contrast`
+    /// and `vertical_pan`, and changes to the clock settings as if made through
+    /// `set_refresh_rate`. Other settings which the driver relies on, such as the remapping and
+    /// MUX ratio, are not tracked, so changing them may break drawing until the next `init`.
+    pub fn send_command(&mut self, cmd: Command) -> Result<(), CommandError<DI::Error>> {
+        if let Command::SetCommandLock(locked) = cmd {
+            return self.command_lock(locked);
+        }
+        cmd.send(self.command_iface()?)?;
+        self.registers.record(cmd);
+        match cmd {
+            Command::SetMasterContrast(contrast) => self.master_contrast = contrast,
+            Command::SetStartLine(line) => self.start_line = line,
+            Command::SetClockFoscDivset(fosc, divset) => self.clock = (fosc, divset),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Send an arbitrary command with a buffer argument to the chip. See `send_command`.
+    pub fn send_buf_command(&mut self, cmd: BufCommand) -> Result<(), CommandError<DI::Error>> {
+        cmd.send(self.command_iface()?)
+    }
+
+    /// Lock (`true`) or unlock the chip's command interface. While locked, the chip ignores all
+    /// commands other than this one, including image data, so everything else sent through the
+    /// driver returns `CommandError::Locked` until it is unlocked again. See
+    /// `Config::command_lock`.
+    pub fn command_lock(&mut self, locked: bool) -> Result<(), CommandError<DI::Error>> {
+        if self.flush.is_some() {
+            return Err(CommandError::Busy);
+        }
+        self.write_window = None;
+        Command::SetCommandLock(locked).send(&mut self.iface)?;
+        self.locked = locked;
+        Ok(())
+    }
+
+    /// Whether the chip's command interface is locked, by `command_lock` or by `init` with a
+    /// `Config` which relocks it.
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Control sleep mode.
+    pub fn sleep(&mut self, enabled: bool) -> Result<(), CommandError<DI::Error>> {
+        Command::SetSleepMode(enabled).send(self.command_iface()?)
+    }
+
+    /// Shut the panel down in the order the datasheet requires: put the chip to sleep, call
+    /// `vcc_off` to remove the high voltage OLED supply, and wait 100 ms for it to discharge.
+    /// When this returns, the logic supply (VDD) may be removed safely.
+    ///
+    /// Removing VCC while the display is still being driven, or VDD before VCC has discharged, can
+    /// damage the panel over time.
+    pub fn power_off<D, F>(
+        &mut self,
+        delay: &mut D,
+        mut vcc_off: F,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        D: hal::blocking::delay::DelayMs<u16>,
+        F: FnMut(),
+    {
+        self.sleep(true)?;
+        vcc_off();
+        delay.delay_ms(100);
+        Ok(())
+    }
+
+    /// Switch on the panel's VCC supply with `power`, wait for it to settle, and then initialize
+    /// the display as with `init`. Call this after the chip has been powered and reset.
+    ///
+    /// If `power` fails to switch VCC on, `PowerError::Power` is returned and nothing is sent.
+    #[cfg(feature = "power")]
+    pub fn init_with_power<P, D>(
+        &mut self,
+        config: Config,
+        power: &mut P,
+        delay: &mut D,
+    ) -> Result<(), PowerError<P::Error, DI::Error>>
+    where
+        P: PanelPower,
+        D: hal::blocking::delay::DelayMs<u16>,
+    {
+        power.vcc_on().map_err(PowerError::Power)?;
+        delay.delay_ms(power.vcc_settle_ms());
+        Ok(self.init(config)?)
+    }
+
+    /// Shut the panel down as with `power_off`, switching off VCC with `power`.
+    ///
+    /// If `power` fails to switch VCC off, `PowerError::Power` is returned with the display
+    /// asleep.
+    #[cfg(feature = "power")]
+    pub fn power_off_with<P, D>(
+        &mut self,
+        power: &mut P,
+        delay: &mut D,
+    ) -> Result<(), PowerError<P::Error, DI::Error>>
+    where
+        P: PanelPower,
+        D: hal::blocking::delay::DelayMs<u16>,
+    {
+        self.sleep(true)?;
+        power.vcc_off().map_err(PowerError::Power)?;
+        delay.delay_ms(100);
+        Ok(())
+    }
+
+    /// Control the master contrast. Nothing is sent if the chip already has this contrast, so this
+    /// is cheap to call every frame.
+    pub fn contrast(&mut self, contrast: u8) -> Result<(), CommandError<DI::Error>> {
+        self.send_cached(Command::SetMasterContrast(contrast))?;
+        self.master_contrast = contrast;
+        Ok(())
+    }
+
+    /// Set the contrast current, which scales the brightness of the whole display more finely than
+    /// the master contrast. Nothing is sent if the chip already has this current. See
+    /// `Command::SetContrastCurrent`.
+    pub fn contrast_current(&mut self, current: u8) -> Result<(), CommandError<DI::Error>> {
+        self.send_cached(Command::SetContrastCurrent(current))
+    }
+
+    /// Set the display mode, for example to blank or invert the display. Nothing is sent if the
+    /// chip is already in this mode.
+    pub fn display_mode(&mut self, mode: DisplayMode) -> Result<(), CommandError<DI::Error>> {
+        self.send_cached(Command::SetDisplayMode(mode))
+    }
+
+    /// Change how image data written to display RAM is mapped onto pixels, by sending
+    /// `Command::SetRemapping` again with these settings and the COM scan direction and COM layout
+    /// sent by `init`.
+    ///
+    /// Regions and everything drawn through them assume the settings `init` sends, which are
+    /// those of the `Config`, by default `IncrementAxis::Horizontal`, `ColumnRemap::Forward` and
+    /// `NibbleRemap::Forward`. With any others, the image data of a region lands wherever the chip
+    /// puts it, which is the point of tricks such as streaming image data column by column or
+    /// mirroring, but region coordinates no longer describe what is drawn. Restore the settings
+    /// `init` sent before drawing normally again.
+    ///
+    /// Returns `CommandError::Uninitialized` if `init` has not been called yet.
+    pub fn set_remapping(
+        &mut self,
+        increment_axis: IncrementAxis,
+        column_remap: ColumnRemap,
+        nibble_remap: NibbleRemap,
+    ) -> Result<(), CommandError<DI::Error>> {
+        let persistent_config = self
+            .persistent_config
+            .clone()
+            .ok_or(CommandError::Uninitialized)?;
+        persistent_config.send(
+            self.command_iface()?,
+            increment_axis,
+            column_remap,
+            nibble_remap,
+        )?;
+        self.remapping = (increment_axis, column_remap, nibble_remap);
+        Ok(())
+    }
+
+    /// The address increment axis, column remap and nibble remap last sent by `init` or
+    /// `set_remapping`.
+    pub fn remapping(&self) -> (IncrementAxis, ColumnRemap, NibbleRemap) {
+        self.remapping
+    }
+
+    /// Forget the register values sent so far, so that the next call to each of `contrast`,
+    /// `contrast_current`, `vertical_pan` and `display_mode` sends its command even if the value
+    /// is unchanged. Use this when the chip may have lost its state other than through `init`, for
+    /// example after a hardware reset.
+    pub fn force_register_writes(&mut self) {
+        self.registers = RegisterCache::default();
+    }
+
+    /// Send `cmd`, unless the register it sets is known to hold that value already.
+    fn send_cached(&mut self, cmd: Command) -> Result<(), CommandError<DI::Error>> {
+        self.check_ready()?;
+        if self.registers.holds(cmd) {
+            return Ok(());
+        }
+        cmd.send(self.command_iface()?)?;
+        self.registers.record(cmd);
+        Ok(())
+    }
+
+    /// Set the overall display brightness to `percent` (0-100) of maximum, on a perceptually linear
+    /// scale.
+    ///
+    /// The requested perceived lightness is converted to luminance using the CIE 1976 lightness
+    /// curve, and the luminance is then realized as the product of the master contrast (in
+    /// sixteenths) and the contrast current (in 255ths), preferring the lowest master contrast
+    /// setting that can reach it so that the fine-grained contrast current is used for precision.
+    pub fn set_brightness(&mut self, percent: u8) -> Result<(), CommandError<DI::Error>> {
+        if percent > 100 {
+            return Err(CommandError::OutOfRange);
+        }
+        // Luminance scaled so that full brightness is 16 * 255, i.e. master contrast 15 and
+        // contrast current 255.
+        const FULL: u64 = 16 * 255;
+        let l = percent as u64;
+        let drive = if l > 8 {
+            (l + 16).pow(3) * FULL / 116u64.pow(3)
+        } else {
+            l * FULL * 10 / 9033
+        } as u16;
+        let master_contrast = drive.div_ceil(255).saturating_sub(1) as u8;
+        let current = drive / (master_contrast as u16 + 1);
+        self.contrast_current(current as u8)?;
+        self.contrast(master_contrast)
+    }
+
+    /// Choose the oscillator frequency and clock divider which come closest to a frame rate of
+    /// `hz`, and send them with `Command::SetClockFoscDivset`. Returns the estimated frame rate
+    /// achieved, in Hz.
+    ///
+    /// The frame period is the MUX ratio times the row period, which is the sum of the phase
+    /// lengths set by `init` and the current drive period, in DCLKs. The estimate assumes the
+    /// current drive period is as long as the longest pulse of the default gray scale table, and
+    /// the chip's typical oscillator frequencies; the real rate varies between parts and with
+    /// temperature, so treat the result as approximate.
+    pub fn set_refresh_rate(&mut self, hz: u16) -> Result<u16, CommandError<DI::Error>> {
+        if hz == 0 {
+            return Err(CommandError::OutOfRange);
+        }
+        let frame_dclks = self.frame_dclks();
+        let mut best = (0, 0, 0u32);
+        for divset in 0..=10u8 {
+            for fosc in 0..=15u8 {
+                let frame_cycles = frame_dclks << divset;
+                let rate = (fosc_hz(fosc) + frame_cycles / 2) / frame_cycles;
+                if best.2 == 0 || rate.abs_diff(hz as u32) < best.2.abs_diff(hz as u32) {
+                    best = (fosc, divset, rate);
+                }
+            }
+        }
+        Command::SetClockFoscDivset(best.0, best.1).send(self.command_iface()?)?;
+        self.clock = (best.0, best.1);
+        Ok(best.2 as u16)
+    }
+
+    /// The estimated time the panel takes to refresh one frame, in microseconds, from the phase
+    /// lengths and MUX ratio set by `init` and the clock settings set by `init`, `set_refresh_rate`,
+    /// `send_command` or `set_power_profile`. Phase lengths sent with `send_command` are not seen.
+    ///
+    /// The estimate is approximate in the same ways as for `set_refresh_rate`. It is mainly useful
+    /// for pacing animation, e.g. with a `FrameScheduler` or `wait_for_frame`, so that RAM is
+    /// updated at most once per refresh: an image changed several times during one scan of the
+    /// panel shows the tear between each version.
+    pub fn estimated_frame_period(&self) -> u32 {
+        let (fosc, divset) = self.clock;
+        let frame_cycles = (self.frame_dclks() as u64) << divset;
+        (frame_cycles * 1_000_000 / fosc_hz(fosc) as u64) as u32
+    }
+
+    /// Sleep with `delay` for one estimated frame period, for loops which update display RAM once
+    /// per pass: waiting after each update keeps updates from coming faster than the panel can
+    /// show them.
+    pub fn wait_for_frame<D>(&self, delay: &mut D)
+    where
+        D: hal::blocking::delay::DelayUs<u32>,
+    {
+        delay.delay_us(self.estimated_frame_period());
+    }
+
+    /// The interface, for sending a command. Any command takes the chip out of write mode, so the
+    /// next region drawn must set up its address window again.
+    ///
+    /// Returns `CommandError::Busy` while a background flush is in progress, since a command sent
+    /// in the middle of its image data would end the write, and `CommandError::Locked` while the
+    /// command interface is locked.
+    fn command_iface(&mut self) -> Result<&mut DI, CommandError<DI::Error>> {
+        self.check_ready()?;
+        self.write_window = None;
+        Ok(&mut self.iface)
+    }
+
+    /// Whether the panel scans display RAM from row 0 downwards, rather than upwards from the last
+    /// row, according to the COM scan direction set by `init`.
+    #[cfg(feature = "buffered")]
+    pub(crate) fn scans_row_zero_first(&self) -> bool {
+        self.persistent_config
+            .as_ref()
+            .is_none_or(|config| config.com_scan_direction() == ComScanDirection::RowZeroFirst)
+    }
+
+    /// The number of DCLKs the panel takes to scan one frame, assuming the current drive period
+    /// is `CURRENT_DRIVE_DCLKS`.
+    fn frame_dclks(&self) -> u32 {
+        let (phase_1, phase_2, mux_ratio) = self.row_timing;
+        (phase_1 as u32 + phase_2 as u32 + CURRENT_DRIVE_DCLKS) * mux_ratio as u32
+    }
+
+    /// Fade the display out to black by stepping the master contrast down from its current value
+    /// to 0, spreading the steps evenly over roughly `duration_ms` milliseconds using `delay`.
+    pub fn fade_out<D>(
+        &mut self,
+        duration_ms: u16,
+        delay: &mut D,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        D: hal::blocking::delay::DelayMs<u16>,
+    {
+        self.fade_to(0, duration_ms, delay)
+    }
+
+    /// Fade the display in by stepping the master contrast up from its current value to
+    /// `contrast`, spreading the steps evenly over roughly `duration_ms` milliseconds using
+    /// `delay`. `contrast` has the same range as `Command::SetMasterContrast`.
+    pub fn fade_in<D>(
+        &mut self,
+        contrast: u8,
+        duration_ms: u16,
+        delay: &mut D,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        D: hal::blocking::delay::DelayMs<u16>,
+    {
+        if contrast > 15 {
+            return Err(CommandError::OutOfRange);
+        }
+        self.fade_to(contrast, duration_ms, delay)
+    }
+
+    /// Step the master contrast one level at a time towards `target`, delaying between steps.
+    fn fade_to<D>(
+        &mut self,
+        target: u8,
+        duration_ms: u16,
+        delay: &mut D,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        D: hal::blocking::delay::DelayMs<u16>,
+    {
+        let steps = (self.master_contrast as i16 - target as i16).unsigned_abs();
+        if steps == 0 {
+            return Ok(());
+        }
+        let step_delay = duration_ms / steps;
+        while self.master_contrast != target {
+            delay.delay_ms(step_delay);
+            let next = if self.master_contrast < target {
+                self.master_contrast + 1
+            } else {
+                self.master_contrast - 1
+            };
+            self.contrast(next)?;
+        }
+        Ok(())
+    }
+
+    /// Set the display brightness look-up table.
+    pub fn gray_scale_table(&mut self, table: &[u8]) -> Result<(), CommandError<DI::Error>> {
+        BufCommand::SetGrayScaleTable(table).send(self.command_iface()?)
+    }
+
+    /// Set the vertical pan.
+    ///
+    /// This uses the `Command::SetStartLine` feature to shift the display RAM row addresses
+    /// relative to the active set of COM lines, allowing any display-height-sized window of the
+    /// entire 128 rows of display RAM to be made visible. Nothing is sent if the start line is
+    /// already `offset`.
+    pub fn vertical_pan(&mut self, offset: u8) -> Result<(), CommandError<DI::Error>> {
+        self.send_cached(Command::SetStartLine(offset))?;
+        self.start_line = offset;
+        Ok(())
     }
 
     /// Construct a rectangular region onto which to draw image data which silently discards
@@ -174,11 +963,15 @@ where
     ///
     /// Regions are intended to be short-lived, and mutably borrow the display so clashing writes
     /// are prevented.
+    #[cfg(feature = "overscan")]
+    #[allow(clippy::nonminimal_bool)]
     pub fn overscanned_region<'di>(
         &'di mut self,
         upper_left: PixelCoord,
         lower_right: PixelCoord,
     ) -> Result<OverscannedRegion<'di, DI>, CommandError<DI::Error>> {
+        self.check_ready()?;
+        self.pixels.clear();
         if false
             || upper_left.0 >= lower_right.0
             || upper_left.1 >= lower_right.1
@@ -190,26 +983,194 @@ where
 
         Ok(OverscannedRegion::new(
             &mut self.iface,
+            &mut self.write_window,
             upper_left,
             lower_right,
             self.display_size.0,
             self.display_offset.0,
         ))
     }
+
+    /// Construct an overscanned region covering `rect`, with the same restrictions as
+    /// `overscanned_region`.
+    #[cfg(feature = "overscan")]
+    pub fn overscanned_region_rect<'di>(
+        &'di mut self,
+        rect: Rect,
+    ) -> Result<OverscannedRegion<'di, DI>, CommandError<DI::Error>> {
+        self.overscanned_region(rect.ul, rect.lr)
+    }
+}
+
+/// The reset and first pre-charge phase lengths in DCLKs after a reset.
+const POR_PHASE_LENGTHS: (u8, u8) = (9, 7);
+
+/// The oscillator frequency and clock divider settings after a reset.
+const POR_CLOCK_FOSC_DIVSET: (u8, u8) = (12, 1);
+
+/// The length of the current drive phase of each row in DCLKs, taken to be the longest pulse of
+/// the default gray scale table.
+const CURRENT_DRIVE_DCLKS: u32 = 180;
+
+/// The typical oscillator frequency in Hz for each `fosc` setting of `SetClockFoscDivset`. The
+/// datasheet only characterizes the frequency at the reset setting, so this is a linear
+/// approximation of its typical curve.
+fn fosc_hz(fosc: u8) -> u32 {
+    1_300_000 + fosc as u32 * 120_000
+}
+
+/// The smallest MUX ratio the chip supports. See `Command::SetMuxRatio`.
+const MIN_MUX_RATIO: u8 = 16;
+
+/// The MUX ratio for a display of `display_size` when none is configured: its number of rows, but
+/// at least `MIN_MUX_RATIO`. A panel with fewer rows is scanned as if it had padding rows below
+/// its last one, on COM lines with no glass attached, so each of its rows is lit for a smaller
+/// share of the frame and looks dimmer than on a taller panel at the same contrast.
+fn default_mux_ratio(display_size: PixelCoord) -> u8 {
+    (display_size.1 as u8).max(MIN_MUX_RATIO)
+}
+
+/// The MUX ratio to send when initializing a display of `display_size` with `config`, or
+/// `CommandError::BadMuxRatio` if the one configured is out of range.
+pub(crate) fn init_mux_ratio<IE>(
+    config: &Config,
+    display_size: PixelCoord,
+) -> Result<u8, CommandError<IE>> {
+    match config.mux_ratio.unwrap_or(default_mux_ratio(display_size)) {
+        ratio @ MIN_MUX_RATIO..=consts::NUM_PIXEL_ROWS => Ok(ratio),
+        ratio => Err(CommandError::BadMuxRatio { ratio: ratio }),
+    }
+}
+
+/// Panic if a display of `display_size` at `display_offset` does not fit the RAM of chip `C`.
+#[allow(clippy::nonminimal_bool)]
+pub(crate) fn check_geometry<C: Chip>(display_size: PixelCoord, display_offset: PixelCoord) {
+    if false
+        || display_size.0 > C::NUM_PIXEL_COLS as i16
+        || display_size.1 > C::NUM_PIXEL_ROWS as i16
+        || display_offset.0 + display_size.0 > C::NUM_PIXEL_COLS as i16
+        || display_offset.1 + display_size.1 > C::NUM_PIXEL_ROWS as i16
+        || display_size.0.rem_euclid(C::PIXELS_PER_COLUMN as i16) != 0
+        || display_offset.0.rem_euclid(C::PIXELS_PER_COLUMN as i16) != 0
+    {
+        panic!("Display size or column offset not supported by the chip.");
+    }
+}
+
+/// Whether a region from `upper_left` to `lower_right` is correctly ordered, aligned to columns,
+/// and within the drawable area of a display of `display_size` on chip `C`.
+#[allow(clippy::nonminimal_bool)]
+pub(crate) fn region_in_bounds<C: Chip>(
+    display_size: PixelCoord,
+    upper_left: PixelCoord,
+    lower_right: PixelCoord,
+) -> bool {
+    // The row fields are bounds-checked against the chip's maximum supported row rather than the
+    // display size, because the display supports vertical scrolling by adding an offset to the
+    // memory address that corresponds to row 0 (`SetStartLine` command). This feature makes it
+    // possible to "pan" displays with fewer rows up and down over the entire 128 buffer rows. So,
+    // allow users to draw in that area even if it's currently hidden.
+    //
+    // The chip does not have any such panning support for buffer column addresses outside of the
+    // display's viewable area, so even though the chip allows data to be written there, it is
+    // probably an error because it can never be read back and can never be visible on the
+    // display. So, check column values against the display size and do not allow drawing outside
+    // them.
+    !(false
+        || upper_left.0 > display_size.0
+        || lower_right.0 > display_size.0
+        || upper_left.1 > C::NUM_PIXEL_ROWS as i16
+        || lower_right.1 > C::NUM_PIXEL_ROWS as i16
+        || upper_left.0 >= lower_right.0
+        || upper_left.1 >= lower_right.1
+        || upper_left.0.rem_euclid(C::PIXELS_PER_COLUMN as i16) != 0
+        || lower_right.0.rem_euclid(C::PIXELS_PER_COLUMN as i16) != 0)
+}
+
+/// Send the full init sequence for `config` to an SSD1322 display of `display_size` at
+/// `display_offset`, returning the part of the configuration which must be kept.
+pub(crate) fn send_init_sequence<DI>(
+    iface: &mut DI,
+    config: Config,
+    display_size: PixelCoord,
+    display_offset: PixelCoord,
+) -> Result<PersistentConfig, CommandError<DI::Error>>
+where
+    DI: interface::DisplayInterface,
+{
+    let mux_ratio = init_mux_ratio(&config, display_size)?;
+    if config.command_lock.is_some() {
+        Command::SetCommandLock(false).send(iface)?;
+    }
+    Command::SetSleepMode(true).send(iface)?;
+    Command::SetDisplayMode(DisplayMode::BlankDark).send(iface)?;
+    config.send(iface)?;
+    Command::SetMuxRatio(mux_ratio).send(iface)?;
+    Command::SetDisplayOffset(display_offset.1 as u8).send(iface)?;
+    Command::SetStartLine(0).send(iface)?;
+    let (increment_axis, column_remap, nibble_remap) = config.remapping;
+    config
+        .persistent_config
+        .send(iface, increment_axis, column_remap, nibble_remap)?;
+    Command::SetSleepMode(false).send(iface)?;
+    Command::SetDisplayMode(DisplayMode::Normal).send(iface)?;
+    if config.command_lock == Some(true) {
+        Command::SetCommandLock(true).send(iface)?;
+    }
+    Ok(config.persistent_config)
+}
+
+#[cfg(feature = "adapters")]
+impl<DI, F, C> Display<StatsInterface<DI, F>, C>
+where
+    DI: interface::DisplayInterface,
+    F: FnMut() -> u32,
+    C: Chip,
+{
+    /// The traffic statistics collected by the display's `StatsInterface`.
+    pub fn stats(&self) -> Stats {
+        self.iface.stats()
+    }
+
+    /// Reset the traffic statistics collected by the display's `StatsInterface`.
+    pub fn reset_stats(&mut self) {
+        self.iface.reset_stats()
+    }
+}
+
+#[cfg(feature = "adapters")]
+impl<DI, C> Display<CrcInterface<DI>, C>
+where
+    DI: interface::DisplayInterface,
+    C: Chip,
+{
+    /// The checksum of the latest frame sent through the display's `CrcInterface`.
+    pub fn frame_crc(&self) -> Option<FrameCrc> {
+        self.iface.frame_crc()
+    }
+
+    /// Forget the latest frame checksum and restart the frame count of the display's
+    /// `CrcInterface`.
+    pub fn reset_frame_crc(&mut self) {
+        self.iface.reset_frame_crc()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::{PixelCoord as Px, *};
-    use interface::test_spy::{Sent, TestSpyInterface};
+    use crate::test_support::CountingDelay;
+    use interface::mock::{MockInterface, Sent};
+    use std::cell::RefCell;
+    use std::rc::Rc;
 
     #[test]
     fn init_defaults() {
-        let di = TestSpyInterface::new();
+        let di = MockInterface::new();
         let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
         let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
         disp.init(cfg).unwrap();
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
             0xAE, // sleep enable
             0xA4, // display blank
@@ -224,7 +1185,7 @@ mod tests {
 
     #[test]
     fn init_many_options() {
-        let di = TestSpyInterface::new();
+        let di = MockInterface::new();
         let mut disp = Display::new(di.split(), Px(256, 128), Px(0, 0));
         let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive)
             .contrast_current(160)
@@ -235,7 +1196,7 @@ mod tests {
             .precharge_voltage(5)
             .com_deselect_voltage(6);
         disp.init(cfg).unwrap();
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
             0xAE, // sleep enable
             0xA4, // display blank
@@ -257,11 +1218,11 @@ mod tests {
 
     #[test]
     fn init_row_offset() {
-        let di = TestSpyInterface::new();
+        let di = MockInterface::new();
         let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 32));
         let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
         disp.init(cfg).unwrap();
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
             0xAE, // sleep enable
             0xA4, // display blank
@@ -274,9 +1235,499 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn init_mux_ratio_override() {
+        let di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        assert_eq!(
+            disp.init(cfg.clone().mux_ratio(129)),
+            Err(CommandError::BadMuxRatio { ratio: 129 })
+        );
+        assert_eq!(
+            disp.init(cfg.clone().mux_ratio(15)),
+            Err(CommandError::BadMuxRatio { ratio: 15 })
+        );
+        // The ratio is checked up front, so a bad one leaves the chip as it was.
+        assert_eq!(di.sent(), []);
+        disp.init(cfg.mux_ratio(60)).unwrap();
+        #[rustfmt::skip]
+        di.check_multi(sends!(
+            0xAE, // sleep enable
+            0xA4, // display blank
+            0xCA, [59], // mux ratio 60 lines
+            0xA2, [0], // display offset 0
+            0xA1, [0], // start line 0
+            0xA0, [0b00010100, 0b00010001], // remapping
+            0xAF, // sleep disable
+            0xA6 // display normal
+        ));
+    }
+
+    #[test]
+    fn init_small_panel() {
+        let di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(64, 5), Px(0, 20));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::Progressive);
+        disp.init(cfg).unwrap();
+        #[rustfmt::skip]
+        di.check_multi(sends!(
+            0xAE, // sleep enable
+            0xA4, // display blank
+            0xCA, [15], // mux ratio padded to 16 lines
+            0xA2, [20], // display offset 20
+            0xA1, [0], // start line 0
+            0xA0, [0b00010100, 0b00000001], // remapping
+            0xAF, // sleep disable
+            0xA6 // display normal
+        ));
+        // The padding rows are scanned too, so the frame takes as long as on a 16 row panel.
+        assert_eq!(disp.estimated_frame_period(), 2289);
+    }
+
+    #[test]
+    fn init_command_lock() {
+        let di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive)
+            .command_lock(true);
+        disp.init(cfg).unwrap();
+        #[rustfmt::skip]
+        di.check_multi(sends!(
+            0xFD, [0x12], // command unlock
+            0xAE, // sleep enable
+            0xA4, // display blank
+            0xCA, [63], // mux ratio 64 lines
+            0xA2, [0], // display offset 0
+            0xA1, [0], // start line 0
+            0xA0, [0b00010100, 0b00010001], // remapping
+            0xAF, // sleep disable
+            0xA6, // display normal
+            0xFD, [0x16] // command lock
+        ));
+    }
+
+    #[test]
+    fn locked_refuses_commands() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive)
+            .command_lock(true);
+        disp.init(cfg).unwrap();
+        assert!(disp.is_locked());
+        di.clear();
+        assert_eq!(disp.contrast(3), Err(CommandError::Locked));
+        assert_eq!(disp.sleep(true), Err(CommandError::Locked));
+        assert!(matches!(
+            disp.region(Px(0, 0), Px(4, 1)),
+            Err(CommandError::Locked)
+        ));
+        assert_eq!(disp.set_pixel(Px(0, 0), 15), Err(CommandError::Locked));
+        assert_eq!(di.sent(), []);
+
+        disp.command_lock(false).unwrap();
+        disp.contrast(3).unwrap();
+        disp.send_command(Command::SetCommandLock(true)).unwrap();
+        assert!(disp.is_locked());
+        #[rustfmt::skip]
+        di.check_multi(sends!(
+            0xFD, [0x12], // command unlock
+            0xC7, [3], // master contrast
+            0xFD, [0x16] // command lock
+        ));
+
+        // A config which does not manage the lock is sent unlocked.
+        di.clear();
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        disp.init(cfg).unwrap();
+        assert!(!disp.is_locked());
+        assert_eq!(di.sent()[..2], sends!(0xFD, [0x12])[..]);
+    }
+
+    #[test]
+    fn geometry_getters() {
+        let di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(112, 0));
+        assert_eq!(disp.size(), Px(128, 64));
+        assert_eq!(disp.offset(), Px(112, 0));
+        assert_eq!(disp.current_start_line(), 0);
+        disp.vertical_pan(17).unwrap();
+        assert_eq!(disp.current_start_line(), 17);
+    }
+
+    #[test]
+    fn send_command_passthrough() {
+        let di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        disp.send_command(Command::EnablePartialDisplay(8, 15))
+            .unwrap();
+        assert_eq!(
+            disp.send_command(Command::EnablePartialDisplay(15, 8)),
+            Err(CommandError::OutOfRange)
+        );
+        disp.send_command(Command::SetMasterContrast(4)).unwrap();
+        assert_eq!(disp.master_contrast, 4);
+        di.check_multi(sends!(0xA8, [8, 15], 0xC7, [4]));
+    }
+
+    #[test]
+    fn ram_dirty() {
+        let di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(8, 16), Px(4, 0));
+        assert!(disp.ram_is_dirty());
+        disp.clear(0x13).unwrap();
+        assert!(!disp.ram_is_dirty());
+        let sent = di.sent();
+        #[rustfmt::skip]
+        assert_eq!(sent[..5], [
+            Sent::Cmd(0x15), Sent::Data(vec![1, 2]),
+            Sent::Cmd(0x75), Sent::Data(vec![0, 15]),
+            Sent::Cmd(0x5C),
+        ]);
+        let image: Vec<u8> = sent[5..]
+            .iter()
+            .flat_map(|sent| match sent {
+                Sent::Data(data) => data.clone(),
+                Sent::Cmd(_) => vec![],
+            })
+            .collect();
+        assert_eq!(image, [0x33; 64]);
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::Progressive);
+        disp.init(cfg).unwrap();
+        assert!(disp.ram_is_dirty());
+        disp.mark_ram_clean();
+        assert!(!disp.ram_is_dirty());
+    }
+
+    #[cfg(feature = "retain-config")]
+    #[test]
+    fn reinit_resends_init() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        assert_eq!(disp.reinit(), Err(CommandError::Uninitialized));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive)
+            .contrast_current(160);
+        disp.init(cfg).unwrap();
+        di.clear();
+        disp.reinit().unwrap();
+        #[rustfmt::skip]
+        di.check_multi(sends!(
+            0xAE, // sleep enable
+            0xA4, // display blank
+            0xC1, [160], // contrast current
+            0xCA, [63], // mux ratio 64 lines
+            0xA2, [0], // display offset 0
+            0xA1, [0], // start line 0
+            0xA0, [0b00010100, 0b00010001], // remapping
+            0xAF, // sleep disable
+            0xA6, // display normal
+            0xC7, [15] // master contrast
+        ));
+    }
+
+    #[cfg(feature = "retain-config")]
+    #[test]
+    fn reinit_relocks() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive)
+            .command_lock(true);
+        disp.init(cfg).unwrap();
+        disp.command_lock(false).unwrap();
+        disp.vertical_pan(10).unwrap();
+        disp.command_lock(true).unwrap();
+        di.clear();
+        disp.reinit().unwrap();
+        assert!(disp.is_locked());
+        #[rustfmt::skip]
+        di.check_multi(sends!(
+            0xFD, [0x12], // command unlock
+            0xAE, // sleep enable
+            0xA4, // display blank
+            0xCA, [63], // mux ratio 64 lines
+            0xA2, [0], // display offset 0
+            0xA1, [0], // start line 0
+            0xA0, [0b00010100, 0b00010001], // remapping
+            0xAF, // sleep disable
+            0xA6, // display normal
+            0xFD, [0x16], // command lock
+            0xFD, [0x12], // command unlock
+            0xC7, [15], // master contrast
+            0xA1, [10], // start line 10
+            0xFD, [0x16] // command lock
+        ));
+    }
+
+    #[test]
+    fn set_remapping() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        assert_eq!(
+            disp.set_remapping(
+                IncrementAxis::Vertical,
+                ColumnRemap::Reverse,
+                NibbleRemap::Reverse
+            ),
+            Err(CommandError::Uninitialized)
+        );
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        disp.init(cfg).unwrap();
+        di.clear();
+        disp.set_remapping(
+            IncrementAxis::Vertical,
+            ColumnRemap::Reverse,
+            NibbleRemap::Reverse,
+        )
+        .unwrap();
+        di.check(0xA0, &[0b00010011, 0b00010001]);
+        assert_eq!(
+            disp.remapping(),
+            (
+                IncrementAxis::Vertical,
+                ColumnRemap::Reverse,
+                NibbleRemap::Reverse
+            )
+        );
+        // Init restores the settings regions assume.
+        disp.init(Config::new(
+            ComScanDirection::RowZeroLast,
+            ComLayout::DualProgressive,
+        ))
+        .unwrap();
+        assert_eq!(disp.remapping(), DEFAULT_REMAPPING);
+    }
+
+    #[cfg(feature = "retain-config")]
+    #[test]
+    fn config_not_retained_when_init_fails() {
+        let di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        assert!(disp.init(cfg.clone().mux_ratio(129)).is_err());
+        assert_eq!(disp.config(), None);
+        assert_eq!(disp.reinit(), Err(CommandError::Uninitialized));
+        disp.init(cfg.clone()).unwrap();
+        assert!(disp.init(cfg.clone().mux_ratio(15)).is_err());
+        assert_eq!(disp.config(), Some(&cfg));
+    }
+
+    #[cfg(feature = "retain-config")]
+    #[test]
+    fn reinit_restores_state() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        assert_eq!(disp.reinit(), Err(CommandError::Uninitialized));
+        assert_eq!(disp.config(), None);
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        disp.init(cfg.clone()).unwrap();
+        assert_eq!(disp.config(), Some(&cfg));
+        disp.set_brightness(20).unwrap();
+        disp.vertical_pan(10).unwrap();
+        disp.display_mode(DisplayMode::Inverse).unwrap();
+        disp.set_refresh_rate(60).unwrap();
+        disp.set_remapping(
+            IncrementAxis::Vertical,
+            ColumnRemap::Forward,
+            NibbleRemap::Forward,
+        )
+        .unwrap();
+        let period = disp.estimated_frame_period();
+        di.clear();
+        disp.reinit().unwrap();
+        #[rustfmt::skip]
+        di.check_multi(sends!(
+            0xAE, // sleep enable
+            0xA4, // display blank
+            0xCA, [63], // mux ratio 64 lines
+            0xA2, [0], // display offset 0
+            0xA1, [0], // start line 0
+            0xA0, [0b00010100, 0b00010001], // remapping
+            0xAF, // sleep disable
+            0xA6, // display normal
+            0xA0, [0b00010101, 0b00010001], // remapping
+            0xB3, [0x21], // clock
+            0xC1, [121], // contrast current
+            0xA7, // display inverse
+            0xC7, [0], // master contrast
+            0xA1, [10] // start line 10
+        ));
+        assert_eq!(disp.estimated_frame_period(), period);
+    }
+
+    #[cfg(feature = "retain-config")]
+    #[test]
+    fn reinit_tick() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        disp.init(cfg).unwrap();
+        di.clear();
+
+        // Disabled by default.
+        assert_eq!(disp.reinit_tick(0), Ok(false));
+        assert_eq!(disp.reinit_tick(u32::MAX), Ok(false));
+
+        disp.set_reinit_period(Some(1000));
+        assert_eq!(disp.reinit_tick(u32::MAX - 500), Ok(false));
+        assert_eq!(disp.reinit_tick(498), Ok(false));
+        di.check_multi(&[]);
+        // Period elapses across the clock wrapping around.
+        assert_eq!(disp.reinit_tick(499), Ok(true));
+        assert_eq!(disp.reinit_tick(1000), Ok(false));
+        assert_eq!(disp.reinit_tick(1499), Ok(true));
+    }
+
+    #[test]
+    fn power_off() {
+        struct Delay(Rc<RefCell<Vec<&'static str>>>);
+        impl hal::blocking::delay::DelayMs<u16> for Delay {
+            fn delay_ms(&mut self, ms: u16) {
+                assert_eq!(ms, 100);
+                self.0.borrow_mut().push("delay");
+            }
+        }
+
+        let di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut delay = Delay(events.clone());
+        disp.power_off(&mut delay, || {
+            di.check(0xAE, &[]);
+            events.borrow_mut().push("vcc off");
+        })
+        .unwrap();
+        assert_eq!(*events.borrow(), ["vcc off", "delay"]);
+    }
+
+    #[test]
+    fn set_refresh_rate() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(256, 64), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive)
+            .phase_lengths(5, 14);
+        disp.init(cfg).unwrap();
+        di.clear();
+        // A row is 5 + 14 + 180 DCLKs, so 64 rows at 60 Hz need DCLK near 764 kHz.
+        assert_eq!(disp.set_refresh_rate(60), Ok(60));
+        di.check(0xB3, &[0x21]);
+        di.clear();
+        // Faster than the chip can go, so the fastest setting is chosen.
+        assert_eq!(disp.set_refresh_rate(1000), Ok(243));
+        di.check(0xB3, &[0xF0]);
+        assert_eq!(disp.set_refresh_rate(0), Err(CommandError::OutOfRange));
+    }
+
+    #[test]
+    fn estimated_frame_period() {
+        let di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(256, 64), Px(0, 0));
+        // Before init: 9 + 7 + 180 DCLKs per row, 64 rows, 2.74 MHz divided by 2.
+        assert_eq!(disp.estimated_frame_period(), 9156);
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive)
+            .phase_lengths(5, 14)
+            .clock_fosc_divset(9, 1)
+            .mux_ratio(32);
+        disp.init(cfg).unwrap();
+        assert_eq!(disp.estimated_frame_period(), 5351);
+        // The clock chosen for 60 Hz with all 64 rows and default phases.
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive)
+            .phase_lengths(5, 14);
+        disp.init(cfg).unwrap();
+        assert_eq!(disp.set_refresh_rate(60), Ok(60));
+        assert_eq!(disp.estimated_frame_period(), 16540);
+        let mut delay = CountingDelay(0);
+        disp.wait_for_frame(&mut delay);
+        assert_eq!(delay.0, 16540);
+    }
+
+    #[test]
+    fn set_brightness() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        disp.set_brightness(100).unwrap();
+        di.check_multi(sends!(0xC1, [255], 0xC7, [15]));
+        di.clear();
+        disp.set_brightness(50).unwrap();
+        di.check_multi(sends!(0xC1, [250], 0xC7, [2]));
+        di.clear();
+        disp.set_brightness(5).unwrap();
+        di.check_multi(sends!(0xC1, [22], 0xC7, [0]));
+        di.clear();
+        // The master contrast is already 0.
+        disp.set_brightness(0).unwrap();
+        di.check_multi(sends!(0xC1, [0]));
+        assert_eq!(disp.set_brightness(101), Err(CommandError::OutOfRange));
+    }
+
+    #[test]
+    fn register_cache() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        // Nothing is known before init.
+        disp.vertical_pan(0).unwrap();
+        di.check_multi(sends!(0xA1, [0]));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive)
+            .contrast_current(160);
+        disp.init(cfg).unwrap();
+        di.clear();
+        // Init sets all but the master contrast.
+        disp.contrast_current(160).unwrap();
+        disp.vertical_pan(0).unwrap();
+        disp.display_mode(DisplayMode::Normal).unwrap();
+        disp.contrast(15).unwrap();
+        disp.contrast(15).unwrap();
+        di.check_multi(sends!(0xC7, [15]));
+        di.clear();
+        disp.display_mode(DisplayMode::Inverse).unwrap();
+        disp.display_mode(DisplayMode::Inverse).unwrap();
+        disp.send_command(Command::SetStartLine(4)).unwrap();
+        disp.vertical_pan(4).unwrap();
+        // Values out of range are never sent.
+        assert_eq!(disp.contrast(16), Err(CommandError::OutOfRange));
+        di.check_multi(sends!(0xA7, 0xA1, [4]));
+        di.clear();
+        disp.force_register_writes();
+        disp.vertical_pan(4).unwrap();
+        disp.contrast(15).unwrap();
+        disp.contrast(15).unwrap();
+        di.check_multi(sends!(0xA1, [4], 0xC7, [15]));
+    }
+
+    #[test]
+    fn fade_out_and_in() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        disp.init(cfg).unwrap();
+        disp.contrast(4).unwrap();
+        di.clear();
+
+        let mut delay = CountingDelay(0);
+        disp.fade_out(100, &mut delay).unwrap();
+        assert_eq!(delay.0, 100);
+        #[rustfmt::skip]
+        di.check_multi(sends!(0xC7, [3], 0xC7, [2], 0xC7, [1], 0xC7, [0]));
+        di.clear();
+
+        let mut delay = CountingDelay(0);
+        disp.fade_in(2, 50, &mut delay).unwrap();
+        assert_eq!(delay.0, 50);
+        #[rustfmt::skip]
+        di.check_multi(sends!(0xC7, [1], 0xC7, [2]));
+        di.clear();
+
+        // Already there, nothing to do.
+        disp.fade_in(2, 50, &mut delay).unwrap();
+        di.check_multi(&[]);
+        assert_eq!(
+            disp.fade_in(16, 50, &mut delay),
+            Err(CommandError::OutOfRange)
+        );
+    }
+
     #[test]
     fn region_build() {
-        let di = TestSpyInterface::new();
+        let di = MockInterface::new();
         let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
         let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
         disp.init(cfg).unwrap();
@@ -302,9 +1753,10 @@ mod tests {
         assert!(disp.region(Px(4, 60), Px(20, 130)).is_err());
     }
 
+    #[cfg(feature = "overscan")]
     #[test]
     fn overscanned_region_build() {
-        let di = TestSpyInterface::new();
+        let di = MockInterface::new();
         let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
         let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
         disp.init(cfg).unwrap();
@@ -331,4 +1783,72 @@ mod tests {
         assert!(disp.overscanned_region(Px(-16, 130), Px(-4, 160)).is_ok());
         assert!(disp.overscanned_region(Px(128, -16), Px(132, -4)).is_ok());
     }
+
+    #[test]
+    fn pixel_coord_arithmetic() {
+        assert_eq!(Px(3, 4) + Px(10, -2), Px(13, 2));
+        assert_eq!(Px(3, 4) - Px(10, -2), Px(-7, 6));
+        assert_eq!(Px(3, -4) * 2, Px(6, -8));
+    }
+
+    #[test]
+    fn rect() {
+        let rect = Rect::new(Px(4, 2), Px(12, 5));
+        assert_eq!((rect.width(), rect.height()), (8, 3));
+        assert!(rect.contains(Px(4, 2)));
+        assert!(rect.contains(Px(11, 4)));
+        assert!(!rect.contains(Px(12, 4)));
+        assert!(!rect.contains(Px(4, 5)));
+        assert!(!rect.contains(Px(3, 2)));
+        assert_eq!(rect.translate(Px(-4, 1)), Rect::new(Px(0, 3), Px(8, 6)));
+    }
+
+    #[test]
+    fn rect_intersect() {
+        let rect = Rect::new(Px(4, 2), Px(12, 6));
+        assert_eq!(
+            rect.intersect(&Rect::new(Px(8, -4), Px(20, 4))),
+            Some(Rect::new(Px(8, 2), Px(12, 4)))
+        );
+        assert_eq!(rect.intersect(&Rect::new(Px(0, 0), Px(40, 40))), Some(rect));
+        // Touching edges do not overlap.
+        assert_eq!(rect.intersect(&Rect::new(Px(12, 2), Px(16, 6))), None);
+        assert_eq!(rect.intersect(&Rect::new(Px(4, -2), Px(12, 2))), None);
+        assert_eq!(
+            rect.union(&Rect::new(Px(16, 0), Px(20, 4))),
+            Rect::new(Px(4, 0), Px(20, 6))
+        );
+
+        let disp = Display::new(MockInterface::new(), Px(128, 64), Px(0, 0));
+        assert_eq!(
+            Rect::new(Px(-8, 60), Px(8, 70)).clip_to_display(&disp),
+            Some(Rect::new(Px(0, 60), Px(8, 64)))
+        );
+        assert_eq!(
+            Rect::new(Px(128, 0), Px(132, 4)).clip_to_display(&disp),
+            None
+        );
+    }
+
+    #[test]
+    fn region_rect() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        disp.init(cfg).unwrap();
+        di.clear();
+        let rect = Rect::new(Px(12, 10), Px(16, 12));
+        disp.region_rect(rect).unwrap().draw_packed(0..4).unwrap();
+        #[rustfmt::skip]
+        di.check_multi(sends!(
+            0x15, [3, 3],
+            0x75, [10, 11],
+            0x5C, [0, 1, 2, 3]
+        ));
+        assert!(disp.region_rect(rect.translate(Px(1, 0))).is_err());
+        #[cfg(feature = "overscan")]
+        assert!(disp
+            .overscanned_region_rect(rect.translate(Px(-16, 0)))
+            .is_ok());
+    }
 }
@@ -6,9 +6,10 @@
 use itertools::iproduct;
 
 use crate::command::consts::*;
-use crate::display::region::{Pack8to4, Region};
-use crate::display::PixelCoord;
+use crate::display::region::{AddressWindow, Pack8to4, Region};
+use crate::display::{PixelCoord, Rect};
 use crate::interface;
+use crate::sprite::Sprite;
 
 /// A handle to a rectangular region which can be drawn into, but which is permitted to have
 /// portions that lie outside the viewable area of the display. Pixels that fall outside the
@@ -31,17 +32,12 @@ where
     viewable_pixel_cols: i16,
 }
 
-/// Clip a value between some low and high limit.
-fn clip<T: PartialOrd>(lo: T, x: T, hi: T) -> T {
-    match () {
-        _ if x > hi => hi,
-        _ if x < lo => lo,
-        _ => x,
-    }
-}
-
-fn in_range<T: PartialOrd>(x: T, lo: T, hi: T) -> bool {
-    x >= lo && x < hi
+/// The area of display RAM which may be drawn, which is every row but only the viewable columns.
+fn viewable_area(viewable_pixel_cols: i16) -> Rect {
+    Rect::new(
+        PixelCoord(0, 0),
+        PixelCoord(viewable_pixel_cols, NUM_PIXEL_ROWS as i16),
+    )
 }
 
 impl<'di, DI> OverscannedRegion<'di, DI>
@@ -53,28 +49,17 @@ where
     /// and pre-compensates the column coordinates for the display column offset.
     pub(super) fn new(
         iface: &'di mut DI,
+        window: &'di mut Option<AddressWindow>,
         upper_left: PixelCoord,
         lower_right: PixelCoord,
         viewable_pixel_cols: i16,
         pixel_col_offset: i16,
     ) -> Self {
-        let viewable_ul = PixelCoord(
-            clip(0, upper_left.0, viewable_pixel_cols),
-            clip(0, upper_left.1, NUM_PIXEL_ROWS as i16),
-        );
-        let viewable_lr = PixelCoord(
-            clip(0, lower_right.0, viewable_pixel_cols),
-            clip(0, lower_right.1, NUM_PIXEL_ROWS as i16),
-        );
-        let viewable_region = if viewable_ul.0 == viewable_lr.0 || viewable_ul.1 == viewable_lr.1 {
-            None
-        } else {
-            Some(Region::new(
-                iface,
-                PixelCoord(viewable_ul.0 + pixel_col_offset, viewable_ul.1),
-                PixelCoord(viewable_lr.0 + pixel_col_offset, viewable_lr.1),
-            ))
-        };
+        let viewable = Rect::new(upper_left, lower_right)
+            .intersect(&viewable_area(viewable_pixel_cols))
+            .map(|rect| rect.translate(PixelCoord(pixel_col_offset, 0)));
+        let viewable_region =
+            viewable.map(move |rect| Region::new(iface, window, rect.ul, rect.lr));
         Self {
             viewable_region: viewable_region,
             upper_left: upper_left,
@@ -99,11 +84,9 @@ where
             (self.upper_left.0..self.lower_right.0).step_by(2)
         );
         let input_with_coords = input_coords.zip(iter);
-        let viewable_pixel_cols = self.viewable_pixel_cols;
+        let viewable = viewable_area(self.viewable_pixel_cols);
         let only_viewable = input_with_coords
-            .filter(|((r, c), _)| {
-                in_range(*r, 0, NUM_PIXEL_ROWS as i16) && in_range(*c, 0, viewable_pixel_cols)
-            })
+            .filter(|((r, c), _)| viewable.contains(PixelCoord(*c, *r)))
             .map(|(_, pixels)| pixels);
         self.viewable_region
             .as_mut()
@@ -121,6 +104,16 @@ where
     {
         self.draw_packed(Pack8to4(iter))
     }
+
+    /// Draw `sprite` into the region with its upper left corner at `dest`, relative to the upper
+    /// left corner of the region. The sprite is clipped to the region, and any part of the region
+    /// the sprite does not cover is drawn with gray level 0. Parts of the region outside the
+    /// viewable area are dropped as usual, so a sprite can be blitted partly off screen.
+    pub fn blit(&mut self, sprite: &Sprite, dest: PixelCoord) -> Result<(), DI::Error> {
+        let cols = (self.lower_right.0 - self.upper_left.0) as u16;
+        let rows = (self.lower_right.1 - self.upper_left.1) as u16;
+        self.draw(sprite.window((dest.0, dest.1), cols, rows))
+    }
 }
 
 #[cfg(test)]
@@ -128,11 +121,12 @@ mod tests {
     use crate::command::{ComLayout, ComScanDirection};
     use crate::config::Config;
     use crate::display::{Display, PixelCoord as Px};
-    use crate::interface::test_spy::{Sent, TestSpyInterface};
+    use crate::interface::mock::{MockInterface, Sent};
+    use crate::sprite::Sprite;
 
     #[test]
     fn draw_packed_interior() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
         let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
         disp.init(cfg).unwrap();
@@ -143,7 +137,7 @@ mod tests {
                 .draw_packed([0xDE, 0xAD, 0xBE, 0xEF].iter().cloned())
                 .unwrap();
         }
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
             0x15, [3, 3],
             0x75, [10, 11],
@@ -153,7 +147,7 @@ mod tests {
 
     #[test]
     fn draw_packed_complete_crop() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
         let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
         disp.init(cfg).unwrap();
@@ -164,7 +158,7 @@ mod tests {
                 .draw_packed([0xDE, 0xAD, 0xBE, 0xEF].iter().cloned())
                 .unwrap();
         }
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
         ));
         di.clear();
@@ -174,14 +168,14 @@ mod tests {
                 .draw_packed([0xDE, 0xAD, 0xBE, 0xEF].iter().cloned())
                 .unwrap();
         }
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
         ));
     }
 
     #[test]
     fn draw_packed_crop_row_edge() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
         let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
         disp.init(cfg).unwrap();
@@ -192,7 +186,7 @@ mod tests {
                 .draw_packed([0xDE, 0xAD, 0xBE, 0xEF].iter().cloned())
                 .unwrap();
         }
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
             0x15, [4, 4],
             0x75, [0, 0],
@@ -205,7 +199,7 @@ mod tests {
                 .draw_packed([0xDE, 0xAD, 0xBE, 0xEF].iter().cloned())
                 .unwrap();
         }
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
             0x15, [4, 4],
             0x75, [127, 127],
@@ -215,7 +209,7 @@ mod tests {
 
     #[test]
     fn draw_packed_crop_col_edge() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
         let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
         disp.init(cfg).unwrap();
@@ -230,7 +224,7 @@ mod tests {
                 )
                 .unwrap();
         }
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
             0x15, [0, 0],
             0x75, [10, 11],
@@ -247,7 +241,7 @@ mod tests {
                 )
                 .unwrap();
         }
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
             0x15, [31, 31],
             0x75, [10, 11],
@@ -257,7 +251,7 @@ mod tests {
 
     #[test]
     fn draw_packed_crop_corner() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
         let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
         disp.init(cfg).unwrap();
@@ -272,7 +266,7 @@ mod tests {
                 )
                 .unwrap();
         }
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
             0x15, [0, 0],
             0x75, [0, 0],
@@ -289,7 +283,7 @@ mod tests {
                 )
                 .unwrap();
         }
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
             0x15, [31, 31],
             0x75, [127, 127],
@@ -299,7 +293,7 @@ mod tests {
 
     #[test]
     fn draw_packed_display_column_offset_interior() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         let mut disp = Display::new(di.split(), Px(128, 64), Px(64, 0));
         let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
         disp.init(cfg).unwrap();
@@ -310,7 +304,7 @@ mod tests {
                 .draw_packed([0xDE, 0xAD, 0xBE, 0xEF].iter().cloned())
                 .unwrap();
         }
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
             0x15, [16, 17],
             0x75, [10, 11],
@@ -320,7 +314,7 @@ mod tests {
 
     #[test]
     fn draw_packed_display_column_offset_crop_col() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         let mut disp = Display::new(di.split(), Px(128, 64), Px(24, 0));
         let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
         disp.init(cfg).unwrap();
@@ -331,7 +325,7 @@ mod tests {
                 .draw_packed([0xDE, 0xAD, 0xBE, 0xEF].iter().cloned())
                 .unwrap();
         }
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
             0x15, [6, 6],
             0x75, [10, 10],
@@ -344,11 +338,32 @@ mod tests {
                 .draw_packed([0xDE, 0xAD, 0xBE, 0xEF].iter().cloned())
                 .unwrap();
         }
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
             0x15, [37, 37],
             0x75, [10, 10],
             0x5C, [0xDE, 0xAD]
         ));
     }
+
+    #[test]
+    fn blit_crop_col_edge() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        disp.init(cfg).unwrap();
+        di.clear();
+        // An 8x1 sprite hanging off the left edge of the display only shows its right half.
+        let sprite = Sprite::new(8, 1, &[0x12, 0x34, 0x56, 0x78]);
+        {
+            let mut region = disp.overscanned_region(Px(-4, 10), Px(4, 11)).unwrap();
+            region.blit(&sprite, Px(0, 0)).unwrap();
+        }
+        #[rustfmt::skip]
+        di.check_multi(sends!(
+            0x15, [0, 0],
+            0x75, [10, 10],
+            0x5C, [0x56, 0x78]
+        ));
+    }
 }
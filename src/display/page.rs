@@ -0,0 +1,173 @@
+//! Off-screen pages of display RAM.
+//!
+//! The SSD1322 has 128 rows of RAM, but many modules only show 64 of them (or fewer). The rows
+//! which are not visible can be drawn into ahead of time and then shown with `SetStartLine`,
+//! without any frame buffer in the host. This module divides the RAM into display-height pages
+//! numbered from the top, so that page 0 of a 256x64 module is rows 0-63 and page 1 is rows
+//! 64-127, and hands out regions addressed relative to a page. `Display::present_page` then flips
+//! which page is visible, giving tear-free double buffering entirely in display RAM.
+
+use embedded_hal as hal;
+
+use crate::command::{consts, CommandError};
+use crate::display::region::Region;
+use crate::display::{Display, PixelCoord, Rect};
+use crate::interface;
+
+/// Identifies a display-height page of display RAM, counting from 0 at the top.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageId(pub u8);
+
+/// A handle to one page of display RAM, from which regions can be constructed in page-relative
+/// coordinates. Like regions, pages are intended to be short-lived.
+pub struct Page<'di, DI>
+where
+    DI: 'di + interface::DisplayInterface,
+{
+    display: &'di mut Display<DI>,
+    id: PageId,
+}
+
+impl<'di, DI> Page<'di, DI>
+where
+    DI: 'di + interface::DisplayInterface,
+{
+    /// The page this handle refers to.
+    pub fn id(&self) -> PageId {
+        self.id
+    }
+
+    /// The RAM row at the top of the page, which is also the `SetStartLine` value that makes the
+    /// page visible.
+    pub fn first_row(&self) -> u8 {
+        (self.id.0 as i16 * self.display.display_size.1) as u8
+    }
+
+    /// Construct a rectangular region onto which to draw image data, with coordinates relative
+    /// to the upper left corner of the page.
+    ///
+    /// The same restrictions apply as for `Display::region`, except that the rectangle must also
+    /// lie within the page, so that drawing can never spill into a neighbouring page.
+    #[allow(clippy::nonminimal_bool)]
+    pub fn region<'a>(
+        &'a mut self,
+        upper_left: PixelCoord,
+        lower_right: PixelCoord,
+    ) -> Result<Region<'a, DI>, CommandError<DI::Error>> {
+        if false || upper_left.1 < 0 || lower_right.1 > self.display.display_size.1 {
+            return Err(CommandError::OutOfRange);
+        }
+        let origin = PixelCoord(0, self.first_row() as i16);
+        self.display
+            .region(upper_left + origin, lower_right + origin)
+    }
+
+    /// Construct a rectangular region covering `rect`, with the same restrictions as `region`.
+    pub fn region_rect<'a>(
+        &'a mut self,
+        rect: Rect,
+    ) -> Result<Region<'a, DI>, CommandError<DI::Error>> {
+        self.region(rect.ul, rect.lr)
+    }
+}
+
+impl<DI> Display<DI>
+where
+    DI: interface::DisplayInterface,
+{
+    /// The number of whole display-height pages that fit in the display RAM.
+    pub fn page_count(&self) -> u8 {
+        (consts::NUM_PIXEL_ROWS as i16 / self.display_size.1) as u8
+    }
+
+    /// Construct a handle to page `id` of the display RAM, for drawing off-screen. Returns
+    /// `CommandError::OutOfRange` if `id` is not less than `page_count`.
+    pub fn page(&mut self, id: PageId) -> Result<Page<'_, DI>, CommandError<DI::Error>> {
+        if id.0 >= self.page_count() {
+            return Err(CommandError::OutOfRange);
+        }
+        Ok(Page {
+            display: self,
+            id: id,
+        })
+    }
+
+    /// Make page `id` visible, by setting the start line to its first row. The switch happens
+    /// between one frame and the next, so the image does not tear.
+    pub fn present_page(&mut self, id: PageId) -> Result<(), CommandError<DI::Error>> {
+        let first_row = self.page(id)?.first_row();
+        self.vertical_pan(first_row)
+    }
+
+    /// Make page `id` visible by scrolling the start line one row at a time from its current
+    /// value, spreading the steps evenly over roughly `duration_ms` milliseconds using `delay`.
+    pub fn present_page_scrolled<D>(
+        &mut self,
+        id: PageId,
+        duration_ms: u16,
+        delay: &mut D,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        D: hal::blocking::delay::DelayMs<u16>,
+    {
+        let target = self.page(id)?.first_row();
+        let steps = (self.start_line as i16 - target as i16).unsigned_abs();
+        if steps == 0 {
+            return Ok(());
+        }
+        let step_delay = duration_ms / steps;
+        while self.start_line != target {
+            delay.delay_ms(step_delay);
+            let next = if self.start_line < target {
+                self.start_line + 1
+            } else {
+                self.start_line - 1
+            };
+            self.vertical_pan(next)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::test_support::{initialized_display, CountingDelay};
+
+    #[test]
+    fn draw_into_page() {
+        let (di, mut disp) = initialized_display(Px(8, 64));
+        assert_eq!(disp.page_count(), 2);
+        assert!(disp.page(PageId(2)).is_err());
+
+        let mut page = disp.page(PageId(1)).unwrap();
+        assert_eq!(page.first_row(), 64);
+        assert!(page.region(Px(0, 60), Px(8, 65)).is_err());
+        page.region(Px(0, 1), Px(8, 2))
+            .unwrap()
+            .draw(1..=8)
+            .unwrap();
+
+        assert_eq!(di.chip_model(Px(8, 64), Px(0, 0)).render()[8..16], [0; 8]);
+        disp.present_page(PageId(1)).unwrap();
+        let image = di.chip_model(Px(8, 64), Px(0, 0)).render();
+        assert_eq!(&image[8..16], &[1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn scroll_between_pages() {
+        let (mut di, mut disp) = initialized_display(Px(8, 32));
+        di.clear();
+
+        let mut delay = CountingDelay(0);
+        disp.present_page_scrolled(PageId(1), 320, &mut delay)
+            .unwrap();
+        assert_eq!(delay.0, 320);
+        assert_eq!(di.sent().len(), 64);
+        disp.present_page_scrolled(PageId(1), 320, &mut delay)
+            .unwrap();
+        assert_eq!(delay.0, 320);
+        assert_eq!(disp.present_page(PageId(4)), Err(CommandError::OutOfRange));
+    }
+}
@@ -0,0 +1,169 @@
+//! Single pixel writes without a frame buffer.
+//!
+//! Display RAM can only be written a whole column address at a time, which on the SSD1322 holds 4
+//! pixels, and cannot be read back over SPI. To change one pixel without clobbering its neighbors
+//! the driver must know what they hold, so `Display::set_pixel` remembers the column groups it
+//! touched most recently. This is enough for a cursor or a few markers, or for plotting a line or
+//! curve pixel by pixel, without paying for a buffer of the whole display.
+
+use crate::chip::Chip;
+use crate::command::CommandError;
+use crate::display::{region_in_bounds, Display, PixelCoord};
+use crate::interface;
+
+/// The number of column groups remembered by `Display::set_pixel`.
+const CACHED_GROUPS: usize = 4;
+
+/// The column groups most recently written by `Display::set_pixel`, most recent first, each as the
+/// coordinates of its leftmost pixel and the levels of its pixels.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct PixelCache {
+    groups: [Option<(PixelCoord, [u8; 4])>; CACHED_GROUPS],
+}
+
+impl PixelCache {
+    /// Forget every group, as when display RAM is written by other means.
+    pub(crate) fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Move the group at `origin` to the front, starting it with all pixels at 0 if it is not
+    /// cached, dropping the least recently used group to make room. Returns whether it was cached,
+    /// and its levels.
+    fn touch(&mut self, origin: PixelCoord) -> (bool, &mut [u8; 4]) {
+        let found = self
+            .groups
+            .iter()
+            .position(|group| matches!(group, Some((o, _)) if *o == origin));
+        match found {
+            Some(index) => self.groups[..=index].rotate_right(1),
+            None => {
+                self.groups.rotate_right(1);
+                self.groups[0] = Some((origin, [0; 4]));
+            }
+        }
+        match &mut self.groups[0] {
+            Some((_, levels)) => (found.is_some(), levels),
+            None => unreachable!(),
+        }
+    }
+}
+
+impl<DI, C> Display<DI, C>
+where
+    DI: interface::DisplayInterface,
+    C: Chip,
+{
+    /// Set the pixel at `point` to gray level `level`, writing the column group which contains it.
+    ///
+    /// The other pixels of the group keep the levels given to them by earlier calls if the group
+    /// is one of the few most recently set, and are written as 0 otherwise. Drawing anything
+    /// through a region, `init` or a background flush forgets all groups, since the driver cannot
+    /// tell what they wrote. Nothing is sent if the pixel is known to have this level already.
+    ///
+    /// `point` has the same range as the corners of `Display::region`.
+    pub fn set_pixel(
+        &mut self,
+        point: PixelCoord,
+        level: u8,
+    ) -> Result<(), CommandError<DI::Error>> {
+        let width = C::PIXELS_PER_COLUMN as i16;
+        let origin = PixelCoord(point.0 - point.0.rem_euclid(width), point.1);
+        let lower_right = origin + PixelCoord(width, 1);
+        // Check the region before touching the cache, so that a bad point leaves it as it was.
+        self.check_ready()?;
+        if point.0 < 0
+            || point.1 < 0
+            || !region_in_bounds::<C>(self.display_size, origin, lower_right)
+        {
+            return Err(CommandError::OutOfRange);
+        }
+        let (cached, levels) = self.pixels.touch(origin);
+        let pixel = &mut levels[point.0.rem_euclid(width) as usize];
+        if cached && *pixel == level & 0x0F {
+            return Ok(());
+        }
+        *pixel = level & 0x0F;
+        let levels = *levels;
+        let result = self
+            .region_keeping_pixels(origin, lower_right)?
+            .draw_packed(
+                levels[..width as usize]
+                    .chunks(2)
+                    .map(|pair| pair[0] << 4 | pair[1]),
+            );
+        if result.is_err() {
+            // The group may be only partly written.
+            self.pixels.clear();
+        }
+        result.map_err(CommandError::InterfaceError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+    use std::vec::Vec;
+
+    /// The image data sent since the last `WriteImageData` command, if any.
+    fn last_image(di: &MockInterface) -> Option<Vec<u8>> {
+        let sent = di.sent();
+        let start = sent.iter().rposition(|s| *s == Sent::Cmd(0x5C))?;
+        Some(
+            sent[start + 1..]
+                .iter()
+                .flat_map(|s| match s {
+                    Sent::Data(data) => data.clone(),
+                    Sent::Cmd(_) => Vec::new(),
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn neighbors_kept() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        disp.set_pixel(Px(9, 3), 5).unwrap();
+        assert_eq!(last_image(&di), Some(vec![0x05, 0x00]));
+        disp.set_pixel(Px(10, 3), 0x17).unwrap();
+        assert_eq!(last_image(&di), Some(vec![0x05, 0x70]));
+        // The address window is the same, so only the image data is sent again.
+        assert_eq!(
+            di.sent().iter().filter(|s| **s == Sent::Cmd(0x15)).count(),
+            1
+        );
+        di.clear();
+        disp.set_pixel(Px(10, 3), 7).unwrap();
+        assert_eq!(di.sent(), []);
+
+        // Drawing a region forgets what was set.
+        disp.region(Px(0, 0), Px(4, 1))
+            .unwrap()
+            .draw_packed([0, 0].iter().cloned())
+            .unwrap();
+        disp.set_pixel(Px(11, 3), 1).unwrap();
+        assert_eq!(last_image(&di), Some(vec![0x00, 0x01]));
+
+        di.clear();
+        assert_eq!(disp.set_pixel(Px(-1, 0), 1), Err(CommandError::OutOfRange));
+        assert_eq!(disp.set_pixel(Px(128, 0), 1), Err(CommandError::OutOfRange));
+        assert_eq!(di.sent(), []);
+    }
+
+    #[test]
+    fn least_recent_forgotten() {
+        let di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        for x in 0..=4 {
+            disp.set_pixel(Px(x * 4, 0), 15).unwrap();
+        }
+        // The group at x = 4 is still cached, but the one at x = 0 has been dropped.
+        disp.set_pixel(Px(5, 0), 15).unwrap();
+        assert_eq!(last_image(&di), Some(vec![0xFF, 0x00]));
+        disp.set_pixel(Px(1, 0), 15).unwrap();
+        assert_eq!(last_image(&di), Some(vec![0x0F, 0x00]));
+    }
+}
@@ -1,68 +1,111 @@
 //! Region abstraction for drawing into rectangular regions of the display.
 
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use std::io;
+
+#[cfg(feature = "nb")]
 use nb;
 
-use crate::command::{BufCommand, Command, CommandError};
+use crate::chip::{Chip, Ssd1322};
+use crate::command::{Command, CommandError};
+use crate::crc::Crc32;
 use crate::display::PixelCoord;
 use crate::interface;
+use crate::sprite::Sprite;
+#[cfg(feature = "text")]
+use crate::text::wrap::{Overflow, WrappedText};
+#[cfg(feature = "text")]
+use crate::text::{Font, HAlign, VAlign};
+
+/// The errors that may occur while streaming image data into a region with
+/// `Region::draw_from_reader`.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum StreamError<IE> {
+    /// The underlying `DisplayInterface` gave an error while sending the image data.
+    InterfaceError(IE),
+    /// The reader gave an error while the image data was being read from it.
+    ReadError(io::Error),
+}
+
+/// The column and row address window of a region, in chip columns and rows, inclusive.
+pub(crate) type AddressWindow = ((u8, u8), (u8, u8));
 
 /// A handle to a rectangular region of a display which can be drawn into. These are intended to be
 /// short-lived, and contain a mutable borrow of the display that issued them so clashing writes
 /// are prevented.
-pub struct Region<'di, DI>
+pub struct Region<'di, DI, C = Ssd1322>
 where
     DI: 'di + interface::DisplayInterface,
+    C: Chip,
 {
     iface: &'di mut DI,
+    /// The display's record of the window the chip is ready to write from its start, if any.
+    window: &'di mut Option<AddressWindow>,
     top: u8,
     rows: u8,
     buf_left: u8,
     buf_cols: u8,
     pixel_cols: u16,
+    chip: PhantomData<C>,
 }
 
-impl<'di, DI> Region<'di, DI>
+impl<'di, DI, C> Region<'di, DI, C>
 where
     DI: 'di + interface::DisplayInterface,
+    C: Chip,
 {
     /// Construct a new region. This is only called by the factory method `Display::region`, which
     /// checks that the region coordinates are within the viewable area and correctly ordered, and
     /// pre-compensates the column coordinates for the display column offset.
-    pub(super) fn new(iface: &'di mut DI, upper_left: PixelCoord, lower_right: PixelCoord) -> Self {
+    pub(super) fn new(
+        iface: &'di mut DI,
+        window: &'di mut Option<AddressWindow>,
+        upper_left: PixelCoord,
+        lower_right: PixelCoord,
+    ) -> Self {
         let pixel_cols = lower_right.0 - upper_left.0;
         Self {
             iface: iface,
+            window: window,
             top: upper_left.1 as u8,
             rows: (lower_right.1 - upper_left.1) as u8,
-            buf_left: (upper_left.0 / 4) as u8,
-            buf_cols: (pixel_cols / 4) as u8,
+            buf_left: (upper_left.0 as u16 / C::PIXELS_PER_COLUMN) as u8,
+            buf_cols: (pixel_cols as u16 / C::PIXELS_PER_COLUMN) as u8,
             pixel_cols: pixel_cols as u16,
+            chip: PhantomData,
         }
     }
 
     /// Draw packed-pixel image data into the region, such that each byte is two 4-bit gray scale
     /// values of horizontally-adjacent pixels. Pixels are drawn left-to-right and top-to-bottom.
-    pub fn draw_packed<I>(&mut self, mut iter: I) -> Result<(), DI::Error>
+    pub fn draw_packed<I>(&mut self, iter: I) -> Result<(), DI::Error>
     where
         I: Iterator<Item = u8>,
     {
-        // Set the row and column address registers and put the display in write mode. Unwrap all
-        // of the CommandErrors in this scope as interface errors, as all bounds checking should be
-        // done by the time we are here.
-        (|| {
-            Command::SetColumnAddress(self.buf_left, self.buf_left + self.buf_cols - 1)
-                .send(self.iface)?;
-            Command::SetRowAddress(self.top, self.top + self.rows - 1).send(self.iface)?;
-            BufCommand::WriteImageData(&[]).send(self.iface)?;
-            Ok(())
-        })()
-        .map_err(CommandError::unwrap_interface)?;
+        self.begin()?;
+        let result = self.send_packed(iter);
+        if result
+            .as_ref()
+            .is_ok_and(|&sent| sent == self.total_bytes())
+        {
+            self.filled();
+        }
+        result.and(self.end())
+    }
 
+    /// Send packed-pixel image data after `begin`, up to the size of the region. Returns the number
+    /// of bytes sent.
+    #[cfg(feature = "nb")]
+    fn send_packed<I>(&mut self, mut iter: I) -> Result<usize, DI::Error>
+    where
+        I: Iterator<Item = u8>,
+    {
         // Paint the region using asynchronous writes so that iter.next() may run concurrently with
         // the SPI write cycle for a small throughput win.
-        let region_total_bytes = self.pixel_cols as usize * self.rows as usize / 2;
+        let region_total_bytes = self.total_bytes();
         let mut total_written = 0;
-        let mut next_byte: u8;
 
         loop {
             // Break early if we have copied enough bytes to exactly fill the region.
@@ -74,25 +117,236 @@ where
             match iter.next() {
                 Some(pixels) => {
                     total_written += 1;
-                    next_byte = pixels;
+                    self.push(pixels)?;
                 }
                 None => break,
             }
+        }
+        Ok(total_written)
+    }
 
-            // Write the byte to the interface FIFO. If the FIFO is full then poll it until the
-            // send succeeds before continuing the outer loop to consume the next byte from the
-            // iterator.
-            loop {
-                match self.iface.send_data_async(next_byte) {
-                    Ok(()) => break,
-                    Err(nb::Error::WouldBlock) => {}
-                    Err(nb::Error::Other(e)) => return Err(e),
-                }
+    /// Send packed-pixel image data after `begin`, up to the size of the region. Returns the number
+    /// of bytes sent.
+    #[cfg(not(feature = "nb"))]
+    fn send_packed<I>(&mut self, iter: I) -> Result<usize, DI::Error>
+    where
+        I: Iterator<Item = u8>,
+    {
+        // Without non-blocking writes, gather the image data into chunks so that the interface
+        // can send each one in a single blocking transfer.
+        let mut chunk = [0u8; 32];
+        let mut len = 0;
+        let mut total_written = 0;
+        for pixels in iter.take(self.total_bytes()) {
+            chunk[len] = pixels;
+            len += 1;
+            total_written += 1;
+            if len == chunk.len() {
+                self.iface.send_data(&chunk)?;
+                len = 0;
             }
         }
+        if len > 0 {
+            self.iface.send_data(&chunk[..len])?;
+        }
+        Ok(total_written)
+    }
+
+    /// Draw packed-pixel image data into the region as with `draw_packed`, from a source which
+    /// knows its length. The length is checked against the size of the region before anything is
+    /// sent, returning `CommandError::BadDataLength` on a mismatch so that a sizing bug cannot
+    /// leave a partly-drawn or misaligned image on the display.
+    pub fn draw_packed_exact<I>(&mut self, iter: I) -> Result<(), CommandError<DI::Error>>
+    where
+        I: ExactSizeIterator<Item = u8>,
+    {
+        if iter.len() != self.total_bytes() {
+            return Err(CommandError::BadDataLength {
+                expected: self.total_bytes(),
+                actual: iter.len(),
+            });
+        }
+        self.draw_packed(iter).map_err(CommandError::InterfaceError)
+    }
+
+    /// Draw packed-pixel image data into the region as with `draw_packed`, computing a CRC-32 of
+    /// the data as it is sent and comparing it with `expected_crc`, the CRC-32 of the source image
+    /// (see `crc::crc32`). Returns `CommandError::VerifyFailed` if they differ, which means the
+    /// data was corrupted or cut short on its way from the source.
+    pub fn draw_packed_checked<I>(
+        &mut self,
+        iter: I,
+        expected_crc: u32,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        I: Iterator<Item = u8>,
+    {
+        let mut crc = Crc32::new();
+        self.draw_packed(iter.inspect(|byte| crc.update(*byte)))
+            .map_err(CommandError::InterfaceError)?;
+        if crc.finish() != expected_crc {
+            return Err(CommandError::VerifyFailed);
+        }
         Ok(())
     }
 
+    /// Draw packed-pixel image data into the region one row at a time, where each slice holds the
+    /// packed pixels of one row, top to bottom. Rows beyond the height of the region are ignored.
+    ///
+    /// Returns `CommandError::BadRowLength` if a row is not exactly as many bytes as the region is
+    /// wide. That row and those after it are not drawn, but rows before it already have been.
+    pub fn draw_rows<'a, I>(&mut self, rows: I) -> Result<(), CommandError<DI::Error>>
+    where
+        I: Iterator<Item = &'a [u8]>,
+    {
+        let row_bytes = self.pixel_cols as usize / 2;
+        let mut begun = false;
+        let mut sent = 0;
+        let mut result = Ok(());
+        for row in rows.take(self.rows as usize) {
+            if row.len() != row_bytes {
+                result = Err(CommandError::BadRowLength);
+                break;
+            }
+            if !begun {
+                self.begin().map_err(CommandError::InterfaceError)?;
+                begun = true;
+            }
+            result = self
+                .iface
+                .send_data(row)
+                .map_err(CommandError::InterfaceError);
+            if result.is_err() {
+                break;
+            }
+            sent += 1;
+        }
+        if sent == self.rows {
+            self.filled();
+        }
+        if begun {
+            result = result.and(self.end().map_err(CommandError::InterfaceError));
+        }
+        result
+    }
+
+    /// Draw packed-pixel image data into the region as with `draw_packed`, read from `reader` in
+    /// chunks which are each sent in a single transfer. Reading stops when the region is filled,
+    /// so a stream of several frames can be drawn by calling this once per frame, or at the end of
+    /// the reader's data. Returns the number of bytes drawn, which is less than the size of the
+    /// region if the reader reached its end first.
+    ///
+    /// If reading fails the region is left partly drawn, and the error is returned as
+    /// `StreamError::ReadError`. Reads interrupted by a signal are retried.
+    #[cfg(feature = "std")]
+    pub fn draw_from_reader<R>(&mut self, mut reader: R) -> Result<usize, StreamError<DI::Error>>
+    where
+        R: io::Read,
+    {
+        let total = self.total_bytes();
+        let mut drawn = 0;
+        let mut chunk = [0u8; 256];
+        let mut result = Ok(());
+        self.begin().map_err(StreamError::InterfaceError)?;
+        while drawn < total {
+            let want = chunk.len().min(total - drawn);
+            let len = match reader.read(&mut chunk[..want]) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    result = Err(StreamError::ReadError(e));
+                    break;
+                }
+            };
+            if let Err(e) = self.iface.send_data(&chunk[..len]) {
+                result = Err(StreamError::InterfaceError(e));
+                break;
+            }
+            drawn += len;
+        }
+        if result.is_ok() && drawn == total {
+            self.filled();
+        }
+        let ended = self.end().map_err(StreamError::InterfaceError);
+        result.and(ended).map(|()| drawn)
+    }
+
+    /// Start a transaction, set the row and column address registers and put the display in write
+    /// mode, ready for image data to be pushed. The caller ends the transaction after the data.
+    ///
+    /// If the last region drawn on this display had the same address window, was filled exactly,
+    /// and no command was sent since, then the chip's address pointer has wrapped back to the
+    /// start of the window, so only the write command is sent. It is harmless to the chip, and
+    /// marks the start of the image data for wrappers such as `StatsInterface`.
+    ///
+    /// If setting up the region fails, the transaction is ended before returning the error.
+    pub(crate) fn begin(&mut self) -> Result<(), DI::Error> {
+        self.iface.begin_transaction()?;
+        let result = self.begin_write();
+        if result.is_err() {
+            // The setup error is the one worth reporting.
+            let _ = self.iface.end_transaction();
+        }
+        result
+    }
+
+    /// Set up the region for writing, after the transaction has begun.
+    fn begin_write(&mut self) -> Result<(), DI::Error> {
+        let window = self.address_window();
+        if self.window.take() == Some(window) {
+            return match C::WRITE_IMAGE_DATA {
+                Some(cmd) => self.iface.send_command(cmd),
+                None => Ok(()),
+            };
+        }
+        // Unwrap the CommandError as an interface error, as all bounds checking should be done by
+        // the time we are here.
+        C::begin_write(self.iface, window.0, window.1).map_err(CommandError::unwrap_interface)
+    }
+
+    /// Record that the image data sent since `begin` exactly filled the region, leaving the chip's
+    /// address pointer at the start of its window.
+    fn filled(&mut self) {
+        *self.window = Some(self.address_window());
+    }
+
+    fn address_window(&self) -> AddressWindow {
+        (
+            (self.buf_left, self.buf_left + self.buf_cols - 1),
+            (self.top, self.top + self.rows - 1),
+        )
+    }
+
+    /// End the transaction started by `begin`, after the image data.
+    pub(crate) fn end(&mut self) -> Result<(), DI::Error> {
+        self.iface.end_transaction()
+    }
+
+    /// The number of bytes of packed image data which exactly fill the region.
+    pub(crate) fn total_bytes(&self) -> usize {
+        self.pixel_cols as usize * self.rows as usize / 2
+    }
+
+    /// Write a byte of packed image data after `begin`. If the interface FIFO is full then poll
+    /// it until the send succeeds.
+    #[cfg(feature = "nb")]
+    pub(crate) fn push(&mut self, pixels: u8) -> Result<(), DI::Error> {
+        loop {
+            match self.iface.send_data_async(pixels) {
+                Ok(()) => return Ok(()),
+                Err(nb::Error::WouldBlock) => {}
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
+        }
+    }
+
+    /// Write a byte of packed image data after `begin`.
+    #[cfg(all(feature = "tiled", not(feature = "nb")))]
+    pub(crate) fn push(&mut self, pixels: u8) -> Result<(), DI::Error> {
+        self.iface.send_data(&[pixels])
+    }
+
     /// Draw unpacked pixel image data into the region, where each byte independently represents a
     /// single pixel intensity value in the range [0, 15]. Pixels are drawn left-to-right and
     /// top-to-bottom.
@@ -102,6 +356,130 @@ where
     {
         self.draw_packed(Pack8to4(iter))
     }
+
+    /// Draw `sprite` into the region with its upper left corner at `dest`, relative to the upper
+    /// left corner of the region. The sprite is clipped to the region, and any part of the region
+    /// the sprite does not cover is drawn with gray level 0.
+    pub fn blit(&mut self, sprite: &Sprite, dest: PixelCoord) -> Result<(), DI::Error> {
+        self.draw(sprite.window((dest.0, dest.1), self.pixel_cols, self.rows as u16))
+    }
+
+    /// Draw `text` in `font`, in gray level `fg` on a background of `bg`, word-wrapped to the
+    /// width of the region and laid out from its top left. Text needing more lines than fit in the
+    /// region is handled according to `overflow`. Returns the number of lines drawn.
+    #[cfg(feature = "text")]
+    pub fn draw_text<F>(
+        &mut self,
+        font: &F,
+        text: &str,
+        fg: u8,
+        bg: u8,
+        overflow: Overflow,
+    ) -> Result<usize, DI::Error>
+    where
+        F: Font,
+    {
+        self.draw_text_aligned(font, text, fg, bg, overflow, HAlign::Left, VAlign::Top)
+    }
+
+    /// Draw word-wrapped text as with `draw_text`, with each line aligned horizontally within the
+    /// region by `halign` and the lines aligned vertically by `valign`.
+    #[cfg(feature = "text")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_text_aligned<F>(
+        &mut self,
+        font: &F,
+        text: &str,
+        fg: u8,
+        bg: u8,
+        overflow: Overflow,
+        halign: HAlign,
+        valign: VAlign,
+    ) -> Result<usize, DI::Error>
+    where
+        F: Font,
+    {
+        let pixels = WrappedText::new(
+            font,
+            text,
+            self.pixel_cols,
+            self.rows as u16,
+            fg,
+            bg,
+            overflow,
+        )
+        .align(halign, valign);
+        let lines = pixels.lines();
+        self.draw(pixels)?;
+        Ok(lines)
+    }
+}
+
+impl<'di, DI> Region<'di, DI, Ssd1322>
+where
+    DI: 'di + interface::ReadableInterface,
+{
+    /// Read packed-pixel image data back from the region into `buf`, in the same format and order
+    /// as `draw_packed`. Reading stops when either `buf` or the region is filled, and the number of
+    /// bytes read is returned.
+    ///
+    /// Combined with `draw_packed`, this allows read-modify-write drawing such as updating single
+    /// pixels or XOR-ing a cursor onto the existing image.
+    pub fn read_packed(&mut self, buf: &mut [u8]) -> Result<usize, DI::Error> {
+        self.begin_read()?;
+        let len = buf.len().min(self.total_bytes());
+        self.iface.read_data(&mut buf[..len])?;
+        Ok(len)
+    }
+
+    /// Draw packed-pixel image data into the region as with `draw_packed`, then read it back from
+    /// the display RAM and compare the two by CRC-32. Returns `CommandError::VerifyFailed` if they
+    /// differ, which means the data was corrupted on the bus or in the display RAM.
+    pub fn draw_packed_verified<I>(&mut self, iter: I) -> Result<(), CommandError<DI::Error>>
+    where
+        I: Iterator<Item = u8>,
+    {
+        let mut written = Crc32::new();
+        let mut len = 0;
+        self.draw_packed(iter.inspect(|byte| {
+            written.update(*byte);
+            len += 1;
+        }))
+        .map_err(CommandError::InterfaceError)?;
+
+        self.begin_read().map_err(CommandError::InterfaceError)?;
+        let mut read = Crc32::new();
+        let mut buf = [0; 16];
+        while len > 0 {
+            let chunk = &mut buf[..len.min(16)];
+            self.iface
+                .read_data(chunk)
+                .map_err(CommandError::InterfaceError)?;
+            chunk.iter().for_each(|byte| read.update(*byte));
+            len -= chunk.len();
+        }
+        if read.finish() != written.finish() {
+            return Err(CommandError::VerifyFailed);
+        }
+        Ok(())
+    }
+
+    /// Set the row and column address registers and put the display in read mode, discarding the
+    /// dummy word which precedes the image data.
+    fn begin_read(&mut self) -> Result<(), DI::Error> {
+        // Reading moves the address pointer, so the next draw must set up its window again.
+        *self.window = None;
+        // Unwrap all of the CommandErrors in this scope as interface errors, as all bounds
+        // checking should be done by the time we are here.
+        (|| {
+            Command::SetColumnAddress(self.buf_left, self.buf_left + self.buf_cols - 1)
+                .send(self.iface)?;
+            Command::SetRowAddress(self.top, self.top + self.rows - 1).send(self.iface)?;
+            Command::ReadImageData.send(self.iface)
+        })()
+        .map_err(CommandError::unwrap_interface)?;
+        self.iface.read_data(&mut [0])
+    }
 }
 
 /// Pack an iterator of u8 values in the range [0, 15] into an iterator of packed u8 values, such
@@ -127,14 +505,21 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::command::{ComLayout, ComScanDirection};
+    use crate::command::{ComLayout, ComScanDirection, CommandError};
     use crate::config::Config;
     use crate::display::{Display, PixelCoord as Px};
-    use crate::interface::test_spy::{Sent, TestSpyInterface};
+    use crate::interface::mock::{MockInterface, Sent};
+    use crate::sprite::Sprite;
+    #[cfg(feature = "text")]
+    use crate::text::tests::TEST_FONT;
+    #[cfg(feature = "text")]
+    use crate::text::wrap::Overflow;
+    #[cfg(feature = "text")]
+    use crate::text::{HAlign, VAlign};
 
     #[test]
     fn draw_packed() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
         let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
         disp.init(cfg).unwrap();
@@ -145,7 +530,7 @@ mod tests {
                 .draw_packed([0xDE, 0xAD, 0xBE, 0xEF].iter().cloned())
                 .unwrap();
         }
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
             0x15, [3, 3],
             0x75, [10, 11],
@@ -153,9 +538,32 @@ mod tests {
         ));
     }
 
+    #[cfg(feature = "ssd1327")]
+    #[test]
+    fn draw_packed_ssd1327() {
+        use crate::chip::Ssd1327;
+
+        // The SSD1327 addresses columns of 2 pixels and takes image data right after the window,
+        // so the image data follows on directly from the row address arguments.
+        let di = MockInterface::new();
+        let mut disp = Display::with_chip(Ssd1327, di.split(), Px(128, 128), Px(0, 0));
+        {
+            let mut region = disp.region(Px(6, 10), Px(10, 12)).unwrap();
+            region
+                .draw_packed([0xDE, 0xAD, 0xBE, 0xEF].iter().cloned())
+                .unwrap();
+        }
+        #[rustfmt::skip]
+        di.check_multi(sends!(
+            0x15, [3, 4],
+            0x75, [10, 11, 0xDE, 0xAD, 0xBE, 0xEF]
+        ));
+        assert!(disp.region(Px(1, 0), Px(4, 1)).is_err());
+    }
+
     #[test]
     fn draw_packed_end_at_region_filled() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
         let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
         disp.init(cfg).unwrap();
@@ -166,7 +574,7 @@ mod tests {
                 .draw_packed([0xDE, 0xAD, 0xBE, 0xEF, 0xAA].iter().cloned())
                 .unwrap();
         }
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
             0x15, [3, 3],
             0x75, [10, 11],
@@ -177,7 +585,7 @@ mod tests {
 
     #[test]
     fn draw_packed_end_at_iterator_exhausted() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
         let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
         disp.init(cfg).unwrap();
@@ -188,7 +596,7 @@ mod tests {
                 .draw_packed([0xDE, 0xAD, 0xBE].iter().cloned())
                 .unwrap();
         }
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
             0x15, [3, 3],
             0x75, [10, 11],
@@ -197,9 +605,24 @@ mod tests {
         di.clear();
     }
 
+    #[test]
+    fn draw_visible_with_offsets() {
+        let di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(16, 16), Px(8, 4));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        disp.init(cfg).unwrap();
+        disp.region(Px(4, 2), Px(8, 3))
+            .unwrap()
+            .draw([1, 2, 3, 4].iter().cloned())
+            .unwrap();
+        let image = di.chip_model(Px(16, 16), Px(8, 4)).render();
+        assert_eq!(&image[2 * 16 + 4..2 * 16 + 8], &[1, 2, 3, 4]);
+        assert_eq!(image.iter().map(|&p| p as u32).sum::<u32>(), 10);
+    }
+
     #[test]
     fn draw_packed_display_column_offset() {
-        let mut di = TestSpyInterface::new();
+        let mut di = MockInterface::new();
         let mut disp = Display::new(di.split(), Px(128, 64), Px(64, 0));
         let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
         disp.init(cfg).unwrap();
@@ -210,7 +633,7 @@ mod tests {
                 .draw_packed([0xDE, 0xAD, 0xBE, 0xEF].iter().cloned())
                 .unwrap();
         }
-        #[cfg_attr(rustfmt, rustfmt_skip)]
+        #[rustfmt::skip]
         di.check_multi(sends!(
             0x15, [16, 16],
             0x75, [10, 11],
@@ -218,4 +641,270 @@ mod tests {
         ));
         di.clear();
     }
+
+    #[test]
+    fn repeated_window() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        disp.init(cfg).unwrap();
+        let frame = [0x12u8; 4];
+        let draw = |disp: &mut Display<MockInterface>, ul, lr| {
+            let mut region = disp.region(ul, lr).unwrap();
+            region.draw_packed(frame.iter().copied()).unwrap();
+        };
+        draw(&mut disp, Px(0, 0), Px(4, 2));
+        di.clear();
+        // The same window again only needs the write command and the image data.
+        draw(&mut disp, Px(0, 0), Px(4, 2));
+        assert_eq!(di.sent(), [Sent::Cmd(0x5C), Sent::Data(vec![0x12; 4])]);
+        // A window of the same size elsewhere, then a command in between, set it up again.
+        di.clear();
+        draw(&mut disp, Px(4, 0), Px(8, 2));
+        disp.contrast(8).unwrap();
+        draw(&mut disp, Px(4, 0), Px(8, 2));
+        assert_eq!(
+            di.sent().iter().filter(|s| **s == Sent::Cmd(0x15)).count(),
+            2
+        );
+        // A region not filled leaves the address pointer in the middle of the window.
+        di.clear();
+        disp.region(Px(4, 0), Px(8, 2))
+            .unwrap()
+            .draw_packed(frame[..2].iter().copied())
+            .unwrap();
+        draw(&mut disp, Px(4, 0), Px(8, 2));
+        assert_eq!(
+            di.sent().iter().filter(|s| **s == Sent::Cmd(0x15)).count(),
+            1
+        );
+    }
+
+    /// An interface which refuses every command, recording whether a transaction is open.
+    #[derive(Default)]
+    struct RefusesCommands {
+        in_transaction: bool,
+    }
+
+    impl crate::interface::DisplayInterface for RefusesCommands {
+        type Error = ();
+
+        fn send_command(&mut self, _: u8) -> Result<(), ()> {
+            Err(())
+        }
+        fn send_data(&mut self, _: &[u8]) -> Result<(), ()> {
+            Ok(())
+        }
+        #[cfg(feature = "nb")]
+        fn send_data_async(&mut self, _: u8) -> nb::Result<(), ()> {
+            Ok(())
+        }
+        fn begin_transaction(&mut self) -> Result<(), ()> {
+            self.in_transaction = true;
+            Ok(())
+        }
+        fn end_transaction(&mut self) -> Result<(), ()> {
+            self.in_transaction = false;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn transaction_ended_when_setup_fails() {
+        let mut disp = Display::new(RefusesCommands::default(), Px(128, 64), Px(0, 0));
+        let mut region = disp.region(Px(0, 0), Px(4, 1)).unwrap();
+        assert_eq!(region.draw_packed([0, 0].iter().cloned()), Err(()));
+        assert!(!disp.interface().in_transaction);
+    }
+
+    #[test]
+    fn draw_packed_exact() {
+        let di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let mut region = disp.region(Px(0, 0), Px(8, 2)).unwrap();
+        assert_eq!(
+            region.draw_packed_exact([0u8; 7].iter().copied()),
+            Err(CommandError::BadDataLength {
+                expected: 8,
+                actual: 7
+            })
+        );
+        assert_eq!(di.sent().len(), 0);
+        region.draw_packed_exact([0u8; 8].iter().copied()).unwrap();
+        assert_eq!(di.sent().len(), 6);
+    }
+
+    #[test]
+    fn draw_rows() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let image: [[u8; 4]; 3] = [[0x01, 0x23, 0x45, 0x67], [0x89, 0xAB, 0xCD, 0xEF], [0; 4]];
+        let mut region = disp.region(Px(0, 0), Px(8, 2)).unwrap();
+        region.draw_rows(image.iter().map(|r| &r[..])).unwrap();
+        #[rustfmt::skip]
+        di.check_multi(sends!(
+            0x15, [0, 1], 0x75, [0, 1], 0x5C,
+            [0x01, 0x23, 0x45, 0x67], [0x89, 0xAB, 0xCD, 0xEF]
+        ));
+
+        di.clear();
+        // The region was filled, so its window is not set up again.
+        let short: [&[u8]; 2] = [&[0; 4], &[0; 3]];
+        assert_eq!(
+            region.draw_rows(short.iter().copied()),
+            Err(CommandError::BadRowLength)
+        );
+        assert_eq!(di.sent(), [Sent::Cmd(0x5C), Sent::Data(vec![0; 4])]);
+        di.clear();
+        region.draw_rows(short.iter().take(1).copied()).unwrap();
+        assert_eq!(di.sent().len(), 6);
+        di.clear();
+        assert!(region.draw_rows(core::iter::once(&[0u8; 3][..])).is_err());
+        assert_eq!(di.sent().len(), 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn draw_from_reader() {
+        use super::StreamError;
+        use std::io;
+
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        // Two frames of a 4x2 region back to back, then a short third frame.
+        let stream: Vec<u8> = (0..10).collect();
+        let mut reader = &stream[..];
+        let mut region = disp.region(Px(0, 0), Px(4, 2)).unwrap();
+        assert_eq!(region.draw_from_reader(&mut reader).unwrap(), 4);
+        assert_eq!(region.draw_from_reader(&mut reader).unwrap(), 4);
+        // Each whole frame leaves the chip ready for the next, so the window is set up only once.
+        assert_eq!(
+            di.sent().iter().filter(|s| **s == Sent::Cmd(0x15)).count(),
+            1
+        );
+        di.clear();
+        assert_eq!(region.draw_from_reader(&mut reader).unwrap(), 2);
+        assert_eq!(di.sent(), [Sent::Cmd(0x5C), Sent::Data(vec![8, 9])]);
+        di.clear();
+        assert_eq!(region.draw_from_reader(&mut reader).unwrap(), 0);
+        di.check_multi(sends!(0x15, [0, 0], 0x75, [0, 1], 0x5C));
+
+        struct Broken;
+        impl io::Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("broken pipe"))
+            }
+        }
+        assert!(matches!(
+            region.draw_from_reader(Broken),
+            Err(StreamError::ReadError(_))
+        ));
+    }
+
+    #[test]
+    fn blit() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        disp.init(cfg).unwrap();
+        di.clear();
+        // A 2x2 sprite placed one pixel in from the left of a 4x2 region.
+        let sprite = Sprite::new(2, 2, &[0x12, 0x34]);
+        {
+            let mut region = disp.region(Px(0, 10), Px(4, 12)).unwrap();
+            region.blit(&sprite, Px(1, 0)).unwrap();
+        }
+        #[rustfmt::skip]
+        di.check_multi(sends!(
+            0x15, [0, 0],
+            0x75, [10, 11],
+            0x5C, [0x01, 0x20, 0x03, 0x40]
+        ));
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn draw_text() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        disp.init(cfg).unwrap();
+        di.clear();
+        {
+            // Room for two of the three lines.
+            let mut region = disp.region(Px(0, 0), Px(8, 7)).unwrap();
+            let lines = region
+                .draw_text(&TEST_FONT, "AB B A", 15, 0, Overflow::Clip)
+                .unwrap();
+            assert_eq!(lines, 2);
+        }
+        #[rustfmt::skip]
+        di.check_multi(sends!(
+            0x15, [0, 1],
+            0x75, [0, 6],
+            0x5C, [
+                0xFF, 0xFF, 0x0F, 0x00,
+                0xF0, 0x0F, 0x0F, 0x00,
+                0xFF, 0xFF, 0x0F, 0x00,
+                0x0F, 0x00, 0x00, 0x00,
+                0x0F, 0x00, 0x00, 0x00,
+                0x0F, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00
+            ]
+        ));
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn draw_text_aligned() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        disp.init(cfg).unwrap();
+        di.clear();
+        {
+            let mut region = disp.region(Px(0, 0), Px(8, 5)).unwrap();
+            let lines = region
+                .draw_text_aligned(
+                    &TEST_FONT,
+                    "B",
+                    15,
+                    0,
+                    Overflow::Clip,
+                    HAlign::Center,
+                    VAlign::Middle,
+                )
+                .unwrap();
+            assert_eq!(lines, 1);
+        }
+        #[rustfmt::skip]
+        di.check_multi(sends!(
+            0x15, [0, 1],
+            0x75, [0, 4],
+            0x5C, [
+                0x00, 0x00, 0x00, 0x00,
+                0x00, 0x0F, 0x00, 0x00,
+                0x00, 0x0F, 0x00, 0x00,
+                0x00, 0x0F, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00
+            ]
+        ));
+    }
+
+    #[test]
+    fn draw_packed_checked() {
+        let di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let image = [0xDE, 0xAD, 0xBE, 0xEF];
+        let crc = crate::crc::crc32(image.iter().cloned());
+        let mut region = disp.region(Px(12, 10), Px(16, 12)).unwrap();
+        assert!(region
+            .draw_packed_checked(image.iter().cloned(), crc)
+            .is_ok());
+        // The source ran short of the data it was supposed to provide.
+        assert_eq!(
+            region.draw_packed_checked(image[..3].iter().cloned(), crc),
+            Err(CommandError::VerifyFailed)
+        );
+    }
 }
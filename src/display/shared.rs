@@ -0,0 +1,101 @@
+//! A display shared between several tasks or interrupt handlers.
+//!
+//! A `Display` is normally owned by one task, so firmware in which several tasks draw (say, a
+//! status bar task and a plotting task) must funnel all drawing through that owner. A
+//! `SharedDisplay` can instead live in a `static` and be locked by whichever task wants to draw,
+//! for as long as it takes to draw a region or two.
+//!
+//! Locking uses the `critical-section` crate, so it works on any target with a critical section
+//! implementation, including single-core microcontrollers without atomic compare-and-swap. While
+//! a task holds the lock no other code can run, so keep the work done under it short.
+//!
+//! Available with the `critical-section` feature.
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+
+use crate::display::Display;
+use crate::interface;
+
+/// A lockable slot holding a `Display`. See the module documentation.
+pub struct SharedDisplay<DI>
+where
+    DI: interface::DisplayInterface,
+{
+    display: Mutex<RefCell<Option<Display<DI>>>>,
+}
+
+impl<DI> SharedDisplay<DI>
+where
+    DI: interface::DisplayInterface,
+{
+    /// Create an empty slot, suitable for initializing a `static`. Use `put` to move a display
+    /// into it once the display has been constructed.
+    pub const fn new() -> Self {
+        SharedDisplay {
+            display: Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    /// Move `display` into the slot, returning the display it held before, if any.
+    pub fn put(&self, display: Display<DI>) -> Option<Display<DI>> {
+        critical_section::with(|cs| self.display.borrow(cs).replace(Some(display)))
+    }
+
+    /// Move the display out of the slot, leaving it empty.
+    pub fn take(&self) -> Option<Display<DI>> {
+        critical_section::with(|cs| self.display.borrow(cs).take())
+    }
+
+    /// Lock the display and call `f` with it, for example to draw a region, returning the result
+    /// of `f`. Returns `None` without calling `f` if the slot is empty.
+    ///
+    /// # Panics
+    ///
+    /// If called from within `f`, since the display is already locked.
+    pub fn lock<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut Display<DI>) -> R,
+    {
+        critical_section::with(|cs| self.display.borrow(cs).borrow_mut().as_mut().map(f))
+    }
+}
+
+impl<DI> Default for SharedDisplay<DI>
+where
+    DI: interface::DisplayInterface,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+
+    #[test]
+    fn lock_and_draw() {
+        let di = MockInterface::new();
+        let shared = SharedDisplay::new();
+        assert_eq!(shared.lock(|_| ()), None);
+
+        assert!(shared
+            .put(Display::new(di.split(), Px(128, 64), Px(0, 0)))
+            .is_none());
+        let drawn = shared.lock(|disp| {
+            disp.region(Px(0, 0), Px(4, 1))
+                .unwrap()
+                .draw_packed([0xFF, 0xFF].iter().cloned())
+        });
+        assert_eq!(drawn, Some(Ok(())));
+        assert_eq!(shared.lock(|disp| disp.size()), Some(Px(128, 64)));
+        di.check_multi(sends!(0x15, [0, 0], 0x75, [0, 0], 0x5C, [0xFF, 0xFF]));
+
+        assert!(shared.take().is_some());
+        assert_eq!(shared.lock(|_| ()), None);
+    }
+}
@@ -0,0 +1,251 @@
+//! Splitting a display into a command handle and a draw handle, which can be owned by different
+//! tasks or RTIC resources without a lock around the whole driver.
+//!
+//! The `CommandHandle` never touches the interface: it posts settings (master contrast, vertical
+//! pan, display mode) to a `PendingCommands` block using only atomic loads and stores, so it can be
+//! used from an interrupt handler at any priority. The `DrawHandle` owns the display and sends
+//! any posted settings before each region it hands out, so that commands are never interleaved
+//! with an image data stream in progress.
+
+use core::convert::Infallible;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::command::{CommandError, DisplayMode};
+#[cfg(feature = "overscan")]
+use crate::display::overscanned_region::OverscannedRegion;
+use crate::display::region::Region;
+use crate::display::{Display, PixelCoord, Rect};
+use crate::interface;
+
+/// One posted setting. The writer stores the value and then bumps the generation, and the reader
+/// sends the value whenever the generation differs from the one it last saw. A reader that races
+/// a writer may send the newer value twice, but never misses the final value.
+struct Slot {
+    value: AtomicU8,
+    generation: AtomicU8,
+}
+
+impl Slot {
+    const fn new() -> Self {
+        Slot {
+            value: AtomicU8::new(0),
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    fn post(&self, value: u8) {
+        self.value.store(value, Ordering::Release);
+        let generation = self.generation.load(Ordering::Relaxed);
+        self.generation
+            .store(generation.wrapping_add(1), Ordering::Release);
+    }
+
+    fn take(&self, seen: &mut u8) -> Option<u8> {
+        let generation = self.generation.load(Ordering::Acquire);
+        if generation == *seen {
+            return None;
+        }
+        *seen = generation;
+        Some(self.value.load(Ordering::Acquire))
+    }
+}
+
+/// Storage for settings posted by a `CommandHandle` and not yet sent by its `DrawHandle`. This is
+/// usually declared as a `static` so that both handles can be `'static` RTIC resources.
+pub struct PendingCommands {
+    contrast: Slot,
+    start_line: Slot,
+    display_mode: Slot,
+}
+
+impl PendingCommands {
+    /// Construct an empty block of pending commands.
+    pub const fn new() -> Self {
+        PendingCommands {
+            contrast: Slot::new(),
+            start_line: Slot::new(),
+            display_mode: Slot::new(),
+        }
+    }
+}
+
+impl Default for PendingCommands {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The half of a split display which changes settings that do not affect display RAM. Methods
+/// only validate and post the setting, which is sent the next time the `DrawHandle` starts a
+/// region or calls `apply_pending`.
+///
+/// There is deliberately only one command handle per display, because posting relies on there
+/// being a single writer.
+pub struct CommandHandle<'a> {
+    pending: &'a PendingCommands,
+}
+
+impl<'a> CommandHandle<'a> {
+    /// Post a new master contrast, in the range [0, 15]. See `Display::contrast`.
+    pub fn contrast(&mut self, contrast: u8) -> Result<(), CommandError<Infallible>> {
+        if contrast > 15 {
+            return Err(CommandError::OutOfRange);
+        }
+        self.pending.contrast.post(contrast);
+        Ok(())
+    }
+
+    /// Post a new vertical pan, in the range [0, 127]. See `Display::vertical_pan`.
+    pub fn vertical_pan(&mut self, offset: u8) -> Result<(), CommandError<Infallible>> {
+        if offset > 127 {
+            return Err(CommandError::OutOfRange);
+        }
+        self.pending.start_line.post(offset);
+        Ok(())
+    }
+
+    /// Post a new display mode, for example to blank or invert the display.
+    pub fn display_mode(&mut self, mode: DisplayMode) {
+        let encoded = match mode {
+            DisplayMode::BlankDark => 0,
+            DisplayMode::BlankBright => 1,
+            DisplayMode::Normal => 2,
+            DisplayMode::Inverse => 3,
+        };
+        self.pending.display_mode.post(encoded);
+    }
+}
+
+/// The half of a split display which owns the interface and draws image data.
+pub struct DrawHandle<'a, DI>
+where
+    DI: interface::DisplayInterface,
+{
+    display: Display<DI>,
+    pending: &'a PendingCommands,
+    seen: [u8; 3],
+}
+
+impl<'a, DI> DrawHandle<'a, DI>
+where
+    DI: interface::DisplayInterface,
+{
+    /// Send any settings posted by the `CommandHandle` since they were last sent.
+    pub fn apply_pending(&mut self) -> Result<(), CommandError<DI::Error>> {
+        if let Some(contrast) = self.pending.contrast.take(&mut self.seen[0]) {
+            self.display.contrast(contrast)?;
+        }
+        if let Some(line) = self.pending.start_line.take(&mut self.seen[1]) {
+            self.display.vertical_pan(line)?;
+        }
+        if let Some(mode) = self.pending.display_mode.take(&mut self.seen[2]) {
+            let mode = match mode {
+                0 => DisplayMode::BlankDark,
+                1 => DisplayMode::BlankBright,
+                2 => DisplayMode::Normal,
+                _ => DisplayMode::Inverse,
+            };
+            self.display.display_mode(mode)?;
+        }
+        Ok(())
+    }
+
+    /// Send any pending settings, then construct a region as with `Display::region`.
+    ///
+    /// Settings posted while the region is being drawn are sent before the next region, so long
+    /// image transfers may be broken up into several regions to bound the latency.
+    pub fn region<'di>(
+        &'di mut self,
+        upper_left: PixelCoord,
+        lower_right: PixelCoord,
+    ) -> Result<Region<'di, DI>, CommandError<DI::Error>> {
+        self.apply_pending()?;
+        self.display.region(upper_left, lower_right)
+    }
+
+    /// Send any pending settings, then construct a region covering `rect`.
+    pub fn region_rect<'di>(
+        &'di mut self,
+        rect: Rect,
+    ) -> Result<Region<'di, DI>, CommandError<DI::Error>> {
+        self.region(rect.ul, rect.lr)
+    }
+
+    /// Send any pending settings, then construct an overscanned region as with
+    /// `Display::overscanned_region`.
+    #[cfg(feature = "overscan")]
+    pub fn overscanned_region<'di>(
+        &'di mut self,
+        upper_left: PixelCoord,
+        lower_right: PixelCoord,
+    ) -> Result<OverscannedRegion<'di, DI>, CommandError<DI::Error>> {
+        self.apply_pending()?;
+        self.display.overscanned_region(upper_left, lower_right)
+    }
+
+    /// Rejoin the display, discarding the command handle. Settings which are still pending are
+    /// not sent.
+    pub fn unsplit(self, _commands: CommandHandle<'a>) -> Display<DI> {
+        self.display
+    }
+}
+
+impl<DI> Display<DI>
+where
+    DI: interface::DisplayInterface,
+{
+    /// Split the display into a `CommandHandle` and a `DrawHandle`, which communicate through
+    /// `pending`. Settings already pending in `pending` are not sent.
+    pub fn split(self, pending: &PendingCommands) -> (CommandHandle<'_>, DrawHandle<'_, DI>) {
+        let seen = [
+            pending.contrast.generation.load(Ordering::Acquire),
+            pending.start_line.generation.load(Ordering::Acquire),
+            pending.display_mode.generation.load(Ordering::Acquire),
+        ];
+        let draw = DrawHandle {
+            display: self,
+            pending: pending,
+            seen: seen,
+        };
+        (CommandHandle { pending: pending }, draw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::Sent;
+    use crate::test_support::initialized_display;
+
+    #[test]
+    fn pending_sent_before_region() {
+        let (mut di, disp) = initialized_display(Px(8, 16));
+        let pending = PendingCommands::new();
+        let (mut cmd, mut draw) = disp.split(&pending);
+        di.clear();
+
+        assert_eq!(cmd.contrast(16), Err(CommandError::OutOfRange));
+        cmd.contrast(3).unwrap();
+        cmd.contrast(7).unwrap();
+        cmd.display_mode(DisplayMode::Inverse);
+        assert_eq!(di.sent().len(), 0);
+
+        draw.region(Px(0, 0), Px(4, 1)).unwrap().draw(0..4).unwrap();
+        #[rustfmt::skip]
+        di.check_multi(sends!(
+            0xC7, [7], // master contrast
+            0xA7, // display inverse
+            0x15, [0, 0], // column address
+            0x75, [0, 0], // row address
+            0x5C, [0x01, 0x23] // write image data
+        ));
+
+        // Nothing new was posted, so the next region starts immediately.
+        di.clear();
+        cmd.vertical_pan(5).unwrap();
+        draw.apply_pending().unwrap();
+        draw.apply_pending().unwrap();
+        di.check_multi(sends!(0xA1, [5]));
+    }
+}
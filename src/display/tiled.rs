@@ -0,0 +1,213 @@
+//! Composition of several displays into one logical display, e.g. two 256x64 modules side by side
+//! on separate chip select lines.
+
+use crate::command::CommandError;
+use crate::display::region::{Pack8to4, Region};
+use crate::display::{Display, PixelCoord, Rect};
+use crate::interface;
+
+/// `N` displays placed at fixed positions in one logical coordinate space. Regions requested from
+/// the tiled display are split across the panels they overlap, and the image data drawn into them
+/// is routed to the right panel automatically.
+pub struct TiledDisplay<DI, const N: usize>
+where
+    DI: interface::DisplayInterface,
+{
+    panels: [Display<DI>; N],
+    origins: [PixelCoord; N],
+}
+
+impl<DI, const N: usize> TiledDisplay<DI, N>
+where
+    DI: interface::DisplayInterface,
+{
+    /// Compose `panels`, placing the upper left corner of each at the corresponding position in
+    /// `origins`. The panels should not overlap.
+    ///
+    /// Panics if any origin column is not divisible by 4, since regions could then not be split at
+    /// panel boundaries.
+    pub fn new(panels: [Display<DI>; N], origins: [PixelCoord; N]) -> Self {
+        if origins.iter().any(|o| o.0.rem_euclid(4) != 0) {
+            panic!("Panel origin columns must be divisible by 4.");
+        }
+        TiledDisplay {
+            panels: panels,
+            origins: origins,
+        }
+    }
+
+    /// Compose `panels` in a row from left to right, with their top edges aligned.
+    pub fn side_by_side(panels: [Display<DI>; N]) -> Self {
+        let mut left = 0;
+        let origins = core::array::from_fn(|i| {
+            let origin = PixelCoord(left, 0);
+            left += panels[i].size().0;
+            origin
+        });
+        Self::new(panels, origins)
+    }
+
+    /// Access the panels individually, for example to initialize them or change their contrast.
+    pub fn panels_mut(&mut self) -> &mut [Display<DI>; N] {
+        &mut self.panels
+    }
+
+    /// Consume the tiled display, returning the panels.
+    pub fn into_inner(self) -> [Display<DI>; N] {
+        self.panels
+    }
+
+    /// The size of the logical display, which is the bounding box of all of the panels.
+    pub fn size(&self) -> PixelCoord {
+        let mut size = PixelCoord(0, 0);
+        for (panel, origin) in self.panels.iter().zip(self.origins.iter()) {
+            size.0 = size.0.max(origin.0 + panel.size().0);
+            size.1 = size.1.max(origin.1 + panel.size().1);
+        }
+        size
+    }
+
+    /// Construct a rectangular region onto which to draw image data, in logical coordinates.
+    ///
+    /// As with `Display::region`, the horizontal coordinates must be divisible by 4. Like an
+    /// overscanned region, the rectangle may extend beyond the panels, and pixels which do not
+    /// fall on any panel are silently discarded.
+    #[allow(clippy::nonminimal_bool)]
+    pub fn region<'di>(
+        &'di mut self,
+        upper_left: PixelCoord,
+        lower_right: PixelCoord,
+    ) -> Result<TiledRegion<'di, DI, N>, CommandError<DI::Error>> {
+        if false
+            || upper_left.0 >= lower_right.0
+            || upper_left.1 >= lower_right.1
+            || upper_left.0.rem_euclid(4) != 0
+            || lower_right.0.rem_euclid(4) != 0
+        {
+            return Err(CommandError::OutOfRange);
+        }
+        let rect = Rect::new(upper_left, lower_right);
+        let origins = self.origins;
+        let mut bounds = [None; N];
+        for (i, panel) in self.panels.iter().enumerate() {
+            let panel_rect = Rect::new(origins[i], origins[i] + panel.size());
+            bounds[i] = rect.intersect(&panel_rect);
+        }
+        let mut regions = self.panels.each_mut().map(|_| None);
+        for (i, panel) in self.panels.iter_mut().enumerate() {
+            if let Some(bound) = bounds[i] {
+                let local = bound.translate(PixelCoord(0, 0) - origins[i]);
+                regions[i] = Some(panel.region_rect(local)?);
+            }
+        }
+        Ok(TiledRegion {
+            regions: regions,
+            bounds: bounds,
+            upper_left: upper_left,
+            lower_right: lower_right,
+        })
+    }
+}
+
+/// A handle to a rectangular region of a `TiledDisplay`, made up of a region on each panel it
+/// overlaps. Like other regions, these are intended to be short-lived.
+pub struct TiledRegion<'di, DI, const N: usize>
+where
+    DI: 'di + interface::DisplayInterface,
+{
+    regions: [Option<Region<'di, DI>>; N],
+    /// The part of the region on each panel, in logical coordinates.
+    bounds: [Option<Rect>; N],
+    upper_left: PixelCoord,
+    lower_right: PixelCoord,
+}
+
+impl<'di, DI, const N: usize> TiledRegion<'di, DI, N>
+where
+    DI: 'di + interface::DisplayInterface,
+{
+    /// Draw packed-pixel image data into the region, such that each byte is two 4-bit gray scale
+    /// values of horizontally-adjacent pixels. Pixels are drawn left-to-right and top-to-bottom,
+    /// and each pair is sent to the panel it falls on.
+    pub fn draw_packed<I>(&mut self, iter: I) -> Result<(), DI::Error>
+    where
+        I: Iterator<Item = u8>,
+    {
+        // End the transaction of every region which was begun, even if drawing fails part way.
+        let mut begun = 0;
+        let mut result = self
+            .regions
+            .iter_mut()
+            .flatten()
+            .try_for_each(|region| region.begin().map(|()| begun += 1));
+        if result.is_ok() {
+            result = self.push_all(iter);
+        }
+        for region in self.regions.iter_mut().flatten().take(begun) {
+            result = result.and(region.end());
+        }
+        result
+    }
+
+    /// Send each pair of pixels to the panel it falls on, after every region has begun.
+    fn push_all<I>(&mut self, iter: I) -> Result<(), DI::Error>
+    where
+        I: Iterator<Item = u8>,
+    {
+        let (ul, lr) = (self.upper_left, self.lower_right);
+        let coords = (ul.1..lr.1).flat_map(|y| (ul.0..lr.0).step_by(2).map(move |x| (x, y)));
+        for ((x, y), pixels) in coords.zip(iter) {
+            let panel = self.bounds.iter().position(|b| match *b {
+                Some(bound) => bound.contains(PixelCoord(x, y)),
+                None => false,
+            });
+            if let Some(region) = panel.and_then(|i| self.regions[i].as_mut()) {
+                region.push(pixels)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw unpacked pixel image data into the region, where each byte independently represents a
+    /// single pixel intensity value in the range [0, 15]. Pixels are drawn left-to-right and
+    /// top-to-bottom.
+    pub fn draw<I>(&mut self, iter: I) -> Result<(), DI::Error>
+    where
+        I: Iterator<Item = u8>,
+    {
+        self.draw_packed(Pack8to4(iter))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::test_support::initialized_display;
+
+    #[test]
+    fn split_across_panels() {
+        let (left_di, left) = initialized_display(Px(8, 16));
+        let (right_di, right) = initialized_display(Px(8, 16));
+        let mut tiled = TiledDisplay::side_by_side([left, right]);
+        assert_eq!(tiled.size().0, 16);
+        // An 8x2 region straddling the boundary, and hanging off the right hand edge.
+        tiled
+            .region(Px(4, 1), Px(12, 3))
+            .unwrap()
+            .draw((0..16).map(|level| 15 - level))
+            .unwrap();
+        tiled
+            .region(Px(12, 5), Px(20, 6))
+            .unwrap()
+            .draw(1..=8)
+            .unwrap();
+        let left = left_di.chip_model(Px(8, 16), Px(0, 0)).render();
+        let right = right_di.chip_model(Px(8, 16), Px(0, 0)).render();
+        assert_eq!(&left[8..16], &[0, 0, 0, 0, 15, 14, 13, 12]);
+        assert_eq!(&left[16..24], &[0, 0, 0, 0, 7, 6, 5, 4]);
+        assert_eq!(&right[8..16], &[11, 10, 9, 8, 0, 0, 0, 0]);
+        assert_eq!(&right[16..24], &[3, 2, 1, 0, 0, 0, 0, 0]);
+        assert_eq!(&right[40..48], &[0, 0, 0, 0, 1, 2, 3, 4]);
+    }
+}
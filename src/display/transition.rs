@@ -0,0 +1,148 @@
+//! Gradual changes of the master contrast, as for always-on devices where an abrupt change of
+//! brightness would be jarring.
+//!
+//! A transition is created by `Display::contrast_transition` and then driven either by calling
+//! `ContrastTransition::tick` from the application's main loop, or all at once with
+//! `ContrastTransition::run` and a delay.
+
+use embedded_hal as hal;
+
+use crate::command::CommandError;
+use crate::display::Display;
+use crate::interface;
+
+/// A ramp of the master contrast from its value when the transition was created to a target, in a
+/// fixed number of steps. Each step moves the contrast to the nearest level on a straight line
+/// between the two, so with more steps than levels to cross some steps leave it unchanged and
+/// send nothing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContrastTransition {
+    from: u8,
+    target: u8,
+    steps: u16,
+    step: u16,
+}
+
+impl ContrastTransition {
+    /// The master contrast the transition ends at.
+    pub fn target(&self) -> u8 {
+        self.target
+    }
+
+    /// Whether every step has been taken.
+    pub fn is_finished(&self) -> bool {
+        self.step >= self.steps
+    }
+
+    /// Take the next step, if any. Returns whether there are steps left to take.
+    pub fn tick<DI>(&mut self, display: &mut Display<DI>) -> Result<bool, CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        if self.is_finished() {
+            return Ok(false);
+        }
+        self.step += 1;
+        let (step, steps) = (self.step as u32, self.steps as u32);
+        let level =
+            (self.from as u32 * (steps - step) + self.target as u32 * step + steps / 2) / steps;
+        display.contrast(level as u8)?;
+        Ok(!self.is_finished())
+    }
+
+    /// Take all remaining steps, waiting `step_ms` milliseconds with `delay` before each.
+    pub fn run<DI, D>(
+        &mut self,
+        display: &mut Display<DI>,
+        step_ms: u16,
+        delay: &mut D,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+        D: hal::blocking::delay::DelayMs<u16>,
+    {
+        while !self.is_finished() {
+            delay.delay_ms(step_ms);
+            self.tick(display)?;
+        }
+        Ok(())
+    }
+}
+
+impl<DI> Display<DI>
+where
+    DI: interface::DisplayInterface,
+{
+    /// Create a transition of the master contrast from its current value to `target` in `steps`
+    /// steps, or in one if `steps` is 0. `target` has the same range as
+    /// `Command::SetMasterContrast`. Nothing is sent until the transition is driven.
+    pub fn contrast_transition(
+        &self,
+        target: u8,
+        steps: u16,
+    ) -> Result<ContrastTransition, CommandError<DI::Error>> {
+        if target > 15 {
+            return Err(CommandError::OutOfRange);
+        }
+        Ok(ContrastTransition {
+            from: self.master_contrast,
+            target: target,
+            steps: steps.max(1),
+            step: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+    use crate::test_support::CountingDelay;
+
+    #[test]
+    fn ramp() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        disp.contrast(12).unwrap();
+        di.clear();
+
+        let mut fade = disp.contrast_transition(4, 4).unwrap();
+        assert_eq!(fade.tick(&mut disp), Ok(true));
+        assert_eq!(fade.tick(&mut disp), Ok(true));
+        assert_eq!(fade.tick(&mut disp), Ok(true));
+        assert_eq!(fade.tick(&mut disp), Ok(false));
+        assert!(fade.is_finished());
+        assert_eq!(fade.tick(&mut disp), Ok(false));
+        #[rustfmt::skip]
+        assert_eq!(di.sent(), [
+            Sent::Cmd(0xC7), Sent::Data(vec![10]),
+            Sent::Cmd(0xC7), Sent::Data(vec![8]),
+            Sent::Cmd(0xC7), Sent::Data(vec![6]),
+            Sent::Cmd(0xC7), Sent::Data(vec![4]),
+        ]);
+        assert_eq!(
+            disp.contrast_transition(16, 4),
+            Err(CommandError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn run_with_delay() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        disp.contrast(0).unwrap();
+        di.clear();
+
+        // More steps than levels, so half of them leave the contrast where it is.
+        let mut delay = CountingDelay(0);
+        let mut fade = disp.contrast_transition(2, 4).unwrap();
+        fade.run(&mut disp, 25, &mut delay).unwrap();
+        assert_eq!(delay.0, 100);
+        #[rustfmt::skip]
+        assert_eq!(di.sent(), [
+            Sent::Cmd(0xC7), Sent::Data(vec![1]),
+            Sent::Cmd(0xC7), Sent::Data(vec![2]),
+        ]);
+    }
+}
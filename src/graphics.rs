@@ -0,0 +1,303 @@
+//! Lightweight shape drawing, for projects which need a few lines and boxes but cannot afford a
+//! full graphics library.
+//!
+//! The display RAM can only be written in groups of 4 horizontally adjacent pixels, and this
+//! driver does not buffer or read back its contents, so any pixels of a touched column group which
+//! are not part of the shape are filled with a background level. Shapes are drawn as a few
+//! rectangular regions covering only the column groups and rows the shape passes through, so the
+//! rest of the display is left untouched; for example, the interior of a large rectangle outline
+//! is not redrawn.
+
+use core::convert::TryFrom;
+
+use crate::command::consts::*;
+use crate::command::CommandError;
+use crate::display::{Display, PixelCoord};
+use crate::interface;
+
+/// Draws shapes onto a display in a foreground gray level, using a background level for the rest
+/// of each column group it touches. Like a region, a painter is intended to be short-lived and
+/// mutably borrows the display.
+///
+/// Shapes are clipped to the display's viewable columns and the 128 rows of display RAM.
+pub struct Painter<'d, DI>
+where
+    DI: interface::DisplayInterface,
+{
+    display: &'d mut Display<DI>,
+    fg: u8,
+    bg: u8,
+}
+
+impl<'d, DI> Painter<'d, DI>
+where
+    DI: interface::DisplayInterface,
+{
+    /// Create a painter drawing in gray level 15 on a background of 0.
+    pub fn new(display: &'d mut Display<DI>) -> Self {
+        Painter {
+            display: display,
+            fg: 15,
+            bg: 0,
+        }
+    }
+
+    /// Set the gray levels used for shapes and for the rest of the column groups they touch.
+    pub fn set_colors(&mut self, fg: u8, bg: u8) {
+        self.fg = fg & 0x0F;
+        self.bg = bg & 0x0F;
+    }
+
+    /// Draw a horizontal line `length` pixels long, starting at `start` and extending right.
+    ///
+    /// Returns `CommandError::OutOfRange` if `length` does not fit in an `i16`.
+    pub fn hline(&mut self, start: PixelCoord, length: u16) -> Result<(), CommandError<DI::Error>> {
+        let length = to_coord(length)?;
+        let lower_right = PixelCoord(start.0.saturating_add(length), start.1.saturating_add(1));
+        self.fill_rect(start, lower_right)
+    }
+
+    /// Draw a vertical line `length` pixels long, starting at `start` and extending down.
+    ///
+    /// Returns `CommandError::OutOfRange` if `length` does not fit in an `i16`.
+    pub fn vline(&mut self, start: PixelCoord, length: u16) -> Result<(), CommandError<DI::Error>> {
+        let length = to_coord(length)?;
+        let lower_right = PixelCoord(start.0.saturating_add(1), start.1.saturating_add(length));
+        self.fill_rect(start, lower_right)
+    }
+
+    /// Draw the one pixel wide outline of the rectangle from `upper_left` to `lower_right`,
+    /// exclusive.
+    pub fn rect(
+        &mut self,
+        upper_left: PixelCoord,
+        lower_right: PixelCoord,
+    ) -> Result<(), CommandError<DI::Error>> {
+        let (ul, lr) = (upper_left, lower_right);
+        self.draw_shape(ul, lr, |x, y| {
+            x == ul.0 || x == lr.0 - 1 || y == ul.1 || y == lr.1 - 1
+        })
+    }
+
+    /// Fill the rectangle from `upper_left` to `lower_right`, exclusive.
+    pub fn fill_rect(
+        &mut self,
+        upper_left: PixelCoord,
+        lower_right: PixelCoord,
+    ) -> Result<(), CommandError<DI::Error>> {
+        self.draw_shape(upper_left, lower_right, |_, _| true)
+    }
+
+    /// Draw the one pixel wide outline of a circle centred on the pixel `center`.
+    ///
+    /// Returns `CommandError::OutOfRange` if `radius` does not fit in an `i16`.
+    pub fn circle(
+        &mut self,
+        center: PixelCoord,
+        radius: u16,
+    ) -> Result<(), CommandError<DI::Error>> {
+        // A pixel is on the outline if its centre lies within half a pixel of the circle. The
+        // comparisons are done at double scale to stay in integers.
+        let (inner, outer) = (
+            (2 * radius as i64 - 1).pow(2),
+            (2 * radius as i64 + 1).pow(2),
+        );
+        self.draw_circle(center, radius, move |d2| d2 >= inner && d2 < outer)
+    }
+
+    /// Fill a circle centred on the pixel `center`.
+    ///
+    /// Returns `CommandError::OutOfRange` if `radius` does not fit in an `i16`.
+    pub fn fill_circle(
+        &mut self,
+        center: PixelCoord,
+        radius: u16,
+    ) -> Result<(), CommandError<DI::Error>> {
+        let outer = (2 * radius as i64 + 1).pow(2);
+        self.draw_circle(center, radius, move |d2| d2 < outer)
+    }
+
+    /// Draw a circle, where `inside` tests four times the squared distance of a pixel from the
+    /// centre.
+    fn draw_circle<F>(
+        &mut self,
+        center: PixelCoord,
+        radius: u16,
+        inside: F,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        F: Fn(i64) -> bool,
+    {
+        let r = to_coord(radius)?;
+        let upper_left = PixelCoord(center.0.saturating_sub(r), center.1.saturating_sub(r));
+        let lower_right = PixelCoord(
+            center.0.saturating_add(r).saturating_add(1),
+            center.1.saturating_add(r).saturating_add(1),
+        );
+        self.draw_shape(upper_left, lower_right, |x, y| {
+            let (dx, dy) = (x as i64 - center.0 as i64, y as i64 - center.1 as i64);
+            inside(4 * (dx * dx + dy * dy))
+        })
+    }
+
+    /// Draw the pixels for which `inside` is true within the bounding box from `upper_left` to
+    /// `lower_right`.
+    ///
+    /// Each column group of the bounding box is split into the runs of rows which contain some
+    /// part of the shape. Adjacent column groups with the same runs are batched into one region
+    /// per run, so for example a horizontal line is drawn as a single region.
+    fn draw_shape<F>(
+        &mut self,
+        upper_left: PixelCoord,
+        lower_right: PixelCoord,
+        inside: F,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        F: Fn(i16, i16) -> bool,
+    {
+        let x0 = upper_left.0.max(0);
+        let x1 = lower_right.0.min(self.display.size().0);
+        let y0 = upper_left.1.max(0);
+        let y1 = lower_right.1.min(NUM_PIXEL_ROWS as i16);
+        if x0 >= x1 || y0 >= y1 {
+            return Ok(());
+        }
+        let on = |x: i16, y: i16| x >= x0 && x < x1 && inside(x, y);
+        let group_hit = |group: i16, y: i16| (group..group + 4).any(|x| on(x, y));
+
+        let mut group = x0 & !3;
+        while group < x1 {
+            // Extend the batch across the following groups with an identical pattern of rows.
+            let mut end = group + 4;
+            while end < x1 && (y0..y1).all(|y| group_hit(group, y) == group_hit(end, y)) {
+                end += 4;
+            }
+            let mut y = y0;
+            while y < y1 {
+                if !group_hit(group, y) {
+                    y += 1;
+                    continue;
+                }
+                let run_start = y;
+                while y < y1 && group_hit(group, y) {
+                    y += 1;
+                }
+                let (fg, bg) = (self.fg, self.bg);
+                let pixels = (run_start..y)
+                    .flat_map(|py| (group..end).map(move |px| if on(px, py) { fg } else { bg }));
+                self.display
+                    .region(PixelCoord(group, run_start), PixelCoord(end, y))?
+                    .draw(pixels)
+                    .map_err(CommandError::InterfaceError)?;
+            }
+            group = end;
+        }
+        Ok(())
+    }
+}
+
+/// Convert a length or radius to a pixel coordinate offset.
+fn to_coord<E>(length: u16) -> Result<i16, CommandError<E>> {
+    i16::try_from(length).map_err(|_| CommandError::OutOfRange)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+    use crate::test_support::initialized_display;
+    use std::vec::Vec;
+
+    /// Render the display as hex gray levels, one string per row.
+    fn screen(di: &MockInterface) -> Vec<Vec<u8>> {
+        let image = di.chip_model(Px(16, 16), Px(0, 0)).render();
+        image
+            .chunks(16)
+            .map(|row| {
+                row.iter()
+                    .map(|&p| b"0123456789ABCDEF"[p as usize])
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lines_batch_column_groups() {
+        let (di, mut disp) = initialized_display(Px(16, 16));
+        let before = di.sent().len();
+        let mut painter = Painter::new(&mut disp);
+        painter.set_colors(9, 1);
+        painter.hline(Px(2, 3), 8).unwrap();
+        let sent = &di.sent()[before..];
+        assert_eq!(sent.iter().filter(|s| **s == Sent::Cmd(0x15)).count(), 1);
+        assert_eq!(screen(&di)[3], b"1199999999110000");
+        painter.vline(Px(13, 1), 3).unwrap();
+        let rows = screen(&di);
+        assert_eq!(rows[0], b"0000000000000000");
+        assert_eq!(rows[1], b"0000000000001911");
+        assert_eq!(rows[3], b"1199999999111911");
+
+        // Lines running off the display are clipped, but lengths must fit a coordinate.
+        painter.set_colors(15, 0);
+        painter.hline(Px(12, 5), i16::MAX as u16).unwrap();
+        assert_eq!(screen(&di)[5], b"000000000000FFFF");
+        assert_eq!(
+            painter.vline(Px(0, 0), 40_000),
+            Err(CommandError::OutOfRange)
+        );
+        assert_eq!(
+            painter.circle(Px(0, 0), 40_000),
+            Err(CommandError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn rect_outline_leaves_interior() {
+        let (di, mut disp) = initialized_display(Px(16, 16));
+        disp.region(Px(0, 0), Px(16, 16))
+            .unwrap()
+            .draw(core::iter::repeat_n(5, 256))
+            .unwrap();
+        let mut painter = Painter::new(&mut disp);
+        painter.rect(Px(1, 1), Px(15, 6)).unwrap();
+        // The middle column groups only have the top and bottom edges drawn.
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di)[..7], &[
+            b"5555555555555555",
+            b"0FFFFFFFFFFFFFF0",
+            b"0F005555555500F0",
+            b"0F005555555500F0",
+            b"0F005555555500F0",
+            b"0FFFFFFFFFFFFFF0",
+            b"5555555555555555",
+        ]);
+    }
+
+    #[test]
+    fn circles() {
+        let (di, mut disp) = initialized_display(Px(16, 16));
+        let mut painter = Painter::new(&mut disp);
+        painter.circle(Px(4, 4), 3).unwrap();
+        painter.fill_circle(Px(12, 12), 2).unwrap();
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di)[..], &[
+            b"0000000000000000",
+            b"000FFF0000000000",
+            b"00F000F000000000",
+            b"0F00000F00000000",
+            b"0F00000F00000000",
+            b"0F00000F00000000",
+            b"00F000F000000000",
+            b"000FFF0000000000",
+            b"0000000000000000",
+            b"0000000000000000",
+            b"00000000000FFF00",
+            b"0000000000FFFFF0",
+            b"0000000000FFFFF0",
+            b"0000000000FFFFF0",
+            b"00000000000FFF00",
+            b"0000000000000000",
+        ]);
+    }
+}
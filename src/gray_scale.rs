@@ -0,0 +1,146 @@
+//! Construction of gray scale tables from a few control points.
+//!
+//! The gray scale table maps each of the gray levels 1-15 to a pixel drive pulse width in DCLKs
+//! (see `BufCommand::SetGrayScaleTable`). Rather than authoring all 15 entries by hand, measure
+//! or choose the pulse widths for a handful of levels and let `GrayScaleTableBuilder` fill in the
+//! rest.
+
+use core::convert::Infallible;
+
+use crate::command::CommandError;
+
+/// The largest pulse width the chip accepts in the gray scale table.
+const MAX_PULSE_WIDTH: i16 = 180;
+
+/// A builder for a gray scale table, interpolating linearly between control points. Level 0 is
+/// always driven with a pulse width of 0, and levels above the last control point continue its
+/// slope.
+///
+/// The result is adjusted to satisfy the chip's rules: pulse widths are clamped to 0-180 and made
+/// to increase strictly from level 1 upwards, moving entries as little as possible.
+#[derive(Clone, Debug)]
+pub struct GrayScaleTableBuilder {
+    /// The pulse width for each level 0-15, where given.
+    points: [Option<u8>; 16],
+    out_of_range: bool,
+}
+
+impl GrayScaleTableBuilder {
+    /// Start a table with no control points.
+    pub fn new() -> Self {
+        GrayScaleTableBuilder {
+            points: [None; 16],
+            out_of_range: false,
+        }
+    }
+
+    /// Add a control point driving gray level `level` (1-15) with `pulse_width` DCLKs. A second
+    /// point for the same level replaces the first.
+    pub fn point(mut self, level: u8, pulse_width: u8) -> Self {
+        match level {
+            1..=15 => self.points[level as usize] = Some(pulse_width),
+            _ => self.out_of_range = true,
+        }
+        self
+    }
+
+    /// Interpolate the table, ready for `Display::gray_scale_table`. Returns
+    /// `CommandError::OutOfRange` if no control points were given, or if any was for a level
+    /// outside 1-15.
+    pub fn build(&self) -> Result<[u8; 15], CommandError<Infallible>> {
+        if self.out_of_range || self.points.iter().all(|p| p.is_none()) {
+            return Err(CommandError::OutOfRange);
+        }
+        let anchor = |level: usize| match level {
+            0 => Some(0),
+            _ => self.points[level].map(|w| w as i16),
+        };
+
+        let mut widths = [0i16; 16];
+        for (level, width) in widths.iter_mut().enumerate().skip(1) {
+            let below = (0..=level).rev().find(|&l| anchor(l).is_some()).unwrap();
+            let above = (level..16).find(|&l| anchor(l).is_some());
+            // Interpolate between the neighbouring points, or beyond the last point extend the
+            // line through it and the point before it.
+            let (lo, hi) = match above {
+                Some(above) => (below, above),
+                None => (
+                    (0..below).rev().find(|&l| anchor(l).is_some()).unwrap(),
+                    below,
+                ),
+            };
+            let (lo_w, hi_w) = (anchor(lo).unwrap(), anchor(hi).unwrap());
+            *width = if lo == hi {
+                lo_w
+            } else {
+                let span = (hi - lo) as i16;
+                lo_w + ((hi_w - lo_w) * (level - lo) as i16 * 2 + span).div_euclid(span * 2)
+            };
+        }
+
+        // Enforce a strictly increasing table within range: first raise each entry above the one
+        // before it, then lower each entry below the one after it, starting from the limit.
+        widths[1] = widths[1].max(0);
+        for level in 2..16 {
+            widths[level] = widths[level].max(widths[level - 1] + 1);
+        }
+        let mut ceiling = MAX_PULSE_WIDTH + 1;
+        for width in widths[1..].iter_mut().rev() {
+            *width = (*width).min(ceiling - 1);
+            ceiling = *width;
+        }
+
+        let mut table = [0u8; 15];
+        for (entry, width) in table.iter_mut().zip(widths[1..].iter()) {
+            *entry = *width as u8;
+        }
+        Ok(table)
+    }
+}
+
+impl Default for GrayScaleTableBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::BufCommand;
+    use crate::interface::mock::MockInterface;
+
+    #[test]
+    fn interpolate() {
+        let table = GrayScaleTableBuilder::new()
+            .point(1, 2)
+            .point(5, 10)
+            .point(15, 180)
+            .build()
+            .unwrap();
+        #[rustfmt::skip]
+        assert_eq!(table, [2, 4, 6, 8, 10, 27, 44, 61, 78, 95, 112, 129, 146, 163, 180]);
+
+        // Extrapolated past the last point and clamped, then forced to increase.
+        let table = GrayScaleTableBuilder::new()
+            .point(3, 30)
+            .point(5, 5)
+            .point(10, 200)
+            .build()
+            .unwrap();
+        #[rustfmt::skip]
+        assert_eq!(table, [10, 20, 30, 31, 32, 44, 83, 122, 161, 175, 176, 177, 178, 179, 180]);
+        let mut di = MockInterface::new();
+        BufCommand::SetGrayScaleTable(&table).send(&mut di).unwrap();
+
+        // A falling curve is pushed up from zero.
+        let table = GrayScaleTableBuilder::new()
+            .point(1, 90)
+            .point(2, 0)
+            .build();
+        assert_eq!(&table.unwrap()[..4], &[90, 91, 92, 93]);
+
+        assert!(GrayScaleTableBuilder::new().build().is_err());
+        assert!(GrayScaleTableBuilder::new().point(16, 1).build().is_err());
+    }
+}
@@ -0,0 +1,66 @@
+//! An adapter for drawing BMP images decoded by `tinybmp` straight into a region, without going
+//! through the embedded-graphics `DrawTarget` machinery. Enabled by the `tinybmp` feature.
+
+use embedded_graphics_core::pixelcolor::{Rgb888, RgbColor};
+use tinybmp::Bmp;
+
+use crate::image::{luma, scale_to_gray4};
+
+/// An iterator of the pixels of a BMP image as 4-bit gray levels, in left-to-right, top-to-bottom
+/// order, suitable for `Region::draw`.
+///
+/// Any BMP format which `tinybmp` can decode is accepted. Grayscale images, which BMP stores as
+/// indexed color with a gray palette, keep their gray levels, and color images are converted to
+/// their luma.
+pub struct BmpPixels<'b> {
+    pixels: tinybmp::Pixels<'b, Rgb888>,
+}
+
+impl<'b> BmpPixels<'b> {
+    /// Iterate over the pixels of `bmp`.
+    pub fn new(bmp: &'b Bmp<'_, Rgb888>) -> Self {
+        BmpPixels {
+            pixels: bmp.pixels(),
+        }
+    }
+}
+
+impl<'b> Iterator for BmpPixels<'b> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        let color = self.pixels.next()?.1;
+        Some(scale_to_gray4(
+            luma(color.r(), color.g(), color.b()) as u16,
+            255,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    /// A 2x2 pixel 24-bit BMP, stored bottom row first.
+    #[rustfmt::skip]
+    const IMAGE: &[u8] = &[
+        // File header.
+        b'B', b'M', 70, 0, 0, 0, 0, 0, 0, 0, 54, 0, 0, 0,
+        // Info header: 2x2, 1 plane, 24 bpp, uncompressed, 16 bytes of pixel data.
+        40, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 1, 0, 24, 0,
+        0, 0, 0, 0, 16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0,
+        // Bottom row: black, white, padding.
+        0, 0, 0, 255, 255, 255, 0, 0,
+        // Top row: red, mid gray, padding. Pixels are stored BGR.
+        0, 0, 255, 128, 128, 128, 0, 0,
+    ];
+
+    #[test]
+    fn bmp_pixels() {
+        let bmp = Bmp::<Rgb888>::from_slice(IMAGE).unwrap();
+        let pixels = BmpPixels::new(&bmp).collect::<Vec<_>>();
+        assert_eq!(pixels, vec![5, 8, 0, 15]);
+    }
+}
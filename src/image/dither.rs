@@ -0,0 +1,115 @@
+//! Floyd-Steinberg error diffusion dithering, for reducing 8-bit gray images to the panel's 16
+//! levels without visible banding. The only state is a single row of errors in a buffer provided
+//! by the caller, so images can be dithered as they are streamed, e.g. from an SD card.
+
+use crate::image::scale_to_gray4;
+
+/// Dither an iterator of 8-bit gray values, in left-to-right, top-to-bottom order, down to the
+/// 4-bit gray levels expected by `Region::draw`.
+///
+/// The image width is the length of the error buffer passed to `new`.
+pub struct Dither<'b, I> {
+    iter: I,
+    /// Errors to be added to the pixels of the current row at and after `x`, and to the pixels of
+    /// the next row before `x`.
+    errors: &'b mut [i16],
+    x: usize,
+    /// Error carried to the next pixel on the right, to the pixel below the previous one, and to
+    /// the pixel below the current one.
+    right: i16,
+    below_left: i16,
+    below: i16,
+}
+
+impl<'b, I> Dither<'b, I>
+where
+    I: Iterator<Item = u8>,
+{
+    /// Dither `iter` as an image as wide as `errors`, using `errors` as scratch space. The buffer
+    /// is cleared before use.
+    pub fn new(iter: I, errors: &'b mut [i16]) -> Self {
+        for e in errors.iter_mut() {
+            *e = 0;
+        }
+        Dither {
+            iter: iter,
+            errors: errors,
+            x: 0,
+            right: 0,
+            below_left: 0,
+            below: 0,
+        }
+    }
+}
+
+impl<'b, I> Iterator for Dither<'b, I>
+where
+    I: Iterator<Item = u8>,
+{
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        let value = self.iter.next()?;
+        let width = self.errors.len();
+        if width == 0 {
+            return Some(scale_to_gray4(value as u16, 255));
+        }
+        let x = self.x;
+        let wanted = (value as i16 + self.errors[x] + self.right).clamp(0, 255);
+        let level = scale_to_gray4(wanted as u16, 255);
+        let error = wanted - level as i16 * 17;
+
+        // Distribute the error 7/16 to the right, and 3/16, 5/16 and 1/16 to the pixels below
+        // left, below and below right. The slot for the pixel below left has now been read for
+        // this row, so it can take that pixel's final error for the next row.
+        self.right = error * 7 / 16;
+        if x > 0 {
+            self.errors[x - 1] = self.below_left + error * 3 / 16;
+        }
+        self.below_left = self.below + error * 5 / 16;
+        self.below = error / 16;
+
+        self.x += 1;
+        if self.x == width {
+            // Error off the right hand edge is dropped.
+            self.errors[x] = self.below_left;
+            self.x = 0;
+            self.right = 0;
+            self.below_left = 0;
+            self.below = 0;
+        }
+        Some(level)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    #[test]
+    fn exact_levels_are_untouched() {
+        let mut errors = [0; 4];
+        let input = [0u8, 17, 136, 255].iter().cloned().cycle().take(16);
+        let levels = Dither::new(input, &mut errors).collect::<Vec<_>>();
+        assert_eq!(&levels[..4], &[0, 1, 8, 15]);
+        assert_eq!(&levels[12..], &[0, 1, 8, 15]);
+    }
+
+    #[test]
+    fn mid_gray_averages_out() {
+        // 144 lies between levels 8 (136) and 9 (153), so the output should mix those two. The
+        // stale contents of the error buffer must not matter.
+        let mut errors = [7; 8];
+        let input = core::iter::repeat_n(144u8, 64);
+        let levels = Dither::new(input, &mut errors).collect::<Vec<_>>();
+        assert!(levels.iter().all(|&l| l == 8 || l == 9));
+        let sum = levels.iter().map(|&l| l as u32 * 17).sum::<u32>();
+        assert!((sum as i32 / 64 - 144).abs() <= 1);
+        assert!(levels.contains(&8) && levels.contains(&9));
+    }
+}
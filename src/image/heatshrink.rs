@@ -0,0 +1,264 @@
+//! Streaming decompression of assets compressed with
+//! [heatshrink](https://github.com/atomicobject/heatshrink), an LZSS variant designed for embedded
+//! systems. Packed 4-bit images such as splash screens and icon sets typically shrink to half
+//! their size or less, and decompress straight into a region:
+//!
+//! ```
+//! # use ssd1322::image::heatshrink::HeatshrinkDecoder;
+//! # use ssd1322::command::CommandError;
+//! # use ssd1322::interface::DisplayInterface;
+//! # use ssd1322::{Display, PixelCoord};
+//! # static SPLASH: &[u8] = &[];
+//! // static SPLASH: &[u8] = include_bytes!("splash.hs");
+//! fn draw_splash<DI>(display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>>
+//! where
+//!     DI: DisplayInterface,
+//! {
+//!     let mut window = [0u8; 256];
+//!     let pixels = HeatshrinkDecoder::new(SPLASH, &mut window, 4).unwrap();
+//!     let mut region = display.region(PixelCoord(0, 0), PixelCoord(256, 64))?;
+//!     region.draw_packed(pixels).map_err(CommandError::InterfaceError)
+//! }
+//! ```
+//!
+//! The decoder needs a window buffer of `2^window_bits` bytes, which the caller provides so that
+//! it can be reused between assets or placed in a particular RAM bank. The window and lookahead
+//! sizes must match those used to compress the asset: the `-w` and `-l` options of the
+//! `heatshrink` command line tool, or the arguments of `compress`, which is available with the
+//! `std` feature for build scripts and asset tools.
+//!
+//! Available with the `heatshrink` feature.
+
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+/// Errors which can occur when setting up a `HeatshrinkDecoder`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HeatshrinkError {
+    /// The window buffer is not a power of two between 16 and 32768 bytes long.
+    BadWindow,
+    /// The lookahead size is not at least 3 bits and less than the window size in bits.
+    BadLookahead,
+}
+
+/// Check the window and lookahead sizes, in bits, against the limits heatshrink supports.
+fn check_params(window_bits: u8, lookahead_bits: u8) -> Result<(), HeatshrinkError> {
+    if !(4..=15).contains(&window_bits) {
+        return Err(HeatshrinkError::BadWindow);
+    }
+    if lookahead_bits < 3 || lookahead_bits >= window_bits {
+        return Err(HeatshrinkError::BadLookahead);
+    }
+    Ok(())
+}
+
+/// An iterator of the bytes decompressed from a heatshrink stream held in memory. See the module
+/// documentation.
+pub struct HeatshrinkDecoder<'a, 'w> {
+    input: &'a [u8],
+    /// The position in `input` of the next bit to read.
+    bit_pos: usize,
+    window: &'w mut [u8],
+    window_bits: u8,
+    lookahead_bits: u8,
+    /// The number of bytes output so far, which locates the next byte in the window.
+    head: usize,
+    /// The offset and remaining length of the back-reference being copied, if any.
+    backref: Option<(usize, usize)>,
+}
+
+impl<'a, 'w> HeatshrinkDecoder<'a, 'w> {
+    /// Decompress `input`, compressed with a window of `window.len()` bytes and a lookahead of
+    /// `2^lookahead_bits` bytes. The window must be a power of two from 16 to 32768 bytes long.
+    pub fn new(
+        input: &'a [u8],
+        window: &'w mut [u8],
+        lookahead_bits: u8,
+    ) -> Result<Self, HeatshrinkError> {
+        if !window.len().is_power_of_two() {
+            return Err(HeatshrinkError::BadWindow);
+        }
+        let window_bits = window.len().trailing_zeros() as u8;
+        check_params(window_bits, lookahead_bits)?;
+        // Back-references reaching before the start of the stream read zeros.
+        window.iter_mut().for_each(|byte| *byte = 0);
+        Ok(HeatshrinkDecoder {
+            input: input,
+            bit_pos: 0,
+            window: window,
+            window_bits: window_bits,
+            lookahead_bits: lookahead_bits,
+            head: 0,
+            backref: None,
+        })
+    }
+
+    /// Read the next `count` bits of input, most significant first, or `None` at the end of the
+    /// input.
+    fn bits(&mut self, count: u8) -> Option<usize> {
+        if self.bit_pos + count as usize > self.input.len() * 8 {
+            return None;
+        }
+        let mut value = 0;
+        for _ in 0..count {
+            let bit = self.input[self.bit_pos / 8] >> (7 - self.bit_pos % 8) & 1;
+            value = value << 1 | bit as usize;
+            self.bit_pos += 1;
+        }
+        Some(value)
+    }
+
+    /// Append `byte` to the window and return it.
+    fn emit(&mut self, byte: u8) -> u8 {
+        let mask = self.window.len() - 1;
+        self.window[self.head & mask] = byte;
+        self.head = self.head.wrapping_add(1);
+        byte
+    }
+}
+
+impl<'a, 'w> Iterator for HeatshrinkDecoder<'a, 'w> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.backref.is_none() {
+            // A set tag bit introduces a literal byte, a clear one a back-reference. The zero bits
+            // padding out the last byte of input are too few to make a whole back-reference.
+            if self.bits(1)? == 1 {
+                let byte = self.bits(8)? as u8;
+                return Some(self.emit(byte));
+            }
+            let offset = self.bits(self.window_bits)? + 1;
+            let len = self.bits(self.lookahead_bits)? + 1;
+            self.backref = Some((offset, len));
+        }
+        let (offset, len) = self.backref.unwrap();
+        self.backref = if len > 1 {
+            Some((offset, len - 1))
+        } else {
+            None
+        };
+        let mask = self.window.len() - 1;
+        let byte = self.window[self.head.wrapping_sub(offset) & mask];
+        Some(self.emit(byte))
+    }
+}
+
+/// Compress `data` into a heatshrink stream with a window of `2^window_bits` bytes and a lookahead
+/// of `2^lookahead_bits` bytes, which can be decompressed with `HeatshrinkDecoder` or the
+/// reference implementation. Larger windows usually compress better, at the cost of a larger
+/// window buffer for the decoder; a window of 8 bits and lookahead of 4 suits most images.
+#[cfg(feature = "std")]
+pub fn compress(
+    data: &[u8],
+    window_bits: u8,
+    lookahead_bits: u8,
+) -> Result<Vec<u8>, HeatshrinkError> {
+    check_params(window_bits, lookahead_bits)?;
+    let window = 1 << window_bits;
+    let lookahead = 1 << lookahead_bits;
+    // A back-reference only pays for itself if it replaces more bits of literals than it takes.
+    let backref_bits = 1 + window_bits as usize + lookahead_bits as usize;
+
+    let mut out = BitWriter::default();
+    let mut pos = 0;
+    while pos < data.len() {
+        let max_len = lookahead.min(data.len() - pos);
+        let mut best = (0, 0);
+        for start in pos.saturating_sub(window)..pos {
+            // Matches may run on past `pos`, copying bytes the decoder has just output.
+            let len = (0..max_len)
+                .take_while(|&i| data[start + i] == data[pos + i])
+                .count();
+            if len > best.1 {
+                best = (pos - start, len);
+            }
+        }
+        if best.1 * 9 > backref_bits {
+            out.push(0, 1);
+            out.push(best.0 - 1, window_bits);
+            out.push(best.1 - 1, lookahead_bits);
+            pos += best.1;
+        } else {
+            out.push(1, 1);
+            out.push(data[pos] as usize, 8);
+            pos += 1;
+        }
+    }
+    Ok(out.bytes)
+}
+
+/// Packs bit fields into bytes, most significant bit first.
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    /// The number of bits used in the last byte, or 0 if it is full.
+    used: u8,
+}
+
+#[cfg(feature = "std")]
+impl BitWriter {
+    fn push(&mut self, value: usize, count: u8) {
+        for i in (0..count).rev() {
+            if self.used == 0 {
+                self.bytes.push(0);
+            }
+            let bit = (value >> i & 1) as u8;
+            *self.bytes.last_mut().unwrap() |= bit << (7 - self.used);
+            self.used = (self.used + 1) % 8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    #[test]
+    fn decode() {
+        // A literal 'a', then a back-reference to the previous byte repeated 4 times.
+        let input = [0xB0, 0x80, 0x0C];
+        let mut window = [0u8; 256];
+        let decoder = HeatshrinkDecoder::new(&input, &mut window, 4).unwrap();
+        assert_eq!(decoder.collect::<Vec<_>>(), b"aaaaa");
+        assert_eq!(compress(b"aaaaa", 8, 4).unwrap(), input);
+    }
+
+    #[test]
+    fn round_trip() {
+        // A packed image of a gradient with a repeated icon, much like typical UI assets.
+        let mut image = Vec::new();
+        for row in 0..32u8 {
+            image.extend((0..32u8).map(|col| (col / 2) * 0x11));
+            image.extend(
+                [0x00, 0xFF, 0xFF, 0x00]
+                    .iter()
+                    .cycle()
+                    .take(32 + row as usize % 4),
+            );
+        }
+        let compressed = compress(&image, 8, 4).unwrap();
+        assert!(compressed.len() * 2 < image.len());
+
+        let mut window = [0u8; 256];
+        let decoder = HeatshrinkDecoder::new(&compressed, &mut window, 4).unwrap();
+        assert_eq!(decoder.collect::<Vec<_>>(), image);
+    }
+
+    #[test]
+    fn bad_params() {
+        let mut window = [0u8; 100];
+        assert!(matches!(
+            HeatshrinkDecoder::new(&[], &mut window, 4),
+            Err(HeatshrinkError::BadWindow)
+        ));
+        let mut window = [0u8; 64];
+        assert!(matches!(
+            HeatshrinkDecoder::new(&[], &mut window, 6),
+            Err(HeatshrinkError::BadLookahead)
+        ));
+        assert_eq!(compress(&[], 16, 4), Err(HeatshrinkError::BadWindow));
+    }
+}
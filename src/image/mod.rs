@@ -0,0 +1,141 @@
+//! Image decoding and pixel format conversion, producing streams of 4-bit gray pixels which can be
+//! drawn straight into a region with `Region::draw`. The conversion adapters work one pixel at a
+//! time, so color assets and camera frames can be converted as they are drawn without a separate
+//! pass or buffer.
+
+#[cfg(feature = "tinybmp")]
+pub mod bmp;
+pub mod dither;
+#[cfg(feature = "heatshrink")]
+pub mod heatshrink;
+pub mod netpbm;
+pub mod scale;
+pub mod transform;
+
+/// Convert an iterator of 8-bit gray values into the 4-bit gray levels expected by `Region::draw`,
+/// rounding each value to the nearest level rather than truncating it.
+pub struct Gray8To4<I>(pub I);
+
+impl<I> Iterator for Gray8To4<I>
+where
+    I: Iterator<Item = u8>,
+{
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        self.0.next().map(|value| scale_to_gray4(value as u16, 255))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// Convert an iterator of 24-bit `[red, green, blue]` colors into 4-bit gray levels of their luma.
+pub struct Rgb888ToGray4<I>(pub I);
+
+impl<I> Iterator for Rgb888ToGray4<I>
+where
+    I: Iterator<Item = [u8; 3]>,
+{
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        self.0
+            .next()
+            .map(|[r, g, b]| scale_to_gray4(luma(r, g, b) as u16, 255))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// Convert an iterator of 16-bit RGB565 colors, with red in the most significant bits, into 4-bit
+/// gray levels of their luma.
+pub struct Rgb565ToGray4<I>(pub I);
+
+impl<I> Iterator for Rgb565ToGray4<I>
+where
+    I: Iterator<Item = u16>,
+{
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        self.0.next().map(|color| {
+            // Widen each channel to 8 bits by replicating its high bits into the low bits, so that
+            // full scale maps to 255.
+            let r = (color >> 11) as u8 & 0x1F;
+            let g = (color >> 5) as u8 & 0x3F;
+            let b = color as u8 & 0x1F;
+            let (r, g, b) = (r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2);
+            scale_to_gray4(luma(r, g, b) as u16, 255)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// Scale `value` in the range [0, `max`] to a 4-bit gray level, rounding to nearest.
+pub(crate) fn scale_to_gray4(value: u16, max: u16) -> u8 {
+    if max == 0 {
+        return 0;
+    }
+    let value = value.min(max) as u32;
+    ((value * 15 + max as u32 / 2) / max as u32) as u8
+}
+
+/// The luma of an 8-bit RGB color using the Rec. 601 weights, in integer math.
+pub(crate) fn luma(r: u8, g: u8, b: u8) -> u8 {
+    ((77 * r as u32 + 150 * g as u32 + 29 * b as u32 + 128) >> 8) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    #[test]
+    fn scale() {
+        assert_eq!(scale_to_gray4(0, 255), 0);
+        assert_eq!(scale_to_gray4(255, 255), 15);
+        // 8 and 9 are just either side of the boundary between levels 0 and 1.
+        assert_eq!(scale_to_gray4(8, 255), 0);
+        assert_eq!(scale_to_gray4(9, 255), 1);
+        assert_eq!(scale_to_gray4(1, 1), 15);
+        assert_eq!(scale_to_gray4(5, 0), 0);
+    }
+
+    #[test]
+    fn gray8_to_4() {
+        let levels = Gray8To4([0u8, 8, 9, 127, 128, 246, 247, 255].iter().cloned());
+        assert_eq!(levels.collect::<Vec<_>>(), vec![0, 0, 1, 7, 8, 14, 15, 15]);
+    }
+
+    #[test]
+    fn rgb_to_gray4() {
+        let colors = [
+            [0, 0, 0],
+            [255, 255, 255],
+            [255, 0, 0],
+            [0, 255, 0],
+            [0, 0, 255],
+        ];
+        let levels = Rgb888ToGray4(colors.iter().cloned());
+        assert_eq!(levels.collect::<Vec<_>>(), vec![0, 15, 5, 9, 2]);
+        let colors = [0x0000, 0xFFFF, 0xF800, 0x07E0, 0x001F];
+        let levels = Rgb565ToGray4(colors.iter().cloned());
+        assert_eq!(levels.collect::<Vec<_>>(), vec![0, 15, 5, 9, 2]);
+    }
+
+    #[test]
+    fn luma_weights() {
+        assert_eq!(luma(0, 0, 0), 0);
+        assert_eq!(luma(255, 255, 255), 255);
+        assert_eq!(luma(255, 0, 0), 77);
+        assert_eq!(luma(0, 255, 0), 149);
+        assert_eq!(luma(0, 0, 255), 29);
+    }
+}
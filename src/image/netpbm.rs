@@ -0,0 +1,228 @@
+//! A decoder for binary PGM (`P5`) and PBM (`P4`) images, the simplest formats that any image tool
+//! can export. The decoder works on an image held in memory, e.g. with `include_bytes!`, and
+//! yields 4-bit gray pixels without any intermediate buffer.
+
+use crate::image::scale_to_gray4;
+
+/// Errors which can occur while parsing a PGM or PBM header.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NetpbmError {
+    /// The image does not start with `P4` or `P5`. Note that the ASCII variants `P1` and `P2`
+    /// are not supported.
+    BadMagic,
+    /// The width, height or maximum value is missing or not a valid number.
+    BadHeader,
+    /// There is less pixel data than the header says.
+    Truncated,
+}
+
+/// The format of a Netpbm image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NetpbmFormat {
+    /// Binary bitmap (`P4`), 1 bit per pixel where 1 is black.
+    Pbm,
+    /// Binary graymap (`P5`), 8 or 16 bits per pixel up to a maximum value.
+    Pgm,
+}
+
+/// A parsed PGM or PBM image, referencing its pixel data.
+#[derive(Clone, Copy, Debug)]
+pub struct Netpbm<'a> {
+    format: NetpbmFormat,
+    width: u16,
+    height: u16,
+    max: u16,
+    data: &'a [u8],
+}
+
+impl<'a> Netpbm<'a> {
+    /// Parse the header of an image in `bytes`, and check that it contains enough pixel data.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, NetpbmError> {
+        let format = match bytes.get(..2) {
+            Some(b"P4") => NetpbmFormat::Pbm,
+            Some(b"P5") => NetpbmFormat::Pgm,
+            _ => return Err(NetpbmError::BadMagic),
+        };
+        let mut pos = 2;
+        let width = header_number(bytes, &mut pos)?;
+        let height = header_number(bytes, &mut pos)?;
+        let max = match format {
+            NetpbmFormat::Pbm => 1,
+            NetpbmFormat::Pgm => header_number(bytes, &mut pos)?,
+        };
+        if width == 0 || height == 0 || max == 0 {
+            return Err(NetpbmError::BadHeader);
+        }
+        // Exactly one whitespace character separates the header from the pixel data.
+        match bytes.get(pos) {
+            Some(c) if c.is_ascii_whitespace() => pos += 1,
+            _ => return Err(NetpbmError::Truncated),
+        }
+        let image = Netpbm {
+            format: format,
+            width: width,
+            height: height,
+            max: max,
+            data: &bytes[pos..],
+        };
+        if image.data.len() < image.row_bytes() * height as usize {
+            return Err(NetpbmError::Truncated);
+        }
+        Ok(image)
+    }
+
+    /// The format of the image.
+    pub fn format(&self) -> NetpbmFormat {
+        self.format
+    }
+
+    /// The width of the image in pixels.
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// The height of the image in pixels.
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// An iterator of the image's pixels as 4-bit gray levels, in left-to-right, top-to-bottom
+    /// order, suitable for `Region::draw`. Gray values are scaled to 4 bits with rounding, and
+    /// bitmap pixels become 15 for white and 0 for black.
+    pub fn pixels(&self) -> NetpbmPixels<'a> {
+        NetpbmPixels {
+            image: *self,
+            x: 0,
+            y: 0,
+        }
+    }
+
+    /// The number of bytes in one row of pixel data.
+    fn row_bytes(&self) -> usize {
+        let width = self.width as usize;
+        match self.format {
+            NetpbmFormat::Pbm => width.div_ceil(8),
+            NetpbmFormat::Pgm if self.max > 255 => width * 2,
+            NetpbmFormat::Pgm => width,
+        }
+    }
+
+    fn pixel(&self, x: u16, y: u16) -> u8 {
+        let row = &self.data[y as usize * self.row_bytes()..];
+        let x = x as usize;
+        match self.format {
+            NetpbmFormat::Pbm if row[x / 8] & (0x80 >> (x % 8)) != 0 => 0,
+            NetpbmFormat::Pbm => 15,
+            NetpbmFormat::Pgm if self.max > 255 => {
+                let value = (row[2 * x] as u16) << 8 | row[2 * x + 1] as u16;
+                scale_to_gray4(value, self.max)
+            }
+            NetpbmFormat::Pgm => scale_to_gray4(row[x] as u16, self.max),
+        }
+    }
+}
+
+/// An iterator of the pixels of a `Netpbm` image as 4-bit gray levels.
+pub struct NetpbmPixels<'a> {
+    image: Netpbm<'a>,
+    x: u16,
+    y: u16,
+}
+
+impl<'a> Iterator for NetpbmPixels<'a> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.y >= self.image.height {
+            return None;
+        }
+        let pixel = self.image.pixel(self.x, self.y);
+        self.x += 1;
+        if self.x >= self.image.width {
+            self.x = 0;
+            self.y += 1;
+        }
+        Some(pixel)
+    }
+}
+
+/// Skip whitespace and comments in a header starting at `pos`, then parse a decimal number.
+fn header_number(bytes: &[u8], pos: &mut usize) -> Result<u16, NetpbmError> {
+    loop {
+        match bytes.get(*pos) {
+            Some(c) if c.is_ascii_whitespace() => *pos += 1,
+            Some(b'#') => {
+                while !matches!(bytes.get(*pos), Some(b'\n') | Some(b'\r') | None) {
+                    *pos += 1;
+                }
+            }
+            _ => break,
+        }
+    }
+    let start = *pos;
+    let mut value: u32 = 0;
+    while let Some(c) = bytes.get(*pos).filter(|c| c.is_ascii_digit()) {
+        value = value * 10 + (c - b'0') as u32;
+        if value > u16::MAX as u32 {
+            return Err(NetpbmError::BadHeader);
+        }
+        *pos += 1;
+    }
+    if *pos == start {
+        return Err(NetpbmError::BadHeader);
+    }
+    Ok(value as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    #[test]
+    fn pgm() {
+        let image = Netpbm::parse(b"P5\n# comment\n3 2\n255\n\x00\x80\xFF\x11\x22\x33").unwrap();
+        assert_eq!(image.format(), NetpbmFormat::Pgm);
+        assert_eq!((image.width(), image.height()), (3, 2));
+        let pixels = image.pixels().collect::<Vec<_>>();
+        assert_eq!(pixels, vec![0, 8, 15, 1, 2, 3]);
+    }
+
+    #[test]
+    fn pgm_16_bit() {
+        let image = Netpbm::parse(b"P5 2 1 1000 \x00\x00\x03\xE8").unwrap();
+        assert_eq!(image.pixels().collect::<Vec<_>>(), vec![0, 15]);
+    }
+
+    #[test]
+    fn pbm() {
+        // Rows are padded to whole bytes.
+        let image = Netpbm::parse(b"P4\n10 2\n\xA0\x40\xFF\xC0").unwrap();
+        #[rustfmt::skip]
+        assert_eq!(image.pixels().collect::<Vec<_>>(), vec![
+            0, 15, 0, 15, 15, 15, 15, 15, 15, 0,
+            0,  0, 0,  0,  0,  0,  0,  0,  0, 0,
+        ]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Netpbm::parse(b"P2 1 1 255 ").err(),
+            Some(NetpbmError::BadMagic)
+        );
+        assert_eq!(
+            Netpbm::parse(b"P5 1 x 255 ").err(),
+            Some(NetpbmError::BadHeader)
+        );
+        assert_eq!(
+            Netpbm::parse(b"P5 0 1 255 ").err(),
+            Some(NetpbmError::BadHeader)
+        );
+        assert_eq!(
+            Netpbm::parse(b"P5 2 2 255 \x00\x00\x00").err(),
+            Some(NetpbmError::Truncated)
+        );
+        assert_eq!(Netpbm::parse(b"P4 1 1").err(), Some(NetpbmError::Truncated));
+    }
+}
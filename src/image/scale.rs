@@ -0,0 +1,74 @@
+//! Nearest-neighbor scaling of pixel streams, for chunky retro-style graphics drawn from small
+//! sources without storing a scaled copy.
+
+/// Scale an iterator of unpacked pixels, in left-to-right, top-to-bottom order, up by 2 in both
+/// directions. Each pixel is repeated horizontally, and each row is repeated from a line buffer
+/// provided by the caller.
+///
+/// The source image width is the length of the line buffer passed to `new`; the output is twice
+/// as wide and twice as tall.
+pub struct PixelDouble<'b, I> {
+    iter: I,
+    row: &'b mut [u8],
+    /// The position within the output row, and whether the row being output is the repeat.
+    x: usize,
+    repeat: bool,
+}
+
+impl<'b, I> PixelDouble<'b, I>
+where
+    I: Iterator<Item = u8>,
+{
+    /// Scale `iter` as an image as wide as `row`, using `row` to hold each source row while it is
+    /// output the second time.
+    pub fn new(iter: I, row: &'b mut [u8]) -> Self {
+        PixelDouble {
+            iter: iter,
+            row: row,
+            x: 0,
+            repeat: false,
+        }
+    }
+}
+
+impl<'b, I> Iterator for PixelDouble<'b, I>
+where
+    I: Iterator<Item = u8>,
+{
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.row.is_empty() {
+            return None;
+        }
+        let src = self.x / 2;
+        if !self.repeat && self.x.is_multiple_of(2) {
+            self.row[src] = self.iter.next()?;
+        }
+        self.x += 1;
+        if self.x == self.row.len() * 2 {
+            self.x = 0;
+            self.repeat = !self.repeat;
+        }
+        Some(self.row[src])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    #[test]
+    fn double() {
+        let mut row = [0; 3];
+        let pixels = PixelDouble::new([1, 2, 3, 4, 5, 6].iter().cloned(), &mut row);
+        #[rustfmt::skip]
+        assert_eq!(pixels.collect::<Vec<_>>(), vec![
+            1, 1, 2, 2, 3, 3,
+            1, 1, 2, 2, 3, 3,
+            4, 4, 5, 5, 6, 6,
+            4, 4, 5, 5, 6, 6,
+        ]);
+    }
+}
@@ -0,0 +1,249 @@
+//! Mirroring and rotation of images, so that assets authored in one orientation can be drawn in
+//! another without converting them on the host.
+//!
+//! Rotating by 90 degrees or flipping vertically reads the source in a different order than it is
+//! stored, which cannot be done with a stream that may only be read once without buffering the
+//! whole image. So `Transformed` works on images held in memory, such as sprites in flash, and
+//! needs no buffer at all. Mirroring horizontally only reorders pixels within each row, so
+//! `MirrorRows` can do it for any stream with a single row buffer.
+
+use crate::sprite::Sprite;
+
+/// A change of orientation. Rotations are clockwise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transform {
+    /// Leave the image as it is.
+    Identity,
+    /// Swap left and right.
+    MirrorHorizontal,
+    /// Swap top and bottom.
+    MirrorVertical,
+    /// Rotate by 90 degrees clockwise.
+    Rotate90,
+    /// Rotate by 180 degrees.
+    Rotate180,
+    /// Rotate by 270 degrees clockwise, i.e. 90 degrees counterclockwise.
+    Rotate270,
+}
+
+/// How the source pixels are stored.
+#[derive(Clone, Copy, Debug)]
+enum Source<'a> {
+    /// One pixel per byte.
+    Unpacked(&'a [u8]),
+    /// Two pixels per byte with the leftmost in the upper nibble, each row starting on a new byte.
+    Packed(&'a [u8]),
+}
+
+/// An iterator of the unpacked pixels of an in-memory image after applying a `Transform`, in
+/// left-to-right, top-to-bottom order, suitable for `Region::draw`.
+pub struct Transformed<'a> {
+    source: Source<'a>,
+    transform: Transform,
+    /// The size of the source image.
+    width: u16,
+    height: u16,
+    /// The position of the next pixel in the output image.
+    x: u16,
+    y: u16,
+}
+
+impl<'a> Transformed<'a> {
+    /// Transform an image `width` by `height` pixels stored with one pixel per byte.
+    ///
+    /// Panics if `data` is too short for the given size.
+    pub fn unpacked(data: &'a [u8], width: u16, height: u16, transform: Transform) -> Self {
+        assert!(
+            data.len() >= width as usize * height as usize,
+            "Image data is too short for its size."
+        );
+        Self::new(Source::Unpacked(data), width, height, transform)
+    }
+
+    /// Transform an image `width` by `height` pixels stored as packed pixels, in the same format as
+    /// `Region::draw_packed` and `Sprite`.
+    ///
+    /// Panics if `data` is too short for the given size.
+    pub fn packed(data: &'a [u8], width: u16, height: u16, transform: Transform) -> Self {
+        assert!(
+            data.len() >= (width as usize).div_ceil(2) * height as usize,
+            "Image data is too short for its size."
+        );
+        Self::new(Source::Packed(data), width, height, transform)
+    }
+
+    /// Transform a sprite.
+    pub fn sprite(sprite: &Sprite<'a>, transform: Transform) -> Self {
+        Self::new(
+            Source::Packed(sprite.data()),
+            sprite.width(),
+            sprite.height(),
+            transform,
+        )
+    }
+
+    fn new(source: Source<'a>, width: u16, height: u16, transform: Transform) -> Self {
+        Transformed {
+            source: source,
+            transform: transform,
+            width: width,
+            height: height,
+            x: 0,
+            y: 0,
+        }
+    }
+
+    /// The width of the output image, which is the height of the source for quarter turns.
+    pub fn width(&self) -> u16 {
+        match self.transform {
+            Transform::Rotate90 | Transform::Rotate270 => self.height,
+            _ => self.width,
+        }
+    }
+
+    /// The height of the output image, which is the width of the source for quarter turns.
+    pub fn height(&self) -> u16 {
+        match self.transform {
+            Transform::Rotate90 | Transform::Rotate270 => self.width,
+            _ => self.height,
+        }
+    }
+
+    /// The source pixel shown at `x`, `y` in the output.
+    fn source_pixel(&self, x: u16, y: u16) -> u8 {
+        let (w, h) = (self.width, self.height);
+        let (sx, sy) = match self.transform {
+            Transform::Identity => (x, y),
+            Transform::MirrorHorizontal => (w - 1 - x, y),
+            Transform::MirrorVertical => (x, h - 1 - y),
+            Transform::Rotate90 => (y, h - 1 - x),
+            Transform::Rotate180 => (w - 1 - x, h - 1 - y),
+            Transform::Rotate270 => (w - 1 - y, x),
+        };
+        let (sx, sy) = (sx as usize, sy as usize);
+        match self.source {
+            Source::Unpacked(data) => data[sy * w as usize + sx],
+            Source::Packed(data) => {
+                let byte = data[sy * (w as usize).div_ceil(2) + sx / 2];
+                if sx % 2 == 0 {
+                    byte >> 4
+                } else {
+                    byte & 0x0F
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Transformed<'a> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        let (width, height) = (self.width(), self.height());
+        if self.y >= height || width == 0 {
+            return None;
+        }
+        let pixel = self.source_pixel(self.x, self.y);
+        self.x += 1;
+        if self.x >= width {
+            self.x = 0;
+            self.y += 1;
+        }
+        Some(pixel)
+    }
+}
+
+/// Mirror an iterator of unpacked pixels horizontally, using a row buffer provided by the caller.
+///
+/// The source image width is the length of the row buffer passed to `new`. Each row is read
+/// completely into the buffer before it is output in reverse.
+pub struct MirrorRows<'b, I> {
+    iter: I,
+    row: &'b mut [u8],
+    /// The number of pixels of the buffered row still to be output.
+    left: usize,
+}
+
+impl<'b, I> MirrorRows<'b, I>
+where
+    I: Iterator<Item = u8>,
+{
+    /// Mirror `iter` as an image as wide as `row`, using `row` to hold each source row.
+    pub fn new(iter: I, row: &'b mut [u8]) -> Self {
+        MirrorRows {
+            iter: iter,
+            row: row,
+            left: 0,
+        }
+    }
+}
+
+impl<'b, I> Iterator for MirrorRows<'b, I>
+where
+    I: Iterator<Item = u8>,
+{
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.left == 0 {
+            for pixel in self.row.iter_mut() {
+                *pixel = self.iter.next()?;
+            }
+            self.left = self.row.len();
+        }
+        if self.left == 0 {
+            return None;
+        }
+        self.left -= 1;
+        Some(self.row[self.left])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    #[rustfmt::skip]
+    const UNPACKED: [u8; 6] = [
+        1, 2, 3,
+        4, 5, 6,
+    ];
+    #[rustfmt::skip]
+    const PACKED: [u8; 4] = [
+        0x12, 0x30,
+        0x45, 0x60,
+    ];
+
+    fn check(transform: Transform, size: (u16, u16), expected: &[u8]) {
+        let unpacked = Transformed::unpacked(&UNPACKED, 3, 2, transform);
+        assert_eq!((unpacked.width(), unpacked.height()), size);
+        assert_eq!(unpacked.collect::<Vec<_>>(), expected);
+        let packed = Transformed::packed(&PACKED, 3, 2, transform);
+        assert_eq!(packed.collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn transforms() {
+        check(Transform::Identity, (3, 2), &[1, 2, 3, 4, 5, 6]);
+        check(Transform::MirrorHorizontal, (3, 2), &[3, 2, 1, 6, 5, 4]);
+        check(Transform::MirrorVertical, (3, 2), &[4, 5, 6, 1, 2, 3]);
+        check(Transform::Rotate90, (2, 3), &[4, 1, 5, 2, 6, 3]);
+        check(Transform::Rotate180, (3, 2), &[6, 5, 4, 3, 2, 1]);
+        check(Transform::Rotate270, (2, 3), &[3, 6, 2, 5, 1, 4]);
+    }
+
+    #[test]
+    fn sprite() {
+        let sprite = Sprite::new(3, 2, &PACKED);
+        let pixels = Transformed::sprite(&sprite, Transform::Rotate90).collect::<Vec<_>>();
+        assert_eq!(pixels, vec![4, 1, 5, 2, 6, 3]);
+    }
+
+    #[test]
+    fn mirror_rows() {
+        let mut row = [0; 3];
+        let pixels = MirrorRows::new(UNPACKED.iter().cloned(), &mut row).collect::<Vec<_>>();
+        assert_eq!(pixels, vec![3, 2, 1, 6, 5, 4]);
+    }
+}
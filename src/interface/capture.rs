@@ -0,0 +1,155 @@
+//! An interface which captures the command stream to any `std::io::Write` instead of sending it
+//! to hardware, for recording UI sessions on a host and debugging them offline, or for generating
+//! golden vectors for hardware-in-the-loop tests.
+
+use std::io;
+
+use super::DisplayInterface;
+#[cfg(feature = "nb")]
+use nb;
+
+/// The serialization used by a `CaptureInterface`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CaptureFormat {
+    /// Each transfer is a record starting with a tag byte. A command is `0x00` followed by the
+    /// command word. Data is `0x01`, followed by the number of data words as a 16-bit little-endian
+    /// value, followed by the data words. Buffers longer than 65535 words are split into several
+    /// records.
+    Binary,
+    /// Each transfer is a line of text: `C` followed by the command word, or `D` followed by the
+    /// data words, all as space-separated two-digit uppercase hex.
+    Text,
+    /// The exact stream of words on the bus, each preceded by a marker byte giving the level of
+    /// the D/C pin: `0x00` for a command word or `0x01` for a data word. Unlike the other formats
+    /// this does not record how the stream was split into transfers, so the same image sent with
+    /// `send_data` or word by word with `send_data_async` captures identically, which suits
+    /// comparison against logic analyzer traces or golden vectors.
+    Marked,
+}
+
+/// A `DisplayInterface` which writes every command and data transfer to `W` in a `CaptureFormat`.
+///
+/// Data words sent with `send_data_async` are recorded one transfer per word, since the capture
+/// has no way of knowing where the stream of words ends.
+pub struct CaptureInterface<W> {
+    writer: W,
+    format: CaptureFormat,
+}
+
+impl<W> CaptureInterface<W>
+where
+    W: io::Write,
+{
+    /// Create a new capture interface writing to `writer` in `format`.
+    pub fn new(writer: W, format: CaptureFormat) -> Self {
+        Self {
+            writer: writer,
+            format: format,
+        }
+    }
+
+    /// Consume the capture interface, returning the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_record(&mut self, tag: u8, words: &[u8]) -> io::Result<()> {
+        match self.format {
+            CaptureFormat::Binary if tag == 0 => self.writer.write_all(&[0x00, words[0]]),
+            CaptureFormat::Binary => {
+                for chunk in words.chunks(u16::MAX as usize) {
+                    let len = chunk.len() as u16;
+                    self.writer
+                        .write_all(&[0x01, len as u8, (len >> 8) as u8])?;
+                    self.writer.write_all(chunk)?;
+                }
+                Ok(())
+            }
+            CaptureFormat::Marked => {
+                let mut marked = [tag; 64];
+                for chunk in words.chunks(marked.len() / 2) {
+                    for (i, word) in chunk.iter().enumerate() {
+                        marked[2 * i + 1] = *word;
+                    }
+                    self.writer.write_all(&marked[..2 * chunk.len()])?;
+                }
+                Ok(())
+            }
+            CaptureFormat::Text => {
+                self.writer.write_all(if tag == 0 { b"C" } else { b"D" })?;
+                for word in words {
+                    write!(self.writer, " {:02X}", word)?;
+                }
+                self.writer.write_all(b"\n")
+            }
+        }
+    }
+}
+
+impl<W> DisplayInterface for CaptureInterface<W>
+where
+    W: io::Write,
+{
+    type Error = io::Error;
+
+    fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+        self.write_record(0, &[cmd])
+    }
+
+    fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.write_record(1, buf)
+    }
+
+    #[cfg(feature = "nb")]
+    fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.write_record(1, &[word]).map_err(nb::Error::Other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{BufCommand, Command};
+    use std::vec::Vec;
+
+    #[test]
+    fn capture_binary() {
+        let mut ci = CaptureInterface::new(Vec::new(), CaptureFormat::Binary);
+        Command::SetColumnAddress(1, 2).send(&mut ci).unwrap();
+        BufCommand::WriteImageData(&[]).send(&mut ci).unwrap();
+        #[cfg(feature = "nb")]
+        ci.send_data_async(0xAB).unwrap();
+        #[cfg(not(feature = "nb"))]
+        ci.send_data(&[0xAB]).unwrap();
+        assert_eq!(
+            ci.into_inner(),
+            vec![0x00, 0x15, 0x01, 2, 0, 1, 2, 0x00, 0x5C, 0x01, 1, 0, 0xAB]
+        );
+    }
+
+    #[test]
+    fn capture_text() {
+        let mut ci = CaptureInterface::new(Vec::new(), CaptureFormat::Text);
+        Command::SetColumnAddress(1, 42).send(&mut ci).unwrap();
+        Command::SetSleepMode(false).send(&mut ci).unwrap();
+        assert_eq!(&ci.into_inner()[..], &b"C 15\nD 01 2A\nC AF\n"[..]);
+    }
+
+    #[test]
+    fn capture_marked() {
+        let mut ci = CaptureInterface::new(Vec::new(), CaptureFormat::Marked);
+        Command::SetColumnAddress(1, 2).send(&mut ci).unwrap();
+        BufCommand::WriteImageData(&[]).send(&mut ci).unwrap();
+        ci.send_data(&[0xAB; 40]).unwrap();
+        #[cfg(feature = "nb")]
+        ci.send_data_async(0xCD).unwrap();
+        #[cfg(not(feature = "nb"))]
+        ci.send_data(&[0xCD]).unwrap();
+        let mut expected = vec![0x00, 0x15, 0x01, 1, 0x01, 2, 0x00, 0x5C];
+        for _ in 0..40 {
+            expected.extend_from_slice(&[0x01, 0xAB]);
+        }
+        expected.extend_from_slice(&[0x01, 0xCD]);
+        assert_eq!(ci.into_inner(), expected);
+    }
+}
@@ -0,0 +1,135 @@
+//! A wrapper interface which limits the size of each data transfer.
+//!
+//! Some buses cannot send arbitrarily large buffers in one go: Linux spidev refuses transfers
+//! larger than its `bufsiz` parameter (4096 bytes by default), and on microcontrollers the best
+//! transfer size may depend on FIFO or DMA limits. Wrap the real interface in a `ChunkedInterface`
+//! to split every `send_data` call into transfers of at most a given number of bytes.
+
+use super::DisplayInterface;
+#[cfg(feature = "nb")]
+use nb;
+
+/// A `DisplayInterface` which passes everything through to an inner interface, splitting data
+/// into transfers of at most `max_chunk` bytes.
+pub struct ChunkedInterface<DI> {
+    iface: DI,
+    max_chunk: usize,
+}
+
+impl<DI> ChunkedInterface<DI>
+where
+    DI: DisplayInterface,
+{
+    /// Wrap `iface`, sending data in transfers of at most `max_chunk` bytes.
+    ///
+    /// # Panics
+    ///
+    /// If `max_chunk` is 0.
+    pub fn new(iface: DI, max_chunk: usize) -> Self {
+        assert!(max_chunk > 0);
+        Self {
+            iface: iface,
+            max_chunk: max_chunk,
+        }
+    }
+
+    /// The maximum number of bytes sent in one transfer.
+    pub fn max_chunk(&self) -> usize {
+        self.max_chunk
+    }
+
+    /// Change the maximum number of bytes sent in one transfer.
+    ///
+    /// # Panics
+    ///
+    /// If `max_chunk` is 0.
+    pub fn set_max_chunk(&mut self, max_chunk: usize) {
+        assert!(max_chunk > 0);
+        self.max_chunk = max_chunk;
+    }
+
+    /// Consume the wrapper, returning the inner interface.
+    pub fn into_inner(self) -> DI {
+        self.iface
+    }
+}
+
+impl<DI> DisplayInterface for ChunkedInterface<DI>
+where
+    DI: DisplayInterface,
+{
+    type Error = DI::Error;
+
+    #[cfg(feature = "nb")]
+    const NON_BLOCKING: bool = DI::NON_BLOCKING;
+
+    fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+        self.iface.send_command(cmd)
+    }
+
+    fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        buf.chunks(self.max_chunk)
+            .try_for_each(|chunk| self.iface.send_data(chunk))
+    }
+
+    #[cfg(feature = "nb")]
+    fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.iface.send_data_async(word)
+    }
+
+    fn begin_transaction(&mut self) -> Result<(), Self::Error> {
+        self.iface.begin_transaction()
+    }
+
+    fn end_transaction(&mut self) -> Result<(), Self::Error> {
+        self.iface.end_transaction()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::{Display, PixelCoord as Px};
+    use crate::interface::mock::{MockInterface, Sent};
+
+    /// An interface recording the size of each data transfer.
+    struct Sizes(Vec<usize>);
+
+    impl DisplayInterface for Sizes {
+        type Error = core::convert::Infallible;
+        fn send_command(&mut self, _cmd: u8) -> Result<(), Self::Error> {
+            Ok(())
+        }
+        fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+            self.0.push(buf.len());
+            Ok(())
+        }
+        #[cfg(feature = "nb")]
+        fn send_data_async(&mut self, _word: u8) -> nb::Result<(), Self::Error> {
+            self.0.push(1);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn split_transfers() {
+        let mut iface = ChunkedInterface::new(Sizes(Vec::new()), 3);
+        iface.send_data(&[0; 8]).unwrap();
+        iface.set_max_chunk(4);
+        iface.send_data(&[0; 8]).unwrap();
+        assert_eq!(iface.into_inner().0, [3, 3, 2, 4, 4]);
+
+        let di = MockInterface::new();
+        let mut disp = Display::new(ChunkedInterface::new(di.split(), 2), Px(128, 64), Px(0, 0));
+        let rows = [[1u8, 2, 3, 4], [5, 6, 7, 8]];
+        disp.region(Px(0, 0), Px(8, 2))
+            .unwrap()
+            .draw_rows(rows.iter().map(|r| &r[..]))
+            .unwrap();
+        #[rustfmt::skip]
+        assert_eq!(di.sent()[5..], [
+            Sent::Data(vec![1, 2]), Sent::Data(vec![3, 4]),
+            Sent::Data(vec![5, 6]), Sent::Data(vec![7, 8]),
+        ]);
+    }
+}
@@ -0,0 +1,206 @@
+//! Adapters between `DisplayInterface` and the `WriteOnlyDataCommand` trait of the
+//! `display-interface` crate, which many display drivers and bus implementations in the ecosystem
+//! use.
+//!
+//! - `DataCommandInterface` lets an existing `WriteOnlyDataCommand` implementation, such as an
+//!   `SPIInterface` from `display-interface-spi`, drive a `Display`.
+//! - `DataCommandAdapter` lets one of this crate's interfaces be used by code which expects a
+//!   `WriteOnlyDataCommand`.
+//!
+//! The SSD1322 takes command arguments as data words, so each word passed to
+//! `WriteOnlyDataCommand::send_commands` is treated as a separate command word.
+//!
+//! Available with the `display-interface` feature.
+
+use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
+
+use super::DisplayInterface;
+#[cfg(feature = "nb")]
+use nb;
+
+/// A `DisplayInterface` sending through a `WriteOnlyDataCommand` implementation.
+pub struct DataCommandInterface<W> {
+    iface: W,
+}
+
+impl<W> DataCommandInterface<W>
+where
+    W: WriteOnlyDataCommand,
+{
+    /// Send through `iface`.
+    pub fn new(iface: W) -> Self {
+        Self { iface: iface }
+    }
+
+    /// Consume the adapter, returning the inner interface.
+    pub fn into_inner(self) -> W {
+        self.iface
+    }
+}
+
+impl<W> DisplayInterface for DataCommandInterface<W>
+where
+    W: WriteOnlyDataCommand,
+{
+    type Error = DisplayError;
+
+    fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+        self.iface.send_commands(DataFormat::U8(&[cmd]))
+    }
+
+    fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.iface.send_data(DataFormat::U8(buf))
+    }
+
+    /// `WriteOnlyDataCommand` has no non-blocking operations, so this sends the word immediately
+    /// and never returns `WouldBlock`.
+    #[cfg(feature = "nb")]
+    fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.iface
+            .send_data(DataFormat::U8(&[word]))
+            .map_err(nb::Error::Other)
+    }
+}
+
+/// A `WriteOnlyDataCommand` implementation sending through a `DisplayInterface`. Errors from the
+/// interface are reported as `DisplayError::BusWriteError`.
+pub struct DataCommandAdapter<DI> {
+    iface: DI,
+}
+
+impl<DI> DataCommandAdapter<DI>
+where
+    DI: DisplayInterface,
+{
+    /// Send through `iface`.
+    pub fn new(iface: DI) -> Self {
+        Self { iface: iface }
+    }
+
+    /// Consume the adapter, returning the inner interface.
+    pub fn into_inner(self) -> DI {
+        self.iface
+    }
+
+    /// Send the words from `words`, gathered into chunks so that each is a single transfer.
+    fn send_words<I>(&mut self, words: I) -> Result<(), DisplayError>
+    where
+        I: Iterator<Item = u8>,
+    {
+        let mut chunk = [0u8; 32];
+        let mut len = 0;
+        for word in words {
+            chunk[len] = word;
+            len += 1;
+            if len == chunk.len() {
+                self.send_chunk(&chunk)?;
+                len = 0;
+            }
+        }
+        self.send_chunk(&chunk[..len])
+    }
+
+    fn send_chunk(&mut self, chunk: &[u8]) -> Result<(), DisplayError> {
+        if chunk.is_empty() {
+            return Ok(());
+        }
+        self.iface
+            .send_data(chunk)
+            .map_err(|_| DisplayError::BusWriteError)
+    }
+}
+
+impl<DI> WriteOnlyDataCommand for DataCommandAdapter<DI>
+where
+    DI: DisplayInterface,
+{
+    fn send_commands(&mut self, cmd: DataFormat<'_>) -> Result<(), DisplayError> {
+        let cmds = match cmd {
+            DataFormat::U8(cmds) => cmds,
+            _ => return Err(DisplayError::DataFormatNotImplemented),
+        };
+        for cmd in cmds {
+            self.iface
+                .send_command(*cmd)
+                .map_err(|_| DisplayError::BusWriteError)?;
+        }
+        Ok(())
+    }
+
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        match buf {
+            DataFormat::U8(buf) => self.send_chunk(buf),
+            DataFormat::U8Iter(iter) => self.send_words(iter),
+            DataFormat::U16(buf) => self.send_words(buf.iter().flat_map(|w| w.to_ne_bytes())),
+            DataFormat::U16BE(buf) => self.send_words(buf.iter().flat_map(|w| w.to_be_bytes())),
+            DataFormat::U16LE(buf) => self.send_words(buf.iter().flat_map(|w| w.to_le_bytes())),
+            DataFormat::U16BEIter(iter) => self.send_words(iter.flat_map(|w| w.to_be_bytes())),
+            DataFormat::U16LEIter(iter) => self.send_words(iter.flat_map(|w| w.to_le_bytes())),
+            _ => Err(DisplayError::DataFormatNotImplemented),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::{Display, PixelCoord as Px};
+    use crate::interface::mock::{MockInterface, Sent};
+
+    #[test]
+    fn round_trip() {
+        // A display driving a `DisplayInterface` wrapped as a `WriteOnlyDataCommand` and back.
+        let di = MockInterface::new();
+        let wrapped = DataCommandInterface::new(DataCommandAdapter::new(di.split()));
+        let mut disp = Display::new(wrapped, Px(128, 64), Px(0, 0));
+        disp.contrast(3).unwrap();
+        disp.region(Px(0, 0), Px(4, 1))
+            .unwrap()
+            .draw_packed([0x12, 0x34].iter().cloned())
+            .unwrap();
+        let sent = di.sent();
+        #[rustfmt::skip]
+        assert_eq!(sent[..7], [
+            Sent::Cmd(0xC7), Sent::Data(vec![3]),
+            Sent::Cmd(0x15), Sent::Data(vec![0, 0]),
+            Sent::Cmd(0x75), Sent::Data(vec![0, 0]),
+            Sent::Cmd(0x5C),
+        ]);
+        // With `nb`, each image data word is a separate transfer.
+        let image: Vec<u8> = sent[7..]
+            .iter()
+            .flat_map(|s| match s {
+                Sent::Data(d) => d.clone(),
+                Sent::Cmd(_) => panic!("unexpected command"),
+            })
+            .collect();
+        assert_eq!(image, [0x12, 0x34]);
+    }
+
+    #[test]
+    fn data_formats() {
+        let mut di = MockInterface::new();
+        let mut adapter = DataCommandAdapter::new(di.split());
+        adapter
+            .send_commands(DataFormat::U8(&[0xAF, 0xA6]))
+            .unwrap();
+        adapter
+            .send_data(DataFormat::U16BE(&mut [0x1234, 0x5678]))
+            .unwrap();
+        adapter
+            .send_data(DataFormat::U16LEIter(&mut [0x1234].iter().cloned()))
+            .unwrap();
+        assert!(adapter.send_commands(DataFormat::U16(&[0x5C])).is_err());
+        #[rustfmt::skip]
+        assert_eq!(di.sent(), [
+            Sent::Cmd(0xAF), Sent::Cmd(0xA6),
+            Sent::Data(vec![0x12, 0x34, 0x56, 0x78]),
+            Sent::Data(vec![0x34, 0x12]),
+        ]);
+
+        di.clear();
+        let mut long = 0..40u8;
+        adapter.send_data(DataFormat::U8Iter(&mut long)).unwrap();
+        assert_eq!(di.sent().len(), 2);
+    }
+}
@@ -0,0 +1,184 @@
+//! A wrapper interface which computes a CRC-32 of the image data of each frame sent through it, so
+//! that hardware-in-the-loop tests and field diagnostics can confirm that the expected image went
+//! out over the bus. Wrap the real interface in a `CrcInterface` before handing it to
+//! `Display::new`, and read the checksum of the latest frame back with `Display::frame_crc`.
+//!
+//! As for `StatsInterface`, a frame is the image data of one region draw, from the
+//! `WriteImageData` command to the next command. Its checksum is the same as `crc::crc32` of the
+//! packed image data drawn, so the expected value can be computed from the source image. Chips
+//! which have no `WriteImageData` command, such as the SSD1327, give no frames.
+
+use super::DisplayInterface;
+use crate::crc::Crc32;
+#[cfg(feature = "nb")]
+use nb;
+
+/// The command word for `BufCommand::WriteImageData`, which marks the start of each frame.
+const WRITE_IMAGE_DATA: u8 = 0x5C;
+
+/// The checksum of a frame sent through a `CrcInterface`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameCrc {
+    /// The number of frames sent before this one, since the interface was created or
+    /// `reset_frame_crc` was called.
+    pub sequence: u32,
+    /// The number of bytes of image data in the frame.
+    pub bytes: u32,
+    /// The CRC-32 of the frame's image data.
+    pub crc: u32,
+}
+
+/// A `DisplayInterface` which passes everything through to an inner interface while computing the
+/// CRC-32 of the image data of each frame.
+pub struct CrcInterface<DI> {
+    iface: DI,
+    /// The number of frames started.
+    frames: u32,
+    /// The checksum and length of the latest frame, if any.
+    frame: Option<(Crc32, u32)>,
+    /// Whether data words are image data for the latest frame, rather than command arguments.
+    in_frame: bool,
+}
+
+impl<DI> CrcInterface<DI>
+where
+    DI: DisplayInterface,
+{
+    /// Wrap `iface`.
+    pub fn new(iface: DI) -> Self {
+        Self {
+            iface: iface,
+            frames: 0,
+            frame: None,
+            in_frame: false,
+        }
+    }
+
+    /// The checksum of the latest frame, including the image data sent so far if it is still being
+    /// drawn, or `None` if no frame has been sent.
+    pub fn frame_crc(&self) -> Option<FrameCrc> {
+        self.frame.map(|(crc, bytes)| FrameCrc {
+            sequence: self.frames - 1,
+            bytes: bytes,
+            crc: crc.finish(),
+        })
+    }
+
+    /// Forget the latest frame and restart the frame count from 0.
+    pub fn reset_frame_crc(&mut self) {
+        self.frames = 0;
+        self.frame = None;
+        self.in_frame = false;
+    }
+
+    /// Consume the wrapper, returning the inner interface.
+    pub fn into_inner(self) -> DI {
+        self.iface
+    }
+
+    fn note_data(&mut self, buf: &[u8]) {
+        if let (true, Some((crc, bytes))) = (self.in_frame, self.frame.as_mut()) {
+            buf.iter().for_each(|&byte| crc.update(byte));
+            *bytes += buf.len() as u32;
+        }
+    }
+}
+
+impl<DI> DisplayInterface for CrcInterface<DI>
+where
+    DI: DisplayInterface,
+{
+    type Error = DI::Error;
+
+    #[cfg(feature = "nb")]
+    const NON_BLOCKING: bool = DI::NON_BLOCKING;
+
+    fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+        self.in_frame = false;
+        self.iface.send_command(cmd)?;
+        if cmd == WRITE_IMAGE_DATA {
+            self.frames = self.frames.wrapping_add(1);
+            self.frame = Some((Crc32::new(), 0));
+            self.in_frame = true;
+        }
+        Ok(())
+    }
+
+    fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.iface.send_data(buf)?;
+        self.note_data(buf);
+        Ok(())
+    }
+
+    #[cfg(feature = "nb")]
+    fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.iface.send_data_async(word)?;
+        self.note_data(&[word]);
+        Ok(())
+    }
+
+    fn begin_transaction(&mut self) -> Result<(), Self::Error> {
+        self.iface.begin_transaction()
+    }
+
+    fn end_transaction(&mut self) -> Result<(), Self::Error> {
+        self.iface.end_transaction()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{ComLayout, ComScanDirection};
+    use crate::config::Config;
+    use crate::crc::crc32;
+    use crate::display::{Display, PixelCoord as Px};
+    use crate::interface::mock::MockInterface;
+
+    #[test]
+    fn frame_checksums() {
+        let mut disp = Display::new(
+            CrcInterface::new(MockInterface::new()),
+            Px(128, 64),
+            Px(0, 0),
+        );
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        disp.init(cfg).unwrap();
+        assert_eq!(disp.frame_crc(), None);
+
+        let image = [0x01, 0x23, 0x45, 0x67];
+        disp.region(Px(0, 0), Px(4, 2))
+            .unwrap()
+            .draw_packed(image.iter().cloned())
+            .unwrap();
+        // Commands after the frame, and their arguments, are not part of it.
+        disp.contrast(3).unwrap();
+        assert_eq!(
+            disp.frame_crc(),
+            Some(FrameCrc {
+                sequence: 0,
+                bytes: 4,
+                crc: crc32(image.iter().cloned()),
+            })
+        );
+
+        disp.region(Px(4, 0), Px(8, 1))
+            .unwrap()
+            .draw_packed([0xAB, 0xCD].iter().cloned())
+            .unwrap();
+        let frame = disp.frame_crc().unwrap();
+        assert_eq!(frame.sequence, 1);
+        assert_eq!(frame.crc, crc32([0xAB, 0xCD].iter().cloned()));
+        // Redrawing the same window skips the address setup, but is still a frame of its own.
+        disp.region(Px(4, 0), Px(8, 1))
+            .unwrap()
+            .draw_packed([0x11, 0x22].iter().cloned())
+            .unwrap();
+        let frame = disp.frame_crc().unwrap();
+        assert_eq!((frame.sequence, frame.bytes), (2, 2));
+        assert_eq!(frame.crc, crc32([0x11, 0x22].iter().cloned()));
+
+        disp.reset_frame_crc();
+        assert_eq!(disp.frame_crc(), None);
+    }
+}
@@ -0,0 +1,112 @@
+//! An adapter for an interface shared between thread and interrupt context.
+//!
+//! When the bus to the display is also used from interrupt handlers, every access to it must be
+//! made in a critical section so that an interrupt cannot break into the middle of a transfer. Put
+//! the interface in a `critical_section::Mutex<RefCell<_>>`, for example in a `static`, and hand
+//! the `Display` a `CriticalSectionInterface` referring to it; other code can lock the same mutex
+//! to use the interface in between.
+//!
+//! Each command word and each buffer of data words is sent in its own critical section, so
+//! interrupts are held off for one transfer at a time rather than a whole region draw. This keeps
+//! the bus consistent, but not the chip's state: if more than one context draws to the display,
+//! share the `Display` itself too, for example with `display::shared::SharedDisplay`. Likewise an
+//! inner interface which keeps chip select asserted through a region draw, such as an
+//! `SpiInterface` with a /CS pin, should not share its bus with other devices.
+//!
+//! Available with the `critical-section` feature.
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+
+use super::DisplayInterface;
+#[cfg(feature = "nb")]
+use nb;
+
+/// A `DisplayInterface` which locks a shared inner interface around each transfer. See the module
+/// documentation.
+pub struct CriticalSectionInterface<'a, DI> {
+    iface: &'a Mutex<RefCell<DI>>,
+}
+
+impl<'a, DI> CriticalSectionInterface<'a, DI>
+where
+    DI: DisplayInterface,
+{
+    /// Send through the interface in `iface`.
+    pub fn new(iface: &'a Mutex<RefCell<DI>>) -> Self {
+        Self { iface: iface }
+    }
+
+    fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut DI) -> R,
+    {
+        critical_section::with(|cs| f(&mut self.iface.borrow(cs).borrow_mut()))
+    }
+}
+
+impl<'a, DI> DisplayInterface for CriticalSectionInterface<'a, DI>
+where
+    DI: DisplayInterface,
+{
+    type Error = DI::Error;
+
+    #[cfg(feature = "nb")]
+    const NON_BLOCKING: bool = DI::NON_BLOCKING;
+
+    fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+        self.with(|iface| iface.send_command(cmd))
+    }
+
+    fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.with(|iface| iface.send_data(buf))
+    }
+
+    #[cfg(feature = "nb")]
+    fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.with(|iface| iface.send_data_async(word))
+    }
+
+    fn begin_transaction(&mut self) -> Result<(), Self::Error> {
+        self.with(|iface| iface.begin_transaction())
+    }
+
+    fn end_transaction(&mut self) -> Result<(), Self::Error> {
+        self.with(|iface| iface.end_transaction())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::{Display, PixelCoord as Px};
+    use crate::interface::mock::{MockInterface, Sent};
+
+    #[test]
+    fn pass_through() {
+        let di = MockInterface::new();
+        let shared = Mutex::new(RefCell::new(di.split()));
+        let mut disp = Display::new(
+            CriticalSectionInterface::new(&shared),
+            Px(128, 64),
+            Px(0, 0),
+        );
+        disp.contrast(3).unwrap();
+
+        // Another context uses the interface in between.
+        critical_section::with(|cs| shared.borrow(cs).borrow_mut().send_command(0xE3).unwrap());
+        disp.region(Px(0, 0), Px(4, 1))
+            .unwrap()
+            .draw_packed([0x12, 0x34].iter().cloned())
+            .unwrap();
+        #[rustfmt::skip]
+        assert_eq!(di.sent(), [
+            Sent::Cmd(0xC7), Sent::Data(vec![3]),
+            Sent::Cmd(0xE3),
+            Sent::Cmd(0x15), Sent::Data(vec![0, 0]),
+            Sent::Cmd(0x75), Sent::Data(vec![0, 0]),
+            Sent::Cmd(0x5C), Sent::Data(vec![0x12, 0x34]),
+        ]);
+    }
+}
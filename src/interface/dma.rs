@@ -0,0 +1,326 @@
+//! A `DisplayInterface` which sends data with DMA, filling one buffer while the other is sent.
+//!
+//! DMA APIs differ between HALs, so the interface drives a `DmaChannel`, a small trait which the
+//! application implements on top of its HAL's DMA transfer type. Buffers are passed to the channel
+//! as `&'static mut [u8]`, which satisfies the `embedded-dma` `ReadBuffer` trait that most HAL
+//! transfer APIs require.
+//!
+//! The interface owns two buffers, typically `static` arrays. Data is copied into one while the
+//! other is being sent, and a buffer is handed to the channel when it is full, or when the data
+//! must reach the display: before a command, at the end of a region draw, or after data sent
+//! outside a region draw. The D/C pin is only switched once the channel has finished sending.
+
+use super::DisplayInterface;
+#[cfg(feature = "nb")]
+use nb;
+
+use embedded_hal as hal;
+
+/// A DMA channel able to send a buffer to the display's SPI bus, wrapping a HAL's DMA transfer.
+pub trait DmaChannel {
+    type Error;
+
+    /// Start sending the first `len` bytes of `buf`. The channel keeps the buffer until it is
+    /// returned by `wait`. Only one transfer is started at a time.
+    fn start(&mut self, buf: &'static mut [u8], len: usize) -> Result<(), Self::Error>;
+
+    /// Whether the transfer started by `start` is still in progress.
+    fn is_busy(&self) -> bool;
+
+    /// Block until the transfer started by `start` is complete, including the last word leaving
+    /// the SPI peripheral, and return its buffer.
+    fn wait(&mut self) -> Result<&'static mut [u8], Self::Error>;
+}
+
+/// The pair of buffers a `DmaInterface` alternates between.
+pub type DmaBuffers = (&'static mut [u8], &'static mut [u8]);
+
+/// The union of all errors that may occur on the DMA interface. This consists of variants for the
+/// error types of the D/C GPIO and the DMA channel.
+#[derive(Debug)]
+pub enum DmaInterfaceError<DCE, DMAE> {
+    DCError(DCE),
+    DmaError(DMAE),
+}
+
+/// A double-buffered DMA `DisplayInterface`. See the module documentation.
+pub struct DmaInterface<CH, DC> {
+    channel: CH,
+    dc: DC,
+    /// The buffer being filled, and the number of bytes in it.
+    fill: Option<&'static mut [u8]>,
+    fill_len: usize,
+    /// The other buffer, when it is neither being filled nor sent.
+    spare: Option<&'static mut [u8]>,
+    /// Whether the channel holds a buffer, sending it or finished but not yet waited for.
+    busy: bool,
+    in_transaction: bool,
+}
+
+impl<CH, DC> DmaInterface<CH, DC>
+where
+    CH: DmaChannel,
+    DC: hal::digital::v2::OutputPin,
+{
+    /// Create a DMA interface sending with `channel`, with `dc` the GPIO output pin connected to
+    /// the D/C pin of the SSD1322, and the two buffers to alternate between. Larger buffers mean
+    /// fewer DMA transfers; each must hold at least one byte.
+    pub fn new(channel: CH, dc: DC, buffers: DmaBuffers) -> Self {
+        assert!(!buffers.0.is_empty() && !buffers.1.is_empty());
+        Self {
+            channel: channel,
+            dc: dc,
+            fill: Some(buffers.0),
+            fill_len: 0,
+            spare: Some(buffers.1),
+            busy: false,
+            in_transaction: false,
+        }
+    }
+
+    /// Wait for any data to finish sending, and return the channel, the D/C pin and the buffers.
+    #[allow(clippy::type_complexity)]
+    pub fn release(
+        mut self,
+    ) -> Result<(CH, DC, DmaBuffers), DmaInterfaceError<DC::Error, CH::Error>> {
+        self.quiesce()?;
+        let buffers = (self.fill.take().unwrap(), self.spare.take().unwrap());
+        Ok((self.channel, self.dc, buffers))
+    }
+
+    /// Wait for the transfer in progress, if any, taking back its buffer.
+    fn finish(&mut self) -> Result<&'static mut [u8], CH::Error> {
+        self.busy = false;
+        self.channel.wait()
+    }
+
+    /// Make sure there is a buffer to fill, waiting for one if both are taken.
+    fn fill_buffer(&mut self) -> Result<&mut [u8], CH::Error> {
+        if self.fill.is_none() {
+            self.fill = Some(match self.spare.take() {
+                Some(buf) => buf,
+                None => self.finish()?,
+            });
+        }
+        Ok(self.fill.as_mut().unwrap())
+    }
+
+    /// Hand the buffer being filled to the channel, if it holds any data, once the channel is
+    /// free.
+    fn send_fill(&mut self) -> Result<(), CH::Error> {
+        if self.fill_len == 0 {
+            return Ok(());
+        }
+        if self.busy {
+            self.spare = Some(self.finish()?);
+        }
+        let buf = self.fill.take().unwrap();
+        self.channel.start(buf, self.fill_len)?;
+        self.busy = true;
+        self.fill_len = 0;
+        Ok(())
+    }
+
+    /// Send everything pending and wait until it has gone.
+    fn quiesce(&mut self) -> Result<(), DmaInterfaceError<DC::Error, CH::Error>> {
+        self.send_fill().map_err(DmaInterfaceError::DmaError)?;
+        if self.busy {
+            let buf = self.finish().map_err(DmaInterfaceError::DmaError)?;
+            match self.fill {
+                None => self.fill = Some(buf),
+                Some(_) => self.spare = Some(buf),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<CH, DC> DisplayInterface for DmaInterface<CH, DC>
+where
+    CH: DmaChannel,
+    DC: hal::digital::v2::OutputPin,
+{
+    type Error = DmaInterfaceError<DC::Error, CH::Error>;
+
+    #[cfg(feature = "nb")]
+    const NON_BLOCKING: bool = true;
+
+    /// Send a command word to the display's command register, after any pending data. Synchronous.
+    fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+        self.quiesce()?;
+        self.dc.set_low().map_err(DmaInterfaceError::DCError)?;
+        self.fill_buffer().map_err(DmaInterfaceError::DmaError)?[0] = cmd;
+        self.fill_len = 1;
+        let bus_op = self.quiesce();
+        self.dc.set_high().map_err(DmaInterfaceError::DCError)?;
+        bus_op
+    }
+
+    /// Queue a sequence of data words for the display. Outside a region draw they are sent before
+    /// returning; within one, a partly filled buffer is held back until it fills or the draw ends.
+    fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        let mut rest = buf;
+        while !rest.is_empty() {
+            let fill_len = self.fill_len;
+            let fill = self.fill_buffer().map_err(DmaInterfaceError::DmaError)?;
+            let n = rest.len().min(fill.len() - fill_len);
+            fill[fill_len..fill_len + n].copy_from_slice(&rest[..n]);
+            let full = fill_len + n == fill.len();
+            self.fill_len += n;
+            rest = &rest[n..];
+            if full {
+                self.send_fill().map_err(DmaInterfaceError::DmaError)?;
+            }
+        }
+        if !self.in_transaction {
+            self.quiesce()?;
+        }
+        Ok(())
+    }
+
+    /// Queue a data word for the display. Returns `WouldBlock` if both buffers are full or being
+    /// sent.
+    #[cfg(feature = "nb")]
+    fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        let full = match self.fill {
+            Some(ref fill) => self.fill_len == fill.len(),
+            None => false,
+        };
+        if full || (self.fill.is_none() && self.spare.is_none()) {
+            if self.channel.is_busy() {
+                return Err(nb::Error::WouldBlock);
+            }
+            if full {
+                self.send_fill().map_err(DmaInterfaceError::DmaError)?;
+            }
+        }
+        let fill_len = self.fill_len;
+        self.fill_buffer().map_err(DmaInterfaceError::DmaError)?[fill_len] = word;
+        self.fill_len += 1;
+        Ok(())
+    }
+
+    fn begin_transaction(&mut self) -> Result<(), Self::Error> {
+        self.in_transaction = true;
+        Ok(())
+    }
+
+    /// Hand any data held back to the channel, without waiting for it to be sent.
+    fn end_transaction(&mut self) -> Result<(), Self::Error> {
+        self.in_transaction = false;
+        self.send_fill().map_err(DmaInterfaceError::DmaError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::{Display, PixelCoord as Px};
+    use core::convert::Infallible;
+    use std::boxed::Box;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::vec::Vec;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Event {
+        Dc(bool),
+        Dma(Vec<u8>),
+    }
+
+    type Log = Rc<RefCell<Vec<Event>>>;
+
+    struct Pin(Log);
+
+    impl hal::digital::v2::OutputPin for Pin {
+        type Error = Infallible;
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().push(Event::Dc(false));
+            Ok(())
+        }
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().push(Event::Dc(true));
+            Ok(())
+        }
+    }
+
+    /// A channel which logs each transfer as it starts. Transfers complete instantly, unless
+    /// `stall` is set, when they only complete once waited for.
+    struct Channel {
+        log: Log,
+        stall: bool,
+        buf: Option<&'static mut [u8]>,
+    }
+
+    impl DmaChannel for Channel {
+        type Error = Infallible;
+        fn start(&mut self, buf: &'static mut [u8], len: usize) -> Result<(), Infallible> {
+            assert!(self.buf.is_none());
+            self.log.borrow_mut().push(Event::Dma(buf[..len].to_vec()));
+            self.buf = Some(buf);
+            Ok(())
+        }
+        fn is_busy(&self) -> bool {
+            self.stall && self.buf.is_some()
+        }
+        fn wait(&mut self) -> Result<&'static mut [u8], Infallible> {
+            Ok(self.buf.take().unwrap())
+        }
+    }
+
+    fn channel(log: &Log, stall: bool) -> Channel {
+        Channel {
+            log: log.clone(),
+            stall: stall,
+            buf: None,
+        }
+    }
+
+    fn buffers() -> DmaBuffers {
+        (Box::leak(Box::new([0u8; 4])), Box::leak(Box::new([0u8; 4])))
+    }
+
+    #[test]
+    fn double_buffered_region() {
+        let log = Log::default();
+        let iface = DmaInterface::new(channel(&log, false), Pin(log.clone()), buffers());
+        let mut disp = Display::new(iface, Px(128, 64), Px(0, 0));
+        disp.region(Px(0, 0), Px(20, 1))
+            .unwrap()
+            .draw_packed(1..=10)
+            .unwrap();
+        disp.contrast(7).unwrap();
+
+        let dma = |data: &[u8]| Event::Dma(data.to_vec());
+        #[rustfmt::skip]
+        assert_eq!(*log.borrow(), [
+            Event::Dc(false), dma(&[0x15]), Event::Dc(true), dma(&[0, 4]),
+            Event::Dc(false), dma(&[0x75]), Event::Dc(true), dma(&[0, 0]),
+            Event::Dc(false), dma(&[0x5C]), Event::Dc(true),
+            dma(&[1, 2, 3, 4]), dma(&[5, 6, 7, 8]), dma(&[9, 10]),
+            Event::Dc(false), dma(&[0xC7]), Event::Dc(true), dma(&[7]),
+        ]);
+    }
+
+    #[cfg(feature = "nb")]
+    #[test]
+    fn release_after_async() {
+        let log = Log::default();
+        let mut iface = DmaInterface::new(channel(&log, true), Pin(log.clone()), buffers());
+        iface.begin_transaction().unwrap();
+        for word in 0..8 {
+            iface.send_data_async(word).unwrap();
+        }
+        // Both buffers are taken, one still being sent.
+        assert!(matches!(
+            iface.send_data_async(8),
+            Err(nb::Error::WouldBlock)
+        ));
+        let (_, _, (a, b)) = iface.release().unwrap();
+        assert_eq!((a.len(), b.len()), (4, 4));
+        #[rustfmt::skip]
+        assert_eq!(*log.borrow(), [
+            Event::Dma(vec![0, 1, 2, 3]), Event::Dma(vec![4, 5, 6, 7]),
+        ]);
+    }
+}
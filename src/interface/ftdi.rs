@@ -0,0 +1,164 @@
+//! A `DisplayInterface` for driving the display from a desktop PC through an FT232H, or another
+//! FTDI chip with an MPSSE, using `ftdi-embedded-hal`. UI code can then be tried on a real panel
+//! without flashing a microcontroller.
+//!
+//! The interface is a `BlockingSpiInterface` on the FTDI SPI bus, since each blocking write is
+//! sent as one USB command while a full duplex word would take a round trip of its own. Wire the
+//! board to the SSD1322 in 4-wire SPI mode as follows:
+//!
+//! | FT232H | SSD1322       |
+//! |--------|---------------|
+//! | AD0    | SCLK (D0)     |
+//! | AD1    | SDIN (D1)     |
+//! | AD3    | /CS           |
+//! | AD4    | D/C           |
+//!
+//! AD2 is taken by the SPI bus as MISO and is left unconnected. The other pins can still be taken
+//! from the `FtHal`, for example AD5 to drive /RESET.
+//!
+//! `ftdi-embedded-hal` talks to the chip through either the `libftd2xx` or the `ftdi` crate, which
+//! the application enables as a feature of its own `ftdi-embedded-hal` dependency:
+//!
+//! ```no_run
+//! # use ftdi_embedded_hal::ftdi_mpsse::MpsseCmdExecutor;
+//! # use ssd1322::interface::ftdi;
+//! # use ssd1322::DisplayBuilder;
+//! # fn example<D: MpsseCmdExecutor<Error = std::io::Error>>(device: D) {
+//! // With the `libftd2xx` backend, `device` is `libftd2xx::Ft232h::with_description(..)`.
+//! let hal = ftdi_embedded_hal::FtHal::init_freq(device, 10_000_000).unwrap();
+//! let iface = ftdi::open(&hal).unwrap();
+//! let mut disp = DisplayBuilder::preset("NHD-3.12-25664UCY2")
+//!     .unwrap()
+//!     .build(iface)
+//!     .unwrap();
+//! # }
+//! ```
+//!
+//! Available with the `ftdi` feature.
+
+use ftdi_embedded_hal::eh0::digital::v2::OutputPin as _;
+use ftdi_embedded_hal::ftdi_mpsse::MpsseCmdExecutor;
+use ftdi_embedded_hal::{Error, FtHal, OutputPin, Spi};
+
+use super::spi::BlockingSpiInterface;
+
+/// A `DisplayInterface` on the SPI bus and pins of an FTDI chip, created by `open`.
+pub type FtdiInterface<Device> =
+    BlockingSpiInterface<Spi<Device>, OutputPin<Device>, OutputPin<Device>>;
+
+/// Take the SPI bus and the AD3 and AD4 pins of `hal` for an interface to an SSD1322 wired as in
+/// the module documentation, with /CS deasserted. The SPI clock is the frequency `hal` was
+/// initialized with; the SSD1322 accepts up to 10 MHz.
+///
+/// # Panics
+///
+/// Panics if any of AD0 to AD4 has already been taken from `hal`.
+pub fn open<Device, E>(hal: &FtHal<Device>) -> Result<FtdiInterface<Device>, Error<E>>
+where
+    Device: MpsseCmdExecutor<Error = E>,
+    E: std::error::Error,
+    Error<E>: From<E>,
+{
+    let spi = hal.spi()?;
+    let mut cs = hal.ad3()?;
+    let dc = hal.ad4()?;
+    cs.set_high()?;
+    Ok(BlockingSpiInterface::with_cs(spi, dc, cs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::{Display, PixelCoord as Px};
+    use ftdi_embedded_hal::ftdi_mpsse::MpsseSettings;
+    use std::cell::RefCell;
+    use std::io;
+    use std::rc::Rc;
+    use std::vec::Vec;
+
+    /// An FTDI device which records the MPSSE commands sent to it.
+    struct Device(Rc<RefCell<Vec<u8>>>);
+
+    impl MpsseCmdExecutor for Device {
+        type Error = io::Error;
+
+        fn init(&mut self, _settings: &MpsseSettings) -> Result<(), io::Error> {
+            Ok(())
+        }
+        fn send(&mut self, data: &[u8]) -> Result<(), io::Error> {
+            self.0.borrow_mut().extend_from_slice(data);
+            Ok(())
+        }
+        fn recv(&mut self, _data: &mut [u8]) -> Result<(), io::Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        /// The state of /CS and D/C, in that order.
+        Pins(bool, bool),
+        Write(Vec<u8>),
+    }
+
+    /// Decode the changes of /CS and D/C and the SPI writes from a stream of MPSSE commands.
+    fn events(mut cmds: &[u8]) -> Vec<Event> {
+        let mut events = Vec::new();
+        let mut pins = None;
+        while let Some((&op, rest)) = cmds.split_first() {
+            cmds = match op {
+                // Set the lower GPIO byte: value, then direction.
+                0x80 => {
+                    let now = (rest[0] & 0x08 != 0, rest[0] & 0x10 != 0);
+                    if pins != Some(now) {
+                        events.push(Event::Pins(now.0, now.1));
+                        pins = Some(now);
+                    }
+                    &rest[2..]
+                }
+                // Send immediate.
+                0x87 => rest,
+                // Clock bytes out: length minus one, little endian, then the bytes.
+                0x10 | 0x11 => {
+                    let len = rest[0] as usize + ((rest[1] as usize) << 8) + 1;
+                    events.push(Event::Write(rest[2..2 + len].to_vec()));
+                    &rest[2 + len..]
+                }
+                _ => panic!("Unexpected MPSSE command {:#04x}", op),
+            };
+        }
+        events
+    }
+
+    #[test]
+    fn framing_and_pins() {
+        let sent = Rc::new(RefCell::new(Vec::new()));
+        let hal = FtHal::init_freq(Device(sent.clone()), 10_000_000).unwrap();
+        let iface = open(&hal).unwrap();
+        let opened = events(&sent.borrow());
+        assert_eq!(opened.last(), Some(&Event::Pins(true, false)));
+
+        let mut disp = Display::new(iface, Px(128, 64), Px(0, 0));
+        disp.contrast(3).unwrap();
+        disp.region(Px(0, 0), Px(4, 1))
+            .unwrap()
+            .draw_packed([0x12, 0x34].iter().cloned())
+            .unwrap();
+
+        let write = |data: &[u8]| Event::Write(data.to_vec());
+        #[rustfmt::skip]
+        assert_eq!(events(&sent.borrow())[opened.len()..], [
+            // The contrast command and its argument, each with /CS asserted.
+            Event::Pins(false, false), write(&[0xC7]), Event::Pins(false, true),
+            Event::Pins(true, true),
+            Event::Pins(false, true), write(&[3]), Event::Pins(true, true),
+            // The region draw, with /CS asserted throughout.
+            Event::Pins(false, true),
+            Event::Pins(false, false), write(&[0x15]), Event::Pins(false, true), write(&[0, 0]),
+            Event::Pins(false, false), write(&[0x75]), Event::Pins(false, true), write(&[0, 0]),
+            Event::Pins(false, false), write(&[0x5C]), Event::Pins(false, true),
+            write(&[0x12, 0x34]),
+            Event::Pins(true, true),
+        ]);
+    }
+}
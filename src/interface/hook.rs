@@ -0,0 +1,137 @@
+//! A wrapper interface which calls an application-supplied hook around every transfer, for
+//! instrumentation such as toggling a GPIO for logic analyzer correlation or feeding a tracing
+//! system. Wrap the real interface in a `HookInterface` before handing it to `Display::new`.
+
+use super::DisplayInterface;
+#[cfg(feature = "nb")]
+use nb;
+
+/// A transfer seen by a `HookInterface`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferEvent<'a> {
+    /// A command word is about to be sent.
+    Command(u8),
+    /// Data words, either command arguments or image data, are about to be sent. Image data sent
+    /// without blocking arrives one word at a time.
+    Data(&'a [u8]),
+    /// The command or data of the previous event has been sent, or the interface failed or
+    /// refused to accept it.
+    Done,
+    /// A transaction is starting. See `DisplayInterface::begin_transaction`.
+    BeginTransaction,
+    /// A transaction has ended. See `DisplayInterface::end_transaction`.
+    EndTransaction,
+}
+
+/// A `DisplayInterface` which passes everything through to an inner interface, calling a hook with
+/// a `TransferEvent` before each command and data transfer and again once it is done.
+pub struct HookInterface<DI, F> {
+    iface: DI,
+    hook: F,
+}
+
+impl<DI, F> HookInterface<DI, F>
+where
+    DI: DisplayInterface,
+    F: FnMut(TransferEvent<'_>),
+{
+    /// Wrap `iface`, calling `hook` around every transfer. The hook runs in line with the
+    /// transfers, so it should be quick.
+    pub fn new(iface: DI, hook: F) -> Self {
+        Self {
+            iface: iface,
+            hook: hook,
+        }
+    }
+
+    /// Consume the wrapper, returning the inner interface.
+    pub fn into_inner(self) -> DI {
+        self.iface
+    }
+}
+
+impl<DI, F> DisplayInterface for HookInterface<DI, F>
+where
+    DI: DisplayInterface,
+    F: FnMut(TransferEvent<'_>),
+{
+    type Error = DI::Error;
+
+    #[cfg(feature = "nb")]
+    const NON_BLOCKING: bool = DI::NON_BLOCKING;
+
+    fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+        (self.hook)(TransferEvent::Command(cmd));
+        let result = self.iface.send_command(cmd);
+        (self.hook)(TransferEvent::Done);
+        result
+    }
+
+    fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        (self.hook)(TransferEvent::Data(buf));
+        let result = self.iface.send_data(buf);
+        (self.hook)(TransferEvent::Done);
+        result
+    }
+
+    #[cfg(feature = "nb")]
+    fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        (self.hook)(TransferEvent::Data(&[word]));
+        let result = self.iface.send_data_async(word);
+        (self.hook)(TransferEvent::Done);
+        result
+    }
+
+    fn begin_transaction(&mut self) -> Result<(), Self::Error> {
+        (self.hook)(TransferEvent::BeginTransaction);
+        self.iface.begin_transaction()
+    }
+
+    fn end_transaction(&mut self) -> Result<(), Self::Error> {
+        let result = self.iface.end_transaction();
+        (self.hook)(TransferEvent::EndTransaction);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::{Display, PixelCoord as Px};
+    use crate::interface::mock::MockInterface;
+    use std::cell::RefCell;
+    use std::string::{String, ToString};
+    use std::vec::Vec;
+
+    #[test]
+    fn events() {
+        let log = RefCell::new(Vec::<String>::new());
+        let iface = HookInterface::new(MockInterface::new(), |event| {
+            log.borrow_mut().push(match event {
+                TransferEvent::Command(cmd) => format!("C{:02X}", cmd),
+                TransferEvent::Data(data) => format!("D{:?}", data),
+                TransferEvent::Done => "-".to_string(),
+                TransferEvent::BeginTransaction => "<".to_string(),
+                TransferEvent::EndTransaction => ">".to_string(),
+            })
+        });
+        let mut disp = Display::new(iface, Px(128, 64), Px(0, 0));
+        disp.contrast(3).unwrap();
+        assert_eq!(log.borrow().join(" "), "CC7 - D[3] -");
+        log.borrow_mut().clear();
+
+        disp.region(Px(0, 0), Px(4, 1))
+            .unwrap()
+            .draw_packed([1, 2].iter().cloned())
+            .unwrap();
+        let image = if cfg!(feature = "nb") {
+            "D[1] - D[2] -"
+        } else {
+            "D[1, 2] -"
+        };
+        assert_eq!(
+            log.borrow().join(" "),
+            format!("< C15 - D[0, 0] - C75 - D[0, 0] - C5C - {} >", image)
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! A `DisplayInterface` for Linux hosts such as the Raspberry Pi, sending through the kernel's
+//! spidev driver and driving D/C with a GPIO character device line.
+//!
+//! `SpidevInterface::open` brings up the bus and the D/C pin in one call:
+//!
+//! ```no_run
+//! # use ssd1322::interface::linux::SpidevInterface;
+//! # use ssd1322::DisplayBuilder;
+//! let iface = SpidevInterface::open("/dev/spidev0.0", 10_000_000, "/dev/gpiochip0", 24).unwrap();
+//! let mut disp = DisplayBuilder::preset("NHD-3.12-25664UCY2")
+//!     .unwrap()
+//!     .build(iface)
+//!     .unwrap();
+//! ```
+//!
+//! `linux-embedded-hal`'s `Spidev` only implements the blocking SPI traits, so it cannot drive an
+//! `SpiInterface`, and each write must fit in the spidev driver's transfer buffer (the `bufsiz`
+//! module parameter, 4096 bytes unless changed) or it fails with `EMSGSIZE`.
+//! This interface handles both: data words sent with `send_data_async` are gathered and written
+//! in one transfer per buffer's worth, and longer writes are split to fit.
+//!
+//! Available with the `linux` feature.
+
+use std::io;
+use std::path::Path;
+use std::vec::Vec;
+
+use embedded_hal as hal;
+use linux_embedded_hal::gpio_cdev::{self, Chip, LineRequestFlags};
+use linux_embedded_hal::spidev::{SpiModeFlags, SpidevOptions};
+use linux_embedded_hal::{CdevPin, Spidev};
+
+use super::spi::SpiInterfaceError;
+use super::DisplayInterface;
+#[cfg(feature = "nb")]
+use nb;
+
+/// Where the spidev driver publishes its transfer buffer size.
+const BUFSIZ_PATH: &str = "/sys/module/spidev/parameters/bufsiz";
+
+/// The spidev driver's default transfer buffer size.
+const DEFAULT_BUFSIZ: usize = 4096;
+
+/// The largest transfer the spidev driver accepts, read from its module parameters, or its default
+/// if they cannot be read.
+pub fn max_transfer() -> usize {
+    std::fs::read_to_string(BUFSIZ_PATH)
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(DEFAULT_BUFSIZ)
+}
+
+/// A `DisplayInterface` sending through a blocking SPI device in transfers of at most
+/// `max_transfer` bytes. See the module documentation.
+pub struct SpidevInterface<SPI = Spidev, DC = CdevPin> {
+    spi: SPI,
+    dc: DC,
+    max_transfer: usize,
+    /// Data words from `send_data_async` not yet written.
+    pending: Vec<u8>,
+    in_transaction: bool,
+}
+
+impl SpidevInterface {
+    /// Open the spidev device at `spi_path`, such as `/dev/spidev0.0`, configured for 8-bit SPI
+    /// mode 0 at `speed_hz`, and request line `dc_line` of the GPIO chip at `gpiochip_path`, such
+    /// as `/dev/gpiochip0`, as the D/C output. On a Raspberry Pi the line is the BCM GPIO number.
+    /// The transfer size limit is read from the spidev driver.
+    pub fn open<P, Q>(
+        spi_path: P,
+        speed_hz: u32,
+        gpiochip_path: Q,
+        dc_line: u32,
+    ) -> Result<Self, SpiInterfaceError<gpio_cdev::errors::Error, io::Error>>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let mut spi = Spidev::open(spi_path).map_err(SpiInterfaceError::SPIError)?;
+        let options = SpidevOptions::new()
+            .bits_per_word(8)
+            .max_speed_hz(speed_hz)
+            .mode(SpiModeFlags::SPI_MODE_0)
+            .build();
+        spi.configure(&options)
+            .map_err(SpiInterfaceError::SPIError)?;
+        let dc = Chip::new(gpiochip_path)
+            .and_then(|mut chip| chip.get_line(dc_line))
+            .and_then(|line| line.request(LineRequestFlags::OUTPUT, 1, "ssd1322-dc"))
+            .and_then(CdevPin::new)
+            .map_err(SpiInterfaceError::DCError)?;
+        Ok(Self::new(spi, dc, max_transfer()))
+    }
+}
+
+impl<SPI, DC> SpidevInterface<SPI, DC>
+where
+    SPI: hal::blocking::spi::Write<u8>,
+    DC: hal::digital::v2::OutputPin,
+{
+    /// Create an interface sending through `spi`, with `dc` the GPIO output pin connected to the
+    /// D/C pin of the SSD1322, writing at most `max_transfer` bytes at a time.
+    pub fn new(spi: SPI, dc: DC, max_transfer: usize) -> Self {
+        assert!(max_transfer > 0);
+        Self {
+            spi: spi,
+            dc: dc,
+            max_transfer: max_transfer,
+            pending: Vec::with_capacity(max_transfer),
+            in_transaction: false,
+        }
+    }
+
+    /// The largest number of bytes written in one transfer.
+    pub fn max_transfer(&self) -> usize {
+        self.max_transfer
+    }
+
+    /// Write any pending data, and return the SPI device and the D/C pin.
+    #[allow(clippy::type_complexity)]
+    pub fn release(mut self) -> Result<(SPI, DC), SpiInterfaceError<DC::Error, SPI::Error>> {
+        self.flush().map_err(SpiInterfaceError::SPIError)?;
+        Ok((self.spi, self.dc))
+    }
+
+    /// Write `buf` in transfers no longer than `max_transfer`.
+    fn write(&mut self, buf: &[u8]) -> Result<(), SPI::Error> {
+        for chunk in buf.chunks(self.max_transfer) {
+            self.spi.write(chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Write the data words gathered from `send_data_async`, which never exceed one transfer.
+    fn flush(&mut self) -> Result<(), SPI::Error> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let result = self.spi.write(&self.pending);
+        self.pending.clear();
+        result
+    }
+}
+
+impl<SPI, DC> DisplayInterface for SpidevInterface<SPI, DC>
+where
+    SPI: hal::blocking::spi::Write<u8>,
+    DC: hal::digital::v2::OutputPin,
+{
+    type Error = SpiInterfaceError<DC::Error, SPI::Error>;
+
+    /// Send a command word to the display's command register, after any pending data. Synchronous.
+    fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+        self.flush().map_err(SpiInterfaceError::SPIError)?;
+        self.dc.set_low().map_err(SpiInterfaceError::DCError)?;
+        let bus_op = self.spi.write(&[cmd]).map_err(SpiInterfaceError::SPIError);
+        self.dc.set_high().map_err(SpiInterfaceError::DCError)?;
+        bus_op
+    }
+
+    /// Send a sequence of data words to the display from a buffer, after any pending data.
+    /// Synchronous.
+    fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.flush().map_err(SpiInterfaceError::SPIError)?;
+        self.write(buf).map_err(SpiInterfaceError::SPIError)
+    }
+
+    /// Queue a data word for the display. Within a region draw, words are held back until a full
+    /// transfer has been gathered or the draw ends; outside one they are written immediately.
+    /// Never returns `WouldBlock`.
+    #[cfg(feature = "nb")]
+    fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.pending.push(word);
+        if !self.in_transaction || self.pending.len() == self.max_transfer {
+            self.flush()
+                .map_err(|e| nb::Error::Other(SpiInterfaceError::SPIError(e)))?;
+        }
+        Ok(())
+    }
+
+    fn begin_transaction(&mut self) -> Result<(), Self::Error> {
+        self.in_transaction = true;
+        Ok(())
+    }
+
+    fn end_transaction(&mut self) -> Result<(), Self::Error> {
+        self.in_transaction = false;
+        self.flush().map_err(SpiInterfaceError::SPIError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::{Display, PixelCoord as Px};
+    use core::convert::Infallible;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Event {
+        Dc(bool),
+        Write(Vec<u8>),
+    }
+
+    type Log = Rc<RefCell<Vec<Event>>>;
+
+    struct Spi(Log);
+
+    impl hal::blocking::spi::Write<u8> for Spi {
+        type Error = Infallible;
+        fn write(&mut self, words: &[u8]) -> Result<(), Infallible> {
+            self.0.borrow_mut().push(Event::Write(words.to_vec()));
+            Ok(())
+        }
+    }
+
+    struct Pin(Log);
+
+    impl hal::digital::v2::OutputPin for Pin {
+        type Error = Infallible;
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().push(Event::Dc(false));
+            Ok(())
+        }
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().push(Event::Dc(true));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn transfer_size_limit() {
+        let log = Log::default();
+        let iface = SpidevInterface::new(Spi(log.clone()), Pin(log.clone()), 4);
+        let mut disp = Display::new(iface, Px(128, 64), Px(0, 0));
+        disp.region(Px(0, 0), Px(20, 1))
+            .unwrap()
+            .draw_packed(1..=10)
+            .unwrap();
+        disp.contrast(7).unwrap();
+
+        let write = |data: &[u8]| Event::Write(data.to_vec());
+        #[rustfmt::skip]
+        assert_eq!(*log.borrow(), [
+            Event::Dc(false), write(&[0x15]), Event::Dc(true), write(&[0, 4]),
+            Event::Dc(false), write(&[0x75]), Event::Dc(true), write(&[0, 0]),
+            Event::Dc(false), write(&[0x5C]), Event::Dc(true),
+            write(&[1, 2, 3, 4]), write(&[5, 6, 7, 8]), write(&[9, 10]),
+            Event::Dc(false), write(&[0xC7]), Event::Dc(true), write(&[7]),
+        ]);
+
+        log.borrow_mut().clear();
+        let mut iface = SpidevInterface::new(Spi(log.clone()), Pin(log.clone()), 3);
+        iface.send_data(&[1, 2, 3, 4, 5, 6, 7]).unwrap();
+        assert_eq!(
+            *log.borrow(),
+            [write(&[1, 2, 3]), write(&[4, 5, 6]), write(&[7])]
+        );
+    }
+}
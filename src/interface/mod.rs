@@ -0,0 +1,754 @@
+//! This module provides shims for the `embedded-hal` hardware corresponding to the SSD1322's
+//! supported electrical/bus interfaces. It is a shim between `embedded-hal` implementations and
+//! the display driver's command layer.
+
+#[cfg(feature = "nb")]
+use nb;
+
+/// An interface for the SSD1322 implements this trait, which provides the basic operations for
+/// sending pre-encoded commands and data to the chip via the interface.
+pub trait DisplayInterface {
+    type Error;
+
+    fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error>;
+    fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error>;
+
+    /// Whether `send_data_async` returns `WouldBlock` while the interface is busy rather than
+    /// waiting for it, so that image data can be fed from an interrupt. `Display::flush` refuses
+    /// interfaces which do not set this.
+    #[cfg(feature = "nb")]
+    const NON_BLOCKING: bool = false;
+
+    /// Send one data word without waiting for it to leave the interface, returning `WouldBlock`
+    /// if it cannot be accepted yet. The default sends it with `send_data`, which waits for the
+    /// transfer, so an interface relying on it leaves `NON_BLOCKING` unset.
+    #[cfg(feature = "nb")]
+    fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.send_data(&[word]).map_err(nb::Error::Other)
+    }
+
+    /// Mark the start of a transaction: the commands setting up a region draw and its image data,
+    /// up to the matching `end_transaction`. An interface which controls chip select can keep it
+    /// asserted for the whole transaction rather than framing each transfer. The default does
+    /// nothing.
+    fn begin_transaction(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Mark the end of a transaction started with `begin_transaction`, after any data sent with
+    /// `send_data_async` has been accepted. The default does nothing.
+    fn end_transaction(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// An interface which can also read data back from the chip implements this trait. The SSD1322 can
+/// only be read over its parallel buses, so this is separate from `DisplayInterface`, and enables
+/// reading back display RAM for read-modify-write drawing.
+pub trait ReadableInterface: DisplayInterface {
+    /// Read a sequence of data words from the display into a buffer. Synchronous.
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<(), Self::Error>;
+}
+
+#[cfg(feature = "std")]
+pub mod capture;
+#[cfg(feature = "adapters")]
+pub mod chunked;
+#[cfg(feature = "display-interface")]
+pub mod compat;
+#[cfg(feature = "adapters")]
+pub mod crc;
+#[cfg(feature = "critical-section")]
+pub mod critical;
+#[cfg(feature = "adapters")]
+pub mod dma;
+#[cfg(feature = "ftdi")]
+pub mod ftdi;
+#[cfg(feature = "adapters")]
+pub mod hook;
+#[cfg(feature = "linux")]
+pub mod linux;
+#[cfg(feature = "std")]
+pub mod simulator;
+#[cfg(feature = "adapters")]
+pub mod stats;
+
+pub mod spi {
+    //! The SPI interface supports the "4-wire" interface of the driver, such that each word on the
+    //! SPI bus is 8 bits. The "3-wire" mode is not supported, as it replaces the D/C GPIO with a
+    //! 9th bit on each SPI word, and `embedded-hal` SPI traits do not currently support
+    //! non-byte-aligned SPI word lengths.
+
+    use core::convert::Infallible;
+
+    use embedded_hal as hal;
+
+    use super::DisplayInterface;
+    #[cfg(feature = "nb")]
+    use nb;
+
+    /// The union of all errors that may occur on the SPI interface. This consists of variants for
+    /// the error types of the D/C GPIO, the SPI bus, and the chip select GPIO if there is one.
+    #[derive(Debug)]
+    #[non_exhaustive]
+    pub enum SpiInterfaceError<DCE, SPIE, CSE = Infallible> {
+        DCError(DCE),
+        SPIError(SPIE),
+        CSError(CSE),
+    }
+
+    impl<DCE, SPIE, CSE> SpiInterfaceError<DCE, SPIE, CSE> {
+        fn from_dc(e: DCE) -> Self {
+            Self::DCError(e)
+        }
+        fn from_spi(e: SPIE) -> Self {
+            Self::SPIError(e)
+        }
+        fn from_cs(e: CSE) -> Self {
+            Self::CSError(e)
+        }
+    }
+
+    /// The chip select of an `SpiInterface` created with `SpiInterface::new`, where chip select
+    /// is managed outside the driver or tied low. Setting it does nothing.
+    pub struct NoChipSelect;
+
+    impl hal::digital::v2::OutputPin for NoChipSelect {
+        type Error = Infallible;
+
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    /// A configured `DisplayInterface` for controlling an SSD1322 via 4-wire SPI, with a blocking
+    /// SPI `Write` implementation. Each transfer is sent with one blocking write. Image data sent
+    /// word by word with `send_data_async` during a region draw is gathered into chunks of up to
+    /// 32 bytes, each sent with one write.
+    ///
+    /// If the interface controls the chip select (/CS) pin, each region draw is sent as one
+    /// transaction: /CS stays asserted from the commands setting up the region to the last word of
+    /// image data, rather than being toggled for every transfer. Commands sent outside a region
+    /// draw assert /CS for each transfer.
+    pub struct BlockingSpiInterface<SPI, DC, CS = NoChipSelect> {
+        /// The SPI master device connected to the SSD1322.
+        spi: SPI,
+        /// A GPIO output pin connected to the D/C (data/command) pin of the SSD1322 (the fourth
+        /// "wire" of "4-wire" mode).
+        dc: DC,
+        /// A GPIO output pin connected to the active low /CS pin of the SSD1322.
+        cs: CS,
+        /// Whether a transaction is in progress, keeping /CS asserted between transfers.
+        in_transaction: bool,
+        /// Data words from `send_data_async` not yet written, and how many there are.
+        #[cfg(feature = "nb")]
+        pending: [u8; 32],
+        #[cfg(feature = "nb")]
+        pending_len: usize,
+    }
+
+    impl<SPI, DC> BlockingSpiInterface<SPI, DC>
+    where
+        DC: hal::digital::v2::OutputPin,
+    {
+        /// Create a new SPI interface to communicate with the display driver. `spi` is the SPI
+        /// master device, and `dc` is the GPIO output pin connected to the D/C pin of the SSD1322.
+        pub fn new(spi: SPI, dc: DC) -> Self {
+            Self::with_cs(spi, dc, NoChipSelect)
+        }
+    }
+
+    impl<SPI, DC, CS> BlockingSpiInterface<SPI, DC, CS>
+    where
+        DC: hal::digital::v2::OutputPin,
+        CS: hal::digital::v2::OutputPin,
+    {
+        /// Create a new SPI interface as with `new`, which also drives the /CS pin of the SSD1322
+        /// with the GPIO output pin `cs`.
+        pub fn with_cs(spi: SPI, dc: DC, cs: CS) -> Self {
+            Self {
+                spi: spi,
+                dc: dc,
+                cs: cs,
+                in_transaction: false,
+                #[cfg(feature = "nb")]
+                pending: [0; 32],
+                #[cfg(feature = "nb")]
+                pending_len: 0,
+            }
+        }
+
+        /// Consume the interface, returning the SPI device and the D/C and /CS pins. Data words
+        /// not yet written are discarded; they are always written by the end of a region draw.
+        pub fn release(self) -> (SPI, DC, CS) {
+            (self.spi, self.dc, self.cs)
+        }
+
+        fn select(&mut self) -> Result<(), CS::Error> {
+            self.cs.set_low()
+        }
+
+        /// Deassert /CS after a transfer, unless a transaction is in progress.
+        fn deselect(&mut self) -> Result<(), CS::Error> {
+            if self.in_transaction {
+                return Ok(());
+            }
+            self.cs.set_high()
+        }
+    }
+
+    impl<SPI, DC, CS> DisplayInterface for BlockingSpiInterface<SPI, DC, CS>
+    where
+        SPI: hal::blocking::spi::Write<u8>,
+        DC: hal::digital::v2::OutputPin,
+        CS: hal::digital::v2::OutputPin,
+    {
+        type Error = SpiInterfaceError<
+            <DC as hal::digital::v2::OutputPin>::Error,
+            <SPI as hal::blocking::spi::Write<u8>>::Error,
+            <CS as hal::digital::v2::OutputPin>::Error,
+        >;
+
+        /// Send a command word to the display's command register, after any pending data.
+        /// Synchronous.
+        fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+            self.flush()?;
+            self.select().map_err(Self::Error::from_cs)?;
+            self.dc.set_low().map_err(Self::Error::from_dc)?;
+            let bus_op = self.spi.write(&[cmd]).map_err(Self::Error::from_spi);
+            self.dc.set_high().map_err(Self::Error::from_dc)?;
+            self.deselect().map_err(Self::Error::from_cs)?;
+            bus_op
+        }
+
+        /// Send a sequence of data words to the display from a buffer, in one blocking write
+        /// after any pending data. Synchronous.
+        fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+            self.flush()?;
+            self.select().map_err(Self::Error::from_cs)?;
+            let bus_op = self.spi.write(buf).map_err(Self::Error::from_spi);
+            self.deselect().map_err(Self::Error::from_cs)?;
+            bus_op
+        }
+
+        /// Queue a data word for the display. Within a region draw, words are held back until a
+        /// full chunk has been gathered or the draw ends; outside one they are written
+        /// immediately. Never returns `WouldBlock`, but waits for each chunk to be written.
+        #[cfg(feature = "nb")]
+        fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+            self.pending[self.pending_len] = word;
+            self.pending_len += 1;
+            if !self.in_transaction || self.pending_len == self.pending.len() {
+                self.flush()?;
+            }
+            Ok(())
+        }
+
+        fn begin_transaction(&mut self) -> Result<(), Self::Error> {
+            self.in_transaction = true;
+            self.select().map_err(Self::Error::from_cs)
+        }
+
+        fn end_transaction(&mut self) -> Result<(), Self::Error> {
+            let flushed = self.flush();
+            self.in_transaction = false;
+            flushed.and(self.deselect().map_err(Self::Error::from_cs))
+        }
+    }
+
+    impl<SPI, DC, CS> BlockingSpiInterface<SPI, DC, CS>
+    where
+        SPI: hal::blocking::spi::Write<u8>,
+        DC: hal::digital::v2::OutputPin,
+        CS: hal::digital::v2::OutputPin,
+    {
+        /// Write the data words gathered from `send_data_async`, if there are any.
+        #[allow(clippy::type_complexity)]
+        #[cfg(feature = "nb")]
+        fn flush(&mut self) -> Result<(), SpiInterfaceError<DC::Error, SPI::Error, CS::Error>> {
+            if self.pending_len == 0 {
+                return Ok(());
+            }
+            self.select().map_err(SpiInterfaceError::from_cs)?;
+            let bus_op = self
+                .spi
+                .write(&self.pending[..self.pending_len])
+                .map_err(SpiInterfaceError::from_spi);
+            self.pending_len = 0;
+            self.deselect().map_err(SpiInterfaceError::from_cs)?;
+            bus_op
+        }
+
+        /// Without the `nb` feature no data words are held back.
+        #[allow(clippy::type_complexity)]
+        #[cfg(not(feature = "nb"))]
+        fn flush(&mut self) -> Result<(), SpiInterfaceError<DC::Error, SPI::Error, CS::Error>> {
+            Ok(())
+        }
+    }
+
+    /// A configured `DisplayInterface` for controlling an SSD1322 via 4-wire SPI, with a full
+    /// duplex SPI device. Image data is sent with non-blocking writes, so the driver can encode
+    /// the next word while the last one is on the bus, and `Display::flush` can feed it from an
+    /// interrupt. Chip select is handled as for `BlockingSpiInterface`.
+    ///
+    /// Available with the `nb` feature, which is enabled by default. Without it, use
+    /// `BlockingSpiInterface`.
+    #[cfg(feature = "nb")]
+    pub struct SpiInterface<SPI, DC, CS = NoChipSelect> {
+        /// The SPI device and pins, which are driven as for a blocking interface apart from the
+        /// bus transfers themselves.
+        iface: BlockingSpiInterface<SPI, DC, CS>,
+        /// The number of words sent with `send_data_async` whose received words have not been
+        /// read yet, meaning they may still be on the bus.
+        unread: usize,
+    }
+
+    #[cfg(feature = "nb")]
+    impl<SPI, DC> SpiInterface<SPI, DC>
+    where
+        SPI: hal::spi::FullDuplex<u8>,
+        DC: hal::digital::v2::OutputPin,
+    {
+        /// Create a new SPI interface to communicate with the display driver. `spi` is the SPI
+        /// master device, and `dc` is the GPIO output pin connected to the D/C pin of the SSD1322.
+        pub fn new(spi: SPI, dc: DC) -> Self {
+            Self::with_cs(spi, dc, NoChipSelect)
+        }
+    }
+
+    #[cfg(feature = "nb")]
+    impl<SPI, DC, CS> SpiInterface<SPI, DC, CS>
+    where
+        SPI: hal::spi::FullDuplex<u8>,
+        DC: hal::digital::v2::OutputPin,
+        CS: hal::digital::v2::OutputPin,
+    {
+        /// Create a new SPI interface as with `new`, which also drives the /CS pin of the SSD1322
+        /// with the GPIO output pin `cs`.
+        pub fn with_cs(spi: SPI, dc: DC, cs: CS) -> Self {
+            Self {
+                iface: BlockingSpiInterface::with_cs(spi, dc, cs),
+                unread: 0,
+            }
+        }
+
+        /// Consume the interface, returning the SPI device and the D/C and /CS pins.
+        pub fn release(self) -> (SPI, DC, CS) {
+            self.iface.release()
+        }
+
+        /// Wait for every word sent with `send_data_async` to finish, by reading the word received
+        /// for each. A full duplex transfer has only finished once its received word is readable.
+        fn drain(&mut self) -> Result<(), SPI::Error> {
+            while self.unread > 0 {
+                nb::block!(self.iface.spi.read())?;
+                self.unread -= 1;
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "nb")]
+    impl<SPI, DC, CS> DisplayInterface for SpiInterface<SPI, DC, CS>
+    where
+        SPI: hal::spi::FullDuplex<u8>,
+        DC: hal::digital::v2::OutputPin,
+        CS: hal::digital::v2::OutputPin,
+    {
+        type Error = SpiInterfaceError<
+            <DC as hal::digital::v2::OutputPin>::Error,
+            <SPI as hal::spi::FullDuplex<u8>>::Error,
+            <CS as hal::digital::v2::OutputPin>::Error,
+        >;
+
+        const NON_BLOCKING: bool = true;
+
+        /// Send a command word to the display's command register. Synchronous.
+        fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+            // The SPI device has FIFOs that we must ensure are drained before the bus will
+            // quiesce. This must happen before asserting DC for a command.
+            self.drain().map_err(Self::Error::from_spi)?;
+            self.iface.select().map_err(Self::Error::from_cs)?;
+            self.iface.dc.set_low().map_err(Self::Error::from_dc)?;
+            let bus_op = nb::block!(self.iface.spi.send(cmd))
+                .and_then(|_| nb::block!(self.iface.spi.read()))
+                .map_err(Self::Error::from_spi)
+                .map(core::mem::drop);
+            self.iface.dc.set_high().map_err(Self::Error::from_dc)?;
+            self.iface.deselect().map_err(Self::Error::from_cs)?;
+            bus_op
+        }
+
+        /// Send a sequence of data words to the display from a buffer. Synchronous.
+        fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+            self.iface.select().map_err(Self::Error::from_cs)?;
+            for word in buf {
+                nb::block!(self.iface.spi.send(*word)).map_err(Self::Error::from_spi)?;
+                nb::block!(self.iface.spi.read()).map_err(Self::Error::from_spi)?;
+            }
+            self.iface.deselect().map_err(Self::Error::from_cs)
+        }
+
+        /// Send a data word to the display asynchronously, using `nb` style non-blocking send. If
+        /// the hardware FIFO is full, returns `WouldBlock` which means the word was not accepted
+        /// and should be retried later.
+        ///
+        /// /CS is left asserted, since the word may still be in flight; it is deasserted by the
+        /// next synchronous transfer or `end_transaction`.
+        fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+            self.iface
+                .select()
+                .map_err(|e| nb::Error::Other(Self::Error::from_cs(e)))?;
+            match self.iface.spi.send(word) {
+                Ok(()) => {
+                    self.unread += 1;
+                    // Keep the receive side from overflowing, without waiting for the word.
+                    if self.iface.spi.read().is_ok() {
+                        self.unread -= 1;
+                    }
+                    Ok(())
+                }
+                Err(nb::Error::Other(e)) => Err(nb::Error::Other(Self::Error::from_spi(e))),
+                Err(nb::Error::WouldBlock) => Err(nb::Error::WouldBlock),
+            }
+        }
+
+        fn begin_transaction(&mut self) -> Result<(), Self::Error> {
+            self.iface.in_transaction = true;
+            self.iface.select().map_err(Self::Error::from_cs)
+        }
+
+        fn end_transaction(&mut self) -> Result<(), Self::Error> {
+            // Let any words sent with `send_data_async` leave the FIFO before deasserting /CS.
+            let drained = self.drain().map_err(Self::Error::from_spi);
+            self.iface.in_transaction = false;
+            drained.and(self.iface.deselect().map_err(Self::Error::from_cs))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::display::{Display, PixelCoord as Px};
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        use std::vec::Vec;
+
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        enum Event {
+            Cs(bool),
+            Dc(bool),
+            Word(u8),
+            #[cfg_attr(not(feature = "nb"), allow(dead_code))]
+            Read,
+        }
+
+        type Log = Rc<RefCell<Vec<Event>>>;
+
+        struct Pin(Log, fn(bool) -> Event);
+
+        impl hal::digital::v2::OutputPin for Pin {
+            type Error = Infallible;
+            fn set_low(&mut self) -> Result<(), Infallible> {
+                self.0.borrow_mut().push((self.1)(false));
+                Ok(())
+            }
+            fn set_high(&mut self) -> Result<(), Infallible> {
+                self.0.borrow_mut().push((self.1)(true));
+                Ok(())
+            }
+        }
+
+        /// An SPI bus logging the words sent, with one word of read data per word sent. The count
+        /// of unread words is only used in full duplex mode.
+        struct Spi(Log, usize);
+
+        #[cfg(feature = "nb")]
+        impl hal::spi::FullDuplex<u8> for Spi {
+            type Error = Infallible;
+            fn read(&mut self) -> nb::Result<u8, Infallible> {
+                match self.1 {
+                    0 => Err(nb::Error::WouldBlock),
+                    _ => {
+                        self.1 -= 1;
+                        Ok(0)
+                    }
+                }
+            }
+            fn send(&mut self, word: u8) -> nb::Result<(), Infallible> {
+                self.0.borrow_mut().push(Event::Word(word));
+                self.1 += 1;
+                Ok(())
+            }
+        }
+
+        /// A full duplex SPI bus on which each received word only becomes readable on the second
+        /// attempt, as if its transfer were still in progress at the first. Reads are logged.
+        #[cfg(feature = "nb")]
+        struct SlowSpi(Log, usize, bool);
+
+        #[cfg(feature = "nb")]
+        impl hal::spi::FullDuplex<u8> for SlowSpi {
+            type Error = Infallible;
+            fn read(&mut self) -> nb::Result<u8, Infallible> {
+                if self.1 == 0 {
+                    return Err(nb::Error::WouldBlock);
+                }
+                self.2 = !self.2;
+                if self.2 {
+                    return Err(nb::Error::WouldBlock);
+                }
+                self.1 -= 1;
+                self.0.borrow_mut().push(Event::Read);
+                Ok(0)
+            }
+            fn send(&mut self, word: u8) -> nb::Result<(), Infallible> {
+                self.0.borrow_mut().push(Event::Word(word));
+                self.1 += 1;
+                Ok(())
+            }
+        }
+
+        impl hal::blocking::spi::Write<u8> for Spi {
+            type Error = Infallible;
+            fn write(&mut self, words: &[u8]) -> Result<(), Infallible> {
+                let mut log = self.0.borrow_mut();
+                log.extend(words.iter().map(|w| Event::Word(*w)));
+                Ok(())
+            }
+        }
+
+        /// The words sent, with a `None` wherever /CS was deasserted.
+        fn framing(log: &Log) -> Vec<Option<u8>> {
+            let mut frames = Vec::new();
+            let mut selected = false;
+            for event in log.borrow().iter() {
+                match *event {
+                    Event::Cs(false) => selected = true,
+                    Event::Cs(true) if selected => {
+                        frames.push(None);
+                        selected = false;
+                    }
+                    Event::Word(w) => {
+                        assert!(selected);
+                        frames.push(Some(w));
+                    }
+                    _ => {}
+                }
+            }
+            frames
+        }
+
+        /// Set the contrast and draw a small region through `iface`, returning the framing of
+        /// the words sent.
+        fn draw_framed<DI>(iface: DI, log: &Log) -> Vec<Option<u8>>
+        where
+            DI: DisplayInterface,
+            DI::Error: core::fmt::Debug,
+        {
+            let mut disp = Display::new(iface, Px(128, 64), Px(0, 0));
+            disp.contrast(3).unwrap();
+            disp.region(Px(0, 0), Px(4, 1))
+                .unwrap()
+                .draw_packed([0x12, 0x34].iter().cloned())
+                .unwrap();
+            framing(log)
+        }
+
+        #[test]
+        fn one_transaction_per_region() {
+            #[rustfmt::skip]
+            let expected = [
+                Some(0xC7), None, Some(3), None,
+                Some(0x15), Some(0), Some(0), Some(0x75), Some(0), Some(0), Some(0x5C),
+                Some(0x12), Some(0x34), None,
+            ];
+            let log = Log::default();
+            let iface = BlockingSpiInterface::with_cs(
+                Spi(log.clone(), 0),
+                Pin(log.clone(), Event::Dc),
+                Pin(log.clone(), Event::Cs),
+            );
+            assert_eq!(draw_framed(iface, &log), expected);
+
+            #[cfg(feature = "nb")]
+            {
+                let log = Log::default();
+                let iface = SpiInterface::with_cs(
+                    Spi(log.clone(), 0),
+                    Pin(log.clone(), Event::Dc),
+                    Pin(log.clone(), Event::Cs),
+                );
+                assert_eq!(draw_framed(iface, &log), expected);
+            }
+        }
+
+        /// Image data sent word by word through a `BlockingSpiInterface` is written in chunks.
+        #[cfg(feature = "nb")]
+        #[test]
+        fn blocking_writes_chunked() {
+            struct Writes(Rc<RefCell<Vec<usize>>>);
+
+            impl hal::blocking::spi::Write<u8> for Writes {
+                type Error = Infallible;
+                fn write(&mut self, words: &[u8]) -> Result<(), Infallible> {
+                    self.0.borrow_mut().push(words.len());
+                    Ok(())
+                }
+            }
+
+            let writes = Rc::new(RefCell::new(Vec::new()));
+            let log = Log::default();
+            let iface = BlockingSpiInterface::new(Writes(writes.clone()), Pin(log, Event::Dc));
+            let mut disp = Display::new(iface, Px(128, 64), Px(0, 0));
+            disp.region(Px(0, 0), Px(80, 1))
+                .unwrap()
+                .draw_packed(0..40)
+                .unwrap();
+            // The column and row address commands and their arguments, then the image data.
+            assert_eq!(*writes.borrow(), [1, 2, 1, 2, 1, 32, 8]);
+        }
+
+        #[cfg(feature = "nb")]
+        #[test]
+        fn end_transaction_waits_for_data() {
+            let log = Log::default();
+            let iface = SpiInterface::with_cs(
+                SlowSpi(log.clone(), 0, false),
+                Pin(log.clone(), Event::Dc),
+                Pin(log.clone(), Event::Cs),
+            );
+            let mut disp = Display::new(iface, Px(128, 64), Px(0, 0));
+            disp.region(Px(0, 0), Px(8, 1))
+                .unwrap()
+                .draw_packed([0x12, 0x34, 0x56, 0x78].iter().cloned())
+                .unwrap();
+            // /CS is only deasserted once the word received for every word sent has been read.
+            let log = log.borrow();
+            let deselect = log.iter().rposition(|e| *e == Event::Cs(true)).unwrap();
+            let sent = log.iter().filter(|e| matches!(e, Event::Word(_))).count();
+            let read = log[..deselect]
+                .iter()
+                .filter(|e| **e == Event::Read)
+                .count();
+            assert_eq!((sent, read), (11, 11));
+        }
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+pub mod mock {
+    //! A mock interface which records whatever was sent to it, for testing code that drives a
+    //! `Display` without any hardware attached. Enable the `testing` feature to use it from other
+    //! crates.
+    //!
+    //! A `MockInterface` shares its record with any handles created by `MockInterface::split`, so
+    //! one handle can be given to the `Display` while another is kept to inspect the command
+    //! stream.
+
+    use super::simulator::ChipModel;
+    use super::DisplayInterface;
+    use crate::display::PixelCoord;
+    #[cfg(feature = "nb")]
+    use nb;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::vec::Vec;
+
+    /// A transfer recorded by a `MockInterface`.
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum Sent {
+        /// A command word.
+        Cmd(u8),
+        /// A run of consecutive data words.
+        Data(Vec<u8>),
+    }
+
+    /// A `DisplayInterface` that records the command stream sent to it.
+    #[derive(Default)]
+    pub struct MockInterface {
+        sent: Rc<RefCell<Vec<Sent>>>,
+    }
+
+    impl MockInterface {
+        /// Create a new mock interface with an empty record.
+        pub fn new() -> Self {
+            MockInterface {
+                sent: Rc::new(RefCell::new(Vec::new())),
+            }
+        }
+        /// Create another handle sharing the same record.
+        pub fn split(&self) -> Self {
+            Self {
+                sent: self.sent.clone(),
+            }
+        }
+        /// A copy of everything recorded so far.
+        pub fn sent(&self) -> Vec<Sent> {
+            self.sent.borrow().clone()
+        }
+        /// Assert that the record consists of exactly one command `cmd` followed by `data`, or by
+        /// nothing if `data` is empty.
+        pub fn check(&self, cmd: u8, data: &[u8]) {
+            let sent = self.sent.borrow();
+            if data.is_empty() {
+                assert_eq!(sent.len(), 1);
+            } else {
+                assert_eq!(sent.len(), 2);
+                assert_eq!(sent[1], Sent::Data(data.to_vec()));
+            }
+            assert_eq!(sent[0], Sent::Cmd(cmd));
+        }
+        /// Assert that the record is exactly `expect`.
+        pub fn check_multi(&self, expect: &[Sent]) {
+            assert_eq!(*self.sent.borrow(), expect);
+        }
+        /// Forget everything recorded so far.
+        pub fn clear(&mut self) {
+            self.sent.borrow_mut().clear()
+        }
+        /// Replay everything sent so far into a `ChipModel` of a display module with the given
+        /// size and offset, so tests can check the image that would actually be visible.
+        pub fn chip_model(&self, panel_size: PixelCoord, panel_offset: PixelCoord) -> ChipModel {
+            let mut model = ChipModel::new(panel_size, panel_offset);
+            for sent in self.sent.borrow().iter() {
+                match sent {
+                    Sent::Cmd(c) => model.command(*c),
+                    Sent::Data(d) => d.iter().for_each(|b| model.data(*b)),
+                }
+            }
+            model
+        }
+    }
+
+    impl DisplayInterface for MockInterface {
+        type Error = core::convert::Infallible;
+
+        #[cfg(feature = "nb")]
+        const NON_BLOCKING: bool = true;
+
+        fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+            self.sent.borrow_mut().push(Sent::Cmd(cmd));
+            Ok(())
+        }
+        fn send_data(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+            self.sent.borrow_mut().push(Sent::Data(data.to_vec()));
+            Ok(())
+        }
+        #[cfg(feature = "nb")]
+        fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+            let mut sent = self.sent.borrow_mut();
+            if let Some(Sent::Data(ref mut d)) = sent.last_mut() {
+                d.push(word);
+            } else {
+                sent.push(Sent::Data(vec![word]));
+            }
+            Ok(())
+        }
+    }
+}
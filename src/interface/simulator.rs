@@ -0,0 +1,817 @@
+//! A host-side simulation of an SSD1322 and the display module attached to it, for developing UI
+//! layouts on a desktop without hardware, and for testing what a command stream actually puts on
+//! the screen.
+//!
+//! `ChipModel` interprets the command stream into a model of the chip's display RAM and registers,
+//! including the address window and increment axis, column and nibble remapping, start line,
+//! display offset and MUX ratio, and can render the image that would be visible on the module.
+//! `SimulatorInterface` wraps a `ChipModel` in a `DisplayInterface` so that a `Display` can drive
+//! it directly, and can write the rendered image to a PGM file.
+//!
+//! The COM scan direction and COM layout settings are not modeled, since their effect depends on
+//! how the module wires the COM lines to the OLED rows; the model assumes they are set correctly
+//! for the module, so that COM line 0 corresponds to the top row of the panel.
+//!
+//! For live previews of animation and scrolling, `SimulatorInterface::with_window` shows the
+//! simulated module in an `embedded-graphics-simulator` window, in its 16 gray levels, refreshed as
+//! commands and image data arrive. It needs the `simulator-window` feature and the SDL2 library.
+//! Without SDL2, the `simulator-display` feature still draws the image into an
+//! `embedded-graphics-simulator` display with `ChipModel::simulator_display`. Other front-ends can
+//! be driven from a callback installed with `SimulatorInterface::with_observer`, which is invoked
+//! whenever the visible image may have changed.
+
+use std::boxed::Box;
+#[cfg(feature = "simulator-window")]
+use std::cell::RefCell;
+use std::io;
+#[cfg(feature = "simulator-window")]
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+#[cfg(feature = "simulator-display")]
+use embedded_graphics_core::{pixelcolor::Gray4, prelude::*};
+#[cfg(feature = "simulator-display")]
+use embedded_graphics_simulator::SimulatorDisplay;
+#[cfg(feature = "simulator-window")]
+use embedded_graphics_simulator::{OutputSettingsBuilder, SimulatorEvent, Window};
+
+use super::{DisplayInterface, ReadableInterface};
+use crate::chip::Ssd1322;
+use crate::command::consts::*;
+use crate::display::{check_geometry, PixelCoord};
+#[cfg(feature = "nb")]
+use nb;
+
+/// The display mode register, which affects the rendered image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    BlankDark,
+    BlankBright,
+    Normal,
+    Inverse,
+}
+
+/// The number of argument bytes accepted by each command opcode.
+fn arg_count(cmd: u8) -> usize {
+    match cmd {
+        0x15 | 0x75 | 0xA0 | 0xA8 | 0xB4 | 0xD1 => 2,
+        0xA1 | 0xA2 | 0xAB | 0xB1 | 0xB3 | 0xB5 | 0xB6 | 0xBB | 0xBE | 0xC1 | 0xC7 | 0xCA
+        | 0xFD => 1,
+        0xB8 => 15,
+        _ => 0,
+    }
+}
+
+/// A model of an SSD1322 attached to a display module of a given size and offset, which interprets
+/// a stream of commands and data into the resulting display RAM contents and visible image.
+pub struct ChipModel {
+    panel_size: PixelCoord,
+    panel_offset: PixelCoord,
+    /// Display RAM, one byte per nibble, in address order: each row is 120 column addresses of 4
+    /// nibbles, where nibble 0 is the high nibble of the first byte written to that column.
+    ram: Vec<u8>,
+    /// The most recently received command, and the arguments received for it so far.
+    cmd: Option<u8>,
+    args: Vec<u8>,
+    col_start: u8,
+    col_end: u8,
+    row_start: u8,
+    row_end: u8,
+    /// The RAM write pointer. `col` is a column address, and `half` selects which of the two bytes
+    /// in that column the next data byte fills.
+    col: u8,
+    row: u8,
+    half: u8,
+    vertical_increment: bool,
+    column_reverse: bool,
+    nibble_forward: bool,
+    start_line: u8,
+    display_offset: u8,
+    mux_ratio: u8,
+    mode: Mode,
+    sleep: bool,
+    /// Set when the write pointer wraps around to the start of the address window.
+    wrapped: bool,
+    /// Set by `ReadImageData` until the dummy word has been read.
+    read_dummy: bool,
+}
+
+impl ChipModel {
+    /// Create a model of a display module with the given viewable size and offset, with the same
+    /// meaning as the corresponding arguments to `Display::new`. The chip starts out in its
+    /// power-on reset state, in sleep mode with display RAM cleared.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the module does not fit the chip, as `Display::new` does.
+    pub fn new(panel_size: PixelCoord, panel_offset: PixelCoord) -> Self {
+        check_geometry::<Ssd1322>(panel_size, panel_offset);
+        ChipModel {
+            panel_size: panel_size,
+            panel_offset: panel_offset,
+            ram: vec![0; NUM_PIXEL_COLS as usize * NUM_PIXEL_ROWS as usize],
+            cmd: None,
+            args: Vec::new(),
+            col_start: 0,
+            col_end: BUF_COL_MAX,
+            row_start: 0,
+            row_end: PIXEL_ROW_MAX,
+            col: 0,
+            row: 0,
+            half: 0,
+            vertical_increment: false,
+            column_reverse: false,
+            nibble_forward: false,
+            start_line: 0,
+            display_offset: 0,
+            mux_ratio: NUM_PIXEL_ROWS,
+            mode: Mode::Normal,
+            sleep: true,
+            wrapped: false,
+            read_dummy: false,
+        }
+    }
+
+    /// Receive a command word.
+    pub fn command(&mut self, cmd: u8) {
+        self.cmd = Some(cmd);
+        self.args.clear();
+        if arg_count(cmd) == 0 {
+            self.execute(cmd);
+        }
+    }
+
+    /// Receive a data word, which is either an argument to the last command, or image data if the
+    /// last command was `WriteImageData`.
+    pub fn data(&mut self, byte: u8) {
+        match self.cmd {
+            Some(0x5C) => self.write_image_byte(byte),
+            Some(cmd) if self.args.len() < arg_count(cmd) => {
+                self.args.push(byte);
+                if self.args.len() == arg_count(cmd) {
+                    self.execute(cmd);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The gray level 0-15 stored in display RAM at nibble address `col` (i.e. column address
+    /// times 4 plus the nibble index within the column) of row `row`, or `None` if the address is
+    /// outside display RAM.
+    pub fn ram_pixel(&self, col: u16, row: u8) -> Option<u8> {
+        if col >= NUM_PIXEL_COLS || row >= NUM_PIXEL_ROWS {
+            return None;
+        }
+        Some(self.ram[row as usize * NUM_PIXEL_COLS as usize + col as usize])
+    }
+
+    /// The gray level 0-15 that display RAM presents to segment (pixel column) driver `seg` in row
+    /// `row`, after column and nibble remapping.
+    fn segment_pixel(&self, seg: u16, row: u8) -> u8 {
+        let group = seg / 4;
+        let col = if self.column_reverse {
+            BUF_COL_MAX as u16 - group
+        } else {
+            group
+        };
+        let nibble = if self.nibble_forward {
+            seg % 4
+        } else {
+            3 - seg % 4
+        };
+        self.ram[row as usize * NUM_PIXEL_COLS as usize + (col * 4 + nibble) as usize]
+    }
+
+    /// Render the image currently visible on the display module, as a row-major vector of gray
+    /// levels 0-15, one byte per pixel.
+    pub fn render(&self) -> Vec<u8> {
+        let (width, height) = (self.panel_size.0 as usize, self.panel_size.1 as usize);
+        let mut image = Vec::with_capacity(width * height);
+        for r in 0..height {
+            // The COM line driving this panel row, relative to the first active COM line.
+            let com = (r as i16 + self.panel_offset.1 - self.display_offset as i16)
+                .rem_euclid(NUM_PIXEL_ROWS as i16);
+            let ram_row = (com + self.start_line as i16).rem_euclid(NUM_PIXEL_ROWS as i16) as u8;
+            for c in 0..width {
+                let level = if com >= self.mux_ratio as i16 {
+                    0
+                } else {
+                    self.segment_pixel(c as u16 + self.panel_offset.0 as u16, ram_row)
+                };
+                image.push(match self.mode {
+                    _ if self.sleep => 0,
+                    Mode::BlankDark => 0,
+                    Mode::BlankBright => 15,
+                    Mode::Normal => level,
+                    Mode::Inverse => 15 - level,
+                });
+            }
+        }
+        image
+    }
+
+    /// Write the image currently visible on the display module to `w` as a binary PGM (P5) file
+    /// with a maximum gray value of 15.
+    pub fn write_pgm<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        write!(w, "P5\n{} {}\n15\n", self.panel_size.0, self.panel_size.1)?;
+        w.write_all(&self.render())
+    }
+
+    /// Draw the image currently visible on the display module into an `embedded-graphics-simulator`
+    /// display the size of the module, in 16 gray levels.
+    ///
+    /// Available with the `simulator-display` feature.
+    #[cfg(feature = "simulator-display")]
+    pub fn simulator_display(&self) -> SimulatorDisplay<Gray4> {
+        let size = Size::new(self.panel_size.0 as u32, self.panel_size.1 as u32);
+        let mut display = SimulatorDisplay::new(size);
+        self.draw_simulator_display(&mut display);
+        display
+    }
+
+    /// Draw the image currently visible on the display module into `display`, as for
+    /// `simulator_display`.
+    #[cfg(feature = "simulator-display")]
+    fn draw_simulator_display(&self, display: &mut SimulatorDisplay<Gray4>) {
+        let width = self.panel_size.0 as usize;
+        let pixels = self.render().into_iter().enumerate().map(|(i, level)| {
+            let point = Point::new((i % width) as i32, (i / width) as i32);
+            Pixel(point, Gray4::new(level))
+        });
+        let Ok(()) = display.draw_iter(pixels);
+    }
+
+    /// Whether the write pointer has wrapped around the address window since the last call.
+    #[cfg(feature = "nb")]
+    fn take_wrapped(&mut self) -> bool {
+        let wrapped = self.wrapped;
+        self.wrapped = false;
+        wrapped
+    }
+
+    /// Read a data word, which is image data from the address pointer if the last command was
+    /// `ReadImageData`. As on the parallel buses of the real chip, the first word read after the
+    /// command is a dummy.
+    pub fn read(&mut self) -> u8 {
+        if self.cmd != Some(0x5D) {
+            return 0;
+        }
+        if self.read_dummy {
+            self.read_dummy = false;
+            return 0;
+        }
+        let byte = match self.pointer_index() {
+            Some(base) => self.ram[base] << 4 | self.ram[base + 1],
+            None => 0,
+        };
+        self.advance();
+        byte
+    }
+
+    /// The index into `ram` of the first nibble of the byte at the address pointer, if it lies
+    /// within display RAM.
+    fn pointer_index(&self) -> Option<usize> {
+        let nibble = self.col as usize * 4 + self.half as usize * 2;
+        if nibble < NUM_PIXEL_COLS as usize && (self.row as usize) < NUM_PIXEL_ROWS as usize {
+            Some(self.row as usize * NUM_PIXEL_COLS as usize + nibble)
+        } else {
+            None
+        }
+    }
+
+    /// Store one byte of image data at the write pointer and advance it.
+    fn write_image_byte(&mut self, byte: u8) {
+        if let Some(base) = self.pointer_index() {
+            self.ram[base] = byte >> 4;
+            self.ram[base + 1] = byte & 0x0F;
+        }
+        self.advance();
+    }
+
+    /// Advance the address pointer by one byte within the address window.
+    fn advance(&mut self) {
+        self.half ^= 1;
+        if self.half != 0 {
+            return;
+        }
+        if self.vertical_increment {
+            if self.row < self.row_end {
+                self.row += 1;
+                return;
+            }
+            self.row = self.row_start;
+            if self.col < self.col_end {
+                self.col += 1;
+                return;
+            }
+            self.col = self.col_start;
+        } else {
+            if self.col < self.col_end {
+                self.col += 1;
+                return;
+            }
+            self.col = self.col_start;
+            if self.row < self.row_end {
+                self.row += 1;
+                return;
+            }
+            self.row = self.row_start;
+        }
+        self.wrapped = true;
+    }
+
+    /// Apply a command whose arguments have all been received.
+    fn execute(&mut self, cmd: u8) {
+        let args = &self.args;
+        match cmd {
+            0x15 => {
+                self.col_start = args[0];
+                self.col_end = args[1];
+                self.col = args[0];
+                self.half = 0;
+            }
+            0x75 => {
+                self.row_start = args[0];
+                self.row_end = args[1];
+                self.row = args[0];
+                self.half = 0;
+            }
+            0x5D => self.read_dummy = true,
+            0xA0 => {
+                self.vertical_increment = args[0] & 0x01 != 0;
+                self.column_reverse = args[0] & 0x02 != 0;
+                self.nibble_forward = args[0] & 0x04 != 0;
+            }
+            0xA1 => self.start_line = args[0],
+            0xA2 => self.display_offset = args[0],
+            0xA4 => self.mode = Mode::BlankDark,
+            0xA5 => self.mode = Mode::BlankBright,
+            0xA6 => self.mode = Mode::Normal,
+            0xA7 => self.mode = Mode::Inverse,
+            0xAE => self.sleep = true,
+            0xAF => self.sleep = false,
+            0xCA => self.mux_ratio = args[0] + 1,
+            _ => {}
+        }
+    }
+}
+
+/// A callback notified of changes to the simulated image.
+type Observer = Box<dyn FnMut(&SimulatorInterface)>;
+
+/// The shortest time between refreshes of a preview window.
+#[cfg(feature = "simulator-window")]
+const WINDOW_REFRESH: Duration = Duration::from_millis(16);
+
+/// A live preview of the simulated image in an `embedded-graphics-simulator` window.
+#[cfg(feature = "simulator-window")]
+struct PreviewWindow {
+    window: Window,
+    display: SimulatorDisplay<Gray4>,
+    /// When the window was last refreshed, if it has been opened yet.
+    shown: Option<Instant>,
+}
+
+#[cfg(feature = "simulator-window")]
+impl PreviewWindow {
+    /// Refresh the window with the image visible on `model`, unless it was refreshed less than
+    /// `WINDOW_REFRESH` ago and `force` is not set. Closing the window ends the process.
+    fn refresh(&mut self, model: &ChipModel, force: bool) {
+        if !force && self.shown.is_some_and(|t| t.elapsed() < WINDOW_REFRESH) {
+            return;
+        }
+        model.draw_simulator_display(&mut self.display);
+        self.window.update(&self.display);
+        self.shown = Some(Instant::now());
+        if self.window.events().any(|e| e == SimulatorEvent::Quit) {
+            std::process::exit(0);
+        }
+    }
+}
+
+/// A `DisplayInterface` which simulates an SSD1322 attached to a display module of a given size and
+/// offset, rather than sending anything to hardware.
+pub struct SimulatorInterface {
+    model: ChipModel,
+    /// Set when the visible image may have changed since the observer was last notified.
+    changed: bool,
+    observer: Option<Observer>,
+    #[cfg(feature = "simulator-window")]
+    window: Option<RefCell<PreviewWindow>>,
+}
+
+impl SimulatorInterface {
+    /// Create a simulator for a display module with the given viewable size and offset, with the
+    /// same meaning as the corresponding arguments to `Display::new`. The chip starts out in its
+    /// power-on reset state, in sleep mode with display RAM cleared.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the module does not fit the chip, as `Display::new` does.
+    pub fn new(panel_size: PixelCoord, panel_offset: PixelCoord) -> Self {
+        SimulatorInterface {
+            model: ChipModel::new(panel_size, panel_offset),
+            changed: false,
+            observer: None,
+            #[cfg(feature = "simulator-window")]
+            window: None,
+        }
+    }
+
+    /// Install a callback to be notified when the visible image may have changed, so that a live
+    /// preview can be refreshed. The observer is called after each command and each buffer of data
+    /// sent to the simulator. Image data sent byte-wise with `send_data_async` is coalesced, and the
+    /// observer is notified when the write pointer wraps around the address window (i.e. a whole
+    /// region has been drawn) or when the next command arrives.
+    pub fn with_observer<F>(self, observer: F) -> Self
+    where
+        F: FnMut(&SimulatorInterface) + 'static,
+    {
+        Self {
+            observer: Some(Box::new(observer)),
+            ..self
+        }
+    }
+
+    /// Show the simulated display module in a live `embedded-graphics-simulator` window titled
+    /// `title`, with each pixel drawn as a `scale` by `scale` square in its gray level. The window
+    /// is refreshed as commands and image data arrive, at most 60 times a second, so animation and
+    /// scrolling can be watched as they would appear on the module. It opens with the first
+    /// refresh, and closing it ends the process.
+    ///
+    /// Available with the `simulator-window` feature, which needs the SDL2 library.
+    #[cfg(feature = "simulator-window")]
+    pub fn with_window(self, title: &str, scale: u32) -> Self {
+        let settings = OutputSettingsBuilder::new()
+            .scale(scale)
+            .pixel_spacing(0)
+            .build();
+        let window = PreviewWindow {
+            window: Window::new(title, &settings),
+            display: self.model.simulator_display(),
+            shown: None,
+        };
+        Self {
+            window: Some(RefCell::new(window)),
+            ..self
+        }
+    }
+
+    /// Refresh the window opened by `with_window` with the final image, and wait for it to be
+    /// closed, which ends the process. Returns immediately if there is no window.
+    #[cfg(feature = "simulator-window")]
+    pub fn wait_for_window_close(&self) {
+        if let Some(ref window) = self.window {
+            loop {
+                window.borrow_mut().refresh(&self.model, true);
+                std::thread::sleep(WINDOW_REFRESH);
+            }
+        }
+    }
+
+    /// The model of the simulated chip.
+    pub fn model(&self) -> &ChipModel {
+        &self.model
+    }
+
+    /// Render the image currently visible on the display module. See `ChipModel::render`.
+    pub fn render(&self) -> Vec<u8> {
+        self.model.render()
+    }
+
+    /// Write the image currently visible on the display module to `w` as a PGM file. See
+    /// `ChipModel::write_pgm`.
+    pub fn write_pgm<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        self.model.write_pgm(w)
+    }
+
+    /// Call the observer, and refresh the window, if the image may have changed since they were
+    /// last called.
+    fn notify(&mut self) {
+        if !self.changed {
+            return;
+        }
+        self.changed = false;
+        if let Some(mut observer) = self.observer.take() {
+            observer(self);
+            self.observer = Some(observer);
+        }
+        #[cfg(feature = "simulator-window")]
+        if let Some(ref mut window) = self.window {
+            window.get_mut().refresh(&self.model, false);
+        }
+    }
+}
+
+impl DisplayInterface for SimulatorInterface {
+    type Error = core::convert::Infallible;
+
+    #[cfg(feature = "nb")]
+    const NON_BLOCKING: bool = true;
+
+    fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+        self.notify();
+        self.model.command(cmd);
+        self.changed = true;
+        if arg_count(cmd) == 0 {
+            self.notify();
+        }
+        Ok(())
+    }
+
+    fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        for byte in buf {
+            self.model.data(*byte);
+        }
+        self.changed = true;
+        self.notify();
+        Ok(())
+    }
+
+    #[cfg(feature = "nb")]
+    fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.model.data(word);
+        self.changed = true;
+        if self.model.take_wrapped() {
+            self.notify();
+        }
+        Ok(())
+    }
+}
+
+impl ReadableInterface for SimulatorInterface {
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        for byte in buf.iter_mut() {
+            *byte = self.model.read();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{ComLayout, ComScanDirection, CommandError};
+    use crate::config::Config;
+    use crate::display::{Display, PixelCoord as Px};
+
+    fn init_display() -> Display<SimulatorInterface> {
+        let mut disp = Display::new(
+            SimulatorInterface::new(Px(16, 16), Px(8, 4)),
+            Px(16, 16),
+            Px(8, 4),
+        );
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::Progressive);
+        disp.init(cfg).unwrap();
+        disp
+    }
+
+    #[test]
+    fn render_region() {
+        let mut disp = init_display();
+        disp.region(Px(4, 1), Px(8, 3))
+            .unwrap()
+            .draw_packed([0x12, 0x34, 0x56, 0x78].iter().cloned())
+            .unwrap();
+        let image = disp.interface().render();
+        assert_eq!(image.len(), 16 * 16);
+        assert_eq!(&image[16 + 4..16 + 8], &[1, 2, 3, 4]);
+        assert_eq!(&image[32 + 4..32 + 8], &[5, 6, 7, 8]);
+        assert_eq!(image.iter().map(|&p| p as u32).sum::<u32>(), 36);
+        // The panel column offset is applied to RAM addresses.
+        assert_eq!(disp.interface().model().ram_pixel(12, 1), Some(1));
+        assert_eq!(disp.interface().model().ram_pixel(480, 1), None);
+        assert_eq!(disp.interface().model().ram_pixel(12, 128), None);
+    }
+
+    #[test]
+    fn read_back() {
+        let mut disp = init_display();
+        let image = [0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0];
+        disp.region(Px(4, 1), Px(12, 3))
+            .unwrap()
+            .draw_packed(image.iter().cloned())
+            .unwrap();
+        let mut buf = [0; 10];
+        let mut region = disp.region(Px(4, 1), Px(12, 3)).unwrap();
+        assert_eq!(region.read_packed(&mut buf).unwrap(), 8);
+        assert_eq!(&buf[..8], &image);
+        // A read-modify-write of a single pixel.
+        let mut buf = [0; 2];
+        let mut region = disp.region(Px(8, 2), Px(12, 3)).unwrap();
+        region.read_packed(&mut buf).unwrap();
+        buf[0] ^= 0x0F;
+        region.draw_packed(buf.iter().cloned()).unwrap();
+        assert_eq!(disp.interface().render()[32 + 9], 0x0E ^ 0x0F);
+    }
+
+    #[test]
+    fn redraw_after_partial_read() {
+        let mut disp = init_display();
+        let mut region = disp.region(Px(0, 0), Px(8, 2)).unwrap();
+        region.draw_packed(0x10..0x18).unwrap();
+        let mut buf = [0; 3];
+        region.read_packed(&mut buf).unwrap();
+        assert_eq!(buf, [0x10, 0x11, 0x12]);
+        // The read left the address pointer in the middle of the window, so it is set up again.
+        let image = [0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0];
+        region.draw_packed(image.iter().cloned()).unwrap();
+        let image = disp.interface().render();
+        assert_eq!(image[..8], [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(image[16..24], [9, 10, 11, 12, 13, 14, 15, 0]);
+    }
+
+    /// A simulator whose read-back is corrupted, like a bad data line on a parallel bus.
+    struct Corrupted(SimulatorInterface);
+
+    impl DisplayInterface for Corrupted {
+        type Error = core::convert::Infallible;
+
+        fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+            self.0.send_command(cmd)
+        }
+        fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+            self.0.send_data(buf)
+        }
+        #[cfg(feature = "nb")]
+        fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+            self.0.send_data_async(word)
+        }
+    }
+
+    impl ReadableInterface for Corrupted {
+        fn read_data(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+            self.0.read_data(buf)?;
+            buf.iter_mut().for_each(|byte| *byte |= 0x08);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn draw_verified() {
+        let mut disp = init_display();
+        let mut region = disp.region(Px(4, 1), Px(12, 3)).unwrap();
+        assert!(region.draw_packed_verified(0x10..0x18).is_ok());
+
+        let iface = Corrupted(SimulatorInterface::new(Px(16, 16), Px(8, 4)));
+        let mut disp = Display::new(iface, Px(16, 16), Px(8, 4));
+        let mut region = disp.region(Px(4, 1), Px(12, 3)).unwrap();
+        assert_eq!(
+            region.draw_packed_verified(0x10..0x18),
+            Err(CommandError::VerifyFailed)
+        );
+    }
+
+    #[test]
+    fn render_pan_and_mode() {
+        let mut disp = init_display();
+        disp.region(Px(0, 2), Px(4, 3))
+            .unwrap()
+            .draw_packed([0xFF, 0xFF].iter().cloned())
+            .unwrap();
+        disp.vertical_pan(2).unwrap();
+        assert_eq!(&disp.interface().render()[0..5], &[15, 15, 15, 15, 0]);
+
+        disp.sleep(true).unwrap();
+        assert!(disp.interface().render().iter().all(|&p| p == 0));
+        disp.sleep(false).unwrap();
+        assert_eq!(&disp.interface().render()[0..5], &[15, 15, 15, 15, 0]);
+    }
+
+    #[test]
+    fn observer() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let calls = Rc::new(Cell::new(0));
+        let sum = Rc::new(Cell::new(0u32));
+        let (c, s) = (calls.clone(), sum.clone());
+        let sim = SimulatorInterface::new(Px(16, 16), Px(0, 0)).with_observer(move |sim| {
+            c.set(c.get() + 1);
+            s.set(sim.render().iter().map(|&p| p as u32).sum());
+        });
+        let mut disp = Display::new(sim, Px(16, 16), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::Progressive);
+        disp.init(cfg).unwrap();
+        let after_init = calls.get();
+        assert!(after_init > 0);
+
+        // Two commands to set the address window, one for write, and one when the region fills.
+        disp.region(Px(0, 0), Px(4, 2))
+            .unwrap()
+            .draw_packed(core::iter::repeat(0x11))
+            .unwrap();
+        assert_eq!(calls.get(), after_init + 4);
+        assert_eq!(sum.get(), 8);
+    }
+
+    #[test]
+    fn remapping() {
+        use crate::command::*;
+
+        let mut sim = SimulatorInterface::new(Px(8, 2), Px(0, 0));
+        let remap = |ia, cr, nr| {
+            Command::SetRemapping(
+                ia,
+                cr,
+                nr,
+                ComScanDirection::RowZeroFirst,
+                ComLayout::Progressive,
+            )
+        };
+        let write = |sim: &mut SimulatorInterface, data: &[u8]| {
+            Command::SetColumnAddress(0, 1).send(sim).unwrap();
+            Command::SetRowAddress(0, 1).send(sim).unwrap();
+            BufCommand::WriteImageData(data).send(sim).unwrap();
+        };
+        Command::SetMuxRatio(16).send(&mut sim).unwrap();
+        Command::SetSleepMode(false).send(&mut sim).unwrap();
+
+        // Horizontal increment, forward columns and nibbles.
+        let data = [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF];
+        remap(
+            IncrementAxis::Horizontal,
+            ColumnRemap::Forward,
+            NibbleRemap::Forward,
+        )
+        .send(&mut sim)
+        .unwrap();
+        write(&mut sim, &data);
+        assert_eq!(sim.render(), (0..16).collect::<Vec<u8>>());
+
+        // Vertical increment fills the rows of each column before moving on.
+        remap(
+            IncrementAxis::Vertical,
+            ColumnRemap::Forward,
+            NibbleRemap::Forward,
+        )
+        .send(&mut sim)
+        .unwrap();
+        write(&mut sim, &data);
+        #[rustfmt::skip]
+        assert_eq!(sim.render(), vec![
+            0, 1, 2, 3, 8, 9, 10, 11,
+            4, 5, 6, 7, 12, 13, 14, 15,
+        ]);
+
+        // Reversed nibbles flip each group of 4 pixels.
+        remap(
+            IncrementAxis::Horizontal,
+            ColumnRemap::Forward,
+            NibbleRemap::Reverse,
+        )
+        .send(&mut sim)
+        .unwrap();
+        write(&mut sim, &data);
+        #[rustfmt::skip]
+        assert_eq!(sim.render(), vec![
+            3, 2, 1, 0, 7, 6, 5, 4,
+            11, 10, 9, 8, 15, 14, 13, 12,
+        ]);
+
+        // Reversed columns put column address 0 at the far right of the chip.
+        let mut sim = SimulatorInterface::new(Px(8, 1), Px(472, 0));
+        Command::SetSleepMode(false).send(&mut sim).unwrap();
+        remap(
+            IncrementAxis::Horizontal,
+            ColumnRemap::Reverse,
+            NibbleRemap::Forward,
+        )
+        .send(&mut sim)
+        .unwrap();
+        write(&mut sim, &data[..4]);
+        assert_eq!(sim.render(), vec![4, 5, 6, 7, 0, 1, 2, 3]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn geometry_checked() {
+        SimulatorInterface::new(Px(480, 64), Px(8, 0));
+    }
+
+    #[test]
+    fn write_pgm() {
+        let sim = SimulatorInterface::new(Px(4, 2), Px(0, 0));
+        let mut out = Vec::new();
+        sim.write_pgm(&mut out).unwrap();
+        assert_eq!(&out[..], b"P5\n4 2\n15\n\0\0\0\0\0\0\0\0");
+    }
+
+    #[cfg(feature = "simulator-display")]
+    #[test]
+    fn simulator_display() {
+        let mut disp = init_display();
+        disp.region(Px(4, 1), Px(8, 3))
+            .unwrap()
+            .draw_packed([0x12, 0x34, 0x5F, 0x0F].iter().cloned())
+            .unwrap();
+        let display = disp.interface().model().simulator_display();
+        assert_eq!(display.size(), Size::new(16, 16));
+        assert_eq!(display.get_pixel(Point::new(4, 1)), Gray4::new(1));
+        assert_eq!(display.get_pixel(Point::new(7, 1)), Gray4::new(4));
+        assert_eq!(display.get_pixel(Point::new(7, 2)), Gray4::new(15));
+        assert_eq!(display.get_pixel(Point::new(6, 2)), Gray4::new(0));
+        assert_eq!(display.get_pixel(Point::new(0, 0)), Gray4::new(0));
+    }
+}
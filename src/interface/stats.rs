@@ -0,0 +1,216 @@
+//! A wrapper interface which collects statistics about the traffic sent through it, for budgeting
+//! bus bandwidth between the display and other peripherals. Wrap the real interface in a
+//! `StatsInterface` before handing it to `Display::new`, and read the figures back with
+//! `Display::stats`.
+
+use super::DisplayInterface;
+#[cfg(feature = "nb")]
+use nb;
+
+/// The command word for `BufCommand::WriteImageData`, which marks the start of each frame.
+const WRITE_IMAGE_DATA: u8 = 0x5C;
+
+/// Traffic statistics collected by a `StatsInterface`. Durations are in ticks of the clock
+/// provided to `StatsInterface::new`. The counters wrap around on overflow, like the clock, so a
+/// long-running application should reset them periodically with `StatsInterface::reset_stats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// The number of command words sent.
+    pub commands: u32,
+    /// The number of data words sent, including command arguments and image data.
+    pub data_bytes: u32,
+    /// The number of frames flushed, i.e. region draws started with `WriteImageData`.
+    pub frames: u32,
+    /// The longest duration of a single frame, from the `WriteImageData` command to the last image
+    /// data word.
+    pub max_draw_ticks: u32,
+    /// The sum of the durations of all frames.
+    pub total_draw_ticks: u64,
+}
+
+impl Stats {
+    /// The total number of words sent on the bus, commands and data alike.
+    pub fn bytes(&self) -> u32 {
+        self.commands.wrapping_add(self.data_bytes)
+    }
+
+    /// The mean duration of a frame, or 0 if no frames have been flushed.
+    pub fn avg_draw_ticks(&self) -> u32 {
+        match self.frames {
+            0 => 0,
+            n => (self.total_draw_ticks / n as u64) as u32,
+        }
+    }
+
+    fn record_draw(&mut self, ticks: u32) {
+        self.frames = self.frames.wrapping_add(1);
+        self.total_draw_ticks = self.total_draw_ticks.wrapping_add(ticks as u64);
+        if ticks > self.max_draw_ticks {
+            self.max_draw_ticks = ticks;
+        }
+    }
+}
+
+/// A `DisplayInterface` which passes everything through to an inner interface while counting
+/// commands, data words and frames, and timing frames with a user-provided clock.
+pub struct StatsInterface<DI, C> {
+    iface: DI,
+    clock: C,
+    stats: Stats,
+    /// The start and latest timestamps of the frame currently being drawn, if any.
+    draw: Option<(u32, u32)>,
+}
+
+impl<DI, C> StatsInterface<DI, C>
+where
+    DI: DisplayInterface,
+    C: FnMut() -> u32,
+{
+    /// Wrap `iface`, using `clock` to timestamp frames. `clock` returns a monotonic tick count in
+    /// any unit the application likes, and may wrap around.
+    pub fn new(iface: DI, clock: C) -> Self {
+        Self {
+            iface: iface,
+            clock: clock,
+            stats: Stats::default(),
+            draw: None,
+        }
+    }
+
+    /// The statistics collected so far, including the frame currently being drawn.
+    pub fn stats(&self) -> Stats {
+        let mut stats = self.stats;
+        if let Some((start, latest)) = self.draw {
+            stats.record_draw(latest.wrapping_sub(start));
+        }
+        stats
+    }
+
+    /// Reset all statistics to zero.
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+        self.draw = None;
+    }
+
+    /// Consume the wrapper, returning the inner interface.
+    pub fn into_inner(self) -> DI {
+        self.iface
+    }
+
+    fn note_data(&mut self, words: u32) {
+        self.stats.data_bytes = self.stats.data_bytes.wrapping_add(words);
+        if let Some((_, ref mut latest)) = self.draw {
+            *latest = (self.clock)();
+        }
+    }
+}
+
+impl<DI, C> DisplayInterface for StatsInterface<DI, C>
+where
+    DI: DisplayInterface,
+    C: FnMut() -> u32,
+{
+    type Error = DI::Error;
+
+    #[cfg(feature = "nb")]
+    const NON_BLOCKING: bool = DI::NON_BLOCKING;
+
+    fn send_command(&mut self, cmd: u8) -> Result<(), Self::Error> {
+        if let Some((start, latest)) = self.draw.take() {
+            self.stats.record_draw(latest.wrapping_sub(start));
+        }
+        self.stats.commands = self.stats.commands.wrapping_add(1);
+        self.iface.send_command(cmd)?;
+        if cmd == WRITE_IMAGE_DATA {
+            let now = (self.clock)();
+            self.draw = Some((now, now));
+        }
+        Ok(())
+    }
+
+    fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.iface.send_data(buf)?;
+        self.note_data(buf.len() as u32);
+        Ok(())
+    }
+
+    #[cfg(feature = "nb")]
+    fn send_data_async(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.iface.send_data_async(word)?;
+        self.note_data(1);
+        Ok(())
+    }
+
+    fn begin_transaction(&mut self) -> Result<(), Self::Error> {
+        self.iface.begin_transaction()
+    }
+
+    fn end_transaction(&mut self) -> Result<(), Self::Error> {
+        self.iface.end_transaction()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{ComLayout, ComScanDirection};
+    use crate::config::Config;
+    use crate::display::{Display, PixelCoord as Px};
+    use crate::interface::mock::MockInterface;
+    use std::cell::Cell;
+
+    #[test]
+    fn collect_stats() {
+        let now = Cell::new(0u32);
+        let iface = StatsInterface::new(MockInterface::new(), || {
+            now.set(now.get() + 10);
+            now.get()
+        });
+        let mut disp = Display::new(iface, Px(128, 64), Px(0, 0));
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        disp.init(cfg).unwrap();
+        let after_init = disp.stats();
+        assert_eq!(after_init.frames, 0);
+        assert_eq!(after_init.commands, 8);
+        assert_eq!(after_init.data_bytes, 5);
+        disp.reset_stats();
+
+        disp.region(Px(0, 0), Px(4, 2))
+            .unwrap()
+            .draw_packed([1, 2, 3, 4].iter().cloned())
+            .unwrap();
+        let stats = disp.stats();
+        assert_eq!(stats.frames, 1);
+        assert_eq!(stats.commands, 3);
+        assert_eq!(stats.data_bytes, 8);
+        assert_eq!(stats.bytes(), 11);
+        // The clock ticks on every interface call, and the timings below assume each byte of image
+        // data is sent with its own non-blocking write.
+        if !cfg!(feature = "nb") {
+            return;
+        }
+        assert_eq!(stats.max_draw_ticks, 40);
+
+        disp.region(Px(0, 0), Px(4, 1))
+            .unwrap()
+            .draw_packed([1, 2].iter().cloned())
+            .unwrap();
+        let stats = disp.stats();
+        assert_eq!(stats.frames, 2);
+        assert_eq!(stats.max_draw_ticks, 40);
+        assert_eq!(stats.avg_draw_ticks(), 30);
+    }
+
+    #[test]
+    fn counters_wrap() {
+        let mut iface = StatsInterface::new(MockInterface::new(), || 0);
+        iface.stats.commands = u32::MAX;
+        iface.stats.data_bytes = u32::MAX - 1;
+        iface.stats.frames = u32::MAX;
+        assert_eq!(iface.stats().bytes(), u32::MAX - 2);
+        iface.send_command(WRITE_IMAGE_DATA).unwrap();
+        iface.send_data(&[0, 0]).unwrap();
+        let stats = iface.stats();
+        assert_eq!((stats.commands, stats.data_bytes, stats.frames), (0, 0, 0));
+    }
+}
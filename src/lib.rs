@@ -22,7 +22,8 @@
 //!
 //! - Referring to your display module's datasheet, create a `Config` to set the various parameters
 //!   in the chip appropriately for the OLEDs in your display module, and send it to the display
-//!   with `Display::init`.
+//!   with `Display::init`. Alternatively, a `DisplayBuilder` does these two steps, and resetting
+//!   the chip, in one call.
 //!
 //! - To draw, call `Display::region` or `Display::overscanned_region` to obtain a region instance
 //!   for the rectangular area where you want to write image information. Use the `draw_packed` or
@@ -36,17 +37,52 @@
 //! Example code is available in the `examples` folder.
 
 #![cfg_attr(not(feature = "std"), no_std)]
+// Explicit `field: field` initializers are deliberate house style. So are `if false || ...`
+// condition lists, and the functions which use them allow `clippy::nonminimal_bool`.
+#![allow(clippy::redundant_field_names)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
 #[cfg(feature = "std")]
 extern crate core;
 
+#[cfg(feature = "animation")]
+pub mod animation;
+#[cfg(feature = "assets")]
+pub mod assets;
+#[cfg(feature = "embassy")]
+pub mod asynch;
+pub mod chip;
 pub mod command;
 pub mod config;
+#[cfg(feature = "text")]
+pub mod console;
+pub mod crc;
 pub mod display;
+#[cfg(feature = "graphics")]
+pub mod graphics;
+#[cfg(feature = "gray-scale")]
+pub mod gray_scale;
+#[cfg(feature = "image")]
+pub mod image;
 pub mod interface;
+#[cfg(feature = "power")]
+pub mod power;
+#[cfg(feature = "scheduler")]
+pub mod scheduler;
+pub mod sprite;
+#[cfg(test)]
+mod test_support;
+#[cfg(feature = "text")]
+pub mod text;
+#[cfg(feature = "widgets")]
+pub mod widgets;
 
 // Re-exports for primary API.
 pub use crate::command::{consts, ComLayout, ComScanDirection};
 pub use crate::config::Config;
-pub use crate::display::{Display, PixelCoord};
+pub use crate::display::builder::DisplayBuilder;
+pub use crate::display::{Display, PixelCoord, Rect};
+pub use crate::interface::spi::BlockingSpiInterface;
+#[cfg(feature = "nb")]
 pub use crate::interface::spi::SpiInterface;
@@ -0,0 +1,268 @@
+//! Sequencing of the panel's high voltage supply.
+//!
+//! OLED modules need a separate high voltage supply (VCC, typically 12-15 V) which on many boards
+//! comes from a boost converter with an enable pin. The datasheet requires VCC to be switched on
+//! only after the chip is powered and reset, and off only after the display is put to sleep. A
+//! `PanelPower` implementation lets `Display::init_with_power` and `Display::power_off_with` do
+//! this in the right order.
+//!
+//! For battery powered devices, `PowerProfile` bundles the settings which trade refresh rate,
+//! brightness and active area for power, so that `Display::set_power_profile` can switch between
+//! normal and power-saving operation in one call.
+
+use embedded_hal as hal;
+
+use crate::command::{Command, CommandError};
+use crate::display::Display;
+use crate::interface;
+
+/// Control over the VCC supply of a panel.
+pub trait PanelPower {
+    /// The error type returned when switching VCC fails.
+    type Error;
+
+    /// Switch VCC on.
+    fn vcc_on(&mut self) -> Result<(), Self::Error>;
+
+    /// Switch VCC off.
+    fn vcc_off(&mut self) -> Result<(), Self::Error>;
+
+    /// The time in milliseconds VCC takes to reach its working voltage after `vcc_on`, which the
+    /// display waits before driving the panel. The default suits a typical boost converter; use a
+    /// longer time for converters with a slow soft start.
+    fn vcc_settle_ms(&self) -> u16 {
+        10
+    }
+}
+
+/// An error from `Display::init_with_power` or `Display::power_off_with`.
+#[derive(Debug, PartialEq)]
+pub enum PowerError<PE, IE> {
+    /// The `PanelPower` failed to switch VCC.
+    Power(PE),
+    /// A command to the display failed.
+    Command(CommandError<IE>),
+}
+
+impl<PE, IE> From<CommandError<IE>> for PowerError<PE, IE> {
+    fn from(e: CommandError<IE>) -> Self {
+        PowerError::Command(e)
+    }
+}
+
+/// A `PanelPower` implementation for a VCC regulator enabled by a GPIO output.
+pub struct GpioPanelPower<P> {
+    pin: P,
+    active_high: bool,
+}
+
+impl<P> GpioPanelPower<P>
+where
+    P: hal::digital::v2::OutputPin,
+{
+    /// Control VCC with `pin`, which enables the regulator when high if `active_high` is true, or
+    /// when low otherwise.
+    pub fn new(pin: P, active_high: bool) -> Self {
+        GpioPanelPower {
+            pin: pin,
+            active_high: active_high,
+        }
+    }
+
+    /// Consume the power control, returning the pin.
+    pub fn into_inner(self) -> P {
+        self.pin
+    }
+
+    fn set(&mut self, on: bool) -> Result<(), P::Error> {
+        if on == self.active_high {
+            self.pin.set_high()
+        } else {
+            self.pin.set_low()
+        }
+    }
+}
+
+impl<P> PanelPower for GpioPanelPower<P>
+where
+    P: hal::digital::v2::OutputPin,
+{
+    type Error = P::Error;
+
+    fn vcc_on(&mut self) -> Result<(), P::Error> {
+        self.set(true)
+    }
+
+    fn vcc_off(&mut self) -> Result<(), P::Error> {
+        self.set(false)
+    }
+}
+
+/// A set of the power-related display settings. Typically an application defines a normal
+/// profile matching its `Config`, and a power-saving one with a larger clock divider (a lower
+/// refresh rate), a lower contrast current, and perhaps only part of the display active.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PowerProfile {
+    fosc: u8,
+    divset: u8,
+    contrast_current: u8,
+    partial_display: Option<(u8, u8)>,
+}
+
+impl PowerProfile {
+    /// Create a profile with the given clock settings and contrast current, with the whole display
+    /// active. See `Command::SetClockFoscDivset` and `Command::SetContrastCurrent`.
+    pub fn new(fosc: u8, divset: u8, contrast_current: u8) -> Self {
+        PowerProfile {
+            fosc: fosc,
+            divset: divset,
+            contrast_current: contrast_current,
+            partial_display: None,
+        }
+    }
+
+    /// Extend this profile to activate only the display rows from `start` to `end` inclusive,
+    /// leaving the rest dark. See `Command::EnablePartialDisplay`.
+    pub fn partial_display(self, start: u8, end: u8) -> Self {
+        Self {
+            partial_display: Some((start, end)),
+            ..self
+        }
+    }
+}
+
+impl<DI> Display<DI>
+where
+    DI: interface::DisplayInterface,
+{
+    /// Switch the display to the settings of `profile`. The partial display window is enabled or
+    /// disabled as the profile requires, so switching back to a normal profile undoes a saving
+    /// one completely.
+    pub fn set_power_profile(
+        &mut self,
+        profile: &PowerProfile,
+    ) -> Result<(), CommandError<DI::Error>> {
+        self.send_command(Command::SetClockFoscDivset(profile.fosc, profile.divset))?;
+        self.send_command(Command::SetContrastCurrent(profile.contrast_current))?;
+        match profile.partial_display {
+            Some((start, end)) => self.send_command(Command::EnablePartialDisplay(start, end)),
+            None => self.send_command(Command::DisablePartialDisplay),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{ComLayout, ComScanDirection};
+    use crate::config::Config;
+    use crate::display::{Display, PixelCoord as Px};
+    use crate::interface::mock::{MockInterface, Sent};
+    use crate::test_support::CountingDelay;
+    use core::convert::Infallible;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// A pin recording its level, and the number of commands sent before each change.
+    struct Pin(Rc<RefCell<Vec<(bool, usize)>>>, MockInterface);
+
+    impl hal::digital::v2::OutputPin for Pin {
+        type Error = Infallible;
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().push((true, self.1.sent().len()));
+            Ok(())
+        }
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().push((false, self.1.sent().len()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn sequence_vcc() {
+        let di = MockInterface::new();
+        let levels = Rc::new(RefCell::new(Vec::new()));
+        let mut power = GpioPanelPower::new(Pin(levels.clone(), di.split()), false);
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let mut delay = CountingDelay(0);
+
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        disp.init_with_power(cfg, &mut power, &mut delay).unwrap();
+        assert_eq!(delay.0, 10);
+        let init_len = di.sent().len();
+        disp.power_off_with(&mut power, &mut delay).unwrap();
+        assert_eq!(delay.0, 110);
+        assert_eq!(di.sent()[init_len], Sent::Cmd(0xAE));
+
+        // Active low: on before any commands, off after the sleep command.
+        assert_eq!(*levels.borrow(), [(false, 0), (true, init_len + 1)]);
+    }
+
+    /// A pin which cannot be driven.
+    struct BrokenPin;
+
+    impl hal::digital::v2::OutputPin for BrokenPin {
+        type Error = ();
+        fn set_high(&mut self) -> Result<(), ()> {
+            Err(())
+        }
+        fn set_low(&mut self) -> Result<(), ()> {
+            Err(())
+        }
+    }
+
+    #[test]
+    fn vcc_errors() {
+        let di = MockInterface::new();
+        let mut power = GpioPanelPower::new(BrokenPin, true);
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let mut delay = CountingDelay(0);
+
+        let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::DualProgressive);
+        assert_eq!(
+            disp.init_with_power(cfg, &mut power, &mut delay),
+            Err(PowerError::Power(()))
+        );
+        // The display is not initialized without VCC.
+        assert_eq!(delay.0, 0);
+        assert_eq!(di.sent(), []);
+        assert_eq!(
+            disp.power_off_with(&mut power, &mut delay),
+            Err(PowerError::Power(()))
+        );
+        // VCC is still on, so the discharge time is not waited.
+        assert_eq!(di.sent(), [Sent::Cmd(0xAE)]);
+        assert_eq!(delay.0, 0);
+    }
+
+    #[test]
+    fn switch_power_profile() {
+        let mut di = MockInterface::new();
+        let mut disp = Display::new(di.split(), Px(128, 64), Px(0, 0));
+        let normal = PowerProfile::new(9, 1, 159);
+        let saving = PowerProfile::new(9, 4, 60).partial_display(0, 15);
+        let period = disp.estimated_frame_period();
+
+        disp.set_power_profile(&saving).unwrap();
+        // The clock divider went from 2 to 16 and the oscillator slowed, so the frame period is
+        // over 8 times as long.
+        assert!(disp.estimated_frame_period() > 8 * period);
+        #[rustfmt::skip]
+        assert_eq!(di.sent(), [
+            Sent::Cmd(0xB3), Sent::Data(vec![0x94]),
+            Sent::Cmd(0xC1), Sent::Data(vec![60]),
+            Sent::Cmd(0xA8), Sent::Data(vec![0, 15]),
+        ]);
+        di.clear();
+        disp.set_power_profile(&normal).unwrap();
+        #[rustfmt::skip]
+        assert_eq!(di.sent(), [
+            Sent::Cmd(0xB3), Sent::Data(vec![0x91]),
+            Sent::Cmd(0xC1), Sent::Data(vec![159]),
+            Sent::Cmd(0xA9),
+        ]);
+        assert!(disp.estimated_frame_period() > period);
+        let bad = PowerProfile::new(9, 11, 60);
+        assert_eq!(disp.set_power_profile(&bad), Err(CommandError::OutOfRange));
+    }
+}
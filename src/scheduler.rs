@@ -0,0 +1,322 @@
+//! Scheduling of redraws within a per-frame byte budget.
+//!
+//! On a slow bus a full redraw of every widget may not fit in one frame. Widgets, or the
+//! application on their behalf, report damaged rectangles to a `Scheduler` along with a priority,
+//! and once per frame `Scheduler::service` redraws the most important damage that fits within the
+//! number of bytes the bus can carry. The rest stays queued for later frames, so critical
+//! read-outs stay fresh while decorative updates are deferred.
+//!
+//! A `FrameScheduler` decides when those frames happen, pacing them at a fixed rate from a
+//! monotonic timestamp. Frames which come due while the previous one is still being sent are
+//! dropped rather than queued, so that motion keeps to the clock however slow the bus is.
+
+use embedded_hal as hal;
+
+use crate::display::Rect;
+
+/// A damaged rectangle waiting to be redrawn. `id` identifies what to redraw, typically a widget,
+/// and is chosen by the application.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Damage {
+    pub id: u16,
+    pub rect: Rect,
+    pub priority: u8,
+}
+
+impl Damage {
+    /// The number of bytes of packed image data needed to redraw the damage, counting whole
+    /// 4-pixel column groups.
+    pub fn cost(&self) -> usize {
+        let left = self.rect.ul.0 & !3;
+        let right = (self.rect.lr.0 + 3) & !3;
+        (right - left) as usize / 2 * self.rect.height() as usize
+    }
+}
+
+/// A queue of up to `N` damaged rectangles, redrawn in priority order within a byte budget.
+pub struct Scheduler<const N: usize> {
+    queue: [Option<Damage>; N],
+}
+
+impl<const N: usize> Scheduler<N> {
+    /// Construct an empty scheduler.
+    pub const fn new() -> Self {
+        Scheduler { queue: [None; N] }
+    }
+
+    /// Report that `rect` of the item `id` needs redrawing, with `priority` (higher is more
+    /// urgent). Damage reported again for an `id` which is still queued is merged into the
+    /// existing entry, growing its rectangle to cover both and keeping the higher priority.
+    ///
+    /// Returns the damage back if the queue is full.
+    pub fn damage(&mut self, id: u16, rect: Rect, priority: u8) -> Result<(), Damage> {
+        let new = Damage {
+            id: id,
+            rect: rect,
+            priority: priority,
+        };
+        if let Some(existing) = self.queue.iter_mut().flatten().find(|d| d.id == id) {
+            existing.rect = existing.rect.union(&rect);
+            existing.priority = existing.priority.max(priority);
+            return Ok(());
+        }
+        match self.queue.iter_mut().find(|d| d.is_none()) {
+            Some(slot) => {
+                *slot = Some(new);
+                Ok(())
+            }
+            None => Err(new),
+        }
+    }
+
+    /// The number of damaged rectangles waiting to be redrawn.
+    pub fn pending(&self) -> usize {
+        self.queue.iter().flatten().count()
+    }
+
+    /// Redraw queued damage, most urgent first, by calling `redraw` for each entry whose cost fits
+    /// in what remains of `budget_bytes`. Entries which do not fit are skipped and stay queued, so
+    /// a cheaper, less urgent entry may still be redrawn this time. The most urgent entry is always
+    /// redrawn even if it alone exceeds the budget, so that large damage cannot starve.
+    ///
+    /// Returns the number of bytes spent. If `redraw` fails, the failing entry stays queued and
+    /// the error is returned.
+    pub fn service<F, E>(&mut self, budget_bytes: usize, mut redraw: F) -> Result<usize, E>
+    where
+        F: FnMut(&Damage) -> Result<(), E>,
+    {
+        let mut spent = 0;
+        let mut first = true;
+        let mut skipped = [false; N];
+        loop {
+            // Highest priority first, and in order of reporting among equals.
+            let next = (0..N)
+                .filter(|&i| !skipped[i])
+                .filter_map(|i| self.queue[i].map(|d| (i, d)))
+                .fold(None, |best: Option<(usize, Damage)>, (i, d)| match best {
+                    Some((_, b)) if b.priority >= d.priority => best,
+                    _ => Some((i, d)),
+                });
+            let (i, damage) = match next {
+                Some(next) => next,
+                None => return Ok(spent),
+            };
+            let cost = damage.cost();
+            if first || spent + cost <= budget_bytes {
+                redraw(&damage)?;
+                self.queue[i] = None;
+                spent += cost;
+                first = false;
+            } else {
+                skipped[i] = true;
+            }
+        }
+    }
+}
+
+impl<const N: usize> Default for Scheduler<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Counts of frames rendered and dropped by a `FrameScheduler`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Frames for which `should_render` returned true.
+    pub rendered: u32,
+    /// Frames which came and went without being rendered, because the application was busy.
+    pub dropped: u32,
+}
+
+/// Paces redraws at a fixed frame rate, from a timestamp in microseconds which may wrap around,
+/// such as a free-running hardware timer.
+///
+/// Frames fall due at fixed intervals from the first, so the frame rate does not drift when
+/// rendering takes a varying time. When rendering overruns one or more whole frame periods, the
+/// missed frames are counted as dropped and the schedule skips ahead, rather than rendering them
+/// late in a burst.
+#[derive(Clone, Debug)]
+pub struct FrameScheduler {
+    period_us: u32,
+    /// The time the next frame is due, or `None` before the first frame.
+    next_us: Option<u32>,
+    stats: FrameStats,
+}
+
+impl FrameScheduler {
+    /// Construct a scheduler with one frame every `period_us` microseconds, at least 1.
+    pub const fn new(period_us: u32) -> Self {
+        FrameScheduler {
+            period_us: if period_us == 0 { 1 } else { period_us },
+            next_us: None,
+            stats: FrameStats {
+                rendered: 0,
+                dropped: 0,
+            },
+        }
+    }
+
+    /// Construct a scheduler with `fps` frames per second, at least 1.
+    pub const fn from_fps(fps: u32) -> Self {
+        Self::new(1_000_000 / if fps == 0 { 1 } else { fps })
+    }
+
+    /// The time between frames in microseconds.
+    pub fn period_us(&self) -> u32 {
+        self.period_us
+    }
+
+    /// Whether a frame is due at time `now_us`. If so, the frame counts as rendered and the next
+    /// one is scheduled, so call this once per pass of the main loop and render when it returns
+    /// true. The first call always returns true, starting the schedule.
+    pub fn should_render(&mut self, now_us: u32) -> bool {
+        let next = match self.next_us {
+            Some(next) => next,
+            None => now_us,
+        };
+        let late = now_us.wrapping_sub(next);
+        if late > i32::MAX as u32 {
+            // Not due yet.
+            return false;
+        }
+        let missed = late / self.period_us;
+        self.stats.rendered = self.stats.rendered.wrapping_add(1);
+        self.stats.dropped = self.stats.dropped.wrapping_add(missed);
+        self.next_us = Some(next.wrapping_add(self.period_us.wrapping_mul(missed + 1)));
+        true
+    }
+
+    /// The microseconds from `now_us` until the next frame is due, or 0 if it is due already.
+    pub fn until_next(&self, now_us: u32) -> u32 {
+        match self.next_us {
+            Some(next) => {
+                let wait = next.wrapping_sub(now_us);
+                if wait > i32::MAX as u32 {
+                    0
+                } else {
+                    wait
+                }
+            }
+            None => 0,
+        }
+    }
+
+    /// Sleep with `delay` until the next frame is due, for main loops with nothing else to do
+    /// between frames.
+    pub fn wait<D>(&self, now_us: u32, delay: &mut D)
+    where
+        D: hal::blocking::delay::DelayUs<u32>,
+    {
+        let wait = self.until_next(now_us);
+        if wait > 0 {
+            delay.delay_us(wait);
+        }
+    }
+
+    /// The frames rendered and dropped since construction or the last `reset_stats`.
+    pub fn stats(&self) -> FrameStats {
+        self.stats
+    }
+
+    /// Zero the frame counts, e.g. after reporting them.
+    pub fn reset_stats(&mut self) {
+        self.stats = FrameStats::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::test_support::CountingDelay;
+
+    fn rect(width: i16, height: i16) -> Rect {
+        Rect::new(Px(0, 0), Px(width, height))
+    }
+
+    #[test]
+    fn service_within_budget() {
+        let mut sched = Scheduler::<4>::new();
+        sched.damage(1, rect(16, 8), 1).unwrap(); // 64 bytes, decorative
+        sched.damage(2, rect(32, 8), 9).unwrap(); // 128 bytes, critical
+        sched.damage(3, rect(8, 4), 5).unwrap(); // 16 bytes
+        sched.damage(3, Rect::new(Px(6, 2), Px(12, 6)), 0).unwrap(); // merged: 12x6, 36 bytes
+
+        let mut drawn = Vec::new();
+        let mut record = |d: &Damage| -> Result<(), ()> {
+            drawn.push(d.id);
+            Ok(())
+        };
+        assert_eq!(sched.service(164, &mut record), Ok(164));
+        assert_eq!(sched.pending(), 1);
+
+        sched.damage(4, rect(256, 64), 0).unwrap();
+        assert_eq!(sched.service(160, &mut record), Ok(64));
+        assert_eq!(sched.service(160, &mut record), Ok(8192));
+        assert_eq!(drawn, [2, 3, 1, 4]);
+        assert_eq!(sched.pending(), 0);
+    }
+
+    #[test]
+    fn only_most_urgent_over_budget() {
+        let mut sched = Scheduler::<2>::new();
+        // The most urgent entry is empty, which must not let the next one past the budget.
+        sched.damage(1, rect(16, 0), 9).unwrap();
+        sched.damage(2, rect(256, 64), 0).unwrap();
+        let mut drawn = Vec::new();
+        let record = |d: &Damage| -> Result<(), ()> {
+            drawn.push(d.id);
+            Ok(())
+        };
+        assert_eq!(sched.service(160, record), Ok(0));
+        assert_eq!(drawn, [1]);
+        assert_eq!(sched.pending(), 1);
+    }
+
+    #[test]
+    fn failed_redraw_stays_queued() {
+        let mut sched = Scheduler::<1>::new();
+        sched.damage(1, rect(4, 1), 0).unwrap();
+        assert!(sched.damage(2, rect(4, 1), 0).is_err());
+        assert_eq!(sched.service(100, |_| Err("bus")), Err("bus"));
+        assert_eq!(sched.pending(), 1);
+    }
+
+    #[test]
+    fn frame_pacing() {
+        let mut frames = FrameScheduler::from_fps(50);
+        assert_eq!(frames.period_us(), 20_000);
+        assert!(frames.should_render(1_000));
+        assert!(!frames.should_render(20_999));
+        assert_eq!(frames.until_next(15_000), 6_000);
+        // A little late: the next frame is still due on the original schedule.
+        assert!(frames.should_render(25_000));
+        assert_eq!(frames.until_next(25_000), 16_000);
+        // Rendering overran two whole frames.
+        assert!(frames.should_render(85_000));
+        assert_eq!(frames.until_next(85_000), 16_000);
+        assert_eq!(
+            frames.stats(),
+            FrameStats {
+                rendered: 3,
+                dropped: 2
+            }
+        );
+        frames.reset_stats();
+        assert_eq!(frames.stats(), FrameStats::default());
+    }
+
+    #[test]
+    fn frame_wait_across_wrap() {
+        let mut frames = FrameScheduler::new(1_000);
+        let start = u32::MAX - 499;
+        assert!(frames.should_render(start));
+        let mut delay = CountingDelay(0);
+        frames.wait(start.wrapping_add(200), &mut delay);
+        assert_eq!(delay.0, 800);
+        assert!(!frames.should_render(499));
+        assert!(frames.should_render(500));
+        assert_eq!(frames.stats().dropped, 0);
+    }
+}
@@ -0,0 +1,137 @@
+//! Sprites: rectangular images of packed 4-bit pixels which can be blitted into regions, for icons,
+//! tiles and other images stored in flash.
+
+/// Declare a `Sprite<'static>` from packed 4-bit pixel data, checking at compile time that the data
+/// is exactly the right length for the given size.
+///
+/// The data can be any constant byte slice expression, such as an array literal or
+/// `include_bytes!`:
+///
+/// ```
+/// # #[macro_use] extern crate ssd1322;
+/// # fn main() {
+/// let arrow = gray4_image!(4, 2, &[0xF0, 0x00, 0xFF, 0xF0]);
+/// assert_eq!((arrow.width(), arrow.height()), (4, 2));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! gray4_image {
+    ($width:expr, $height:expr, $data:expr) => {{
+        const IMAGE: $crate::sprite::Sprite<'static> = {
+            let data: &'static [u8] = $data;
+            assert!(
+                data.len() == ($width as usize).div_ceil(2) * $height as usize,
+                "gray4_image! data length does not match its size."
+            );
+            $crate::sprite::Sprite::new($width, $height, data)
+        };
+        IMAGE
+    }};
+}
+
+/// An image of `width` by `height` pixels, stored as packed pixel data in the same format as
+/// `Region::draw_packed`: each byte holds two horizontally adjacent pixels, with the leftmost in
+/// the upper nibble. Each row starts on a new byte, so for an odd width the last nibble of each
+/// row is unused.
+#[derive(Clone, Copy, Debug)]
+pub struct Sprite<'a> {
+    width: u16,
+    height: u16,
+    data: &'a [u8],
+}
+
+impl<'a> Sprite<'a> {
+    /// Create a sprite from packed pixel data.
+    ///
+    /// Panics if `data` is too short for the given size. Since this is a `const fn`, a sprite
+    /// declared as a `const` or `static` is checked at compile time.
+    pub const fn new(width: u16, height: u16, data: &'a [u8]) -> Self {
+        assert!(
+            data.len() >= (width as usize).div_ceil(2) * height as usize,
+            "Sprite data is too short for its size."
+        );
+        Sprite {
+            width: width,
+            height: height,
+            data: data,
+        }
+    }
+
+    /// The width of the sprite in pixels.
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// The height of the sprite in pixels.
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// The packed pixel data.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// The gray level of the pixel at `x`, `y`, or `None` if it lies outside the sprite.
+    pub fn pixel(&self, x: i16, y: i16) -> Option<u8> {
+        if x < 0 || y < 0 || x as u16 >= self.width || y as u16 >= self.height {
+            return None;
+        }
+        let row_bytes = (self.width as usize).div_ceil(2);
+        let byte = self.data[y as usize * row_bytes + x as usize / 2];
+        Some(if x % 2 == 0 { byte >> 4 } else { byte & 0x0F })
+    }
+
+    /// An iterator of unpacked pixels for a window `cols` by `rows` pixels, in left-to-right,
+    /// top-to-bottom order, with the sprite's upper left corner placed at `dest` relative to the
+    /// window. Pixels of the window outside the sprite are 0.
+    pub(crate) fn window(
+        &self,
+        dest: (i16, i16),
+        cols: u16,
+        rows: u16,
+    ) -> impl Iterator<Item = u8> + 'a {
+        let sprite = *self;
+        (0..rows as i16).flat_map(move |y| {
+            (0..cols as i16).map(move |x| sprite.pixel(x - dest.0, y - dest.1).unwrap_or(0))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    // A 3x2 sprite; the unused low nibble of each row is set to check it is ignored.
+    const SPRITE: Sprite<'static> = Sprite::new(3, 2, &[0x12, 0x3F, 0x45, 0x6F]);
+
+    #[test]
+    fn pixels() {
+        assert_eq!(SPRITE.pixel(0, 0), Some(1));
+        assert_eq!(SPRITE.pixel(2, 0), Some(3));
+        assert_eq!(SPRITE.pixel(1, 1), Some(5));
+        assert_eq!(SPRITE.pixel(3, 0), None);
+        assert_eq!(SPRITE.pixel(-1, 0), None);
+        assert_eq!(SPRITE.pixel(0, 2), None);
+    }
+
+    #[test]
+    fn window() {
+        let pixels = SPRITE.window((1, -1), 4, 2).collect::<Vec<_>>();
+        assert_eq!(pixels, vec![0, 4, 5, 6, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn image_macro() {
+        let image = gray4_image!(3, 2, &[0x12, 0x30, 0x45, 0x60]);
+        assert_eq!((image.width(), image.height()), (3, 2));
+        assert_eq!(image.pixel(1, 1), Some(5));
+    }
+
+    #[test]
+    #[should_panic]
+    fn short_data() {
+        Sprite::new(4, 2, &[0; 3]);
+    }
+}
@@ -0,0 +1,56 @@
+//! Fixtures shared by the unit tests of several modules.
+
+use embedded_hal as hal;
+
+use crate::command::{ComLayout, ComScanDirection};
+use crate::config::Config;
+use crate::display::{Display, PixelCoord};
+use crate::interface::mock::MockInterface;
+use std::vec::Vec;
+
+/// A display of `size` pixels with no offset, initialized with the COM scan direction and layout
+/// of most small test panels, and another handle to the record of its interface.
+pub(crate) fn initialized_display(size: PixelCoord) -> (MockInterface, Display<MockInterface>) {
+    let di = MockInterface::new();
+    let mut disp = Display::new(di.split(), size, PixelCoord(0, 0));
+    let cfg = Config::new(ComScanDirection::RowZeroLast, ComLayout::Progressive);
+    disp.init(cfg).unwrap();
+    (di, disp)
+}
+
+/// A display of `size` pixels as from `initialized_display`, together with `widget` to draw into
+/// it.
+pub(crate) fn widget_fixture<W>(
+    size: PixelCoord,
+    widget: W,
+) -> (MockInterface, Display<MockInterface>, W) {
+    let (di, disp) = initialized_display(size);
+    (di, disp, widget)
+}
+
+/// Render the top `rows` rows and left `cols` columns of a 16x16 display driven through `di` as
+/// one hex digit per pixel gray level, for comparing against a picture in a byte string.
+pub(crate) fn screen(di: &MockInterface, rows: usize, cols: usize) -> Vec<u8> {
+    let image = di.chip_model(PixelCoord(16, 16), PixelCoord(0, 0)).render();
+    image
+        .chunks(16)
+        .take(rows)
+        .flat_map(|row| row[..cols].iter().map(|&p| b"0123456789ABCDEF"[p as usize]))
+        .collect()
+}
+
+/// A delay which does not wait, but adds up how long it was asked to, in milliseconds or
+/// microseconds according to the delay trait it was called through.
+pub(crate) struct CountingDelay(pub(crate) u32);
+
+impl hal::blocking::delay::DelayMs<u16> for CountingDelay {
+    fn delay_ms(&mut self, ms: u16) {
+        self.0 += ms as u32;
+    }
+}
+
+impl hal::blocking::delay::DelayUs<u32> for CountingDelay {
+    fn delay_us(&mut self, us: u32) {
+        self.0 += us;
+    }
+}
@@ -0,0 +1,376 @@
+//! Proportional bitmap fonts converted from BDF, the format of the X11 bitmap fonts and the output
+//! of most bitmap font editors.
+//!
+//! Parsing BDF is too slow and memory hungry for a microcontroller, so fonts are converted ahead
+//! of time into a `BdfFont`, a compact table of glyph metrics and 1 bit per pixel bitmaps which can
+//! live in flash. With the `std` feature, `BdfFontBuf::parse` reads BDF source, typically in a
+//! build script, and `BdfFontBuf::rust_source` writes the table out as Rust source:
+//!
+//! ```no_run
+//! # #[cfg(feature = "std")] {
+//! // build.rs
+//! use ssd1322::text::bdf::BdfFontBuf;
+//!
+//! let bdf = std::fs::read_to_string("fonts/helvR10.bdf").unwrap();
+//! let font = BdfFontBuf::parse(&bdf, ' ', '~').unwrap();
+//! let out = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+//! std::fs::write(out.join("helv.rs"), font.rust_source("HELV")).unwrap();
+//! # }
+//! ```
+//!
+//! The firmware then declares `HELV` with `include!(concat!(env!("OUT_DIR"), "/helv.rs"));` and
+//! renders with it like any other `Font`, e.g. with `TextPixels`, which expands the bitmaps to the
+//! foreground and background gray levels it is given. Each glyph advances by its own width; there
+//! is no kerning.
+
+#[cfg(feature = "std")]
+use core::convert::TryFrom;
+#[cfg(feature = "std")]
+use std::fmt::Write;
+#[cfg(feature = "std")]
+use std::string::String;
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+use crate::text::Font;
+
+/// The metrics of one glyph of a `BdfFont`, and the location of its bitmap.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BdfGlyph {
+    /// The offset of the glyph's bitmap in `BdfFont::bitmaps`. The bitmap is `height` rows, each
+    /// `(width + 7) / 8` bytes with the leftmost pixel in the most significant bit.
+    pub offset: u32,
+    /// The horizontal distance in pixels from the start of this glyph to the start of the next.
+    pub advance: u8,
+    /// The width of the bitmap in pixels.
+    pub width: u8,
+    /// The height of the bitmap in pixels.
+    pub height: u8,
+    /// The position of the left edge of the bitmap, relative to the start of the glyph.
+    pub x: i8,
+    /// The position of the top edge of the bitmap, relative to the top of the line.
+    pub y: i8,
+}
+
+/// A proportional font with 1 bit per pixel glyph bitmaps, covering a contiguous range of
+/// characters. Characters outside the range, or missing from the font it was converted from, have
+/// no width and are not drawn.
+#[derive(Clone, Copy, Debug)]
+pub struct BdfFont<'a> {
+    /// The height of a line of text in pixels.
+    pub height: u8,
+    /// The first character in `glyphs`.
+    pub first: char,
+    /// The glyph for each character starting at `first`.
+    pub glyphs: &'a [BdfGlyph],
+    /// Packed glyph bitmaps.
+    pub bitmaps: &'a [u8],
+}
+
+impl<'a> BdfFont<'a> {
+    fn glyph(&self, c: char) -> Option<&'a BdfGlyph> {
+        let index = (c as u32).checked_sub(self.first as u32)?;
+        self.glyphs.get(index as usize)
+    }
+}
+
+impl<'a> Font for BdfFont<'a> {
+    fn height(&self) -> u8 {
+        self.height
+    }
+
+    fn advance(&self, c: char) -> u8 {
+        self.glyph(c).map_or(0, |glyph| glyph.advance)
+    }
+
+    fn pixel(&self, c: char, x: u8, y: u8) -> u8 {
+        let glyph = match self.glyph(c) {
+            Some(glyph) => glyph,
+            None => return 0,
+        };
+        let gx = x as i16 - glyph.x as i16;
+        let gy = y as i16 - glyph.y as i16;
+        if gx < 0 || gy < 0 || gx >= glyph.width as i16 || gy >= glyph.height as i16 {
+            return 0;
+        }
+        let row_bytes = (glyph.width as usize).div_ceil(8);
+        let offset = glyph.offset as usize + gy as usize * row_bytes + gx as usize / 8;
+        match self.bitmaps.get(offset) {
+            Some(byte) if byte & (0x80 >> (gx % 8)) != 0 => 15,
+            _ => 0,
+        }
+    }
+}
+
+/// Errors which can occur while parsing a BDF font.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+pub enum BdfError {
+    /// A line could not be parsed. Holds the line number, counting from 1.
+    BadLine(usize),
+    /// The font has no `FONTBOUNDINGBOX`, or a glyph has no `BBX`.
+    MissingBoundingBox,
+    /// A glyph, or the line height, is too large for the metrics of a `BdfFont`.
+    TooLarge,
+}
+
+/// A `BdfFont` which owns its tables, as produced by parsing BDF source. Available with the `std`
+/// feature.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BdfFontBuf {
+    height: u8,
+    first: char,
+    glyphs: Vec<BdfGlyph>,
+    bitmaps: Vec<u8>,
+}
+
+/// The glyph being parsed: its encoding, advance and bounding box.
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct BdfChar {
+    encoding: Option<u32>,
+    advance: i32,
+    bbx: Option<(i32, i32, i32, i32)>,
+}
+
+#[cfg(feature = "std")]
+impl BdfFontBuf {
+    /// Parse the BDF font in `source`, keeping the glyphs for the characters from `first` to
+    /// `last` inclusive.
+    pub fn parse(source: &str, first: char, last: char) -> Result<Self, BdfError> {
+        let range = first as u32..=last as u32;
+        let count = range.clone().count();
+        let mut glyphs = vec![BdfGlyph::default(); count];
+        let mut bitmaps = Vec::new();
+        let mut bounding_box = None;
+        let (mut ascent, mut descent) = (None, None);
+        let mut current: Option<BdfChar> = None;
+        // The glyph whose bitmap rows are being read, and the number of rows left.
+        let mut bitmap: Option<(usize, i32)> = None;
+
+        for (number, line) in source.lines().enumerate() {
+            let bad_line = || BdfError::BadLine(number + 1);
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap_or("");
+            let mut numbers = || -> Result<i32, BdfError> {
+                words
+                    .next()
+                    .and_then(|word| word.parse().ok())
+                    .ok_or_else(bad_line)
+            };
+
+            if let Some((index, rows)) = bitmap {
+                if keyword == "ENDCHAR" || rows == 0 {
+                    bitmap = None;
+                } else {
+                    if index < count {
+                        let row_bytes = (glyphs[index].width as usize).div_ceil(8);
+                        for i in 0..row_bytes {
+                            let byte = keyword
+                                .get(2 * i..2 * i + 2)
+                                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                                .ok_or_else(bad_line)?;
+                            bitmaps.push(byte);
+                        }
+                    }
+                    bitmap = Some((index, rows - 1));
+                    continue;
+                }
+            }
+
+            match keyword {
+                "FONTBOUNDINGBOX" => {
+                    let (w, h, x, y) = (numbers()?, numbers()?, numbers()?, numbers()?);
+                    bounding_box = Some((w, h, x, y));
+                }
+                "FONT_ASCENT" => ascent = Some(numbers()?),
+                "FONT_DESCENT" => descent = Some(numbers()?),
+                "STARTCHAR" => current = Some(BdfChar::default()),
+                "ENCODING" => {
+                    let ch = current.as_mut().ok_or_else(bad_line)?;
+                    ch.encoding = u32::try_from(numbers()?).ok();
+                }
+                "DWIDTH" => current.as_mut().ok_or_else(bad_line)?.advance = numbers()?,
+                "BBX" => {
+                    let (w, h, x, y) = (numbers()?, numbers()?, numbers()?, numbers()?);
+                    current.as_mut().ok_or_else(bad_line)?.bbx = Some((w, h, x, y));
+                }
+                "BITMAP" => {
+                    let ch = current.take().ok_or_else(bad_line)?;
+                    let (w, h, x, y) = ch.bbx.ok_or(BdfError::MissingBoundingBox)?;
+                    let font_ascent = match ascent {
+                        Some(ascent) => ascent,
+                        None => {
+                            let (_, fh, _, fy) =
+                                bounding_box.ok_or(BdfError::MissingBoundingBox)?;
+                            fh + fy
+                        }
+                    };
+                    let index = match ch.encoding {
+                        Some(encoding) if range.contains(&encoding) => {
+                            (encoding - first as u32) as usize
+                        }
+                        _ => count,
+                    };
+                    if index < count {
+                        let narrow = |v: i32| u8::try_from(v).map_err(|_| BdfError::TooLarge);
+                        let signed = |v: i32| i8::try_from(v).map_err(|_| BdfError::TooLarge);
+                        glyphs[index] = BdfGlyph {
+                            offset: bitmaps.len() as u32,
+                            advance: narrow(ch.advance)?,
+                            width: narrow(w)?,
+                            height: narrow(h)?,
+                            x: signed(x)?,
+                            // BDF measures the bottom of the bitmap up from the baseline.
+                            y: signed(font_ascent - (y + h))?,
+                        };
+                    }
+                    bitmap = Some((index, h));
+                }
+                _ => {}
+            }
+        }
+
+        let height = match (ascent, descent, bounding_box) {
+            (Some(ascent), Some(descent), _) => ascent + descent,
+            (_, _, Some((_, h, _, _))) => h,
+            _ => return Err(BdfError::MissingBoundingBox),
+        };
+        Ok(BdfFontBuf {
+            height: u8::try_from(height).map_err(|_| BdfError::TooLarge)?,
+            first: first,
+            glyphs: glyphs,
+            bitmaps: bitmaps,
+        })
+    }
+
+    /// Borrow the font as a `BdfFont`, to render with it on the host.
+    pub fn as_font(&self) -> BdfFont<'_> {
+        BdfFont {
+            height: self.height,
+            first: self.first,
+            glyphs: &self.glyphs,
+            bitmaps: &self.bitmaps,
+        }
+    }
+
+    /// Rust source declaring a `pub const` `BdfFont` called `name` with the font's tables.
+    pub fn rust_source(&self, name: &str) -> String {
+        let path = "::ssd1322::text::bdf";
+        let mut src = String::new();
+        let _ = writeln!(
+            src,
+            "pub const {}: {}::BdfFont<'static> = {1}::BdfFont {{",
+            name, path
+        );
+        let _ = writeln!(src, "    height: {},", self.height);
+        let _ = writeln!(src, "    first: {:?},", self.first);
+        src.push_str("    glyphs: &[\n");
+        for g in &self.glyphs {
+            let _ = writeln!(
+                src,
+                "        {}::BdfGlyph {{ offset: {}, advance: {}, width: {}, height: {}, \
+                 x: {}, y: {} }},",
+                path, g.offset, g.advance, g.width, g.height, g.x, g.y
+            );
+        }
+        src.push_str("    ],\n    bitmaps: &[\n");
+        for row in self.bitmaps.chunks(16) {
+            src.push_str("       ");
+            for byte in row {
+                let _ = write!(src, " 0x{:02X},", byte);
+            }
+            src.push('\n');
+        }
+        src.push_str("    ],\n};\n");
+        src
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::{text_width, TextPixels};
+
+    /// A font with a 3x3 'A' sitting on the baseline, a 1x2 '.' with a descender, and a glyph
+    /// outside the range converted.
+    const SOURCE: &str = "\
+STARTFONT 2.1
+FONT test
+FONTBOUNDINGBOX 3 5 0 -1
+STARTPROPERTIES 2
+FONT_ASCENT 4
+FONT_DESCENT 1
+ENDPROPERTIES
+CHARS 3
+STARTCHAR period
+ENCODING 46
+DWIDTH 2 0
+BBX 1 2 0 -1
+BITMAP
+80
+80
+ENDCHAR
+STARTCHAR A
+ENCODING 65
+DWIDTH 4 0
+BBX 3 3 0 0
+BITMAP
+40
+A0
+E0
+ENDCHAR
+STARTCHAR B
+ENCODING 66
+DWIDTH 4 0
+BBX 3 3 0 0
+BITMAP
+C0
+C0
+C0
+ENDCHAR
+ENDFONT
+";
+
+    #[test]
+    fn parse_and_render() {
+        let buf = BdfFontBuf::parse(SOURCE, '.', 'A').unwrap();
+        let font = buf.as_font();
+        assert_eq!(font.height(), 5);
+        assert_eq!(text_width(&font, "A.A"), 10);
+        assert_eq!(font.advance('/'), 0);
+        assert_eq!(font.advance('B'), 0);
+
+        let pixels = TextPixels::new(&font, "A.", 6, 15, 0).collect::<Vec<_>>();
+        #[rustfmt::skip]
+        assert_eq!(pixels, vec![
+            0,  0,  0, 0,  0, 0,
+            0, 15,  0, 0,  0, 0,
+            15, 0, 15, 0,  0, 0,
+            15, 15, 15, 0, 15, 0,
+            0,  0,  0, 0, 15, 0,
+        ]);
+    }
+
+    #[test]
+    fn rust_source() {
+        let buf = BdfFontBuf::parse(SOURCE, 'A', 'A').unwrap();
+        let src = buf.rust_source("TINY");
+        assert!(src.starts_with(
+            "pub const TINY: ::ssd1322::text::bdf::BdfFont<'static> = \
+             ::ssd1322::text::bdf::BdfFont {\n    height: 5,\n    first: 'A',\n"
+        ));
+        assert!(src.contains("offset: 0, advance: 4, width: 3, height: 3, x: 0, y: 1 },"));
+        assert!(src.contains("0x40, 0xA0, 0xE0,"));
+    }
+
+    #[test]
+    fn bad_bitmap() {
+        let source = SOURCE.replace("A0", "ZZ");
+        assert_eq!(
+            BdfFontBuf::parse(&source, 'A', 'A'),
+            Err(BdfError::BadLine(23))
+        );
+    }
+}
@@ -0,0 +1,430 @@
+//! Text rendering. Fonts are described by the `Font` trait, and `TextPixels` renders a line of text
+//! as a stream of unpacked pixels which can be drawn straight into a region with `Region::draw`,
+//! without buffering the rendered image.
+
+pub mod bdf;
+pub mod num;
+pub mod u8g2;
+pub mod wrap;
+
+use core::iter::Chain;
+use core::str::Chars;
+
+/// A font which can render characters as a grid of pixel coverage values.
+pub trait Font {
+    /// The height of every glyph in pixels.
+    fn height(&self) -> u8;
+
+    /// The horizontal distance in pixels from the start of character `c` to the start of the next
+    /// character.
+    fn advance(&self, c: char) -> u8;
+
+    /// The coverage of the pixel at `x`, `y` within the glyph for `c`, in the range 0 (background)
+    /// to 15 (foreground). `x` is less than `advance(c)` and `y` is less than `height()`.
+    fn pixel(&self, c: char, x: u8, y: u8) -> u8;
+}
+
+/// A monospaced font with 1 bit per pixel glyph bitmaps, covering a contiguous range of
+/// characters.
+///
+/// `glyphs` contains the glyph for each character starting at `first`, one after another. Each
+/// glyph is `height` rows, and each row is `(width + 7) / 8` bytes with the leftmost pixel in the
+/// most significant bit of the first byte. Characters outside the range render as blank cells.
+#[derive(Clone, Copy, Debug)]
+pub struct MonoFont<'a> {
+    /// The glyph cell width in pixels.
+    pub width: u8,
+    /// The glyph cell height in pixels.
+    pub height: u8,
+    /// The first character in `glyphs`.
+    pub first: char,
+    /// Packed glyph bitmaps.
+    pub glyphs: &'a [u8],
+}
+
+impl<'a> MonoFont<'a> {
+    fn row_bytes(&self) -> usize {
+        (self.width as usize).div_ceil(8)
+    }
+}
+
+impl<'a> Font for MonoFont<'a> {
+    fn height(&self) -> u8 {
+        self.height
+    }
+
+    fn advance(&self, _c: char) -> u8 {
+        self.width
+    }
+
+    fn pixel(&self, c: char, x: u8, y: u8) -> u8 {
+        let index = match (c as u32).checked_sub(self.first as u32) {
+            Some(index) => index as usize,
+            None => return 0,
+        };
+        let row_bytes = self.row_bytes();
+        let offset = (index * self.height as usize + y as usize) * row_bytes + x as usize / 8;
+        match self.glyphs.get(offset) {
+            Some(byte) if byte & (0x80 >> (x % 8)) != 0 => 15,
+            _ => 0,
+        }
+    }
+}
+
+/// A monospaced anti-aliased font with 4 bits per pixel glyph bitmaps, covering a contiguous range
+/// of characters. Each pixel is a coverage value from 0 to 15, so edges are drawn in intermediate
+/// gray levels, as produced by rendering an outline font at the target size.
+///
+/// `glyphs` contains the glyph for each character starting at `first`, one after another. Each
+/// glyph is `height` rows, and each row is `(width + 1) / 2` bytes with two pixels to a byte, the
+/// leftmost in the upper nibble, as in `Sprite` data. Characters outside the range render as blank
+/// cells.
+#[derive(Clone, Copy, Debug)]
+pub struct GrayFont<'a> {
+    /// The glyph cell width in pixels.
+    pub width: u8,
+    /// The glyph cell height in pixels.
+    pub height: u8,
+    /// The first character in `glyphs`.
+    pub first: char,
+    /// Packed glyph bitmaps.
+    pub glyphs: &'a [u8],
+}
+
+impl<'a> Font for GrayFont<'a> {
+    fn height(&self) -> u8 {
+        self.height
+    }
+
+    fn advance(&self, _c: char) -> u8 {
+        self.width
+    }
+
+    fn pixel(&self, c: char, x: u8, y: u8) -> u8 {
+        let index = match (c as u32).checked_sub(self.first as u32) {
+            Some(index) => index as usize,
+            None => return 0,
+        };
+        let row_bytes = (self.width as usize).div_ceil(2);
+        let offset = (index * self.height as usize + y as usize) * row_bytes + x as usize / 2;
+        match self.glyphs.get(offset) {
+            Some(byte) if x.is_multiple_of(2) => byte >> 4,
+            Some(byte) => byte & 0x0F,
+            None => 0,
+        }
+    }
+}
+
+/// Anti-aliasing for any font by supersampling: wraps a font drawn at twice the intended size,
+/// such as a 1 bit per pixel font converted from BDF at double the point size, and renders each
+/// 2x2 block of its pixels as one pixel with their average coverage. Metrics are halved, rounding
+/// up.
+///
+/// This gives smoother text than a 1 bit per pixel font at the target size without converting to
+/// a 4 bit per pixel font, at the cost of four glyph lookups per pixel.
+#[derive(Clone, Copy, Debug)]
+pub struct Supersampled<F>(pub F);
+
+impl<F> Supersampled<F>
+where
+    F: Font,
+{
+    /// The coverage of a pixel of the wrapped font, or 0 outside its glyph cell.
+    fn sample(&self, c: char, x: u16, y: u16) -> u16 {
+        if x < self.0.advance(c) as u16 && y < self.0.height() as u16 {
+            self.0.pixel(c, x as u8, y as u8) as u16
+        } else {
+            0
+        }
+    }
+}
+
+impl<F> Font for Supersampled<F>
+where
+    F: Font,
+{
+    fn height(&self) -> u8 {
+        self.0.height().div_ceil(2)
+    }
+
+    fn advance(&self, c: char) -> u8 {
+        self.0.advance(c).div_ceil(2)
+    }
+
+    fn pixel(&self, c: char, x: u8, y: u8) -> u8 {
+        let (x, y) = (x as u16 * 2, y as u16 * 2);
+        let sum = self.sample(c, x, y)
+            + self.sample(c, x + 1, y)
+            + self.sample(c, x, y + 1)
+            + self.sample(c, x + 1, y + 1);
+        ((sum + 2) / 4) as u8
+    }
+}
+
+/// The width in pixels of `text` rendered in `font`.
+pub fn text_width<F: Font>(font: &F, text: &str) -> u16 {
+    text.chars().map(|c| font.advance(c) as u16).sum()
+}
+
+/// Horizontal alignment of text within its box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HAlign {
+    /// Against the left edge.
+    Left,
+    /// Centered, rounding to the left.
+    Center,
+    /// Against the right edge.
+    Right,
+}
+
+/// Vertical alignment of text within its box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VAlign {
+    /// Against the top edge.
+    Top,
+    /// Centered, rounding upwards.
+    Middle,
+    /// Against the bottom edge.
+    Bottom,
+}
+
+/// The space to leave before content `size` pixels long to align it within `room` pixels, where
+/// `center` and `end` select centered and right or bottom alignment. Content which does not fit is
+/// aligned to the start, so that only its end is clipped.
+fn align_offset(room: u16, size: u16, center: bool, end: bool) -> u16 {
+    let free = room.saturating_sub(size);
+    if center {
+        free / 2
+    } else if end {
+        free
+    } else {
+        0
+    }
+}
+
+/// Blend gray levels `bg` and `fg` according to a font pixel coverage value.
+pub(crate) fn blend(coverage: u8, fg: u8, bg: u8) -> u8 {
+    ((fg as u16 * coverage as u16 + bg as u16 * (15 - coverage as u16) + 7) / 15) as u8
+}
+
+/// An iterator rendering one line of text as unpacked pixels, in left-to-right, top-to-bottom
+/// order, into a box `width` pixels wide and as tall as the font. The text is clipped at the right
+/// edge of the box, and the remainder of each row is filled with the background level. The text is
+/// aligned to the left of the box unless set otherwise with `align`.
+pub struct TextPixels<'a, F> {
+    font: &'a F,
+    text: &'a str,
+    /// Text drawn straight after `text`, such as an ellipsis.
+    suffix: &'a str,
+    width: u16,
+    fg: u8,
+    bg: u8,
+    /// The number of background pixels before the text on each row.
+    offset: u16,
+    /// The current row, and the column within it.
+    y: u8,
+    x: u16,
+    /// The characters remaining in the current row, and the current character along with the
+    /// column within it.
+    chars: Chain<Chars<'a>, Chars<'a>>,
+    current: Option<(char, u8)>,
+}
+
+impl<'a, F> TextPixels<'a, F>
+where
+    F: Font,
+{
+    /// Render `text` with `font` in gray level `fg` on a background of `bg`.
+    pub fn new(font: &'a F, text: &'a str, width: u16, fg: u8, bg: u8) -> Self {
+        Self::with_suffix(font, text, "", width, fg, bg)
+    }
+
+    /// Render `text` followed by `suffix`, as with `new`.
+    pub(crate) fn with_suffix(
+        font: &'a F,
+        text: &'a str,
+        suffix: &'a str,
+        width: u16,
+        fg: u8,
+        bg: u8,
+    ) -> Self {
+        let mut chars = text.chars().chain(suffix.chars());
+        let current = chars.next().map(|c| (c, 0));
+        Self {
+            font: font,
+            text: text,
+            suffix: suffix,
+            width: width,
+            fg: fg,
+            bg: bg,
+            offset: 0,
+            y: 0,
+            x: 0,
+            chars: chars,
+            current: current,
+        }
+    }
+
+    /// Align the text horizontally within the box by measuring its width.
+    pub fn align(mut self, align: HAlign) -> Self {
+        let width = text_width(self.font, self.text) + text_width(self.font, self.suffix);
+        self.offset = align_offset(
+            self.width,
+            width,
+            align == HAlign::Center,
+            align == HAlign::Right,
+        );
+        self
+    }
+}
+
+impl<'a, F> Iterator for TextPixels<'a, F>
+where
+    F: Font,
+{
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.y >= self.font.height() {
+            return None;
+        }
+        // Skip over any zero-width characters.
+        while let Some((c, 0)) = self.current {
+            if self.font.advance(c) != 0 {
+                break;
+            }
+            self.current = self.chars.next().map(|c| (c, 0));
+        }
+        let level = match self.current {
+            _ if self.x < self.offset => self.bg,
+            Some((c, cx)) => {
+                let coverage = self.font.pixel(c, cx, self.y);
+                self.current = if cx + 1 < self.font.advance(c) {
+                    Some((c, cx + 1))
+                } else {
+                    self.chars.next().map(|c| (c, 0))
+                };
+                blend(coverage, self.fg, self.bg)
+            }
+            None => self.bg,
+        };
+        self.x += 1;
+        if self.x >= self.width {
+            self.x = 0;
+            self.y += 1;
+            self.chars = self.text.chars().chain(self.suffix.chars());
+            self.current = self.chars.next().map(|c| (c, 0));
+        }
+        Some(level)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    /// A 4x3 test font covering 'A' and 'B'. 'A' is a box outline and 'B' a vertical bar.
+    #[rustfmt::skip]
+    pub(crate) const TEST_FONT: MonoFont<'static> = MonoFont {
+        width: 4,
+        height: 3,
+        first: 'A',
+        glyphs: &[
+            0b1111_0000,
+            0b1001_0000,
+            0b1111_0000,
+
+            0b0100_0000,
+            0b0100_0000,
+            0b0100_0000,
+        ],
+    };
+
+    #[test]
+    fn mono_font_pixels() {
+        assert_eq!(TEST_FONT.pixel('A', 0, 1), 15);
+        assert_eq!(TEST_FONT.pixel('A', 1, 1), 0);
+        assert_eq!(TEST_FONT.pixel('B', 1, 2), 15);
+        // Out of range characters are blank.
+        assert_eq!(TEST_FONT.pixel('C', 1, 2), 0);
+        assert_eq!(TEST_FONT.pixel(' ', 0, 0), 0);
+    }
+
+    #[test]
+    fn gray_font_pixels() {
+        // A 3x2 gradient glyph for '0'.
+        let font = GrayFont {
+            width: 3,
+            height: 2,
+            first: '0',
+            glyphs: &[0x48, 0xF0, 0x12, 0x30],
+        };
+        let pixels = TextPixels::new(&font, "0", 3, 15, 0).collect::<Vec<_>>();
+        assert_eq!(pixels, vec![4, 8, 15, 1, 2, 3]);
+        // Partial coverage blends between the foreground and background.
+        let pixels = TextPixels::new(&font, "0", 3, 0, 15).collect::<Vec<_>>();
+        assert_eq!(pixels, vec![11, 7, 0, 14, 13, 12]);
+        assert_eq!(font.pixel('1', 0, 0), 0);
+    }
+
+    #[test]
+    fn supersampled() {
+        let font = Supersampled(TEST_FONT);
+        assert_eq!((font.height(), font.advance('A')), (2, 2));
+        let pixels = TextPixels::new(&font, "AB", 4, 15, 0).collect::<Vec<_>>();
+        #[rustfmt::skip]
+        assert_eq!(pixels, vec![
+            11, 11, 8, 0,
+             8,  8, 4, 0,
+        ]);
+    }
+
+    #[test]
+    fn text_pixels() {
+        let pixels = TextPixels::new(&TEST_FONT, "AB", 10, 15, 1).collect::<Vec<_>>();
+        #[rustfmt::skip]
+        assert_eq!(pixels, vec![
+            15, 15, 15, 15,  1, 15,  1,  1,  1,  1,
+            15,  1,  1, 15,  1, 15,  1,  1,  1,  1,
+            15, 15, 15, 15,  1, 15,  1,  1,  1,  1,
+        ]);
+        // Clipped at the right edge.
+        let pixels = TextPixels::new(&TEST_FONT, "BA", 6, 9, 0).collect::<Vec<_>>();
+        #[rustfmt::skip]
+        assert_eq!(pixels, vec![
+            0, 9, 0, 0, 9, 9,
+            0, 9, 0, 0, 9, 0,
+            0, 9, 0, 0, 9, 9,
+        ]);
+    }
+
+    #[test]
+    fn aligned_pixels() {
+        let pixels = |align| {
+            TextPixels::new(&TEST_FONT, "B", 7, 15, 0)
+                .align(align)
+                .take(7)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(pixels(HAlign::Left), vec![0, 15, 0, 0, 0, 0, 0]);
+        assert_eq!(pixels(HAlign::Center), vec![0, 0, 15, 0, 0, 0, 0]);
+        assert_eq!(pixels(HAlign::Right), vec![0, 0, 0, 0, 15, 0, 0]);
+        // Text too wide for the box is clipped at the right edge whatever the alignment.
+        let pixels = TextPixels::new(&TEST_FONT, "BA", 6, 9, 0).align(HAlign::Right);
+        assert_eq!(pixels.take(6).collect::<Vec<_>>(), vec![0, 9, 0, 0, 9, 9]);
+    }
+
+    #[test]
+    fn width() {
+        assert_eq!(text_width(&TEST_FONT, ""), 0);
+        assert_eq!(text_width(&TEST_FONT, "AB A"), 16);
+    }
+
+    #[test]
+    fn blend_levels() {
+        assert_eq!(blend(0, 15, 3), 3);
+        assert_eq!(blend(15, 12, 3), 12);
+        assert_eq!(blend(8, 15, 0), 8);
+    }
+}
@@ -0,0 +1,137 @@
+//! Formatting of integers and fixed-point values into a small stack buffer, for numeric fields
+//! redrawn at a high rate, without the code size and speed cost of `core::fmt` or a string type.
+//!
+//! A `NumStr` holds the formatted digits and gives them as a `&str` to render with `TextPixels` or
+//! `Region::draw_text`:
+//!
+//! ```
+//! # use ssd1322::text::num::NumStr;
+//! let rpm = NumStr::int(950).pad(5, ' ');
+//! assert_eq!(rpm.as_str(), "  950");
+//! let volts = NumStr::fixed(-1205, 2);
+//! assert_eq!(volts.as_str(), "-12.05");
+//! ```
+//!
+//! Padding a field to a fixed width keeps its digits in place as the value changes, so that only
+//! the field itself needs redrawing.
+
+/// The buffer size, enough for any `u64` or `i64` with a decimal point and padding.
+const CAPACITY: usize = 32;
+
+/// The most digits after the decimal point `NumStr::fixed` writes.
+const MAX_DECIMALS: u8 = 20;
+
+/// A formatted number. See the module documentation.
+#[derive(Clone, Copy, Debug)]
+pub struct NumStr {
+    /// The text is right-aligned in the buffer, starting at `start`.
+    buf: [u8; CAPACITY],
+    start: usize,
+}
+
+impl NumStr {
+    /// Format an unsigned integer.
+    pub fn uint(value: u64) -> Self {
+        Self::fixed_parts(false, value, 0)
+    }
+
+    /// Format a signed integer.
+    pub fn int(value: i64) -> Self {
+        Self::fixed_parts(value < 0, value.unsigned_abs(), 0)
+    }
+
+    /// Format a fixed-point value of `value / 10^decimals`, with exactly `decimals` digits after
+    /// the decimal point, e.g. `fixed(1205, 2)` is `12.05`. At most 20 decimals are written, and
+    /// any more are treated as 20.
+    pub fn fixed(value: i64, decimals: u8) -> Self {
+        Self::fixed_parts(value < 0, value.unsigned_abs(), decimals)
+    }
+
+    fn fixed_parts(negative: bool, mut magnitude: u64, decimals: u8) -> Self {
+        let mut num = NumStr {
+            buf: [0; CAPACITY],
+            start: CAPACITY,
+        };
+        let decimals = decimals.min(MAX_DECIMALS);
+        // Write digits from the right, at least up to the units digit.
+        let mut written = 0;
+        while magnitude > 0 || written <= decimals {
+            if written == decimals && decimals > 0 {
+                num.push(b'.');
+            }
+            num.push(b'0' + (magnitude % 10) as u8);
+            magnitude /= 10;
+            written += 1;
+        }
+        if negative {
+            num.push(b'-');
+        }
+        num
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.start -= 1;
+        self.buf[self.start] = byte;
+    }
+
+    /// Right-align the number in a field `width` characters wide, filling the space on the left
+    /// with `fill`. With a `fill` of `'0'` the zeros go after any minus sign, and with a fill other
+    /// than ASCII, spaces are used. Fields wider than 32 characters are narrowed to 32.
+    pub fn pad(mut self, width: usize, fill: char) -> Self {
+        let fill = if fill.is_ascii() { fill as u8 } else { b' ' };
+        let width = width.min(CAPACITY);
+        // Take any minus sign off while padding with zeros, and put it back in front of them.
+        let signed_zeros = fill == b'0' && self.buf.get(self.start) == Some(&b'-');
+        if signed_zeros {
+            self.start += 1;
+        }
+        while CAPACITY - self.start < width.saturating_sub(signed_zeros as usize) {
+            self.push(fill);
+        }
+        if signed_zeros {
+            self.push(b'-');
+        }
+        self
+    }
+
+    /// The formatted number.
+    pub fn as_str(&self) -> &str {
+        // Only ASCII is ever written, so this cannot fail.
+        core::str::from_utf8(&self.buf[self.start..]).unwrap_or("")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers() {
+        assert_eq!(NumStr::uint(0).as_str(), "0");
+        assert_eq!(NumStr::uint(u64::MAX).as_str(), "18446744073709551615");
+        assert_eq!(NumStr::int(-42).as_str(), "-42");
+        assert_eq!(NumStr::int(i64::MIN).as_str(), "-9223372036854775808");
+    }
+
+    #[test]
+    fn fixed_point() {
+        assert_eq!(NumStr::fixed(1205, 2).as_str(), "12.05");
+        assert_eq!(NumStr::fixed(-5, 2).as_str(), "-0.05");
+        assert_eq!(NumStr::fixed(0, 1).as_str(), "0.0");
+        assert_eq!(NumStr::fixed(7, 0).as_str(), "7");
+        assert_eq!(
+            NumStr::fixed(i64::MIN, 25).as_str(),
+            "-0.09223372036854775808"
+        );
+    }
+
+    #[test]
+    fn padding() {
+        assert_eq!(NumStr::int(950).pad(5, ' ').as_str(), "  950");
+        assert_eq!(NumStr::int(-7).pad(4, '0').as_str(), "-007");
+        assert_eq!(NumStr::fixed(-75, 1).pad(6, ' ').as_str(), "  -7.5");
+        // Too wide to pad, or too wide for the buffer.
+        assert_eq!(NumStr::int(12345).pad(3, ' ').as_str(), "12345");
+        assert_eq!(NumStr::uint(1).pad(100, '°').as_str().len(), 32);
+    }
+}
@@ -0,0 +1,381 @@
+//! Fonts in the compressed format of the [u8g2](https://github.com/olikraus/u8g2) graphics
+//! library, so that its large collection of fonts can be used without conversion.
+//!
+//! A u8g2 font is a byte array, as found in `u8g2_fonts.c` (e.g. `u8g2_font_helvR10_tr`), holding
+//! a header followed by a list of run-length encoded 1 bit per pixel glyphs. `U8g2Font` renders
+//! straight from the array, decoding each glyph as it is drawn, so the font takes no RAM and no
+//! more flash than it does in u8g2. Copy the array's contents into a `static` to use it:
+//!
+//! ```
+//! # use ssd1322::text::u8g2::U8g2Font;
+//! # const DATA: &[u8] = &[0; 23];
+//! static HELV_R10: &[u8] = DATA; // The bytes of `u8g2_font_helvR10_tr`.
+//! let font = U8g2Font::new(HELV_R10).unwrap();
+//! ```
+//!
+//! Text is laid out on a line as tall as the font's bounding box, with the baseline where u8g2
+//! puts it. Glyphs advance by their own width, and characters missing from the font are skipped.
+
+use crate::text::Font;
+
+/// The length of the font header.
+const HEADER_LEN: usize = 23;
+
+/// Errors which can occur when loading a u8g2 font.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum U8g2Error {
+    /// The data is too short to hold a font header.
+    Truncated,
+    /// The header gives a field a width of more than 8 bits.
+    BadHeader,
+}
+
+/// A font in u8g2's format. See the module documentation.
+#[derive(Clone, Copy, Debug)]
+pub struct U8g2Font<'a> {
+    data: &'a [u8],
+}
+
+/// The metrics of a glyph, and a reader positioned at the start of its pixel runs.
+struct Glyph<'a> {
+    width: u8,
+    height: u8,
+    x: i8,
+    y: i8,
+    advance: i8,
+    runs: Bits<'a>,
+}
+
+impl<'a> U8g2Font<'a> {
+    /// Use the u8g2 font in `data`.
+    pub fn new(data: &'a [u8]) -> Result<Self, U8g2Error> {
+        if data.len() < HEADER_LEN {
+            return Err(U8g2Error::Truncated);
+        }
+        if data[2..9].iter().any(|&bits| bits > 8) {
+            return Err(U8g2Error::BadHeader);
+        }
+        Ok(U8g2Font { data: data })
+    }
+
+    /// The number of glyphs in the font.
+    pub fn glyph_count(&self) -> u8 {
+        self.data[0]
+    }
+
+    /// The distance in pixels from the top of the line to the baseline.
+    pub fn baseline(&self) -> i16 {
+        self.data[10] as i16 + self.data[12] as i8 as i16
+    }
+
+    /// The big-endian 16-bit word at `offset`, or 0 beyond the end of the data.
+    fn word(&self, offset: usize) -> u16 {
+        let byte = |i: usize| self.data.get(i).copied().unwrap_or(0) as u16;
+        byte(offset) << 8 | byte(offset + 1)
+    }
+
+    /// The encoded data of the glyph for `c`, following its encoding and jump fields.
+    fn glyph_data(&self, c: char) -> Option<&'a [u8]> {
+        let encoding = c as u32;
+        let mut pos = HEADER_LEN;
+        if encoding <= 0xFF {
+            // The glyphs are sorted by encoding, with shortcuts to 'A' and 'a'.
+            if encoding >= 'a' as u32 {
+                pos += self.word(19) as usize;
+            } else if encoding >= 'A' as u32 {
+                pos += self.word(17) as usize;
+            }
+            loop {
+                let jump = *self.data.get(pos + 1)? as usize;
+                if jump == 0 {
+                    return None;
+                }
+                if self.data[pos] as u32 == encoding {
+                    return self.data.get(pos + 2..);
+                }
+                pos += jump;
+            }
+        }
+        if encoding > 0xFFFF {
+            return None;
+        }
+        // Unicode glyphs are found through a table of (offset, last encoding) pairs, where each
+        // offset is relative to the glyph found through the previous entry.
+        let mut table = pos + self.word(21) as usize;
+        pos = table;
+        loop {
+            if table + 4 > self.data.len() {
+                return None;
+            }
+            pos += self.word(table) as usize;
+            let last = self.word(table + 2) as u32;
+            table += 4;
+            if last >= encoding {
+                break;
+            }
+        }
+        loop {
+            let e = self.word(pos) as u32;
+            if e == 0 || pos + 3 > self.data.len() {
+                return None;
+            }
+            if e == encoding {
+                return self.data.get(pos + 3..);
+            }
+            pos += self.data[pos + 2] as usize;
+        }
+    }
+
+    fn glyph(&self, c: char) -> Option<Glyph<'a>> {
+        let mut bits = Bits::new(self.glyph_data(c)?);
+        let width = bits.unsigned(self.data[4]);
+        let height = bits.unsigned(self.data[5]);
+        let x = bits.signed(self.data[6]);
+        let y = bits.signed(self.data[7]);
+        let advance = bits.signed(self.data[8]);
+        Some(Glyph {
+            width: width,
+            height: height,
+            x: x,
+            y: y,
+            advance: advance,
+            runs: bits,
+        })
+    }
+}
+
+impl<'a> Font for U8g2Font<'a> {
+    fn height(&self) -> u8 {
+        self.data[10]
+    }
+
+    fn advance(&self, c: char) -> u8 {
+        self.glyph(c).map_or(0, |glyph| glyph.advance.max(0) as u8)
+    }
+
+    fn pixel(&self, c: char, x: u8, y: u8) -> u8 {
+        let mut glyph = match self.glyph(c) {
+            Some(glyph) => glyph,
+            None => return 0,
+        };
+        // The glyph's bitmap sits `y` pixels above the baseline, and `x` pixels to the right of
+        // the start of the glyph.
+        let top = self.baseline() - glyph.height as i16 - glyph.y as i16;
+        let gx = x as i16 - glyph.x as i16;
+        let gy = y as i16 - top;
+        if gx < 0 || gy < 0 || gx >= glyph.width as i16 || gy >= glyph.height as i16 {
+            return 0;
+        }
+        let target = (gy * glyph.width as i16 + gx) as usize;
+        let total = glyph.width as usize * glyph.height as usize;
+
+        // The bitmap is a series of runs of background pixels followed by foreground pixels,
+        // running on from one row to the next. After each pair of runs, a set bit repeats it.
+        let (zeros_bits, ones_bits) = (self.data[2], self.data[3]);
+        let mut pos = 0;
+        while pos < total {
+            let zeros = glyph.runs.unsigned(zeros_bits) as usize;
+            let ones = glyph.runs.unsigned(ones_bits) as usize;
+            if zeros + ones == 0 {
+                // Corrupt or truncated data, which would never fill the glyph.
+                return 0;
+            }
+            loop {
+                if target < pos + zeros {
+                    return 0;
+                }
+                if target < pos + zeros + ones {
+                    return 15;
+                }
+                pos += zeros + ones;
+                if glyph.runs.unsigned(1) == 0 {
+                    break;
+                }
+            }
+        }
+        0
+    }
+}
+
+/// Reads bit fields least significant bit first, as u8g2 packs them.
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Bits<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Bits { data: data, pos: 0 }
+    }
+
+    /// Read an unsigned field of `count` bits, up to 8. Reads beyond the end of the data give 0.
+    fn unsigned(&mut self, count: u8) -> u8 {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = self.data.get(self.pos / 8).copied().unwrap_or(0);
+            value |= (byte >> (self.pos % 8) & 1) << i;
+            self.pos += 1;
+        }
+        value
+    }
+
+    /// Read a signed field of `count` bits, stored with an offset of `2^(count - 1)`.
+    fn signed(&mut self, count: u8) -> i8 {
+        if count == 0 {
+            return 0;
+        }
+        (self.unsigned(count) as i16 - (1 << (count - 1))) as i8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::{text_width, TextPixels};
+    use std::vec::Vec;
+
+    /// Bit field widths: zero runs, one runs, width, height, x, y, advance.
+    const BITS: [u8; 7] = [2, 2, 3, 3, 2, 2, 4];
+
+    /// Packs bit fields least significant bit first.
+    #[derive(Default)]
+    struct BitWriter {
+        bytes: Vec<u8>,
+        pos: usize,
+    }
+
+    impl BitWriter {
+        fn push(&mut self, value: i16, count: u8) {
+            for i in 0..count {
+                if self.pos.is_multiple_of(8) {
+                    self.bytes.push(0);
+                }
+                *self.bytes.last_mut().unwrap() |= ((value >> i & 1) as u8) << (self.pos % 8);
+                self.pos += 1;
+            }
+        }
+    }
+
+    /// Encode a glyph's metrics and bitmap, given as rows of `#` and `.`, in the format u8g2's
+    /// font converter produces.
+    fn encode_glyph(x: i16, y: i16, advance: i16, rows: &[&str]) -> Vec<u8> {
+        let mut w = BitWriter::default();
+        let (width, height) = (rows.first().map_or(0, |r| r.len()), rows.len());
+        w.push(width as i16, BITS[2]);
+        w.push(height as i16, BITS[3]);
+        w.push(x + (1 << (BITS[4] - 1)), BITS[4]);
+        w.push(y + (1 << (BITS[5] - 1)), BITS[5]);
+        w.push(advance + (1 << (BITS[6] - 1)), BITS[6]);
+        // Split the pixels into runs of background then foreground, no longer than the fields
+        // allow, and write each pair once followed by a repeat bit for each repetition.
+        let pixels: Vec<bool> = rows.concat().chars().map(|c| c == '#').collect();
+        let max = [(1 << BITS[0]) - 1, (1 << BITS[1]) - 1];
+        let mut pairs = Vec::new();
+        let mut i = 0;
+        while i < pixels.len() {
+            let mut run = [0, 0];
+            while i < pixels.len() && !pixels[i] && run[0] < max[0] {
+                run[0] += 1;
+                i += 1;
+            }
+            while i < pixels.len() && pixels[i] && run[1] < max[1] {
+                run[1] += 1;
+                i += 1;
+            }
+            pairs.push(run);
+        }
+        for (n, pair) in pairs.iter().enumerate() {
+            if n > 0 && pairs[n - 1] == *pair {
+                w.push(1, 1);
+                continue;
+            }
+            if n > 0 {
+                w.push(0, 1);
+            }
+            w.push(pair[0], BITS[0]);
+            w.push(pair[1], BITS[1]);
+        }
+        if !pairs.is_empty() {
+            w.push(0, 1);
+        }
+        w.bytes
+    }
+
+    /// A font with a 5 pixel tall box, baseline 4 pixels down, holding ' ', 'A', 'a' (with a
+    /// descender) and '€'.
+    fn font_data() -> Vec<u8> {
+        let glyphs: [(u32, Vec<u8>); 3] = [
+            (' ' as u32, encode_glyph(0, 0, 2, &[])),
+            ('A' as u32, encode_glyph(0, 0, 4, &[".#.", "#.#", "###"])),
+            ('a' as u32, encode_glyph(1, -1, 3, &["#", "#"])),
+        ];
+        let mut list = Vec::new();
+        let mut starts = [0u16; 2];
+        for (encoding, data) in glyphs.iter() {
+            match char::from_u32(*encoding).unwrap() {
+                'A' => starts[0] = list.len() as u16,
+                'a' => starts[1] = list.len() as u16,
+                _ => {}
+            }
+            list.push(*encoding as u8);
+            list.push(data.len() as u8 + 2);
+            list.extend_from_slice(data);
+        }
+        list.extend_from_slice(&[0, 0]);
+        let unicode = list.len() as u16;
+        // One table entry pointing past itself, then the euro sign and the end marker.
+        list.extend_from_slice(&[0, 4, 0xFF, 0xFF]);
+        let euro = encode_glyph(0, 0, 2, &["##", "#.", "##"]);
+        list.extend_from_slice(&[0x20, 0xAC, euro.len() as u8 + 3]);
+        list.extend_from_slice(&euro);
+        list.extend_from_slice(&[0, 0]);
+
+        #[rustfmt::skip]
+        let mut data = vec![
+            4, 0, BITS[0], BITS[1], BITS[2], BITS[3], BITS[4], BITS[5], BITS[6],
+            // Bounding box: 3x5, offset (0, -1).
+            3, 5, 0, -1i8 as u8,
+            // Ascent and descent of 'A', 'g' and '(' .
+            3, 0, 3, 0,
+        ];
+        for word in [starts[0], starts[1], unicode].iter() {
+            data.extend_from_slice(&word.to_be_bytes());
+        }
+        data.extend_from_slice(&list);
+        data
+    }
+
+    #[test]
+    fn render() {
+        let data = font_data();
+        let font = U8g2Font::new(&data).unwrap();
+        assert_eq!(
+            (font.glyph_count(), font.height(), font.baseline()),
+            (4, 5, 4)
+        );
+        assert_eq!(text_width(&font, "A a€?"), 4 + 2 + 3 + 2);
+
+        let pixels = TextPixels::new(&font, "Aa€", 10, 15, 0).collect::<Vec<_>>();
+        #[rustfmt::skip]
+        assert_eq!(pixels, vec![
+             0,  0,  0, 0, 0,  0, 0, 0,  0,  0,
+             0, 15,  0, 0, 0,  0, 0, 15, 15, 0,
+            15,  0, 15, 0, 0,  0, 0, 15,  0, 0,
+            15, 15, 15, 0, 0, 15, 0, 15, 15, 0,
+             0,  0,  0, 0, 0, 15, 0, 0,  0,  0,
+        ]);
+    }
+
+    #[test]
+    fn bad_data() {
+        assert_eq!(U8g2Font::new(&[0; 22]).err(), Some(U8g2Error::Truncated));
+        let mut data = font_data();
+        data[4] = 9;
+        assert_eq!(U8g2Font::new(&data).err(), Some(U8g2Error::BadHeader));
+        // A font cut short renders missing glyphs rather than panicking.
+        let data = font_data();
+        let font = U8g2Font::new(&data[..HEADER_LEN + 4]).unwrap();
+        assert_eq!(font.advance('€'), 0);
+        assert_eq!(font.pixel('A', 1, 1), 0);
+    }
+}
@@ -0,0 +1,334 @@
+//! Word-wrapped layout of text over several lines in a box, for drawing paragraphs into a region
+//! with `Region::draw_text`.
+//!
+//! Lines are broken at whitespace so that each fits the width of the box, and at line feeds. A
+//! word too long for a line on its own is broken wherever it reaches the edge. Text which needs
+//! more lines than fit in the box is either clipped at the last line that fits, or has that line
+//! cut short and ended with an ellipsis.
+
+use crate::text::{align_offset, text_width, Font, HAlign, TextPixels, VAlign};
+
+/// The ellipsis marking text cut short. Three full stops, as most fonts lack `…`.
+const ELLIPSIS: &str = "...";
+
+/// What to do with text which needs more lines than fit in its box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overflow {
+    /// Drop the lines which do not fit.
+    Clip,
+    /// End the last line which fits with an ellipsis, shortening it as needed to make room.
+    Ellipsis,
+}
+
+/// An iterator breaking text into lines no wider than a given width, yielding each line with
+/// trailing whitespace removed.
+#[derive(Debug)]
+pub struct WrapLines<'a, F> {
+    font: &'a F,
+    /// The text not yet broken into lines.
+    rest: &'a str,
+    width: u16,
+}
+
+impl<'a, F> WrapLines<'a, F>
+where
+    F: Font,
+{
+    /// Break `text` set in `font` into lines at most `width` pixels wide.
+    pub fn new(font: &'a F, text: &'a str, width: u16) -> Self {
+        WrapLines {
+            font: font,
+            rest: text,
+            width: width,
+        }
+    }
+
+    /// The text not yet broken into lines.
+    pub fn remainder(&self) -> &'a str {
+        self.rest
+    }
+}
+
+// Derived `Clone` would needlessly require the font to be `Clone`.
+impl<'a, F> Clone for WrapLines<'a, F> {
+    fn clone(&self) -> Self {
+        WrapLines {
+            font: self.font,
+            rest: self.rest,
+            width: self.width,
+        }
+    }
+}
+
+impl<'a, F> Iterator for WrapLines<'a, F>
+where
+    F: Font,
+{
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let text = self.rest;
+        let mut width = 0u16;
+        // The end of the line and the start of the next, if it were broken at the last whitespace.
+        let mut space_break = None;
+        let mut in_space = false;
+        for (i, c) in text.char_indices() {
+            if c == '\n' {
+                self.rest = &text[i + 1..];
+                return Some(text[..i].trim_end());
+            }
+            if c.is_whitespace() {
+                // Whitespace may hang past the edge, as it is trimmed from the end of the line.
+                if !in_space {
+                    space_break = Some(i);
+                }
+                in_space = true;
+                width = width.saturating_add(self.font.advance(c) as u16);
+                continue;
+            }
+            in_space = false;
+            let advance = self.font.advance(c) as u16;
+            if width + advance > self.width && i > 0 {
+                let end = match space_break {
+                    Some(end) if !text[..end].trim().is_empty() => end,
+                    // No whitespace to break at, so break the word itself.
+                    _ => i,
+                };
+                self.rest =
+                    text[end..].trim_start_matches(|c: char| c.is_whitespace() && c != '\n');
+                return Some(text[..end].trim_end());
+            }
+            width = width.saturating_add(advance);
+        }
+        self.rest = "";
+        Some(text.trim_end())
+    }
+}
+
+/// The longest start of the first line of `text` which fits in `width` pixels along with an
+/// ellipsis.
+fn ellipsize<'a, F: Font>(font: &F, text: &'a str, width: u16) -> &'a str {
+    let line = text.split('\n').next().unwrap_or("");
+    let room = width.saturating_sub(text_width(font, ELLIPSIS));
+    let mut used = 0u16;
+    for (i, c) in line.char_indices() {
+        used = used.saturating_add(font.advance(c) as u16);
+        if used > room {
+            return line[..i].trim_end();
+        }
+    }
+    line.trim_end()
+}
+
+/// An iterator rendering word-wrapped text as unpacked pixels, in left-to-right, top-to-bottom
+/// order, into a box `width` by `height` pixels. The lines are aligned to the top left of the box
+/// unless set otherwise with `align`, and the rest of the box is filled with the background level.
+pub struct WrappedText<'a, F> {
+    font: &'a F,
+    width: u16,
+    fg: u8,
+    bg: u8,
+    lines: WrapLines<'a, F>,
+    /// The number of lines shown, whether the last of them ends with an ellipsis, and the number
+    /// still to be rendered.
+    shown: usize,
+    ellipsis: bool,
+    lines_left: usize,
+    halign: HAlign,
+    /// The line being rendered.
+    current: Option<TextPixels<'a, F>>,
+    /// The number of background pixels left above the text, and below it.
+    blank_above: u32,
+    blank_below: u32,
+}
+
+impl<'a, F> WrappedText<'a, F>
+where
+    F: Font,
+{
+    /// Render `text` in `font`, in gray level `fg` on a background of `bg`, wrapped to fit a box
+    /// `width` by `height` pixels, with text which does not fit handled according to `overflow`.
+    pub fn new(
+        font: &'a F,
+        text: &'a str,
+        width: u16,
+        height: u16,
+        fg: u8,
+        bg: u8,
+        overflow: Overflow,
+    ) -> Self {
+        let lines = WrapLines::new(font, text, width);
+        let fit = match font.height() {
+            0 => 0,
+            line_height => (height / line_height as u16) as usize,
+        };
+        let total = lines.clone().count();
+        let shown = total.min(fit);
+        let blank_rows = height - (shown as u16 * font.height() as u16);
+        WrappedText {
+            font: font,
+            width: width,
+            fg: fg,
+            bg: bg,
+            lines: lines,
+            shown: shown,
+            ellipsis: overflow == Overflow::Ellipsis && total > shown,
+            lines_left: shown,
+            halign: HAlign::Left,
+            current: None,
+            blank_above: 0,
+            blank_below: blank_rows as u32 * width as u32,
+        }
+    }
+
+    /// Align each line horizontally within the box, and the block of lines vertically.
+    pub fn align(mut self, halign: HAlign, valign: VAlign) -> Self {
+        let blank = self.blank_above + self.blank_below;
+        let rows = (blank / self.width.max(1) as u32) as u16;
+        let above = align_offset(rows, 0, valign == VAlign::Middle, valign == VAlign::Bottom);
+        self.halign = halign;
+        self.blank_above = above as u32 * self.width as u32;
+        self.blank_below = blank - self.blank_above;
+        self
+    }
+
+    /// The number of lines of the box the text takes up.
+    pub fn lines(&self) -> usize {
+        self.shown
+    }
+}
+
+impl<'a, F> Iterator for WrappedText<'a, F>
+where
+    F: Font,
+{
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.blank_above > 0 {
+            self.blank_above -= 1;
+            return Some(self.bg);
+        }
+        loop {
+            if let Some(pixel) = self.current.as_mut().and_then(|line| line.next()) {
+                return Some(pixel);
+            }
+            if self.lines_left == 0 {
+                break;
+            }
+            self.lines_left -= 1;
+            self.current = if self.lines_left == 0 && self.ellipsis {
+                let line = ellipsize(self.font, self.lines.remainder(), self.width);
+                Some(
+                    TextPixels::with_suffix(
+                        self.font, line, ELLIPSIS, self.width, self.fg, self.bg,
+                    )
+                    .align(self.halign),
+                )
+            } else {
+                let line = self.lines.next().unwrap_or("");
+                Some(
+                    TextPixels::new(self.font, line, self.width, self.fg, self.bg)
+                        .align(self.halign),
+                )
+            };
+        }
+        if self.blank_below == 0 {
+            return None;
+        }
+        self.blank_below -= 1;
+        Some(self.bg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::tests::TEST_FONT;
+    use crate::text::MonoFont;
+    use std::vec::Vec;
+
+    /// A font with every character 1 pixel wide and tall, showing all but spaces.
+    const DOT_FONT: MonoFont<'static> = MonoFont {
+        width: 1,
+        height: 1,
+        first: '!',
+        glyphs: &[0x80; 94],
+    };
+
+    fn wrap(text: &str, width: u16) -> Vec<&str> {
+        WrapLines::new(&DOT_FONT, text, width).collect()
+    }
+
+    #[test]
+    fn wrap_lines() {
+        assert_eq!(wrap("the quick brown fox", 10), ["the quick", "brown fox"]);
+        assert_eq!(wrap("the quick brown fox", 9), ["the quick", "brown fox"]);
+        assert_eq!(
+            wrap("the quick brown fox", 8),
+            ["the", "quick", "brown", "fox"]
+        );
+        // Line feeds, blank lines and runs of spaces.
+        assert_eq!(wrap("ab\n\ncd  \nef", 10), ["ab", "", "cd", "ef"]);
+        assert_eq!(wrap("ab    cd", 3), ["ab", "cd"]);
+        assert_eq!(wrap("  indented", 20), ["  indented"]);
+        // Long words are broken.
+        assert_eq!(wrap("abcdefgh ij", 3), ["abc", "def", "gh", "ij"]);
+        assert!(wrap("", 10).is_empty());
+    }
+
+    #[test]
+    fn overflow() {
+        let text = "the quick brown fox";
+        let pixels =
+            |overflow| WrappedText::new(&DOT_FONT, text, 6, 2, 15, 0, overflow).collect::<Vec<_>>();
+        #[rustfmt::skip]
+        assert_eq!(pixels(Overflow::Clip), vec![
+            15, 15, 15,  0,  0,  0,
+            15, 15, 15, 15, 15,  0,
+        ]);
+        #[rustfmt::skip]
+        assert_eq!(pixels(Overflow::Ellipsis), vec![
+            15, 15, 15,  0,  0,  0,
+            15, 15, 15, 15, 15, 15,
+        ]);
+        assert_eq!(ellipsize(&DOT_FONT, "quick brown", 6), "qui");
+        assert_eq!(ellipsize(&DOT_FONT, "qu ick", 6), "qu");
+    }
+
+    #[test]
+    fn lines_and_blank_space() {
+        let text = WrappedText::new(&TEST_FONT, "AB BA", 8, 8, 15, 0, Overflow::Clip);
+        assert_eq!(text.lines(), 2);
+        let pixels = text.collect::<Vec<_>>();
+        assert_eq!(pixels.len(), 64);
+        assert_eq!(&pixels[24..32], &[0, 15, 0, 0, 15, 15, 15, 15]);
+        assert!(pixels[48..].iter().all(|&p| p == 0));
+    }
+
+    #[test]
+    fn aligned() {
+        let pixels = |halign, valign| {
+            WrappedText::new(&DOT_FONT, "ab c", 3, 4, 15, 0, Overflow::Clip)
+                .align(halign, valign)
+                .collect::<Vec<_>>()
+        };
+        #[rustfmt::skip]
+        assert_eq!(pixels(HAlign::Right, VAlign::Bottom), vec![
+             0,  0,  0,
+             0,  0,  0,
+             0, 15, 15,
+             0,  0, 15,
+        ]);
+        #[rustfmt::skip]
+        assert_eq!(pixels(HAlign::Center, VAlign::Middle), vec![
+             0,  0,  0,
+            15, 15,  0,
+             0, 15,  0,
+             0,  0,  0,
+        ]);
+    }
+}
@@ -0,0 +1,383 @@
+//! A dial gauge with a needle, as for instrument clusters and dashboards.
+//!
+//! The scale and needle are rendered from their geometry with anti-aliased edges using the 16 gray
+//! levels. Angles are in whole degrees, measured clockwise from 12 o'clock.
+
+use crate::command::CommandError;
+use crate::display::{Display, PixelCoord, Rect};
+use crate::interface;
+use crate::text::blend;
+use crate::widgets::Widget;
+
+/// `sin(d)` for each whole degree `d` from 0 to 90, scaled by `2^14`.
+#[rustfmt::skip]
+const SIN_Q14: [i32; 91] = [
+    0, 286, 572, 857, 1143, 1428, 1713, 1997, 2280, 2563,
+    2845, 3126, 3406, 3686, 3964, 4240, 4516, 4790, 5063, 5334,
+    5604, 5872, 6138, 6402, 6664, 6924, 7182, 7438, 7692, 7943,
+    8192, 8438, 8682, 8923, 9162, 9397, 9630, 9860, 10087, 10311,
+    10531, 10749, 10963, 11174, 11381, 11585, 11786, 11982, 12176, 12365,
+    12551, 12733, 12911, 13085, 13255, 13421, 13583, 13741, 13894, 14044,
+    14189, 14330, 14466, 14598, 14726, 14849, 14968, 15082, 15191, 15296,
+    15396, 15491, 15582, 15668, 15749, 15826, 15897, 15964, 16026, 16083,
+    16135, 16182, 16225, 16262, 16294, 16322, 16344, 16362, 16374, 16382,
+    16384,
+];
+
+/// Geometry is computed in sixteenths of a pixel.
+const SUB: i32 = 16;
+
+/// The radius of the needle hub, in pixels.
+const HUB: i32 = 2;
+
+/// `sin(degrees)`, scaled by `2^14`.
+fn sin_q14(degrees: i32) -> i32 {
+    match degrees.rem_euclid(360) {
+        d @ 0..=90 => SIN_Q14[d as usize],
+        d @ 91..=180 => SIN_Q14[(180 - d) as usize],
+        d @ 181..=270 => -SIN_Q14[(d - 180) as usize],
+        d => -SIN_Q14[(360 - d) as usize],
+    }
+}
+
+/// The unit vector pointing `degrees` clockwise from straight up, in display coordinates where `y`
+/// grows downwards, scaled by `2^14`.
+fn direction(degrees: i32) -> (i32, i32) {
+    (sin_q14(degrees), -sin_q14(degrees + 90))
+}
+
+/// The cross product of two vectors, positive when `b` is clockwise of `a` on the display.
+fn cross(a: (i32, i32), b: (i32, i32)) -> i64 {
+    a.0 as i64 * b.1 as i64 - a.1 as i64 * b.0 as i64
+}
+
+/// The distance of `p` from the origin.
+fn length(p: (i32, i32)) -> i32 {
+    ((p.0 as i64 * p.0 as i64 + p.1 as i64 * p.1 as i64) as u64).isqrt() as i32
+}
+
+/// The coverage, from 0 to `SUB`, of a pixel at `p` by a line one pixel wide through the origin
+/// along the unit vector `dir`, from `start` to `end` along it.
+fn segment_coverage(p: (i32, i32), dir: (i32, i32), start: i32, end: i32) -> i32 {
+    let along = ((p.0 as i64 * dir.0 as i64 + p.1 as i64 * dir.1 as i64) >> 14) as i32;
+    let across = (cross(dir, p).abs() >> 14) as i32;
+    let distance = if along < start {
+        length((start - along, across))
+    } else if along > end {
+        length((along - end, across))
+    } else {
+        across
+    };
+    (SUB - distance).clamp(0, SUB)
+}
+
+/// A round dial with a scale marked by an arc and evenly spaced ticks, and a needle pointing at
+/// the current value, in a square `size` pixels across.
+///
+/// The scale is drawn once, and each change of value redraws only the column groups and rows
+/// covered by the needle at its old and new positions.
+pub struct Gauge {
+    upper_left: PixelCoord,
+    size: u16,
+    start: i16,
+    sweep: u16,
+    ticks: u16,
+    scale: u8,
+    needle: u8,
+    bg: u8,
+    value: u8,
+    drawn: bool,
+}
+
+impl Gauge {
+    /// Create a gauge filling the square of `size` pixels with its upper left corner at
+    /// `upper_left`. As with `Display::region`, the horizontal coordinate and `size` must be
+    /// divisible by 4.
+    ///
+    /// The scale runs clockwise through 270 degrees from the lower left to the lower right with
+    /// 11 ticks, and the scale and needle are drawn in gray level 15 on 0; use the setters to
+    /// change these.
+    pub fn new(upper_left: PixelCoord, size: u16) -> Self {
+        Gauge {
+            upper_left: upper_left,
+            size: size,
+            start: -135,
+            sweep: 270,
+            ticks: 11,
+            scale: 15,
+            needle: 15,
+            bg: 0,
+            value: 0,
+            drawn: false,
+        }
+    }
+
+    /// Set the scale to run clockwise through `sweep` degrees, up to 360, from the angle `start`.
+    /// The whole gauge is redrawn on the next update.
+    pub fn set_scale(&mut self, start: i16, sweep: u16) {
+        self.start = start;
+        self.sweep = sweep.min(360);
+        self.drawn = false;
+    }
+
+    /// Set the number of ticks marked along the scale, including those at each end. The whole
+    /// gauge is redrawn on the next update.
+    pub fn set_ticks(&mut self, ticks: u16) {
+        self.ticks = ticks;
+        self.drawn = false;
+    }
+
+    /// Set the gray levels of the scale, the needle and the background. The whole gauge is redrawn
+    /// on the next update.
+    pub fn set_colors(&mut self, scale: u8, needle: u8, bg: u8) {
+        self.scale = scale & 0x0F;
+        self.needle = needle & 0x0F;
+        self.bg = bg & 0x0F;
+        self.drawn = false;
+    }
+
+    /// Move the needle to `value`, where 0 is the start of the scale and 255 the end. Only the area
+    /// around the old and new needle positions is redrawn, or the whole gauge if it has not been
+    /// drawn yet.
+    pub fn set_value<DI>(
+        &mut self,
+        display: &mut Display<DI>,
+        value: u8,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        let (old, old_angle) = (self.needle_bounds(), self.angle_of(self.value));
+        self.value = value;
+        if !self.drawn {
+            return self.draw(display);
+        }
+        if self.angle_of(value) == old_angle {
+            return Ok(());
+        }
+        let new = self.needle_bounds();
+        // Overlapping areas are redrawn as one, to avoid sending the overlap twice.
+        if old.intersect(&new).is_some() {
+            let union = Rect::new(
+                PixelCoord(old.ul.0.min(new.ul.0), old.ul.1.min(new.ul.1)),
+                PixelCoord(old.lr.0.max(new.lr.0), old.lr.1.max(new.lr.1)),
+            );
+            self.draw_rect(display, union)
+        } else {
+            self.draw_rect(display, old)?;
+            self.draw_rect(display, new)
+        }
+    }
+
+    /// Draw the whole gauge, e.g. after the display was cleared.
+    pub fn draw<DI>(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        let size = self.size as i16;
+        let lower_right = PixelCoord(self.upper_left.0 + size, self.upper_left.1 + size);
+        self.draw_rect(display, Rect::new(self.upper_left, lower_right))?;
+        self.drawn = true;
+        Ok(())
+    }
+
+    /// The angle at which the needle points for `value`.
+    fn angle_of(&self, value: u8) -> i32 {
+        self.start as i32 + self.sweep as i32 * value as i32 / 255
+    }
+
+    /// The radius of the scale arc, in sixteenths of a pixel, keeping its edges inside the gauge.
+    fn radius(&self) -> i32 {
+        (self.size as i32 * SUB / 2 - 3 * SUB / 2).max(0)
+    }
+
+    /// The centre of the dial, in sixteenths of a pixel. This is the centre of a pixel, rather than
+    /// the corner between four, so that needles pointing straight along a row or column are crisp.
+    fn center(&self) -> (i32, i32) {
+        let half = self.size as i32 / 2;
+        (
+            (self.upper_left.0 as i32 + half) * SUB + SUB / 2,
+            (self.upper_left.1 as i32 + half) * SUB + SUB / 2,
+        )
+    }
+
+    /// The rectangle covering the needle and hub in their current position, widened to whole
+    /// column groups and clipped to the gauge.
+    fn needle_bounds(&self) -> Rect {
+        let (cx, cy) = self.center();
+        let dir = direction(self.angle_of(self.value));
+        let length = self.needle_length() as i64;
+        let tip = (
+            cx + ((dir.0 as i64 * length) >> 14) as i32,
+            cy + ((dir.1 as i64 * length) >> 14) as i32,
+        );
+        let margin = (HUB + 1) * SUB;
+        let size = self.size as i16;
+        let (left, top) = (self.upper_left.0, self.upper_left.1);
+        let to_pixel = |v: i32| v.div_euclid(SUB) as i16;
+        let x0 = to_pixel(cx.min(tip.0) - margin).max(left) & !3;
+        let x1 = (to_pixel(cx.max(tip.0) + margin) + 4).min(left + size) & !3;
+        let y0 = to_pixel(cy.min(tip.1) - margin).max(top);
+        let y1 = (to_pixel(cy.max(tip.1) + margin) + 1).min(top + size);
+        Rect::new(PixelCoord(x0, y0), PixelCoord(x1, y1))
+    }
+
+    /// The length of the needle, in sixteenths of a pixel, stopping short of the ticks.
+    fn needle_length(&self) -> i32 {
+        self.radius() - self.tick_length() - SUB
+    }
+
+    /// The length of the ticks, in sixteenths of a pixel.
+    fn tick_length(&self) -> i32 {
+        (self.radius() / 6).max(SUB)
+    }
+
+    /// Whether the point `p`, relative to the centre, lies within the angles of the scale.
+    fn in_scale(&self, p: (i32, i32)) -> bool {
+        let start = direction(self.start as i32);
+        let end = direction(self.start as i32 + self.sweep as i32);
+        if self.sweep >= 360 {
+            true
+        } else if self.sweep <= 180 {
+            cross(start, p) >= 0 && cross(p, end) >= 0
+        } else {
+            !(cross(end, p) > 0 && cross(p, start) > 0)
+        }
+    }
+
+    /// The gray level of the pixel at `x`, `y` on the display.
+    fn pixel(&self, x: i16, y: i16) -> u8 {
+        let (cx, cy) = self.center();
+        let p = (x as i32 * SUB + SUB / 2 - cx, y as i32 * SUB + SUB / 2 - cy);
+        let distance = length(p);
+        let radius = self.radius();
+
+        let mut scale = 0;
+        if self.in_scale(p) {
+            scale = (SUB - (distance - radius).abs()).clamp(0, SUB);
+        }
+        let inner = radius - self.tick_length();
+        if self.ticks > 0 && distance >= inner - SUB && distance <= radius + SUB {
+            let steps = (self.ticks - 1).max(1) as i32;
+            for tick in 0..self.ticks as i32 {
+                let angle = self.start as i32 + self.sweep as i32 * tick / steps;
+                let coverage = segment_coverage(p, direction(angle), inner, radius);
+                scale = scale.max(coverage);
+            }
+        }
+
+        let dir = direction(self.angle_of(self.value));
+        let needle = segment_coverage(p, dir, 0, self.needle_length())
+            .max((HUB * SUB + SUB / 2 - distance).clamp(0, SUB));
+
+        let base = blend((scale * 15 / SUB) as u8, self.scale, self.bg);
+        blend((needle * 15 / SUB) as u8, self.needle, base)
+    }
+
+    /// Draw the part of the gauge within `rect`, whose horizontal edges are multiples of 4.
+    fn draw_rect<DI>(
+        &self,
+        display: &mut Display<DI>,
+        rect: Rect,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        if rect.width() <= 0 || rect.height() <= 0 {
+            return Ok(());
+        }
+        let pixels = (rect.ul.1..rect.lr.1)
+            .flat_map(move |y| (rect.ul.0..rect.lr.0).map(move |x| self.pixel(x, y)));
+        display
+            .region_rect(rect)?
+            .draw(pixels)
+            .map_err(CommandError::InterfaceError)
+    }
+}
+
+impl<DI> Widget<DI> for Gauge
+where
+    DI: interface::DisplayInterface,
+{
+    fn draw(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        Self::draw(self, display)
+    }
+
+    fn update(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        if self.drawn {
+            return Ok(());
+        }
+        Self::draw(self, display)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+    use crate::test_support::widget_fixture;
+    use std::vec::Vec;
+
+    fn setup() -> (MockInterface, Display<MockInterface>, Gauge) {
+        widget_fixture(Px(32, 32), Gauge::new(Px(0, 0), 32))
+    }
+
+    #[test]
+    fn trig() {
+        assert_eq!(direction(0), (0, -16384));
+        assert_eq!(direction(90), (16384, 0));
+        assert_eq!(direction(-90), (-16384, 0));
+        assert_eq!(direction(225), (-11585, 11585));
+    }
+
+    #[test]
+    fn scale_and_needle() {
+        let (di, mut disp, mut gauge) = setup();
+        gauge.set_scale(-90, 180);
+        gauge.set_ticks(3);
+        // Straight up, halfway along the scale.
+        gauge.set_value(&mut disp, 128).unwrap();
+        let image = di.chip_model(Px(32, 32), Px(0, 0)).render();
+        let at = |x: usize, y: usize| image[y * 32 + x];
+        // The needle and the middle tick run straight up from the centre, and the arc crosses the
+        // top of the dial, but not the bottom, which is outside the scale.
+        assert!((2..=16).all(|y| at(16, y) >= 13));
+        assert_eq!(at(16, 0), 0);
+        assert!(at(16, 1) >= 7 && at(15, 1) == at(17, 1));
+        assert_eq!(at(16, 30), 0);
+        // Edges are shaded, while a needle pointing straight up is crisp.
+        assert!(at(14, 16) > 0 && at(14, 16) < 15);
+        assert_eq!(at(15, 10), 0);
+    }
+
+    #[test]
+    fn minimal_updates() {
+        let (mut di, mut disp, mut gauge) = setup();
+        gauge.set_value(&mut disp, 0).unwrap();
+        di.clear();
+        // Straight up to straight right.
+        gauge.set_scale(0, 90);
+        gauge.draw(&mut disp).unwrap();
+        di.clear();
+        gauge.set_value(&mut disp, 255).unwrap();
+        let sent = di.sent();
+        let columns: Vec<_> = sent
+            .iter()
+            .zip(sent.iter().skip(1))
+            .filter(|(cmd, _)| **cmd == Sent::Cmd(0x15))
+            .map(|(_, args)| args.clone())
+            .collect();
+        // One region from the left of the hub to the right edge, instead of the whole gauge.
+        assert_eq!(columns, vec![Sent::Data(vec![3, 7])]);
+        let rows = sent.iter().position(|s| *s == Sent::Cmd(0x75)).unwrap();
+        match &sent[rows + 1] {
+            Sent::Data(rows) => assert!(rows[0] > 0 && rows[1] < 31),
+            other => panic!("{:?}", other),
+        }
+        // Setting the same value again draws nothing.
+        di.clear();
+        gauge.set_value(&mut disp, 255).unwrap();
+        assert!(di.sent().is_empty());
+    }
+}
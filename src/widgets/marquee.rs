@@ -0,0 +1,331 @@
+//! A single line of text which scrolls horizontally when it is too long to fit in its rectangle,
+//! like a song title on a music player.
+
+use crate::command::CommandError;
+use crate::display::{Display, PixelCoord};
+use crate::interface;
+use crate::text::{blend, Font};
+use crate::widgets::Widget;
+
+/// A line of text scrolling right-to-left through a rectangle as wide as given and as tall as the
+/// font. The text repeats endlessly with a gap of blank pixels between repetitions.
+///
+/// Text which fits in the rectangle does not scroll, and is drawn only once.
+pub struct Marquee<'a, F> {
+    font: F,
+    text: &'a str,
+    upper_left: PixelCoord,
+    width: u16,
+    gap: u16,
+    step: u16,
+    fg: u8,
+    bg: u8,
+    /// The pixel position within the text plus gap shown at the left edge of the rectangle.
+    offset: u32,
+    drawn: bool,
+}
+
+impl<'a, F> Marquee<'a, F>
+where
+    F: Font,
+{
+    /// Create a marquee showing `text` in `font`, in the rectangle starting at `upper_left` and
+    /// `width` pixels wide. As with `Display::region`, the horizontal coordinate and `width` must be
+    /// divisible by 4.
+    ///
+    /// The marquee scrolls by one pixel per tick with a gap as wide as the rectangle, in gray level
+    /// 15 on 0; use the setters to change these.
+    pub fn new(font: F, text: &'a str, upper_left: PixelCoord, width: u16) -> Self {
+        Marquee {
+            font: font,
+            text: text,
+            upper_left: upper_left,
+            width: width,
+            gap: width,
+            step: 1,
+            fg: 15,
+            bg: 0,
+            offset: 0,
+            drawn: false,
+        }
+    }
+
+    /// Set the number of blank pixels between the end of the text and its next repetition.
+    pub fn set_gap(&mut self, gap: u16) {
+        self.gap = gap;
+        self.offset %= self.period();
+    }
+
+    /// Set the number of pixels the text scrolls on each `tick`.
+    pub fn set_step(&mut self, step: u16) {
+        self.step = step;
+    }
+
+    /// Set the gray levels used for the text and background.
+    pub fn set_colors(&mut self, fg: u8, bg: u8) {
+        self.fg = fg & 0x0F;
+        self.bg = bg & 0x0F;
+        self.drawn = false;
+    }
+
+    /// Replace the text, restarting it from the beginning. It is drawn on the next `tick`.
+    pub fn set_text(&mut self, text: &'a str) {
+        self.text = text;
+        self.offset = 0;
+        self.drawn = false;
+    }
+
+    /// Whether the text is too wide for the rectangle and so scrolls.
+    pub fn scrolls(&self) -> bool {
+        self.text_width() > self.width as u32
+    }
+
+    /// Advance the text by one step if it scrolls, and redraw the rectangle if it changed.
+    pub fn tick<DI>(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        if self.scrolls() {
+            if self.drawn {
+                self.offset =
+                    ((self.offset as u64 + self.step as u64) % self.period() as u64) as u32;
+            }
+        } else if self.drawn {
+            return Ok(());
+        }
+        self.draw(display)
+    }
+
+    /// Draw the rectangle at the current scroll position, e.g. after the display was cleared.
+    pub fn draw<DI>(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        let lower_right = PixelCoord(
+            self.upper_left.0 + self.width as i16,
+            self.upper_left.1 + self.font.height() as i16,
+        );
+        let offset = if self.scrolls() { self.offset } else { 0 };
+        let pixels = MarqueePixels::new(self, offset);
+        display
+            .region(self.upper_left, lower_right)?
+            .draw(pixels)
+            .map_err(CommandError::InterfaceError)?;
+        self.drawn = true;
+        Ok(())
+    }
+
+    /// The distance in pixels after which the scrolling text repeats.
+    fn period(&self) -> u32 {
+        (self.text_width() + self.gap as u32).max(1)
+    }
+
+    /// The width of the text in pixels, which unlike `text::text_width` cannot overflow for any
+    /// text a marquee might reasonably show.
+    fn text_width(&self) -> u32 {
+        self.text.chars().map(|c| self.font.advance(c) as u32).sum()
+    }
+}
+
+/// Renders the marquee rectangle as unpacked pixels, wrapping the text around after the gap.
+struct MarqueePixels<'m, 'a, F> {
+    marquee: &'m Marquee<'a, F>,
+    offset: u32,
+    /// The current row, and the column within it.
+    y: u8,
+    x: u16,
+    chars: core::str::Chars<'a>,
+    /// The current character and the column within it, or `None` within the gap.
+    current: Option<(char, u8)>,
+    /// The remaining width of the gap, when within it.
+    gap_left: u16,
+}
+
+impl<'m, 'a, F> MarqueePixels<'m, 'a, F>
+where
+    F: Font,
+{
+    fn new(marquee: &'m Marquee<'a, F>, offset: u32) -> Self {
+        let mut pixels = MarqueePixels {
+            marquee: marquee,
+            offset: offset,
+            y: 0,
+            x: 0,
+            chars: marquee.text.chars(),
+            current: None,
+            gap_left: 0,
+        };
+        pixels.start_row();
+        pixels
+    }
+
+    /// Position at `offset` within the text and gap, at the start of a row.
+    fn start_row(&mut self) {
+        self.chars = self.marquee.text.chars();
+        let mut skip = self.offset;
+        for c in self.chars.by_ref() {
+            let advance = self.marquee.font.advance(c) as u32;
+            if skip < advance {
+                self.current = Some((c, skip as u8));
+                return;
+            }
+            skip -= advance;
+        }
+        self.current = None;
+        // What is left of the offset is within the gap, so it fits its type.
+        self.gap_left = self.marquee.gap - skip as u16;
+        if self.gap_left == 0 {
+            self.restart_text();
+        }
+    }
+
+    /// Move to the next character with a nonzero width, entering the gap after the last one.
+    fn next_char(&mut self) {
+        let font = &self.marquee.font;
+        self.current = self
+            .chars
+            .by_ref()
+            .find(|&c| font.advance(c) != 0)
+            .map(|c| (c, 0));
+        if self.current.is_none() {
+            self.gap_left = self.marquee.gap;
+            if self.gap_left == 0 {
+                self.restart_text();
+            }
+        }
+    }
+
+    fn restart_text(&mut self) {
+        let font = &self.marquee.font;
+        self.chars = self.marquee.text.chars();
+        self.current = self
+            .chars
+            .by_ref()
+            .find(|&c| font.advance(c) != 0)
+            .map(|c| (c, 0));
+    }
+}
+
+impl<'m, 'a, F> Iterator for MarqueePixels<'m, 'a, F>
+where
+    F: Font,
+{
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        let m = self.marquee;
+        if self.y >= m.font.height() {
+            return None;
+        }
+        let level = match self.current {
+            Some((c, cx)) => {
+                let coverage = m.font.pixel(c, cx, self.y);
+                if cx + 1 < m.font.advance(c) {
+                    self.current = Some((c, cx + 1));
+                } else {
+                    self.next_char();
+                }
+                blend(coverage, m.fg, m.bg)
+            }
+            None => {
+                // Text with no visible width is all gap; don't count it down forever.
+                if self.gap_left > 0 {
+                    self.gap_left -= 1;
+                    if self.gap_left == 0 {
+                        self.restart_text();
+                    }
+                }
+                m.bg
+            }
+        };
+        self.x += 1;
+        if self.x >= m.width {
+            self.x = 0;
+            self.y += 1;
+            self.start_row();
+        }
+        Some(level)
+    }
+}
+
+impl<'a, F, DI> Widget<DI> for Marquee<'a, F>
+where
+    F: Font,
+    DI: interface::DisplayInterface,
+{
+    fn draw(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        Self::draw(self, display)
+    }
+
+    fn update(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        self.tick(display)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::MockInterface;
+    use crate::test_support::initialized_display;
+    use crate::text::tests::TEST_FONT;
+    use std::vec::Vec;
+
+    /// The first row of the marquee as rendered by the chip, '#' for lit pixels.
+    fn first_row(di: &MockInterface) -> Vec<u8> {
+        di.chip_model(Px(16, 16), Px(0, 0)).render()[..8]
+            .iter()
+            .map(|&p| if p > 0 { b'#' } else { b'.' })
+            .collect()
+    }
+
+    #[test]
+    fn scroll_and_wrap() {
+        let (di, mut disp) = initialized_display(Px(16, 16));
+        // "BAB" is 12 pixels wide in an 8 pixel rectangle, so it scrolls with a period of 14.
+        let mut marquee = Marquee::new(TEST_FONT, "BAB", Px(0, 0), 8);
+        marquee.set_gap(2);
+        marquee.set_step(3);
+        assert!(marquee.scrolls());
+        marquee.tick(&mut disp).unwrap();
+        assert_eq!(first_row(&di), b".#..####");
+        marquee.tick(&mut disp).unwrap();
+        assert_eq!(first_row(&di), b".####.#.");
+        for _ in 0..3 {
+            marquee.tick(&mut disp).unwrap();
+        }
+        // Offset 12 is the gap, followed by the start of the text again.
+        assert_eq!(first_row(&di), b"...#..##");
+    }
+
+    #[test]
+    fn fitting_text_is_static() {
+        let (di, mut disp) = initialized_display(Px(16, 16));
+        let mut marquee = Marquee::new(TEST_FONT, "A", Px(4, 0), 8);
+        assert!(!marquee.scrolls());
+        marquee.tick(&mut disp).unwrap();
+        let sent = di.sent().len();
+        marquee.tick(&mut disp).unwrap();
+        assert_eq!(di.sent().len(), sent);
+        assert_eq!(first_row(&di), b"....####");
+    }
+
+    #[test]
+    fn long_period() {
+        let (di, mut disp) = initialized_display(Px(16, 16));
+        // A period of 65547 pixels, beyond the range of the gap and step.
+        let mut marquee = Marquee::new(TEST_FONT, "BAB", Px(0, 0), 8);
+        marquee.set_gap(u16::MAX);
+        marquee.set_step(u16::MAX - 7);
+        for _ in 0..2 {
+            marquee.tick(&mut disp).unwrap();
+        }
+        assert_eq!(first_row(&di), b"........");
+        marquee.tick(&mut disp).unwrap();
+        // Offset 131056 wraps around to 65509, 38 pixels from the end of the gap.
+        assert_eq!(first_row(&di), b"........");
+        marquee.set_step(36);
+        marquee.tick(&mut disp).unwrap();
+        assert_eq!(first_row(&di), b"...#..##");
+    }
+}
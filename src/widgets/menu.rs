@@ -0,0 +1,515 @@
+//! A scrollable menu of items with a cursor, driven by the application's input handling.
+//!
+//! Moving the cursor and changing items only marks the rows which changed, and `Menu::update`
+//! redraws just those rows, so that navigating a long menu over a slow interface stays responsive.
+//! A typical input loop looks like:
+//!
+//! ```ignore
+//! match button {
+//!     Button::Up => menu.up(),
+//!     Button::Down => menu.down(),
+//!     Button::Enter => open(menu.select()),
+//! }
+//! menu.update(&mut display)?;
+//! ```
+
+use core::marker::PhantomData;
+
+use crate::command::CommandError;
+use crate::display::{Display, PixelCoord};
+use crate::interface;
+use crate::sprite::Sprite;
+use crate::text::{blend, Font, TextPixels};
+use crate::widgets::Widget;
+
+/// The most rows of items a menu shows at once, one per row of a 128 row display.
+const MAX_ROWS: u16 = 128;
+
+/// An item of a `Menu`: a line of text, with an optional icon to its left.
+#[derive(Clone, Copy, Debug)]
+pub struct MenuItem<'a> {
+    pub text: &'a str,
+    /// An icon whose pixels blend between the row's text and background levels, like the
+    /// coverage of a font, so that it inverts along with the text when the item is selected.
+    pub icon: Option<Sprite<'a>>,
+}
+
+impl<'a> MenuItem<'a> {
+    /// An item showing `text` without an icon.
+    pub fn new(text: &'a str) -> Self {
+        MenuItem {
+            text: text,
+            icon: None,
+        }
+    }
+
+    /// Show `icon` to the left of the text.
+    pub fn with_icon(mut self, icon: Sprite<'a>) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+}
+
+/// A list of items, one per row, of which as many as fit are shown in a rectangle. The item under
+/// the cursor is highlighted, and the list scrolls to keep it in view.
+///
+/// The items are kept in `I`, usually an array of `MenuItem`s, or a `Vec` of them where the menu is
+/// built at run time.
+///
+/// Each row is as tall as the font or the tallest icon, whichever is more. The icons are all drawn
+/// in a column as wide as the widest of them, followed by a blank pixel and then the text.
+pub struct Menu<'a, F, I> {
+    font: F,
+    items: I,
+    upper_left: PixelCoord,
+    width: u16,
+    height: u16,
+    row_height: u16,
+    icon_width: u16,
+    fg: u8,
+    bg: u8,
+    selected_fg: u8,
+    selected_bg: u8,
+    wrap: bool,
+    /// The index of the item under the cursor, and of the item shown in the top row.
+    cursor: usize,
+    top: usize,
+    /// A bit for each row on the display which must be redrawn, counted from the top.
+    dirty: u128,
+    drawn: bool,
+    item: PhantomData<MenuItem<'a>>,
+}
+
+impl<'a, F, I> Menu<'a, F, I>
+where
+    F: Font,
+    I: AsRef<[MenuItem<'a>]> + AsMut<[MenuItem<'a>]>,
+{
+    /// Create a menu of `items` in `font`, filling the rectangle starting at `upper_left`, `width`
+    /// by `height` pixels. As with `Display::region`, the horizontal coordinate and `width` must
+    /// be divisible by 4.
+    ///
+    /// The cursor starts on the first item and stops at either end of the list. Items are drawn in
+    /// gray level 15 on 0, and the selected one in 0 on 15; use the setters to change these.
+    pub fn new(font: F, items: I, upper_left: PixelCoord, width: u16, height: u16) -> Self {
+        let icons = items.as_ref().iter().filter_map(|item| item.icon);
+        let icon_width = icons
+            .clone()
+            .map(|icon| icon.width() + 1)
+            .max()
+            .unwrap_or(0);
+        let row_height = icons
+            .map(|icon| icon.height())
+            .max()
+            .unwrap_or(0)
+            .max(font.height() as u16)
+            .max(1);
+        Menu {
+            font: font,
+            items: items,
+            upper_left: upper_left,
+            width: width,
+            height: height,
+            row_height: row_height,
+            icon_width: icon_width,
+            fg: 15,
+            bg: 0,
+            selected_fg: 0,
+            selected_bg: 15,
+            wrap: false,
+            cursor: 0,
+            top: 0,
+            dirty: 0,
+            drawn: false,
+            item: PhantomData,
+        }
+    }
+
+    /// Set the gray levels of the text and background of items, and of the selected item. The
+    /// whole menu is redrawn on the next update.
+    pub fn set_colors(&mut self, fg: u8, bg: u8, selected_fg: u8, selected_bg: u8) {
+        self.fg = fg & 0x0F;
+        self.bg = bg & 0x0F;
+        self.selected_fg = selected_fg & 0x0F;
+        self.selected_bg = selected_bg & 0x0F;
+        self.drawn = false;
+    }
+
+    /// Set whether moving the cursor past either end of the list wraps around to the other end.
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+    }
+
+    /// Replace the text of item `index`. Its row is redrawn on the next update if it is shown and
+    /// the text changed.
+    ///
+    /// Panics if `index` is out of range.
+    pub fn set_text(&mut self, index: usize, text: &'a str) {
+        let item = &mut self.items.as_mut()[index];
+        if item.text != text {
+            item.text = text;
+            self.mark(index);
+        }
+    }
+
+    /// Replace the icon of item `index`. Its row is redrawn on the next update if it is shown. The
+    /// icon should be no larger than those the menu was created with, as the rows and icon column
+    /// keep their size; larger icons are clipped.
+    ///
+    /// Panics if `index` is out of range.
+    pub fn set_icon(&mut self, index: usize, icon: Option<Sprite<'a>>) {
+        self.items.as_mut()[index].icon = icon;
+        self.mark(index);
+    }
+
+    /// The items of the menu.
+    pub fn items(&self) -> &[MenuItem<'a>] {
+        self.items.as_ref()
+    }
+
+    /// Move the cursor to the previous item.
+    pub fn up(&mut self) {
+        match self.cursor {
+            0 if self.wrap => self.move_to(self.len().saturating_sub(1)),
+            0 => {}
+            cursor => self.move_to(cursor - 1),
+        }
+    }
+
+    /// Move the cursor to the next item.
+    pub fn down(&mut self) {
+        match self.cursor + 1 {
+            next if next < self.len() => self.move_to(next),
+            _ if self.wrap => self.move_to(0),
+            _ => {}
+        }
+    }
+
+    /// Move the cursor to item `index`, scrolling the menu to show it if needed. Indices past the
+    /// end of the list select the last item.
+    pub fn move_to(&mut self, index: usize) {
+        let index = index.min(self.len().saturating_sub(1));
+        if index == self.cursor {
+            return;
+        }
+        let rows = self.rows();
+        self.mark(self.cursor);
+        self.cursor = index;
+        if index < self.top {
+            self.top = index;
+            self.dirty = u128::MAX;
+        } else if index >= self.top + rows {
+            self.top = index + 1 - rows;
+            self.dirty = u128::MAX;
+        } else {
+            self.mark(index);
+        }
+    }
+
+    /// The index of the item under the cursor, for the application to act on, or `None` if the
+    /// menu is empty.
+    pub fn select(&self) -> Option<usize> {
+        (self.len() > 0).then_some(self.cursor)
+    }
+
+    /// Redraw the rows which changed since the last update, or the whole menu if it has not been
+    /// drawn yet.
+    pub fn update<DI>(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        if !self.drawn {
+            return self.draw(display);
+        }
+        for row in 0..self.rows() {
+            if self.dirty & (1 << row) != 0 {
+                self.draw_row(display, row)?;
+                self.dirty &= !(1 << row);
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw the whole menu, e.g. after the display was cleared.
+    pub fn draw<DI>(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        for row in 0..self.rows() {
+            self.draw_row(display, row)?;
+        }
+        // Blank the space left below the last whole row.
+        let used = (self.rows() as u16 * self.row_height) as i16;
+        if (used as u16) < self.height {
+            let PixelCoord(left, top) = self.upper_left;
+            let lower_right = PixelCoord(left + self.width as i16, top + self.height as i16);
+            let blank = (self.width as usize) * (self.height as usize - used as usize);
+            display
+                .region(PixelCoord(left, top + used), lower_right)?
+                .draw(core::iter::repeat_n(self.bg, blank))
+                .map_err(CommandError::InterfaceError)?;
+        }
+        self.dirty = 0;
+        self.drawn = true;
+        Ok(())
+    }
+
+    /// The number of items.
+    fn len(&self) -> usize {
+        self.items.as_ref().len()
+    }
+
+    /// The number of rows shown.
+    fn rows(&self) -> usize {
+        (self.height / self.row_height).min(MAX_ROWS) as usize
+    }
+
+    /// Mark the row showing item `index` for redrawing, if it is shown.
+    fn mark(&mut self, index: usize) {
+        if let Some(row) = index.checked_sub(self.top).filter(|&row| row < self.rows()) {
+            self.dirty |= 1 << row;
+        }
+    }
+
+    /// Draw row `row` of the rectangle, with the item it shows if any.
+    fn draw_row<DI>(
+        &self,
+        display: &mut Display<DI>,
+        row: usize,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        let index = self.top + row;
+        let (fg, bg) = if index == self.cursor {
+            (self.selected_fg, self.selected_bg)
+        } else {
+            (self.fg, self.bg)
+        };
+        let item = self
+            .items
+            .as_ref()
+            .get(index)
+            .copied()
+            .unwrap_or(MenuItem::new(""));
+        let text_width = self.width.saturating_sub(self.icon_width);
+        let pixels = RowPixels {
+            icon: item.icon,
+            icon_width: self.icon_width,
+            icon_top: item
+                .icon
+                .map_or(0, |icon| (self.row_height - icon.height()) / 2),
+            text: TextPixels::new(&self.font, item.text, text_width, fg, bg),
+            text_top: (self.row_height - self.font.height() as u16) / 2,
+            text_bottom: (self.row_height + self.font.height() as u16) / 2,
+            width: self.width,
+            height: self.row_height,
+            fg: fg,
+            bg: bg,
+            x: 0,
+            y: 0,
+        };
+        let PixelCoord(left, top) = self.upper_left;
+        let top = top + (row as u16 * self.row_height) as i16;
+        display
+            .region(
+                PixelCoord(left, top),
+                PixelCoord(left + self.width as i16, top + self.row_height as i16),
+            )?
+            .draw(pixels)
+            .map_err(CommandError::InterfaceError)
+    }
+}
+
+/// Renders one row of a menu as unpacked pixels: the icon column, then the text centered
+/// vertically in the row.
+struct RowPixels<'a, F> {
+    icon: Option<Sprite<'a>>,
+    icon_width: u16,
+    icon_top: u16,
+    text: TextPixels<'a, F>,
+    /// The rows of the row the text covers, from `text_top` up to `text_bottom`.
+    text_top: u16,
+    text_bottom: u16,
+    width: u16,
+    height: u16,
+    fg: u8,
+    bg: u8,
+    /// The current row, and the column within it.
+    x: u16,
+    y: u16,
+}
+
+impl<'a, F> Iterator for RowPixels<'a, F>
+where
+    F: Font,
+{
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.y >= self.height {
+            return None;
+        }
+        let level = if self.x < self.icon_width {
+            let y = self.y as i16 - self.icon_top as i16;
+            self.icon
+                .and_then(|icon| icon.pixel(self.x as i16, y))
+                .map_or(self.bg, |coverage| blend(coverage, self.fg, self.bg))
+        } else if self.y >= self.text_top && self.y < self.text_bottom {
+            self.text.next().unwrap_or(self.bg)
+        } else {
+            self.bg
+        };
+        self.x += 1;
+        if self.x >= self.width {
+            self.x = 0;
+            self.y += 1;
+        }
+        Some(level)
+    }
+}
+
+impl<'a, F, I, DI> Widget<DI> for Menu<'a, F, I>
+where
+    F: Font,
+    I: AsRef<[MenuItem<'a>]> + AsMut<[MenuItem<'a>]>,
+    DI: interface::DisplayInterface,
+{
+    fn draw(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        Self::draw(self, display)
+    }
+
+    fn update(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        Self::update(self, display)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+    use crate::test_support::{initialized_display, screen, widget_fixture};
+    use crate::text::tests::TEST_FONT;
+    use std::vec::Vec;
+
+    /// The rows of the display written since `before`, as ranges of the row address command.
+    fn rows_written(di: &MockInterface, before: usize) -> Vec<Vec<u8>> {
+        let sent = di.sent();
+        sent[before..]
+            .windows(2)
+            .filter_map(|pair| match pair {
+                [Sent::Cmd(0x75), Sent::Data(rows)] => Some(rows.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// A menu of three rows, each 3 pixels tall, showing the text of four items.
+    fn setup() -> (
+        MockInterface,
+        Display<MockInterface>,
+        Menu<'static, crate::text::MonoFont<'static>, [MenuItem<'static>; 4]>,
+    ) {
+        let items = [
+            MenuItem::new("A"),
+            MenuItem::new("B"),
+            MenuItem::new("AB"),
+            MenuItem::new("BA"),
+        ];
+        let mut menu = Menu::new(TEST_FONT, items, Px(0, 0), 8, 10);
+        menu.set_colors(15, 0, 1, 8);
+        widget_fixture(Px(16, 16), menu)
+    }
+
+    #[test]
+    fn scrolling() {
+        let (di, mut disp, mut menu) = setup();
+        menu.update(&mut disp).unwrap();
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di, 8, 8)[..], &b"\
+            11118888\
+            18818888\
+            11118888\
+            0F000000\
+            0F000000\
+            0F000000\
+            FFFF0F00\
+            F00F0F00\
+        "[..]);
+        menu.down();
+        menu.down();
+        menu.down();
+        menu.down();
+        assert_eq!(menu.select(), Some(3));
+        menu.update(&mut disp).unwrap();
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di, 8, 8)[..], &b"\
+            0F000000\
+            0F000000\
+            0F000000\
+            FFFF0F00\
+            F00F0F00\
+            FFFF0F00\
+            81881111\
+            81881881\
+        "[..]);
+    }
+
+    #[test]
+    fn partial_redraw() {
+        let (mut di, mut disp, mut menu) = setup();
+        menu.update(&mut disp).unwrap();
+        // Moving within the rows shown redraws the rows of the old and new items.
+        di.clear();
+        menu.down();
+        menu.update(&mut disp).unwrap();
+        assert_eq!(rows_written(&di, 0), [vec![0, 2], vec![3, 5]]);
+        // Changing the text of an item redraws only its row, and only if the text changed.
+        di.clear();
+        menu.set_text(2, "AB");
+        menu.set_text(0, "BB");
+        menu.update(&mut disp).unwrap();
+        assert_eq!(rows_written(&di, 0), [vec![0, 2]]);
+        // Items scrolled out of view are not drawn.
+        di.clear();
+        menu.set_text(3, "AA");
+        menu.update(&mut disp).unwrap();
+        assert!(di.sent().is_empty());
+        // Wrapping around to the end scrolls, redrawing every row.
+        menu.set_wrap(true);
+        menu.up();
+        menu.up();
+        assert_eq!(menu.select(), Some(3));
+        menu.update(&mut disp).unwrap();
+        // The top row was the last drawn, so its window is not set up again.
+        assert_eq!(rows_written(&di, 0), [vec![3, 5], vec![6, 8]]);
+        assert_eq!(
+            di.sent().iter().filter(|s| **s == Sent::Cmd(0x15)).count(),
+            2
+        );
+    }
+
+    #[test]
+    fn icons() {
+        let (di, mut disp) = initialized_display(Px(16, 16));
+        let dot = Sprite::new(2, 5, &[0x00, 0x00, 0xF0, 0x00, 0x00]);
+        let items = [MenuItem::new("B").with_icon(dot), MenuItem::new("A")];
+        let mut menu = Menu::new(TEST_FONT, items, Px(0, 0), 8, 16);
+        assert_eq!(menu.items()[0].icon.map(|icon| icon.width()), Some(2));
+        menu.set_colors(15, 0, 15, 0);
+        menu.draw(&mut disp).unwrap();
+        // Rows are 5 pixels tall to fit the icon, with the text centered in them.
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di, 8, 8)[..], &b"\
+            00000000\
+            0000F000\
+            F000F000\
+            0000F000\
+            00000000\
+            00000000\
+            000FFFF0\
+            000F00F0\
+        "[..]);
+    }
+}
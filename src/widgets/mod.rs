@@ -0,0 +1,51 @@
+//! Small stateful widgets which draw themselves onto a rectangle of the display. Widgets do not own
+//! the display, so several can share one; each drawing method takes the `Display` to draw on, and
+//! only ever writes inside the widget's own rectangle.
+//!
+//! Widgets which hold all they need to draw themselves implement the `Widget` trait, so that
+//! different kinds can be drawn and updated alike. With the `alloc` feature, a `WidgetSet` keeps
+//! any number of them as trait objects, for hosts where the screen layout is built at run time.
+
+use crate::command::CommandError;
+use crate::display::Display;
+use crate::interface;
+
+pub mod gauge;
+pub mod marquee;
+pub mod menu;
+pub mod progress_bar;
+#[cfg(feature = "qr")]
+pub mod qr;
+pub mod scope;
+#[cfg(feature = "alloc")]
+pub mod set;
+pub mod seven_segment;
+pub mod strip_chart;
+pub mod vu_meter;
+
+pub use self::gauge::Gauge;
+pub use self::marquee::Marquee;
+pub use self::menu::{Menu, MenuItem};
+pub use self::progress_bar::ProgressBar;
+#[cfg(feature = "qr")]
+pub use self::qr::{QrModules, QrView};
+pub use self::scope::Scope;
+#[cfg(feature = "alloc")]
+pub use self::set::WidgetSet;
+pub use self::seven_segment::SevenSegment;
+pub use self::strip_chart::{StripChart, StripMode};
+pub use self::vu_meter::VuMeter;
+
+/// A widget which can be drawn on `Display<DI>` without further input.
+pub trait Widget<DI>
+where
+    DI: interface::DisplayInterface,
+{
+    /// Draw the whole widget, e.g. after the display was cleared.
+    fn draw(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>>;
+
+    /// Bring the widget on the display up to date: draw it if it has not been drawn since it was
+    /// created or its colors changed, and redraw any part which changed since, such as the rows of
+    /// a `Menu` or the scroll position of a `Marquee`.
+    fn update(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>>;
+}
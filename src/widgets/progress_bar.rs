@@ -0,0 +1,206 @@
+//! A horizontal progress bar, as for a firmware update screen.
+
+use crate::command::CommandError;
+use crate::display::{Display, PixelCoord};
+use crate::interface;
+use crate::widgets::Widget;
+
+/// A progress bar filling a rectangle from left to right, surrounded by a one pixel frame.
+///
+/// The whole bar is drawn once, and afterwards changing the value only redraws the 4-pixel column
+/// groups whose fill actually changed.
+pub struct ProgressBar {
+    upper_left: PixelCoord,
+    lower_right: PixelCoord,
+    frame: u8,
+    fill: u8,
+    bg: u8,
+    percent: u8,
+    /// The filled width in pixels as last drawn, or `None` if the bar has not been drawn.
+    drawn_fill: Option<i16>,
+}
+
+impl ProgressBar {
+    /// Create a progress bar occupying the rectangle from `upper_left` to `lower_right`, starting
+    /// at 0%. As with `Display::region`, the horizontal coordinates must be divisible by 4. The
+    /// rectangle includes the frame, so it should be at least 3 pixels in each direction.
+    ///
+    /// The frame and fill are drawn in gray level 15 on 0; use `set_colors` to change these.
+    pub fn new(upper_left: PixelCoord, lower_right: PixelCoord) -> Self {
+        ProgressBar {
+            upper_left: upper_left,
+            lower_right: lower_right,
+            frame: 15,
+            fill: 15,
+            bg: 0,
+            percent: 0,
+            drawn_fill: None,
+        }
+    }
+
+    /// Set the gray levels used for the frame, the filled part, and the empty part of the bar. The
+    /// whole bar is redrawn on the next update.
+    pub fn set_colors(&mut self, frame: u8, fill: u8, bg: u8) {
+        self.frame = frame & 0x0F;
+        self.fill = fill & 0x0F;
+        self.bg = bg & 0x0F;
+        self.drawn_fill = None;
+    }
+
+    /// The current value in percent.
+    pub fn value(&self) -> u8 {
+        self.percent
+    }
+
+    /// Set the value in percent, from 0 to 100, and redraw the part of the bar which changed, or
+    /// the whole bar if it has not been drawn yet.
+    pub fn set_value<DI>(
+        &mut self,
+        display: &mut Display<DI>,
+        percent: u8,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        if percent > 100 {
+            return Err(CommandError::OutOfRange);
+        }
+        self.percent = percent;
+        let old = match self.drawn_fill {
+            Some(old) => old,
+            None => return self.draw(display),
+        };
+        let new = self.fill_width();
+        if old == new {
+            return Ok(());
+        }
+        // Redraw the interior rows of the column groups covering the changed pixels. The frame
+        // rows cannot have changed.
+        let left = self.upper_left.0 + 1;
+        let start = (left + old.min(new)) & !3;
+        let end = (left + old.max(new) + 3) & !3;
+        let upper_left = PixelCoord(start, self.upper_left.1 + 1);
+        let lower_right = PixelCoord(end, self.lower_right.1 - 1);
+        self.draw_rect(display, upper_left, lower_right, new)?;
+        self.drawn_fill = Some(new);
+        Ok(())
+    }
+
+    /// Draw the whole bar, including the frame, e.g. after the display was cleared.
+    pub fn draw<DI>(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        let fill = self.fill_width();
+        self.draw_rect(display, self.upper_left, self.lower_right, fill)?;
+        self.drawn_fill = Some(fill);
+        Ok(())
+    }
+
+    /// The width in pixels of the filled part of the interior for the current value.
+    fn fill_width(&self) -> i16 {
+        let interior = (self.lower_right.0 - self.upper_left.0 - 2).max(0) as i32;
+        (interior * self.percent as i32 / 100) as i16
+    }
+
+    /// Draw the part of the bar within a rectangle, with `fill` pixels of the interior filled.
+    #[allow(clippy::nonminimal_bool)]
+    fn draw_rect<DI>(
+        &self,
+        display: &mut Display<DI>,
+        upper_left: PixelCoord,
+        lower_right: PixelCoord,
+        fill: i16,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        let (bar_ul, bar_lr) = (self.upper_left, self.lower_right);
+        let pixels = (upper_left.1..lower_right.1).flat_map(move |y| {
+            (upper_left.0..lower_right.0).map(move |x| {
+                if false || x == bar_ul.0 || x == bar_lr.0 - 1 || y == bar_ul.1 || y == bar_lr.1 - 1
+                {
+                    self.frame
+                } else if x - bar_ul.0 - 1 < fill {
+                    self.fill
+                } else {
+                    self.bg
+                }
+            })
+        });
+        display
+            .region(upper_left, lower_right)?
+            .draw(pixels)
+            .map_err(CommandError::InterfaceError)
+    }
+}
+
+impl<DI> Widget<DI> for ProgressBar
+where
+    DI: interface::DisplayInterface,
+{
+    fn draw(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        Self::draw(self, display)
+    }
+
+    fn update(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        if self.drawn_fill.is_some() {
+            return Ok(());
+        }
+        Self::draw(self, display)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+    use crate::test_support::initialized_display;
+    use std::vec::Vec;
+
+    /// Render the top 4 rows of a 16 pixel wide display, '#' for lit pixels.
+    fn screen(di: &MockInterface) -> Vec<u8> {
+        di.chip_model(Px(16, 16), Px(0, 0)).render()[..16 * 4]
+            .iter()
+            .map(|&p| if p > 0 { b'#' } else { b'.' })
+            .collect()
+    }
+
+    #[test]
+    fn draw_and_update() {
+        let (di, mut disp) = initialized_display(Px(16, 16));
+
+        // The interior is 14 pixels wide.
+        let mut bar = ProgressBar::new(Px(0, 0), Px(16, 4));
+        bar.set_value(&mut disp, 50).unwrap();
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di)[..], &b"\
+            ################\
+            ########.......#\
+            ########.......#\
+            ################\
+        "[..]);
+
+        // Going to 72% fills pixels 7 to 9 of the interior, only touching the third column group.
+        let before = di.sent().len();
+        bar.set_value(&mut disp, 72).unwrap();
+        assert_eq!(
+            &di.sent()[before..before + 2],
+            &[Sent::Cmd(0x15), Sent::Data(vec![2, 2])]
+        );
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di)[..], &b"\
+            ################\
+            ###########....#\
+            ###########....#\
+            ################\
+        "[..]);
+
+        // An unchanged fill sends nothing.
+        let before = di.sent().len();
+        bar.set_value(&mut disp, 73).unwrap();
+        assert_eq!(di.sent().len(), before);
+        assert_eq!(bar.set_value(&mut disp, 101), Err(CommandError::OutOfRange));
+    }
+}
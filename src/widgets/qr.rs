@@ -0,0 +1,191 @@
+//! Display of QR codes, as for provisioning and pairing flows which show a URL or key for a phone
+//! to scan.
+//!
+//! This module does not encode QR codes itself. Any encoder can supply the modules of a symbol
+//! through the `QrModules` trait, which is implemented for `qrcodegen::QrCode` with the
+//! `qrcodegen` feature enabled.
+
+use crate::command::CommandError;
+use crate::display::{Display, Rect};
+use crate::interface;
+
+/// The width of the quiet zone around a symbol which the QR code standard requires, in modules.
+const QUIET_ZONE: u16 = 4;
+
+/// The modules of a QR code symbol, as produced by an encoder.
+pub trait QrModules {
+    /// The number of modules along each side of the symbol, not counting the quiet zone.
+    fn size(&self) -> u16;
+    /// Whether the module in column `x` and row `y` of the symbol is dark.
+    fn is_dark(&self, x: u16, y: u16) -> bool;
+}
+
+#[cfg(feature = "qrcodegen")]
+impl QrModules for qrcodegen::QrCode {
+    fn size(&self) -> u16 {
+        qrcodegen::QrCode::size(self) as u16
+    }
+
+    fn is_dark(&self, x: u16, y: u16) -> bool {
+        self.get_module(x as i32, y as i32)
+    }
+}
+
+/// A rectangle showing a QR code symbol, centered and scaled up by the largest whole number of
+/// pixels per module which fits the symbol and its quiet zone in the rectangle. Scaling by whole
+/// pixels keeps every module the same size, which scanners need.
+pub struct QrView {
+    rect: Rect,
+    quiet_zone: u16,
+    dark: u8,
+    light: u8,
+}
+
+impl QrView {
+    /// Create a view filling `rect`. As with `Display::region`, the horizontal coordinates of its
+    /// corners must be divisible by 4.
+    ///
+    /// Dark modules are drawn in gray level 0 on 15, as scanners expect dark modules on a light
+    /// background, with a quiet zone of 4 modules; use `set_colors` and `set_quiet_zone` to change
+    /// these.
+    pub fn new(rect: Rect) -> Self {
+        QrView {
+            rect: rect,
+            quiet_zone: QUIET_ZONE,
+            dark: 0,
+            light: 15,
+        }
+    }
+
+    /// Set the gray levels of dark modules and of light ones, which also fill the quiet zone and
+    /// the rest of the rectangle.
+    pub fn set_colors(&mut self, dark: u8, light: u8) {
+        self.dark = dark & 0x0F;
+        self.light = light & 0x0F;
+    }
+
+    /// Set the width of the quiet zone of light modules around the symbol. Many scanners cope with
+    /// less than the 4 modules the standard requires, allowing a larger symbol on a small display.
+    pub fn set_quiet_zone(&mut self, modules: u16) {
+        self.quiet_zone = modules;
+    }
+
+    /// The number of pixels per module `code` would be drawn with, or `None` if it does not fit
+    /// even at one pixel per module.
+    pub fn module_size<Q: QrModules>(&self, code: &Q) -> Option<u16> {
+        let modules = code.size() as u32 + 2 * self.quiet_zone as u32;
+        let room = self.rect.width().min(self.rect.height()).max(0) as u32;
+        match room / modules.max(1) {
+            0 => None,
+            scale => Some(scale as u16),
+        }
+    }
+
+    /// Draw `code` on `display`, returning the number of pixels per module. Gives
+    /// `CommandError::OutOfRange` without drawing anything if the symbol does not fit.
+    pub fn draw<DI, Q>(
+        &self,
+        display: &mut Display<DI>,
+        code: &Q,
+    ) -> Result<u16, CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+        Q: QrModules,
+    {
+        let scale = self.module_size(code).ok_or(CommandError::OutOfRange)?;
+        let size = code.size();
+        let (width, height) = (self.rect.width() as u16, self.rect.height() as u16);
+        // The offsets of the symbol itself, inside the quiet zone, from the corner of the rect.
+        let drawn = (size + 2 * self.quiet_zone) * scale;
+        let left = (width - drawn) / 2 + self.quiet_zone * scale;
+        let top = (height - drawn) / 2 + self.quiet_zone * scale;
+        let module = move |offset: u16, start: u16| match offset.checked_sub(start) {
+            Some(pos) if pos / scale < size => Some(pos / scale),
+            _ => None,
+        };
+        let pixels = (0..height).flat_map(move |row| {
+            (0..width).map(move |col| match (module(col, left), module(row, top)) {
+                (Some(x), Some(y)) if code.is_dark(x, y) => self.dark,
+                _ => self.light,
+            })
+        });
+        display
+            .region_rect(self.rect)?
+            .draw(pixels)
+            .map_err(CommandError::InterfaceError)?;
+        Ok(scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::test_support::{initialized_display, screen};
+
+    /// A 3x3 symbol with dark corners and center.
+    struct Cross;
+
+    impl QrModules for Cross {
+        fn size(&self) -> u16 {
+            3
+        }
+
+        fn is_dark(&self, x: u16, y: u16) -> bool {
+            (x + y).is_multiple_of(2)
+        }
+    }
+
+    #[test]
+    fn centered_and_scaled() {
+        let (di, mut disp) = initialized_display(Px(16, 16));
+        let mut view = QrView::new(Rect::new(Px(0, 0), Px(16, 16)));
+        view.set_quiet_zone(2);
+        view.set_colors(1, 14);
+        // 7 modules with the quiet zone, so 2 pixels each with 1 pixel left over around them.
+        assert_eq!(view.draw(&mut disp, &Cross), Ok(2));
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di, 16, 16)[..], &b"\
+            EEEEEEEEEEEEEEEE\
+            EEEEEEEEEEEEEEEE\
+            EEEEEEEEEEEEEEEE\
+            EEEEEEEEEEEEEEEE\
+            EEEEEEEEEEEEEEEE\
+            EEEEE11EE11EEEEE\
+            EEEEE11EE11EEEEE\
+            EEEEEEE11EEEEEEE\
+            EEEEEEE11EEEEEEE\
+            EEEEE11EE11EEEEE\
+            EEEEE11EE11EEEEE\
+            EEEEEEEEEEEEEEEE\
+            EEEEEEEEEEEEEEEE\
+            EEEEEEEEEEEEEEEE\
+            EEEEEEEEEEEEEEEE\
+            EEEEEEEEEEEEEEEE\
+        "[..]);
+    }
+
+    #[test]
+    fn too_small() {
+        let (di, mut disp) = initialized_display(Px(16, 16));
+        let view = QrView::new(Rect::new(Px(0, 0), Px(16, 8)));
+        // 11 modules with the standard quiet zone, in 8 rows.
+        assert_eq!(view.module_size(&Cross), None);
+        let before = di.sent().len();
+        assert_eq!(view.draw(&mut disp, &Cross), Err(CommandError::OutOfRange));
+        assert_eq!(di.sent().len(), before);
+    }
+
+    #[cfg(feature = "qrcodegen")]
+    #[test]
+    fn qrcodegen_symbol() {
+        use qrcodegen::{QrCode, QrCodeEcc};
+        let code = QrCode::encode_text("hello", QrCodeEcc::Low).unwrap();
+        // A version 1 symbol of 21 modules, 29 with the quiet zone.
+        let view = QrView::new(Rect::new(Px(0, 0), Px(256, 64)));
+        assert_eq!(view.module_size(&code), Some(2));
+        // The top left corner of the finder pattern is dark, and the next module in is light.
+        assert!(code.is_dark(0, 0));
+        assert!(!code.is_dark(1, 1));
+    }
+}
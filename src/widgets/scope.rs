@@ -0,0 +1,247 @@
+//! An oscilloscope-style waveform display with persistence, as for audio or test equipment front
+//! panels.
+
+use crate::command::CommandError;
+use crate::display::{Display, PixelCoord};
+use crate::interface;
+use crate::text::blend;
+use crate::widgets::Widget;
+
+/// A waveform display showing the last `TRAILS` frames of `WIDTH` samples each, one sample per
+/// pixel column, in a rectangle `WIDTH` pixels wide. The newest frame is drawn brightest and older
+/// ones fade towards the background, like the afterglow of a CRT.
+///
+/// Samples range from 0 at the bottom of the display to 255 at the top, and consecutive samples are
+/// joined by vertical lines so that each trace is unbroken. Each frame, only the rows of each
+/// column group crossed by a trace, old or new, are redrawn.
+pub struct Scope<const WIDTH: usize, const TRAILS: usize> {
+    upper_left: PixelCoord,
+    height: u16,
+    fg: u8,
+    bg: u8,
+    /// A ring buffer of the row of each sample in the last `frames` frames, newest at `newest`.
+    rows: [[u8; WIDTH]; TRAILS],
+    newest: usize,
+    frames: usize,
+    drawn: bool,
+}
+
+impl<const WIDTH: usize, const TRAILS: usize> Scope<WIDTH, TRAILS> {
+    /// Create a display with its upper left corner at `upper_left`, `height` pixels tall, up to
+    /// 128. As with `Display::region`, the horizontal coordinate and `WIDTH` must be divisible by 4.
+    ///
+    /// Traces are drawn in gray level 15 on 0; use `set_colors` to change these.
+    pub fn new(upper_left: PixelCoord, height: u16) -> Self {
+        Scope {
+            upper_left: upper_left,
+            height: height,
+            fg: 15,
+            bg: 0,
+            rows: [[0; WIDTH]; TRAILS],
+            newest: 0,
+            frames: 0,
+            drawn: false,
+        }
+    }
+
+    /// Set the gray levels of the newest trace and of the background. The whole display is redrawn
+    /// on the next update.
+    pub fn set_colors(&mut self, fg: u8, bg: u8) {
+        self.fg = fg & 0x0F;
+        self.bg = bg & 0x0F;
+        self.drawn = false;
+    }
+
+    /// Forget all traces. The whole display is redrawn on the next update.
+    pub fn clear(&mut self) {
+        self.frames = 0;
+        self.drawn = false;
+    }
+
+    /// Show a new frame of samples, fading the previous traces and dropping the oldest. Only the
+    /// rows crossed by a trace in each column group are redrawn, or the whole display if it has not
+    /// been drawn yet.
+    pub fn update<DI>(
+        &mut self,
+        display: &mut Display<DI>,
+        samples: &[u8; WIDTH],
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        if TRAILS == 0 {
+            return Ok(());
+        }
+        let span = self.height.saturating_sub(1) as u32;
+        let mut new_rows = [0u8; WIDTH];
+        for (row, &sample) in new_rows.iter_mut().zip(samples.iter()) {
+            *row = ((255 - sample as u32) * span / 255) as u8;
+        }
+        // The rows each column group needs redrawn are those crossed by any trace shown before
+        // or after this frame, including the one about to be dropped.
+        let mut bounds = [(u8::MAX, 0u8); WIDTH];
+        for frame in self
+            .ages()
+            .map(|age| &self.rows[age])
+            .chain(Some(&new_rows))
+        {
+            for col in 0..WIDTH {
+                let (low, high) = Self::span(frame, col);
+                let group = &mut bounds[col / 4];
+                *group = (group.0.min(low), group.1.max(high));
+            }
+        }
+        self.newest = (self.newest + 1) % TRAILS;
+        self.rows[self.newest] = new_rows;
+        self.frames = (self.frames + 1).min(TRAILS);
+        if !self.drawn {
+            return self.draw(display);
+        }
+        for (group, &(low, high)) in bounds.iter().take(WIDTH.div_ceil(4)).enumerate() {
+            if low <= high {
+                self.draw_group(display, group, low as u16, high as u16 + 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw the whole display, e.g. after the display was cleared.
+    pub fn draw<DI>(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        for group in 0..WIDTH.div_ceil(4) {
+            self.draw_group(display, group, 0, self.height)?;
+        }
+        self.drawn = true;
+        Ok(())
+    }
+
+    /// The indices into `rows` of the frames shown, newest first.
+    fn ages(&self) -> impl Iterator<Item = usize> {
+        let (newest, frames) = (self.newest, self.frames);
+        (0..frames).map(move |age| (newest + TRAILS - age) % TRAILS)
+    }
+
+    /// The range of rows, inclusive, crossed by a trace in column `col`, joining its sample to the
+    /// one before.
+    fn span(rows: &[u8; WIDTH], col: usize) -> (u8, u8) {
+        let row = rows[col];
+        let prev = if col > 0 { rows[col - 1] } else { row };
+        (row.min(prev), row.max(prev))
+    }
+
+    /// The gray level of the pixel at `row` in column `col`.
+    fn pixel(&self, col: usize, row: u16) -> u8 {
+        for (age, index) in self.ages().enumerate() {
+            let (low, high) = Self::span(&self.rows[index], col);
+            if row >= low as u16 && row <= high as u16 {
+                // Newer traces are brighter, so the first one found sets the level.
+                let coverage = (15 * (TRAILS - age) / TRAILS) as u8;
+                return blend(coverage, self.fg, self.bg);
+            }
+        }
+        self.bg
+    }
+
+    /// Draw rows `low` up to `high` of column group `group`.
+    fn draw_group<DI>(
+        &self,
+        display: &mut Display<DI>,
+        group: usize,
+        low: u16,
+        high: u16,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        let cols = group * 4..(group * 4 + 4).min(WIDTH);
+        let pixels =
+            (low..high).flat_map(move |row| cols.clone().map(move |col| self.pixel(col, row)));
+        let left = self.upper_left.0 + (group * 4) as i16;
+        display
+            .region(
+                PixelCoord(left, self.upper_left.1 + low as i16),
+                PixelCoord(left + 4, self.upper_left.1 + high as i16),
+            )?
+            .draw(pixels)
+            .map_err(CommandError::InterfaceError)
+    }
+}
+
+impl<const WIDTH: usize, const TRAILS: usize, DI> Widget<DI> for Scope<WIDTH, TRAILS>
+where
+    DI: interface::DisplayInterface,
+{
+    fn draw(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        Self::draw(self, display)
+    }
+
+    fn update(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        if self.drawn {
+            return Ok(());
+        }
+        Self::draw(self, display)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+    use crate::test_support::{screen, widget_fixture};
+
+    fn setup() -> (MockInterface, Display<MockInterface>, Scope<8, 3>) {
+        widget_fixture(Px(16, 16), Scope::new(Px(0, 0), 4))
+    }
+
+    #[test]
+    fn persistence() {
+        let (di, mut disp, mut scope) = setup();
+        scope.update(&mut disp, &[0; 8]).unwrap();
+        scope.update(&mut disp, &[128; 8]).unwrap();
+        scope
+            .update(&mut disp, &[255, 255, 255, 255, 0, 0, 0, 0])
+            .unwrap();
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di, 4, 8)[..], &b"\
+            FFFFF000\
+            AAAAFAAA\
+            0000F000\
+            5555FFFF\
+        "[..]);
+        // The oldest trace drops out after `TRAILS` frames.
+        scope.update(&mut disp, &[255; 8]).unwrap();
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di, 4, 8)[..], &b"\
+            FFFFFFFF\
+            5555A555\
+            0000A000\
+            0000AAAA\
+        "[..]);
+    }
+
+    #[test]
+    fn minimal_updates() {
+        let (mut di, mut disp, mut scope) = setup();
+        scope.update(&mut disp, &[0; 8]).unwrap();
+        // A flat trace along the bottom only touches the bottom row of each column group.
+        di.clear();
+        scope.update(&mut disp, &[0; 8]).unwrap();
+        di.check_multi(&[
+            Sent::Cmd(0x15),
+            Sent::Data(vec![0, 0]),
+            Sent::Cmd(0x75),
+            Sent::Data(vec![3, 3]),
+            Sent::Cmd(0x5C),
+            Sent::Data(vec![0xFF, 0xFF]),
+            Sent::Cmd(0x15),
+            Sent::Data(vec![1, 1]),
+            Sent::Cmd(0x75),
+            Sent::Data(vec![3, 3]),
+            Sent::Cmd(0x5C),
+            Sent::Data(vec![0xFF, 0xFF]),
+        ]);
+    }
+}
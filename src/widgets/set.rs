@@ -0,0 +1,165 @@
+//! A heap allocated collection of widgets of any kind, for hosts with an allocator where the screen
+//! layout is only known at run time.
+
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use crate::command::CommandError;
+use crate::display::Display;
+use crate::interface;
+use crate::widgets::Widget;
+
+/// Widgets kept as trait objects, which are drawn and updated together, in the order they were
+/// added.
+///
+/// A widget is moved into the set, so to keep changing its value, e.g. with
+/// `ProgressBar::set_value`, add it as an `Rc<RefCell<_>>` and keep a clone of the `Rc`.
+pub struct WidgetSet<'w, DI> {
+    widgets: Vec<Box<dyn Widget<DI> + 'w>>,
+}
+
+impl<'w, DI> WidgetSet<'w, DI>
+where
+    DI: interface::DisplayInterface,
+{
+    /// Create an empty set.
+    pub fn new() -> Self {
+        WidgetSet {
+            widgets: Vec::new(),
+        }
+    }
+
+    /// Add `widget` to the set, returning its index.
+    pub fn push<W>(&mut self, widget: W) -> usize
+    where
+        W: Widget<DI> + 'w,
+    {
+        self.widgets.push(Box::new(widget));
+        self.widgets.len() - 1
+    }
+
+    /// Remove the widget at `index` from the set, moving those after it down by one. Nothing is
+    /// drawn, so the widget stays on the display until it is drawn over.
+    ///
+    /// Panics if `index` is out of range.
+    pub fn remove(&mut self, index: usize) -> Box<dyn Widget<DI> + 'w> {
+        self.widgets.remove(index)
+    }
+
+    /// The widget at `index`, if any.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut (dyn Widget<DI> + 'w)> {
+        self.widgets.get_mut(index).map(|widget| &mut **widget)
+    }
+
+    /// The number of widgets in the set.
+    pub fn len(&self) -> usize {
+        self.widgets.len()
+    }
+
+    /// Whether the set has no widgets.
+    pub fn is_empty(&self) -> bool {
+        self.widgets.is_empty()
+    }
+}
+
+impl<'w, DI> Default for WidgetSet<'w, DI>
+where
+    DI: interface::DisplayInterface,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'w, DI> Widget<DI> for WidgetSet<'w, DI>
+where
+    DI: interface::DisplayInterface,
+{
+    /// Draw every widget in the set.
+    fn draw(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        self.widgets
+            .iter_mut()
+            .try_for_each(|widget| widget.draw(display))
+    }
+
+    /// Update every widget in the set.
+    fn update(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        self.widgets
+            .iter_mut()
+            .try_for_each(|widget| widget.update(display))
+    }
+}
+
+impl<DI, W> Widget<DI> for Rc<RefCell<W>>
+where
+    DI: interface::DisplayInterface,
+    W: Widget<DI> + ?Sized,
+{
+    fn draw(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        self.borrow_mut().draw(display)
+    }
+
+    fn update(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        self.borrow_mut().update(display)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+    use crate::test_support::initialized_display;
+    use crate::text::tests::TEST_FONT;
+    use crate::widgets::{Menu, MenuItem, ProgressBar};
+    use std::vec;
+
+    /// The number of regions drawn.
+    fn regions(di: &MockInterface) -> usize {
+        di.sent()
+            .iter()
+            .filter(|sent| **sent == Sent::Cmd(0x5C))
+            .count()
+    }
+
+    #[test]
+    fn draw_and_update() {
+        let (mut di, mut disp) = initialized_display(Px(16, 16));
+        di.clear();
+
+        let bar = Rc::new(RefCell::new(ProgressBar::new(Px(0, 0), Px(16, 4))));
+        let items = vec![MenuItem::new("A"), MenuItem::new("B")];
+        let mut set = WidgetSet::new();
+        assert_eq!(set.push(bar.clone()), 0);
+        assert_eq!(set.push(Menu::new(TEST_FONT, items, Px(0, 4), 8, 6)), 1);
+        assert_eq!(set.len(), 2);
+
+        // Nothing has been drawn yet, so updating draws everything.
+        set.update(&mut disp).unwrap();
+        let all = regions(&di);
+        assert!(all > 0);
+        di.clear();
+        set.update(&mut disp).unwrap();
+        assert_eq!(regions(&di), 0);
+
+        // Changes made through the shared handle are picked up by the next update, which redraws
+        // only the bar.
+        bar.borrow_mut().set_colors(15, 8, 0);
+        set.update(&mut disp).unwrap();
+        let redrawn = regions(&di);
+        di.clear();
+        bar.borrow_mut().draw(&mut disp).unwrap();
+        assert_eq!(regions(&di), redrawn);
+        assert!(redrawn < all);
+
+        di.clear();
+        set.draw(&mut disp).unwrap();
+        assert_eq!(regions(&di), all);
+
+        set.remove(0);
+        assert!(set.get_mut(1).is_none());
+        assert!(set.get_mut(0).is_some());
+    }
+}
@@ -0,0 +1,224 @@
+//! Large numeric read-outs drawn as seven-segment digits, for clocks, RPM and temperature
+//! displays. The segments are rendered from their geometry at any size, with anti-aliased edges
+//! using the 16 gray levels, rather than by scaling up a small bitmap font.
+
+use crate::command::CommandError;
+use crate::display::{Display, PixelCoord};
+use crate::interface;
+use crate::text::blend;
+
+/// Segments lit for each digit 0 to 9, as bits `a` (top) through `g` (middle) from the LSB, going
+/// clockwise around the digit from the top as usual.
+const DIGITS: [u8; 10] = [
+    0b011_1111, 0b000_0110, 0b101_1011, 0b100_1111, 0b110_0110, 0b110_1101, 0b111_1101, 0b000_0111,
+    0b111_1111, 0b110_1111,
+];
+
+/// Segments lit for `-`.
+const MINUS: u8 = 0b100_0000;
+
+/// Subpixel samples per pixel along each axis used for anti-aliasing.
+const SAMPLES: i32 = 4;
+
+/// A read-out of `digits` seven-segment digits filling a rectangle. Digits are sized to fit the
+/// rectangle, and the segment thickness and spacing between digits follow from the digit size
+/// unless set explicitly.
+pub struct SevenSegment {
+    upper_left: PixelCoord,
+    lower_right: PixelCoord,
+    digits: u16,
+    /// The distance from the start of one digit to the next, and the width of the digit itself.
+    pitch: u16,
+    width: u16,
+    thickness: u16,
+    fg: u8,
+    bg: u8,
+}
+
+impl SevenSegment {
+    /// Create a read-out of `digits` digits filling the rectangle from `upper_left` to
+    /// `lower_right`. As with `Display::region`, the horizontal coordinates must be divisible by 4;
+    /// any columns left over after dividing the width into digits are drawn as background.
+    ///
+    /// Digits are drawn in gray level 15 on 0; use `set_colors` to change these.
+    pub fn new(upper_left: PixelCoord, lower_right: PixelCoord, digits: u16) -> Self {
+        let region_width = (lower_right.0 - upper_left.0).max(0) as u16;
+        let height = (lower_right.1 - upper_left.1).max(0) as u16;
+        let pitch = region_width / digits.max(1);
+        let width = pitch - pitch / 5;
+        SevenSegment {
+            upper_left: upper_left,
+            lower_right: lower_right,
+            digits: digits,
+            pitch: pitch,
+            width: width,
+            thickness: (width.min(height / 2) / 4).max(1),
+            fg: 15,
+            bg: 0,
+        }
+    }
+
+    /// Set the segment thickness in pixels.
+    pub fn set_thickness(&mut self, thickness: u16) {
+        self.thickness = thickness.max(1);
+    }
+
+    /// Set the gap in pixels between adjacent digits.
+    pub fn set_spacing(&mut self, spacing: u16) {
+        self.width = self.pitch.saturating_sub(spacing);
+    }
+
+    /// Set the gray levels used for lit segments and the background.
+    pub fn set_colors(&mut self, fg: u8, bg: u8) {
+        self.fg = fg & 0x0F;
+        self.bg = bg & 0x0F;
+    }
+
+    /// Draw `text`, right-aligned. Digits, `-` and space are supported, and any other character is
+    /// drawn as a blank digit. Returns `OutOfRange` if `text` has more characters than the read-out
+    /// has digits.
+    pub fn draw<DI>(
+        &self,
+        display: &mut Display<DI>,
+        text: &str,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        let len = text.chars().count() as u16;
+        if len > self.digits {
+            return Err(CommandError::OutOfRange);
+        }
+        let blank = self.digits - len;
+        let (ul, lr) = (self.upper_left, self.lower_right);
+        let (width, height) = ((lr.0 - ul.0) as u16, (lr.1 - ul.1) as u16);
+        let pitch = self.pitch.max(1);
+        let pixels = (0..height).flat_map(move |y| {
+            (0..width).map(move |x| {
+                let digit = x / pitch;
+                let lit = match digit
+                    .checked_sub(blank)
+                    .and_then(|i| text.chars().nth(i as usize))
+                {
+                    Some(c @ '0'..='9') => DIGITS[c as usize - '0' as usize],
+                    Some('-') => MINUS,
+                    _ => 0,
+                };
+                blend(self.coverage(lit, x % pitch, y, height), self.fg, self.bg)
+            })
+        });
+        display
+            .region(ul, lr)?
+            .draw(pixels)
+            .map_err(CommandError::InterfaceError)
+    }
+
+    /// The coverage, from 0 to 15, of pixel `x`, `y` within a digit `height` pixels tall with the
+    /// segments `lit`.
+    fn coverage(&self, lit: u8, x: u16, y: u16, height: u16) -> u8 {
+        if lit == 0 || x >= self.width {
+            return 0;
+        }
+        let mut hits = 0;
+        for sy in 0..SAMPLES {
+            for sx in 0..SAMPLES {
+                // Sample positions in units of 1/(2 * SAMPLES) pixel, at the sample centres.
+                let px = (x as i32 * SAMPLES + sx) * 2 + 1;
+                let py = (y as i32 * SAMPLES + sy) * 2 + 1;
+                if self.inside(lit, px, py, height) {
+                    hits += 1;
+                }
+            }
+        }
+        (hits * 15 / (SAMPLES * SAMPLES)) as u8
+    }
+
+    /// Whether a point, in units of 1/(2 * SAMPLES) pixel, lies within one of the `lit` segments.
+    #[allow(clippy::nonminimal_bool)]
+    fn inside(&self, lit: u8, px: i32, py: i32, height: u16) -> bool {
+        let unit = SAMPLES * 2;
+        let half = self.thickness as i32 * unit / 2;
+        let (w, h) = (self.width as i32 * unit, height as i32 * unit);
+        // Segments run between the centre lines of the digit's outline, with bevelled ends which
+        // meet at 45 degrees, and a small gap so that adjacent segments stay distinct.
+        let gap = unit / 2;
+        let (left, right) = (half, w - half);
+        let (top, middle, bottom) = (half, h / 2, h - half);
+
+        let horizontal = |yc: i32| {
+            let d = (py - yc).abs();
+            d <= half && px >= left + d + gap && px <= right - d - gap
+        };
+        let vertical = |xc: i32, y0: i32, y1: i32| {
+            let d = (px - xc).abs();
+            d <= half && py >= y0 + d + gap && py <= y1 - d - gap
+        };
+
+        false
+            || (lit & 0x01 != 0 && horizontal(top))
+            || (lit & 0x02 != 0 && vertical(right, top, middle))
+            || (lit & 0x04 != 0 && vertical(right, middle, bottom))
+            || (lit & 0x08 != 0 && horizontal(bottom))
+            || (lit & 0x10 != 0 && vertical(left, middle, bottom))
+            || (lit & 0x20 != 0 && vertical(left, top, middle))
+            || (lit & 0x40 != 0 && horizontal(middle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::test_support::initialized_display;
+    use std::vec::Vec;
+
+    /// Draw `text` on a 2 digit, 16x24 pixel read-out with 2 pixel thick segments, and return the
+    /// image of the first digit.
+    fn render(text: &str) -> Vec<Vec<u8>> {
+        let (di, mut disp) = initialized_display(Px(16, 32));
+        let mut readout = SevenSegment::new(Px(0, 0), Px(16, 24), 2);
+        readout.set_thickness(2);
+        readout.draw(&mut disp, text).unwrap();
+        let image = di.chip_model(Px(16, 32), Px(0, 0)).render();
+        image
+            .chunks(16)
+            .take(24)
+            .map(|row| row[..8].to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn segments() {
+        // Each digit is 8 pixels wide including 1 pixel of spacing.
+        let eight = render("8 ");
+        let one = render("1 ");
+        // Centre of the top, middle and bottom segments.
+        assert_eq!(eight[0][3], 15);
+        assert_eq!(eight[12][3], 15);
+        assert_eq!(eight[23][3], 15);
+        // Upper and lower left and right segments.
+        assert_eq!(eight[6][0], 15);
+        assert_eq!(eight[18][6], 15);
+        // The spacing column and the hole inside the digit stay blank.
+        assert!(eight.iter().all(|row| row[7] == 0));
+        assert_eq!(eight[6][3], 0);
+        // A "1" only lights the right hand segments.
+        assert!(one.iter().all(|row| row[..5].iter().all(|&p| p == 0)));
+        assert_eq!(one[6][6], 15);
+        // A blank digit is blank.
+        assert!(render(" 8").iter().all(|row| row.iter().all(|&p| p == 0)));
+    }
+
+    #[test]
+    fn anti_aliased() {
+        // At an odd thickness the segment edges fall mid-pixel and are partially covered.
+        let (di, mut disp) = initialized_display(Px(16, 32));
+        let mut readout = SevenSegment::new(Px(0, 0), Px(16, 24), 1);
+        readout.set_thickness(3);
+        readout.draw(&mut disp, "-").unwrap();
+        let image = di.chip_model(Px(16, 32), Px(0, 0)).render();
+        let column: Vec<u8> = (9..15).map(|y| image[y * 16 + 6]).collect();
+        assert_eq!(column, vec![0, 7, 15, 15, 7, 0]);
+        assert_eq!(readout.draw(&mut disp, "12"), Err(CommandError::OutOfRange));
+    }
+}
@@ -0,0 +1,267 @@
+//! A line graph of recent samples, as for sensor readings or CPU load.
+//!
+//! The SSD1322 cannot shift display RAM sideways: its only scrolling is the vertical pan of the
+//! start line. A chart which scrolls left therefore cannot be updated by writing just the newly
+//! exposed column group, since every other column has to move too, and `StripMode::Scroll` redraws
+//! the whole chart for each sample. Vertical address increment does not change this, as a window
+//! one column group wide receives the same bytes in either increment mode, so the chart is drawn
+//! with the default horizontal increment.
+//!
+//! For bandwidth-limited updates, `StripMode::Sweep` draws the chart like a patient monitor
+//! instead: new samples overwrite the oldest ones in place, with the write position sweeping across
+//! the chart and wrapping around. Each sample then redraws only the column group, four pixels wide,
+//! which holds it, usually a few hundred times less data than the whole chart.
+
+use crate::command::CommandError;
+use crate::display::{Display, PixelCoord};
+use crate::interface;
+use crate::widgets::Widget;
+
+/// How a `StripChart` moves its samples across the chart.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StripMode {
+    /// The newest sample is at the right edge and the graph scrolls left, redrawing the whole
+    /// chart for each sample, since the chip cannot shift the rest of it.
+    Scroll,
+    /// Samples stay in place while the write position sweeps from left to right and wraps around,
+    /// redrawing only the column groups each sample changes.
+    Sweep,
+}
+
+/// A line graph of the last `WIDTH` samples, one per pixel column, in a rectangle `WIDTH` pixels
+/// wide. Samples range from 0 at the bottom of the chart to 255 at the top, and consecutive samples
+/// are joined by vertical lines so that the trace is unbroken.
+pub struct StripChart<const WIDTH: usize> {
+    upper_left: PixelCoord,
+    height: u16,
+    mode: StripMode,
+    fg: u8,
+    fill: u8,
+    bg: u8,
+    /// A ring buffer of samples, where `head` is the oldest and the next to be replaced.
+    samples: [u8; WIDTH],
+    head: usize,
+    drawn: bool,
+}
+
+impl<const WIDTH: usize> StripChart<WIDTH> {
+    /// Create a chart with its upper left corner at `upper_left`, `height` pixels tall. As with
+    /// `Display::region`, the horizontal coordinate and `WIDTH` must be divisible by 4.
+    ///
+    /// The chart starts out with every sample 0, and draws the trace in gray level 15 on 0 with
+    /// nothing below it; use `set_colors` to change these.
+    pub fn new(upper_left: PixelCoord, height: u16, mode: StripMode) -> Self {
+        StripChart {
+            upper_left: upper_left,
+            height: height,
+            mode: mode,
+            fg: 15,
+            fill: 0,
+            bg: 0,
+            samples: [0; WIDTH],
+            head: 0,
+            drawn: false,
+        }
+    }
+
+    /// Set the gray levels of the trace, of the area below it, and of the background. The whole
+    /// chart is redrawn on the next update.
+    pub fn set_colors(&mut self, fg: u8, fill: u8, bg: u8) {
+        self.fg = fg & 0x0F;
+        self.fill = fill & 0x0F;
+        self.bg = bg & 0x0F;
+        self.drawn = false;
+    }
+
+    /// Reset every sample to 0. The whole chart is redrawn on the next update.
+    pub fn clear(&mut self) {
+        self.samples = [0; WIDTH];
+        self.head = 0;
+        self.drawn = false;
+    }
+
+    /// Add a sample, replacing the oldest, and update the chart on `display`. Only the column
+    /// groups which changed are redrawn in `StripMode::Sweep`, or the whole chart if it has not
+    /// been drawn yet.
+    pub fn push<DI>(
+        &mut self,
+        display: &mut Display<DI>,
+        sample: u8,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        if WIDTH == 0 {
+            return Ok(());
+        }
+        let written = self.head;
+        self.samples[written] = sample;
+        self.head = (written + 1) % WIDTH;
+        if !self.drawn || self.mode == StripMode::Scroll {
+            return self.draw(display);
+        }
+        // The new sample's column changed, and so did the next one, which is no longer joined to
+        // the column before it now that it holds the oldest sample.
+        let group = written / 4;
+        self.draw_columns(display, group * 4, group * 4 + 4)?;
+        let next_group = self.head / 4;
+        if next_group != group {
+            self.draw_columns(display, next_group * 4, next_group * 4 + 4)?;
+        }
+        Ok(())
+    }
+
+    /// Draw the whole chart, e.g. after the display was cleared.
+    pub fn draw<DI>(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        self.draw_columns(display, 0, WIDTH)?;
+        self.drawn = true;
+        Ok(())
+    }
+
+    /// The index into `samples` of the sample shown in column `col`, and of the sample it is
+    /// joined to in the column before, if any.
+    fn sample_at(&self, col: usize) -> (usize, Option<usize>) {
+        match self.mode {
+            StripMode::Scroll => {
+                let index = (self.head + col) % WIDTH;
+                let prev = (col > 0).then(|| (index + WIDTH - 1) % WIDTH);
+                (index, prev)
+            }
+            StripMode::Sweep => {
+                let prev = (col > 0 && col != self.head).then(|| col - 1);
+                (col, prev)
+            }
+        }
+    }
+
+    /// The row, counted down from the top of the chart, at which `sample` is plotted.
+    fn row_of(&self, sample: u8) -> u16 {
+        let span = self.height.saturating_sub(1) as u32;
+        ((255 - sample as u32) * span / 255) as u16
+    }
+
+    /// The gray level of the pixel at `row` in column `col`.
+    fn pixel(&self, col: usize, row: u16) -> u8 {
+        let (index, prev) = self.sample_at(col);
+        let y = self.row_of(self.samples[index]);
+        let prev_y = prev.map_or(y, |prev| self.row_of(self.samples[prev]));
+        if row >= y.min(prev_y) && row <= y.max(prev_y) {
+            self.fg
+        } else if row > y {
+            self.fill
+        } else {
+            self.bg
+        }
+    }
+
+    /// Draw columns `start` up to `end`, which are multiples of 4.
+    fn draw_columns<DI>(
+        &self,
+        display: &mut Display<DI>,
+        start: usize,
+        end: usize,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        let pixels =
+            (0..self.height).flat_map(move |row| (start..end).map(move |col| self.pixel(col, row)));
+        let left = self.upper_left.0 + start as i16;
+        display
+            .region(
+                PixelCoord(left, self.upper_left.1),
+                PixelCoord(
+                    left + (end - start) as i16,
+                    self.upper_left.1 + self.height as i16,
+                ),
+            )?
+            .draw(pixels)
+            .map_err(CommandError::InterfaceError)
+    }
+}
+
+impl<const WIDTH: usize, DI> Widget<DI> for StripChart<WIDTH>
+where
+    DI: interface::DisplayInterface,
+{
+    fn draw(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        Self::draw(self, display)
+    }
+
+    fn update(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        if self.drawn {
+            return Ok(());
+        }
+        Self::draw(self, display)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+    use crate::test_support::{screen, widget_fixture};
+
+    fn setup(mode: StripMode) -> (MockInterface, Display<MockInterface>, StripChart<8>) {
+        let mut chart = StripChart::new(Px(0, 0), 4, mode);
+        chart.set_colors(15, 3, 0);
+        widget_fixture(Px(16, 16), chart)
+    }
+
+    #[test]
+    fn scroll() {
+        let (di, mut disp, mut chart) = setup(StripMode::Scroll);
+        for &sample in [255, 128, 0, 0].iter() {
+            chart.push(&mut disp, sample).unwrap();
+        }
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di, 4, 8)[..], &b"\
+            0000FF00\
+            0000FFF0\
+            0000F3F0\
+            FFFFF3FF\
+        "[..]);
+    }
+
+    #[test]
+    fn sweep() {
+        let (di, mut disp, mut chart) = setup(StripMode::Sweep);
+        chart.push(&mut disp, 255).unwrap();
+        // A sample within a column group redraws just that group.
+        let before = di.sent().len();
+        chart.push(&mut disp, 128).unwrap();
+        let sent = &di.sent()[before..];
+        assert_eq!(sent.iter().filter(|s| **s == Sent::Cmd(0x15)).count(), 1);
+        chart.push(&mut disp, 0).unwrap();
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di, 4, 8)[..], &b"\
+            FF000000\
+            3FF00000\
+            33F00000\
+            33FFFFFF\
+        "[..]);
+        // Wrapping around breaks the trace between the newest and oldest samples.
+        for &sample in [255, 0, 0, 0, 0, 0].iter() {
+            chart.push(&mut disp, sample).unwrap();
+        }
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di, 4, 8)[..], &b"\
+            000FF000\
+            0FFFF000\
+            03FFF000\
+            F3FFFFFF\
+        "[..]);
+        chart.push(&mut disp, 255).unwrap();
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di, 4, 8)[..], &b"\
+            0F0FF000\
+            0F0FF000\
+            0F0FF000\
+            FFFFFFFF\
+        "[..]);
+    }
+}
@@ -0,0 +1,244 @@
+//! A multi-channel bar graph meter with peak hold, as for audio level meters.
+
+use crate::command::CommandError;
+use crate::display::{Display, PixelCoord};
+use crate::interface;
+use crate::widgets::Widget;
+
+/// A row of `BARS` vertical bars growing upward from the bottom of a rectangle. Bars are shaded
+/// with a gray level gradient from bottom to top, and can show a peak marker which holds the
+/// highest recent level for a number of frames before falling back.
+///
+/// Each bar occupies its own column groups, and each frame only the rows of each bar which changed
+/// are redrawn.
+pub struct VuMeter<const BARS: usize> {
+    upper_left: PixelCoord,
+    height: u16,
+    pitch: u16,
+    bar_width: u16,
+    bottom: u8,
+    top: u8,
+    bg: u8,
+    peak: u8,
+    hold: Option<u16>,
+    /// The bar heights and peak marker heights in pixels, and the frames since each peak was set.
+    levels: [u16; BARS],
+    peaks: [u16; BARS],
+    peak_age: [u16; BARS],
+    drawn: bool,
+}
+
+impl<const BARS: usize> VuMeter<BARS> {
+    /// Create a meter with its upper left corner at `upper_left`, `height` pixels tall. Each bar
+    /// is `bar_width` pixels wide and starts `pitch` pixels after the previous one, with the space
+    /// in between left blank. As with `Display::region`, the horizontal coordinate and `pitch` must
+    /// be divisible by 4.
+    ///
+    /// The bars are shaded from gray level 4 at the bottom to 15 at the top, on 0, and peaks are
+    /// held for 30 frames; use the setters to change these.
+    pub fn new(upper_left: PixelCoord, height: u16, pitch: u16, bar_width: u16) -> Self {
+        VuMeter {
+            upper_left: upper_left,
+            height: height,
+            pitch: pitch,
+            bar_width: bar_width,
+            bottom: 4,
+            top: 15,
+            bg: 0,
+            peak: 15,
+            hold: Some(30),
+            levels: [0; BARS],
+            peaks: [0; BARS],
+            peak_age: [0; BARS],
+            drawn: false,
+        }
+    }
+
+    /// Set the gray levels at the bottom and top of the bars, and of the background. The whole
+    /// meter is redrawn on the next update.
+    pub fn set_colors(&mut self, bottom: u8, top: u8, bg: u8) {
+        self.bottom = bottom & 0x0F;
+        self.top = top & 0x0F;
+        self.bg = bg & 0x0F;
+        self.drawn = false;
+    }
+
+    /// Set the gray level of the peak markers, and the number of frames a peak is held before it
+    /// starts falling by one pixel per frame. `None` disables the peak markers.
+    pub fn set_peak_hold(&mut self, level: u8, hold: Option<u16>) {
+        self.peak = level & 0x0F;
+        self.hold = hold;
+        self.drawn = false;
+    }
+
+    /// Update the meter with one frame of levels, one per bar, where 255 is a full-height bar.
+    /// Only the rows which changed are redrawn, or the whole meter if it has not been drawn yet.
+    pub fn update<DI>(
+        &mut self,
+        display: &mut Display<DI>,
+        levels: &[u8; BARS],
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        let (old_levels, old_peaks) = (self.levels, self.peaks);
+        for (bar, &input) in levels.iter().enumerate() {
+            let level = (input as u32 * self.height as u32 / 255) as u16;
+            self.levels[bar] = level;
+            if level >= self.peaks[bar] {
+                self.peaks[bar] = level;
+                self.peak_age[bar] = 0;
+            } else {
+                self.peak_age[bar] = self.peak_age[bar].saturating_add(1);
+                if self.hold.is_some_and(|hold| self.peak_age[bar] > hold) {
+                    self.peaks[bar] = (self.peaks[bar] - 1).max(level);
+                }
+            }
+        }
+        if !self.drawn {
+            return self.draw(display);
+        }
+        for bar in 0..BARS {
+            // The range of rows, counted up from the bottom, which changed in this bar.
+            let (old, new) = (old_levels[bar], self.levels[bar]);
+            let mut low = old.min(new);
+            let mut high = old.max(new);
+            if self.hold.is_some() && old_peaks[bar] != self.peaks[bar] {
+                let (a, b) = (old_peaks[bar], self.peaks[bar]);
+                low = low.min(a.min(b).saturating_sub(1));
+                high = high.max(a.max(b));
+            }
+            if low < high {
+                self.draw_bar(display, bar, low, high)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw the whole meter, e.g. after the display was cleared.
+    pub fn draw<DI>(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        for bar in 0..BARS {
+            self.draw_bar(display, bar, 0, self.height)?;
+        }
+        self.drawn = true;
+        Ok(())
+    }
+
+    /// The gray level of row `row` of a bar, counted up from the bottom.
+    fn gradient(&self, row: u16) -> u8 {
+        let span = self.height.saturating_sub(1).max(1) as i32;
+        let (bottom, top) = (self.bottom as i32, self.top as i32);
+        (bottom + (top - bottom) * row as i32 / span) as u8
+    }
+
+    /// Draw rows `low` up to `high` of bar `bar`, counted up from the bottom.
+    fn draw_bar<DI>(
+        &self,
+        display: &mut Display<DI>,
+        bar: usize,
+        low: u16,
+        high: u16,
+    ) -> Result<(), CommandError<DI::Error>>
+    where
+        DI: interface::DisplayInterface,
+    {
+        let (level, peak) = (self.levels[bar], self.peaks[bar]);
+        let show_peak = self.hold.is_some() && peak > level;
+        let pixels = (low..high).rev().flat_map(move |row| {
+            (0..self.pitch).map(move |col| {
+                if col >= self.bar_width {
+                    self.bg
+                } else if row < level {
+                    self.gradient(row)
+                } else if show_peak && row + 1 == peak {
+                    self.peak
+                } else {
+                    self.bg
+                }
+            })
+        });
+        let left = self.upper_left.0 + (bar as u16 * self.pitch) as i16;
+        let bottom = self.upper_left.1 + self.height as i16;
+        display
+            .region(
+                PixelCoord(left, bottom - high as i16),
+                PixelCoord(left + self.pitch as i16, bottom - low as i16),
+            )?
+            .draw(pixels)
+            .map_err(CommandError::InterfaceError)
+    }
+}
+
+impl<const BARS: usize, DI> Widget<DI> for VuMeter<BARS>
+where
+    DI: interface::DisplayInterface,
+{
+    fn draw(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        Self::draw(self, display)
+    }
+
+    fn update(&mut self, display: &mut Display<DI>) -> Result<(), CommandError<DI::Error>> {
+        if self.drawn {
+            return Ok(());
+        }
+        Self::draw(self, display)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PixelCoord as Px;
+    use crate::interface::mock::{MockInterface, Sent};
+    use crate::test_support::{screen, widget_fixture};
+
+    fn setup() -> (MockInterface, Display<MockInterface>, VuMeter<2>) {
+        let mut meter = VuMeter::new(Px(0, 0), 4, 4, 3);
+        meter.set_colors(3, 12, 0);
+        meter.set_peak_hold(15, Some(1));
+        widget_fixture(Px(16, 16), meter)
+    }
+
+    #[test]
+    fn gradient_and_peaks() {
+        let (di, mut disp, mut meter) = setup();
+        meter.update(&mut disp, &[255, 128]).unwrap();
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di, 4, 8)[..], &b"\
+            CCC00000\
+            99900000\
+            66606660\
+            33303330\
+        "[..]);
+    }
+
+    #[test]
+    fn minimal_updates() {
+        let (di, mut disp, mut meter) = setup();
+        meter.update(&mut disp, &[255, 128]).unwrap();
+        // The first bar drops to 1 row, leaving its peak held at the top; the second is unchanged.
+        let before = di.sent().len();
+        meter.update(&mut disp, &[64, 128]).unwrap();
+        let sent = &di.sent()[before..];
+        assert_eq!(sent.iter().filter(|s| **s == Sent::Cmd(0x15)).count(), 1);
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di, 4, 8)[..], &b"\
+            FFF00000\
+            00000000\
+            00006660\
+            33303330\
+        "[..]);
+        // After the hold time passes the peak falls one row per frame.
+        meter.update(&mut disp, &[64, 128]).unwrap();
+        #[rustfmt::skip]
+        assert_eq!(&screen(&di, 4, 8)[..], &b"\
+            00000000\
+            FFF00000\
+            00006660\
+            33303330\
+        "[..]);
+    }
+}